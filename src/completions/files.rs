@@ -52,7 +52,7 @@ impl FileCompleter {
 
     /// 反转义 shell 字符串
     /// 将 \[ 还原为 [，将 \ 空格还原为空格等
-    fn unescape_shell_chars(s: &str) -> String {
+    pub fn unescape_shell_chars(s: &str) -> String {
         let mut result = String::with_capacity(s.len());
         let mut chars = s.chars().peekable();
         while let Some(c) = chars.next() {
@@ -1,8 +1,36 @@
 //! 文本缓冲区
+//!
+//! 对外的列号都以字素簇计（见 `text` 模块），内部和撤销历史使用字节偏移。
+//!
+//! 读取文件时记下换行符（`\n` 或 `\r\n`）和文件末尾是否有换行，保存时原样写回。
+//! 保存先写到同一目录下的临时文件并同步到磁盘，再改名替换原文件，中途出错不会留下
+//! 写了一半的文件。
 
-use std::fs;
-use std::io;
-use std::path::Path;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use super::text;
+use super::undo::{Edit, UndoHistory};
+
+/// 换行符
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    /// `\n`
+    #[default]
+    Lf,
+    /// `\r\n`（Windows）
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
 
 /// 文本缓冲区
 pub struct Buffer {
@@ -10,6 +38,14 @@ pub struct Buffer {
     lines: Vec<String>,
     /// 命令缓冲区（用于 : 命令）
     pub command_buffer: String,
+    /// 撤销/重做历史
+    undo: UndoHistory,
+    /// 保存时使用的换行符
+    pub line_ending: LineEnding,
+    /// 保存时最后一行之后是否加换行
+    pub trailing_newline: bool,
+    /// 内容修改的次数（包括撤销和重做），用于判断是否需要重写交换文件
+    changes: u64,
 }
 
 impl Buffer {
@@ -18,24 +54,49 @@ impl Buffer {
         Self {
             lines: vec![String::new()],
             command_buffer: String::new(),
+            undo: UndoHistory::new(),
+            line_ending: LineEnding::Lf,
+            trailing_newline: false,
+            changes: 0,
         }
     }
     
     /// 从文件创建缓冲区
     pub fn from_file(path: &Path) -> io::Result<Self> {
-        let content = fs::read_to_string(path)?;
-        let lines: Vec<String> = content.lines().map(String::from).collect();
+        Ok(Self::from_text(&fs::read_to_string(path)?))
+    }
+    
+    /// 从文件内容创建缓冲区，第一个换行是 `\r\n` 时按 CRLF 文件处理
+    pub fn from_text(content: &str) -> Self {
+        let line_ending = match content.find('\n') {
+            Some(i) if content[..i].ends_with('\r') => LineEnding::CrLf,
+            _ => LineEnding::Lf,
+        };
+        let trailing_newline = content.ends_with('\n');
+        let content = content.strip_suffix('\n').unwrap_or(content);
+        let lines = content.split('\n')
+            .map(|line| match line_ending {
+                LineEnding::CrLf => line.strip_suffix('\r').unwrap_or(line),
+                LineEnding::Lf => line,
+            })
+            .map(String::from)
+            .collect();
         
-        Ok(Self {
-            lines: if lines.is_empty() { vec![String::new()] } else { lines },
-            command_buffer: String::new(),
-        })
+        Self { lines, line_ending, trailing_newline, ..Self::new() }
     }
     
-    /// 保存到文件
+    /// 按原来的换行符和文件末尾的换行生成文件内容
+    pub fn to_text(&self) -> String {
+        let mut content = self.lines.join(self.line_ending.as_str());
+        if self.trailing_newline {
+            content.push_str(self.line_ending.as_str());
+        }
+        content
+    }
+    
+    /// 保存到文件（原子地替换，保留原文件的权限）
     pub fn save_to_file(&self, path: &Path) -> io::Result<()> {
-        let content = self.lines.join("\n");
-        fs::write(path, content)
+        write_atomic(path, self.to_text().as_bytes())
     }
     
     /// 获取行数
@@ -48,28 +109,56 @@ impl Buffer {
         self.lines.get(row).map(|s| s.as_str()).unwrap_or("")
     }
     
-    /// 获取指定行长度
+    /// 获取指定行长度（字素簇个数）
     pub fn line_len(&self, row: usize) -> usize {
-        self.lines.get(row).map(|s| s.len()).unwrap_or(0)
+        text::grapheme_count(self.get_line(row))
+    }
+    
+    /// 第 `col` 列开始的字节偏移
+    pub fn byte_offset(&self, row: usize, col: usize) -> usize {
+        text::byte_offset(self.get_line(row), col)
+    }
+    
+    /// 第 `col` 列的字素簇
+    pub fn grapheme(&self, row: usize, col: usize) -> Option<&str> {
+        text::graphemes(self.get_line(row)).nth(col)
+    }
+    
+    /// 光标前的文本
+    pub fn prefix(&self, row: usize, col: usize) -> &str {
+        let line = self.get_line(row);
+        &line[..text::byte_offset(line, col)]
+    }
+    
+    /// 第 `col` 列在屏幕上的显示列
+    pub fn display_col(&self, row: usize, col: usize) -> usize {
+        text::str_width(self.prefix(row, col))
+    }
+    
+    /// 屏幕上第 `x` 列对应的列
+    pub fn col_at_display(&self, row: usize, x: usize) -> usize {
+        text::col_at_width(self.get_line(row), x)
     }
     
-    /// 插入字符
-    pub fn insert_char(&mut self, row: usize, col: usize, c: char) {
+    /// 插入字符，返回插入后光标应在的列
+    ///
+    /// 组合字符会并入前一个字素簇，所以返回值不一定是 `col + 1`。
+    pub fn insert_char(&mut self, row: usize, col: usize, c: char) -> usize {
         if row < self.lines.len() {
-            let line = &mut self.lines[row];
-            let col = col.min(line.len());
-            line.insert(col, c);
+            let at = self.byte_offset(row, col);
+            self.edit(Edit::Insert { row, col: at, text: c.to_string() });
+            text::grapheme_col(&self.lines[row], at + c.len_utf8())
+        } else {
+            col
         }
     }
     
-    /// 删除字符
+    /// 删除字符（一个字素簇）
     pub fn delete_char(&mut self, row: usize, col: usize) -> bool {
-        if row < self.lines.len() {
-            let line = &mut self.lines[row];
-            if col < line.len() {
-                line.remove(col);
-                return true;
-            }
+        if let Some(g) = self.grapheme(row, col).map(String::from) {
+            let at = self.byte_offset(row, col);
+            self.edit(Edit::Delete { row, col: at, text: g });
+            return true;
         }
         false
     }
@@ -77,31 +166,234 @@ impl Buffer {
     /// 插入换行
     pub fn insert_newline(&mut self, row: usize, col: usize) {
         if row < self.lines.len() {
-            let line = &self.lines[row];
-            let col = col.min(line.len());
-            let rest = line[col..].to_string();
-            self.lines[row] = line[..col].to_string();
-            self.lines.insert(row + 1, rest);
+            let at = self.byte_offset(row, col);
+            self.edit(Edit::Insert { row, col: at, text: "\n".to_string() });
         }
     }
     
     /// 在指定行上方插入新行
     pub fn insert_line_above(&mut self, row: usize) {
-        self.lines.insert(row, String::new());
+        let row = row.min(self.lines.len());
+        if row < self.lines.len() {
+            self.edit(Edit::Insert { row, col: 0, text: "\n".to_string() });
+        } else {
+            let at = self.lines[row - 1].len();
+            self.edit(Edit::Insert { row: row - 1, col: at, text: "\n".to_string() });
+        }
     }
     
     /// 删除行
     pub fn delete_line(&mut self, row: usize) {
         if self.lines.len() > 1 && row < self.lines.len() {
-            self.lines.remove(row);
+            let text = self.lines[row].clone();
+            if row + 1 < self.lines.len() {
+                self.edit(Edit::Delete { row, col: 0, text: format!("{}\n", text) });
+            } else {
+                let at = self.lines[row - 1].len();
+                self.edit(Edit::Delete { row: row - 1, col: at, text: format!("\n{}", text) });
+            }
+        }
+    }
+    
+    /// 替换一行中的一段文本，列为字节偏移（正则匹配的位置）
+    pub fn replace_range(&mut self, row: usize, start: usize, end: usize, text: &str) {
+        if row < self.lines.len() && start <= end && end <= self.lines[row].len() {
+            if start < end {
+                let old = self.lines[row][start..end].to_string();
+                self.edit(Edit::Delete { row, col: start, text: old });
+            }
+            if !text.is_empty() {
+                self.edit(Edit::Insert { row, col: start, text: text.to_string() });
+            }
+        }
+    }
+    
+    /// 插入一段文本（可以包含换行），返回插入后文本末尾的位置
+    pub fn insert_text(&mut self, row: usize, col: usize, text: &str) -> (usize, usize) {
+        if row >= self.lines.len() || text.is_empty() {
+            return (row, col);
         }
+        let edit = Edit::Insert { row, col: self.byte_offset(row, col), text: text.to_string() };
+        let end = edit.end();
+        self.edit(edit);
+        self.position(end)
+    }
+    
+    /// 获取从 `start` 到 `end`（不含）的文本，位置为 (行, 列)
+    pub fn text_range(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let (start, end) = (self.byte_position(start), self.byte_position(end));
+        if start >= end {
+            return String::new();
+        }
+        if start.0 == end.0 {
+            return self.lines[start.0][start.1..end.1].to_string();
+        }
+        let mut text = self.lines[start.0][start.1..].to_string();
+        for line in &self.lines[start.0 + 1..end.0] {
+            text.push('\n');
+            text.push_str(line);
+        }
+        text.push('\n');
+        text.push_str(&self.lines[end.0][..end.1]);
+        text
+    }
+    
+    /// 删除从 `start` 到 `end`（不含）的文本，返回删除的文本
+    pub fn delete_range(&mut self, start: (usize, usize), end: (usize, usize)) -> String {
+        let text = self.text_range(start, end);
+        if !text.is_empty() {
+            let (row, col) = self.byte_position(start);
+            self.edit(Edit::Delete { row, col, text: text.clone() });
+        }
+        text
+    }
+    
+    /// 删除 `first` 到 `last` 行（包含），返回删除的文本，每行以换行结尾
+    ///
+    /// 删除全部行时留下一个空行。
+    pub fn delete_lines(&mut self, first: usize, last: usize) -> String {
+        let last = last.min(self.lines.len() - 1);
+        if first > last {
+            return String::new();
+        }
+        let text = self.lines[first..=last].join("\n");
+        let edit = if last + 1 < self.lines.len() {
+            Edit::Delete { row: first, col: 0, text: format!("{}\n", text) }
+        } else if first > 0 {
+            Edit::Delete { row: first - 1, col: self.lines[first - 1].len(), text: format!("\n{}", text) }
+        } else {
+            Edit::Delete { row: 0, col: 0, text: text.clone() }
+        };
+        if !edit.is_empty() {
+            self.edit(edit);
+        }
+        format!("{}\n", text)
+    }
+    
+    /// 切换 `first` 到 `last` 行的行注释，返回是否加上了注释
+    ///
+    /// 非空行都已注释时去掉注释符（和其后的一个空格），否则在这些行的最小缩进处
+    /// 加上 `marker` 和一个空格。空行不变。
+    pub fn toggle_comment(&mut self, first: usize, last: usize, marker: &str) -> bool {
+        let last = last.min(self.lines.len() - 1);
+        let rows: Vec<usize> = (first..=last).filter(|&row| !self.lines[row].trim().is_empty()).collect();
+        let indent = |line: &str| line.len() - line.trim_start().len();
+        let commented = rows.iter().all(|&row| self.lines[row].trim_start().starts_with(marker));
+        if commented {
+            for &row in &rows {
+                let line = &self.lines[row];
+                let start = indent(line);
+                let mut end = start + marker.len();
+                if line[end..].starts_with(' ') {
+                    end += 1;
+                }
+                self.replace_range(row, start, end, "");
+            }
+        } else {
+            let at = rows.iter().map(|&row| indent(&self.lines[row])).min().unwrap_or(0);
+            for &row in &rows {
+                self.replace_range(row, at, at, &format!("{} ", marker));
+            }
+        }
+        !commented
+    }
+
+    /// (行, 列) 换成 (行, 字节偏移)，超出时取最近的有效位置
+    fn byte_position(&self, (row, col): (usize, usize)) -> (usize, usize) {
+        let row = row.min(self.lines.len() - 1);
+        (row, self.byte_offset(row, col))
     }
     
     /// 追加到行末
     pub fn append_to_line(&mut self, row: usize, text: &str) {
-        if row < self.lines.len() {
-            self.lines[row].push_str(text);
+        if row < self.lines.len() && !text.is_empty() {
+            let col = self.lines[row].len();
+            self.edit(Edit::Insert { row, col, text: text.to_string() });
+        }
+    }
+    
+    /// 执行并记录一次修改（列为字节偏移）
+    fn edit(&mut self, edit: Edit) {
+        self.apply(&edit);
+        self.undo.record(edit);
+    }
+    
+    /// 执行修改（不记录）
+    fn apply(&mut self, edit: &Edit) {
+        self.changes += 1;
+        match edit {
+            Edit::Insert { row, col, text } => {
+                let tail = self.lines[*row].split_off(*col);
+                let mut segments = text.split('\n');
+                self.lines[*row].push_str(segments.next().unwrap_or(""));
+                let mut last = *row;
+                for segment in segments {
+                    last += 1;
+                    self.lines.insert(last, segment.to_string());
+                }
+                self.lines[last].push_str(&tail);
+            }
+            Edit::Delete { row, col, text } => {
+                let newlines = text.matches('\n').count();
+                let end_row = row + newlines;
+                let end_col = match text.rfind('\n') {
+                    Some(i) => text.len() - i - 1,
+                    None => col + text.len(),
+                };
+                let tail = self.lines[end_row][end_col..].to_string();
+                self.lines[*row].truncate(*col);
+                self.lines[*row].push_str(&tail);
+                self.lines.drain(row + 1..=end_row);
+            }
+        }
+    }
+    
+    /// 开始合并修改（如一次插入模式中的输入），撤销时作为一步
+    pub fn begin_undo_group(&mut self) {
+        self.undo.begin_group();
+    }
+    
+    /// 结束合并修改
+    pub fn end_undo_group(&mut self) {
+        self.undo.end_group();
+    }
+    
+    /// 撤销一步，返回光标应在的位置
+    pub fn undo(&mut self) -> Option<(usize, usize)> {
+        let edits = self.undo.take_undo()?;
+        for edit in &edits {
+            self.apply(edit);
         }
+        edits.last().map(|e| self.position(e.start()))
+    }
+    
+    /// 重做一步，返回光标应在的位置
+    pub fn redo(&mut self) -> Option<(usize, usize)> {
+        let edits = self.undo.take_redo()?;
+        for edit in &edits {
+            self.apply(edit);
+        }
+        edits.first().map(|e| self.position(e.start()))
+    }
+    
+    /// 把 (行, 字节偏移) 换成 (行, 列)
+    fn position(&self, (row, byte): (usize, usize)) -> (usize, usize) {
+        (row, text::grapheme_col(self.get_line(row), byte))
+    }
+    
+    /// 标记为已保存（撤销回到这个状态时不算修改）
+    pub fn mark_saved(&mut self) {
+        self.undo.mark_saved();
+    }
+    
+    /// 内容与上次保存时是否不同
+    pub fn is_modified(&self) -> bool {
+        self.undo.is_modified()
+    }
+    
+    /// 内容修改的次数，每次修改、撤销或重做都会增加
+    pub fn changes(&self) -> u64 {
+        self.changes
     }
     
     /// 获取所有行的迭代器
@@ -129,3 +421,37 @@ impl Default for Buffer {
     }
 }
 
+/// 原子地写文件：先写到同一目录下的临时文件并同步到磁盘，再改名替换 `path`
+///
+/// `path` 是符号链接时替换它指向的文件；原文件存在时保留它的权限。
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let permissions = fs::metadata(&target).ok().map(|m| m.permissions());
+    let dir = match target.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let name = target.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let temp = dir.join(format!(".{}.{}.tmp", name, std::process::id()));
+    
+    let result = (|| {
+        let mut file = File::create(&temp)?;
+        file.write_all(content)?;
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions)?;
+        }
+        file.sync_all()?;
+        fs::rename(&temp, &target)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+        return result;
+    }
+    
+    // 改名本身也要同步到磁盘（只有 Unix 可以打开目录）
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(&dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
@@ -0,0 +1,131 @@
+//! 缓冲区列表
+//!
+//! 每个打开的文件是一个缓冲区，按打开的顺序编号（从 1 开始），`:ls` 列出、`:b N` 切换。
+//! 当前窗口的缓冲区由 `Editor` 直接持有，列表中对应的一项只保留文件名等信息，
+//! 切换时再交换回来。
+
+use std::path::{Component, Path, PathBuf};
+
+use super::buffer::Buffer;
+use super::cursor::Cursor;
+
+/// 缓冲区列表中的一项
+pub struct Entry {
+    /// 编号
+    pub number: usize,
+    /// 内容，当前缓冲区的内容在 `Editor` 中，这里为空
+    pub buffer: Buffer,
+    /// 文件路径，未命名时为 None
+    pub path: Option<PathBuf>,
+    /// 是否有未保存的修改
+    pub modified: bool,
+    /// 离开这个缓冲区时的光标，切换回来时恢复
+    pub cursor: Cursor,
+    /// 上一次可视选择的首尾行
+    pub last_visual: Option<(usize, usize)>,
+}
+
+impl Entry {
+    /// 显示的名称
+    pub fn name(&self) -> String {
+        display_name(self.path.as_deref())
+    }
+}
+
+/// 缓冲区列表
+pub struct BufferList {
+    entries: Vec<Entry>,
+    next_number: usize,
+}
+
+impl BufferList {
+    /// 空列表
+    pub fn new() -> Self {
+        Self { entries: Vec::new(), next_number: 1 }
+    }
+
+    /// 添加一个缓冲区，返回它的编号
+    pub fn add(&mut self, buffer: Buffer, path: Option<PathBuf>) -> usize {
+        let number = self.next_number;
+        self.next_number = number + 1;
+        self.entries.push(Entry {
+            number,
+            buffer,
+            path,
+            modified: false,
+            cursor: Cursor::new(),
+            last_visual: None,
+        });
+        number
+    }
+
+    pub fn get(&self, number: usize) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.number == number)
+    }
+
+    pub fn get_mut(&mut self, number: usize) -> Option<&mut Entry> {
+        self.entries.iter_mut().find(|e| e.number == number)
+    }
+
+    /// 打开了 `path` 的缓冲区
+    pub fn find(&self, path: &Path) -> Option<usize> {
+        self.entries.iter()
+            .find(|e| e.path.as_deref().is_some_and(|p| same_file(p, path)))
+            .map(|e| e.number)
+    }
+
+    /// `number` 之后（`forward` 为 false 时之前）的缓冲区，到头时回到另一端
+    pub fn next(&self, number: usize, forward: bool) -> usize {
+        let len = self.entries.len();
+        match self.entries.iter().position(|e| e.number == number) {
+            Some(i) if forward => self.entries[(i + 1) % len].number,
+            Some(i) => self.entries[(i + len - 1) % len].number,
+            None => number,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Entry> {
+        self.entries.iter_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for BufferList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 状态栏和 `:ls` 中显示的文件名
+pub fn display_name(path: Option<&Path>) -> String {
+    match path {
+        Some(path) => path.display().to_string(),
+        None => "[未命名]".to_string(),
+    }
+}
+
+/// 两个路径是否指向同一个文件（`a.sh` 和 `./a.sh`）
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    if a == b {
+        return true;
+    }
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        // 文件还不存在时只比较去掉 `.` 后的路径
+        _ => {
+            let parts = |p| Path::components(p).filter(|c| *c != Component::CurDir);
+            parts(a).eq(parts(b))
+        }
+    }
+}
@@ -245,6 +245,15 @@ impl Completer {
         
         results
     }
+    
+    /// 全部词，当前文件的在前，各自按频率排序
+    pub fn words(&self) -> Vec<String> {
+        self.file_trie.find_with_prefix("")
+            .into_iter()
+            .chain(self.global_trie.find_with_prefix(""))
+            .map(|(word, _)| word)
+            .collect()
+    }
 }
 
 impl Default for Completer {
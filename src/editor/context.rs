@@ -459,6 +459,11 @@ impl EditorContext {
     pub fn get_defined_vars(&self) -> &[String] {
         &self.defined_vars
     }
+
+    /// 获取文件中定义的函数名
+    pub fn get_functions(&self) -> &HashSet<String> {
+        &self.functions
+    }
 }
 
 impl Default for EditorContext {
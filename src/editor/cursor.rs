@@ -3,12 +3,13 @@
 use super::Buffer;
 
 /// 光标位置
+#[derive(Debug, Clone)]
 pub struct Cursor {
     /// 行号（从 0 开始）
     pub row: usize,
-    /// 列号（从 0 开始）
+    /// 列号（从 0 开始，以字素簇计）
     pub col: usize,
-    /// 记住的列位置（用于上下移动时保持列位置）
+    /// 记住的屏幕列（用于上下移动时保持列位置，中文等宽字符占两列）
     desired_col: usize,
 }
 
@@ -23,10 +24,10 @@ impl Cursor {
     }
     
     /// 向上移动
-    pub fn move_up(&mut self) {
+    pub fn move_up(&mut self, buffer: &Buffer) {
         if self.row > 0 {
             self.row -= 1;
-            self.col = self.desired_col;
+            self.col = buffer.col_at_display(self.row, self.desired_col);
         }
     }
     
@@ -34,15 +35,15 @@ impl Cursor {
     pub fn move_down(&mut self, buffer: &Buffer) {
         if self.row < buffer.line_count().saturating_sub(1) {
             self.row += 1;
-            self.col = self.desired_col.min(buffer.line_len(self.row));
+            self.col = buffer.col_at_display(self.row, self.desired_col);
         }
     }
     
     /// 向左移动
-    pub fn move_left(&mut self) {
+    pub fn move_left(&mut self, buffer: &Buffer) {
         if self.col > 0 {
             self.col -= 1;
-            self.desired_col = self.col;
+            self.remember_col(buffer);
         }
     }
     
@@ -51,10 +52,15 @@ impl Cursor {
         let line_len = buffer.line_len(self.row);
         if self.col < line_len {
             self.col += 1;
-            self.desired_col = self.col;
+            self.remember_col(buffer);
         }
     }
     
+    /// 水平移动后记住当前的屏幕列
+    pub fn remember_col(&mut self, buffer: &Buffer) {
+        self.desired_col = buffer.display_col(self.row, self.col);
+    }
+    
     /// 移动到行首
     pub fn move_to_start_of_line(&mut self) {
         self.col = 0;
@@ -64,7 +70,7 @@ impl Cursor {
     /// 移动到行尾
     pub fn move_to_end_of_line(&mut self, buffer: &Buffer) {
         self.col = buffer.line_len(self.row);
-        self.desired_col = self.col;
+        self.remember_col(buffer);
     }
     
     /// 移动到文档开头
@@ -78,29 +84,22 @@ impl Cursor {
     pub fn move_to_end(&mut self, buffer: &Buffer) {
         self.row = buffer.line_count().saturating_sub(1);
         self.col = buffer.line_len(self.row);
-        self.desired_col = self.col;
+        self.remember_col(buffer);
     }
     
     /// 向前移动一个单词
     pub fn move_word_forward(&mut self, buffer: &Buffer) {
-        let line = buffer.get_line(self.row);
-        let len = line.len();
+        let len = buffer.line_len(self.row);
+        let row = self.row;
+        let is_space = |col: usize| buffer.grapheme(row, col).map_or(true, |g| g.trim().is_empty());
         
         // 跳过当前单词的剩余部分
-        while self.col < len {
-            let c = line.chars().nth(self.col).unwrap_or(' ');
-            if c.is_whitespace() {
-                break;
-            }
+        while self.col < len && !is_space(self.col) {
             self.col += 1;
         }
         
         // 跳过空白
-        while self.col < len {
-            let c = line.chars().nth(self.col).unwrap_or(' ');
-            if !c.is_whitespace() {
-                break;
-            }
+        while self.col < len && is_space(self.col) {
             self.col += 1;
         }
         
@@ -110,43 +109,63 @@ impl Cursor {
             self.col = 0;
         }
         
-        self.desired_col = self.col;
+        self.remember_col(buffer);
     }
     
     /// 向后移动一个单词
     pub fn move_word_backward(&mut self, buffer: &Buffer) {
-        let line = buffer.get_line(self.row);
-        
         if self.col == 0 {
             // 移动到上一行末尾
             if self.row > 0 {
                 self.row -= 1;
                 self.col = buffer.line_len(self.row);
+                self.remember_col(buffer);
             }
             return;
         }
         
+        let row = self.row;
+        let is_space = |col: usize| buffer.grapheme(row, col).map_or(true, |g| g.trim().is_empty());
         self.col -= 1;
         
         // 跳过空白
-        while self.col > 0 {
-            let c = line.chars().nth(self.col).unwrap_or(' ');
-            if !c.is_whitespace() {
-                break;
-            }
+        while self.col > 0 && is_space(self.col) {
             self.col -= 1;
         }
         
         // 跳过单词
-        while self.col > 0 {
-            let c = line.chars().nth(self.col - 1).unwrap_or(' ');
-            if c.is_whitespace() {
-                break;
-            }
+        while self.col > 0 && !is_space(self.col - 1) {
             self.col -= 1;
         }
         
-        self.desired_col = self.col;
+        self.remember_col(buffer);
+    }
+    
+    /// 移动到单词结尾
+    pub fn move_word_end(&mut self, buffer: &Buffer) {
+        let is_space = |row: usize, col: usize| buffer.grapheme(row, col).map_or(true, |g| g.trim().is_empty());
+        
+        // 至少前进一个字符，跳过空白（可以跨行）
+        self.col += 1;
+        while self.col >= buffer.line_len(self.row) || is_space(self.row, self.col) {
+            if self.col < buffer.line_len(self.row) {
+                self.col += 1;
+            } else if self.row + 1 < buffer.line_count() {
+                self.row += 1;
+                self.col = 0;
+            } else {
+                self.col = buffer.line_len(self.row).saturating_sub(1);
+                self.remember_col(buffer);
+                return;
+            }
+        }
+        
+        // 移到单词最后一个字符
+        while self.col + 1 < buffer.line_len(self.row) && !is_space(self.row, self.col + 1) {
+            self.col += 1;
+        }
+        
+        self.remember_col(buffer);
     }
     
     /// 确保光标在有效范围内
@@ -0,0 +1,71 @@
+//! 外部命令过滤
+//!
+//! `:[范围]!命令` 把范围内的行作为标准输入交给 `sh -c 命令`，用标准输出替换这些行，
+//! 如可视模式中的 `:'<,'>!sort`。没有范围时只运行命令并显示输出。
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use super::buffer::Buffer;
+use super::search;
+
+/// 一条 `:!` 命令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    /// 要过滤的首尾行，None 表示只运行命令
+    pub range: Option<(usize, usize)>,
+    pub command: String,
+}
+
+impl Filter {
+    /// 解析 `[范围]!命令`，不是 `:!` 命令时返回 None
+    pub fn parse(cmd: &str, buffer: &Buffer, cursor_row: usize, visual: Option<(usize, usize)>) -> Option<Result<Self, String>> {
+        let (range, rest) = search::parse_range(cmd, buffer, cursor_row, visual);
+        let command = rest.strip_prefix('!')?.trim();
+        if command.is_empty() {
+            return Some(Err("缺少命令".to_string()));
+        }
+        let range = if rest.len() == cmd.len() {
+            None
+        } else {
+            match range {
+                Ok(range) => Some(range),
+                Err(e) => return Some(Err(e)),
+            }
+        };
+        Some(Ok(Self { range, command: command.to_string() }))
+    }
+}
+
+/// 运行命令，`input` 作为标准输入，返回标准输出
+///
+/// 命令失败时返回标准错误的内容（或退出状态）。
+pub fn run(command: &str, input: &str) -> Result<String, String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("无法运行 {}: {}", command, e))?;
+
+    // 在另一个线程写入，避免输出较多时两边互相等待
+    let mut stdin = child.stdin.take().expect("stdin 已设置为管道");
+    let input = input.to_string();
+    let writer = std::thread::spawn(move || {
+        let _ = stdin.write_all(input.as_bytes());
+    });
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    let _ = writer.join();
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match stderr.lines().find(|l| !l.trim().is_empty()) {
+            Some(line) => Err(line.to_string()),
+            None => Err(format!("{} 失败: {}", command, output.status)),
+        }
+    }
+}
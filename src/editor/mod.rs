@@ -11,9 +11,50 @@ pub mod history;
 pub mod completion;
 pub mod context;
 pub mod nlp;
+pub mod undo;
+pub mod text;
+pub mod search;
+pub mod register;
+pub mod normal;
+pub mod syntax;
+pub mod visual;
+pub mod filter;
+pub mod buffers;
+pub mod window;
+pub mod swap;
+pub mod popup;
+pub mod shell;
 
+use std::collections::HashMap;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use normal::{Action, Command, Motion, Operator, Parse, Range, Target};
+use register::{Register, Registers};
+use search::{Match, Substitute};
+use syntax::Languages;
+use visual::{BlockInsert, Selection, Shape};
+use filter::Filter;
+use buffers::BufferList;
+use render::View;
+use window::{Direction, Windows};
+use swap::SwapFile;
+use popup::{Kind, Popup, PopupItem};
+
+use crate::completions::files::FileCompleter;
+use crate::engine::CompletionEngine;
+
+/// `>`/`<` 每次增减的缩进
+const INDENT: &str = "    ";
+
+/// 停止输入这么久之后写交换文件
+const SWAP_IDLE: Duration = Duration::from_secs(2);
+
+/// 积累了这么多次修改时不等停止输入就写交换文件
+const SWAP_CHANGES: u64 = 200;
 
 pub use buffer::Buffer;
 pub use cursor::Cursor;
@@ -51,10 +92,54 @@ pub struct Editor {
     should_quit: bool,
     /// 状态消息
     status_message: String,
-    /// 当前补全建议
-    current_suggestion: Option<String>,
+    /// 补全弹出菜单
+    popup: Option<Popup>,
+    /// 补全 shell 脚本中命令的主补全引擎，第一次用到时创建
+    shell_engine: Option<CompletionEngine>,
     /// 是否显示欢迎屏幕
     show_welcome_screen: bool,
+    /// 上一次搜索的模式
+    last_search: Option<Regex>,
+    /// 上一次搜索是否向前（`/`）
+    search_forward: bool,
+    /// 开始搜索时的光标位置，取消搜索时回到这里
+    search_origin: (usize, usize),
+    /// 输入搜索模式时的预览
+    search_preview: Option<(Regex, Match)>,
+    /// 是否高亮上一次搜索的匹配（`:noh` 关闭）
+    hlsearch: bool,
+    /// 等待确认的替换和当前匹配
+    substitute: Option<(Substitute, Match)>,
+    /// 已输入但还不完整的 Normal 模式命令
+    pending_keys: String,
+    /// 复制、删除的文本
+    registers: Registers,
+    /// 上一次修改（用于 `.`）和其中插入的文本
+    last_change: Option<(Command, String)>,
+    /// 正在进行的插入：进入插入模式的命令和已输入的文本
+    inserting: Option<(Command, String)>,
+    /// 语法高亮规则
+    languages: Languages,
+    /// 可视模式中选择固定的一端
+    visual_anchor: (usize, usize),
+    /// 上一次可视选择的首尾行（`'<`、`'>`）
+    last_visual: Option<(usize, usize)>,
+    /// 块选择中正在进行的插入
+    block_insert: Option<BlockInsert>,
+    /// 所有缓冲区，当前缓冲区的内容在 `buffer` 中
+    buffers: BufferList,
+    /// 窗口布局，当前窗口的光标在 `cursor` 中
+    windows: Windows,
+    /// 按了 Ctrl+W，等待窗口命令
+    window_pending: bool,
+    /// 命令行中的文件名补全：文件名之前的部分、候选项和当前显示的一项
+    path_completion: Option<(String, Vec<String>, usize)>,
+    /// 各缓冲区的交换文件，未命名的缓冲区没有
+    swaps: HashMap<usize, SwapFile>,
+    /// 交换文件所在的目录
+    swap_dir: PathBuf,
+    /// 上一次输入的时间，停止输入一段时间后写交换文件
+    last_input: Instant,
 }
 
 impl Editor {
@@ -62,6 +147,8 @@ impl Editor {
     pub fn new() -> io::Result<Self> {
         let history = HistoryManager::load();
         let completer = Completer::new();
+        let mut buffers = BufferList::new();
+        let number = buffers.add(Buffer::new(), None);
         
         Ok(Self {
             buffer: Buffer::new(),
@@ -76,8 +163,30 @@ impl Editor {
             modified: false,
             should_quit: false,
             status_message: String::new(),
-            current_suggestion: None,
+            popup: None,
+            shell_engine: None,
             show_welcome_screen: false,
+            last_search: None,
+            search_forward: true,
+            search_origin: (0, 0),
+            search_preview: None,
+            hlsearch: true,
+            substitute: None,
+            pending_keys: String::new(),
+            registers: Registers::new(),
+            last_change: None,
+            inserting: None,
+            languages: Languages::load(),
+            visual_anchor: (0, 0),
+            last_visual: None,
+            block_insert: None,
+            buffers,
+            windows: Windows::new(number),
+            window_pending: false,
+            path_completion: None,
+            swaps: HashMap::new(),
+            swap_dir: swap::swap_dir(),
+            last_input: Instant::now(),
         })
     }
     
@@ -87,6 +196,7 @@ impl Editor {
         self.file_path = Some(path.clone());
         self.cursor = Cursor::new();
         self.modified = false;
+        self.detect_language();
         
         // 加载文件特定历史
         self.history.load_file_history(path);
@@ -94,7 +204,9 @@ impl Editor {
         // 从当前文件内容构建补全数据
         self.completer.build_from_buffer(&self.buffer, &self.history);
         
-        self.status_message = format!("打开: {}", path.display());
+        self.swaps.insert(self.current_buffer(), SwapFile::new(&self.swap_dir, path));
+        self.status_message = self.swap_hint()
+            .unwrap_or_else(|| format!("打开: {}", path.display()));
         Ok(())
     }
     
@@ -102,7 +214,11 @@ impl Editor {
     pub fn save(&mut self) -> io::Result<()> {
         if let Some(ref path) = self.file_path {
             self.buffer.save_to_file(path)?;
+            self.buffer.mark_saved();
             self.modified = false;
+            if let Some(swap) = self.swaps.get_mut(&self.windows.active().buffer) {
+                swap.remove();
+            }
             
             // 更新历史
             self.history.update_from_buffer(&self.buffer);
@@ -117,15 +233,361 @@ impl Editor {
         }
     }
     
+    /// 编辑文件：已经打开时切换到它的缓冲区，否则在当前窗口中打开一个新的缓冲区
+    ///
+    /// 当前缓冲区是没有修改过的空白缓冲区时直接使用它。新文件按扩展名添加文件头。
+    pub fn edit(&mut self, path: &Path) -> io::Result<()> {
+        self.close_welcome_screen();
+        if let Some(number) = self.find_buffer(path) {
+            self.show_buffer(number);
+            return Ok(());
+        }
+        let blank = self.file_path.is_none() && !self.modified
+            && self.buffer.line_count() == 1 && self.buffer.get_line(0).is_empty();
+        if !blank {
+            let number = self.buffers.add(Buffer::new(), None);
+            self.show_buffer(number);
+        }
+        
+        let path = path.to_path_buf();
+        if path.exists() {
+            return self.open(&path);
+        }
+        self.file_path = Some(path.clone());
+        self.cursor = Cursor::new();
+        self.add_file_header(&path);
+        self.detect_language();
+        self.modified = true;
+        self.swaps.insert(self.current_buffer(), SwapFile::new(&self.swap_dir, &path));
+        self.status_message = self.swap_hint()
+            .unwrap_or_else(|| format!("新文件: {}", path.display()));
+        Ok(())
+    }
+    
+    /// 当前窗口显示的缓冲区编号
+    fn current_buffer(&self) -> usize {
+        self.windows.active().buffer
+    }
+    
+    /// 打开了 `path` 的缓冲区
+    fn find_buffer(&self, path: &Path) -> Option<usize> {
+        let current = self.current_buffer();
+        match self.file_path {
+            Some(ref p) if buffers::same_file(p, path) => Some(current),
+            _ => self.buffers.find(path).filter(|&number| number != current),
+        }
+    }
+    
+    /// 有未保存修改的其他缓冲区
+    fn hidden_modified(&self) -> Option<&buffers::Entry> {
+        let current = self.current_buffer();
+        self.buffers.iter().find(|e| e.number != current && e.modified)
+    }
+    
+    /// 在当前窗口中显示缓冲区，光标回到上次离开时的位置
+    fn show_buffer(&mut self, number: usize) {
+        if number != self.current_buffer() {
+            self.stash_buffer();
+            self.windows.active_mut().buffer = number;
+            self.load_buffer();
+        }
+        let name = buffers::display_name(self.file_path.as_deref());
+        self.status_message = self.swap_hint()
+            .unwrap_or_else(|| format!("\"{}\" {} 行", name, self.buffer.line_count()));
+    }
+    
+    /// 把当前缓冲区放回列表（切换缓冲区或窗口之前）
+    fn stash_buffer(&mut self) {
+        self.update_swap(true);
+        if let Some(ref path) = self.file_path {
+            self.history.save_file_history(path);
+        }
+        let number = self.current_buffer();
+        if let Some(entry) = self.buffers.get_mut(number) {
+            entry.buffer = std::mem::take(&mut self.buffer);
+            entry.path = self.file_path.clone();
+            entry.modified = self.modified;
+            entry.cursor = self.cursor.clone();
+            entry.last_visual = self.last_visual;
+        }
+    }
+    
+    /// 从列表中取出当前窗口的缓冲区，补全数据换成这个文件的
+    fn load_buffer(&mut self) {
+        let number = self.current_buffer();
+        if let Some(entry) = self.buffers.get_mut(number) {
+            self.buffer = std::mem::take(&mut entry.buffer);
+            self.file_path = entry.path.clone();
+            self.modified = entry.modified;
+            self.cursor = entry.cursor.clone();
+            self.last_visual = entry.last_visual;
+        }
+        if let Some(ref path) = self.file_path {
+            self.history.load_file_history(path);
+        }
+        self.completer.build_from_buffer(&self.buffer, &self.history);
+    }
+    
+    /// 切换到另一个窗口
+    fn switch_window(&mut self, id: usize) {
+        let target = match self.windows.get(id) {
+            Some(window) if id != self.windows.active().id => window.buffer,
+            _ => return,
+        };
+        self.windows.active_mut().cursor = self.cursor.clone();
+        let changed = target != self.current_buffer();
+        if changed {
+            self.stash_buffer();
+        }
+        self.windows.focus(id);
+        if changed {
+            self.load_buffer();
+        }
+        self.cursor = self.windows.active().cursor.clone();
+        self.cursor.clamp(&self.buffer);
+    }
+    
+    /// 分割当前窗口，有文件名时在新窗口中编辑它
+    fn split_window(&mut self, direction: Direction, file: &str) {
+        self.close_welcome_screen();
+        self.status_message.clear();
+        self.windows.active_mut().cursor = self.cursor.clone();
+        self.windows.split(direction);
+        if !file.is_empty() {
+            self.edit_command(file);
+        }
+    }
+    
+    /// 关闭当前窗口，缓冲区留在列表中；只剩一个窗口时返回 false
+    fn close_window(&mut self) -> bool {
+        if self.windows.len() == 1 {
+            return false;
+        }
+        self.stash_buffer();
+        self.windows.close();
+        self.load_buffer();
+        self.cursor = self.windows.active().cursor.clone();
+        self.cursor.clamp(&self.buffer);
+        self.status_message.clear();
+        true
+    }
+    
+    /// Ctrl+W 之后的窗口命令
+    fn window_command(&mut self, c: char) {
+        self.status_message.clear();
+        match c {
+            's' | 'S' => self.split_window(Direction::Horizontal, ""),
+            'v' => self.split_window(Direction::Vertical, ""),
+            'w' | 'W' => self.switch_window(self.windows.next(c == 'w')),
+            'h' | 'j' | 'k' | 'l' => {
+                if let Some(id) = self.renderer.area().ok().and_then(|area| self.windows.neighbor(area, c)) {
+                    self.switch_window(id);
+                }
+            }
+            'c' | 'q' => {
+                // 只剩一个窗口时 `q` 退出编辑器，`c` 不关闭
+                let closed = self.close_window();
+                if !closed && c == 'q' {
+                    self.quit();
+                } else if !closed {
+                    self.status_message = "不能关闭最后一个窗口".to_string();
+                }
+            }
+            'o' => self.windows.only(),
+            _ => {}
+        }
+    }
+    
+    /// 缓冲区和窗口命令（`:e`、`:ls`、`:b N`、`:bn`、`:sp` 等），不是这类命令时返回 false
+    fn window_command_line(&mut self, cmd: &str) -> bool {
+        let (name, arg) = match cmd.split_once(' ') {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (cmd, ""),
+        };
+        match name {
+            "e" | "edit" if arg.is_empty() => self.status_message = "缺少文件名".to_string(),
+            "e" | "edit" => self.edit_command(arg),
+            "ls" | "buffers" | "files" => self.status_message = self.buffer_list(),
+            "b" | "buffer" => match arg.parse() {
+                Ok(number) if self.buffers.get(number).is_some() => self.show_buffer(number),
+                _ => self.status_message = format!("没有缓冲区: {}", arg),
+            },
+            "bn" | "bnext" | "bp" | "bprevious" | "bN" | "bNext" => {
+                let number = self.buffers.next(self.current_buffer(), name.starts_with("bn"));
+                self.show_buffer(number);
+            }
+            "sp" | "split" => self.split_window(Direction::Horizontal, arg),
+            "vs" | "vsplit" => self.split_window(Direction::Vertical, arg),
+            "clo" | "close" => {
+                if !self.close_window() {
+                    self.status_message = "不能关闭最后一个窗口".to_string();
+                }
+            }
+            "on" | "only" => {
+                self.windows.only();
+                self.status_message.clear();
+            }
+            "qa" | "qall" => self.quit(),
+            "qa!" | "qall!" => self.should_quit = true,
+            "wa" | "wall" => self.save_all(),
+            _ => return false,
+        }
+        true
+    }
+    
+    /// `:e 文件名`：文件名可以带 shell 转义（补全的结果）和 `~`
+    fn edit_command(&mut self, arg: &str) {
+        let path = expand_home(&FileCompleter::unescape_shell_chars(arg));
+        if let Err(e) = self.edit(&path) {
+            self.status_message = format!("无法打开 {}: {}", path.display(), e);
+        }
+    }
+    
+    /// `:ls` 的内容：`%a` 为当前缓冲区，`a` 显示在其他窗口中，`h` 没有显示，`+` 有未保存的修改
+    fn buffer_list(&self) -> String {
+        let current = self.current_buffer();
+        self.buffers.iter()
+            .map(|entry| {
+                let (flag, name, modified) = if entry.number == current {
+                    ("%a", buffers::display_name(self.file_path.as_deref()), self.modified)
+                } else if self.windows.iter().any(|w| w.buffer == entry.number) {
+                    ("a", entry.name(), entry.modified)
+                } else {
+                    ("h", entry.name(), entry.modified)
+                };
+                format!("{} {} \"{}\"{}", entry.number, flag, name, if modified { " +" } else { "" })
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+    
+    /// 退出编辑器，有未保存的缓冲区时提示
+    fn quit(&mut self) {
+        if self.modified {
+            self.status_message = "文件已修改，使用 :q! 强制退出或 :wq 保存退出".to_string();
+        } else if let Some(entry) = self.hidden_modified() {
+            self.status_message = format!("缓冲区 {} \"{}\" 已修改，使用 :qa! 强制退出或 :wa 全部保存", entry.number, entry.name());
+        } else {
+            self.should_quit = true;
+        }
+    }
+    
+    /// 当前缓冲区有新的修改时写交换文件：停止输入一段时间后，或积累了较多修改时
+    ///
+    /// `force` 时只要有新的修改就写（离开这个缓冲区之前）。修改都已保存或撤销时删除交换文件。
+    fn update_swap(&mut self, force: bool) {
+        let swap = match self.swaps.get_mut(&self.windows.active().buffer) {
+            Some(swap) => swap,
+            None => return,
+        };
+        if !self.modified {
+            swap.remove();
+            return;
+        }
+        let pending = swap.pending(&self.buffer);
+        let due = force || pending >= SWAP_CHANGES || self.last_input.elapsed() >= SWAP_IDLE;
+        if swap.is_foreign() || pending == 0 || !due {
+            return;
+        }
+        if let Err(e) = swap.write(&self.buffer) {
+            self.status_message = format!("无法写交换文件 {}: {}", swap.path().display(), e);
+        }
+    }
+    
+    /// 当前文件打开时已经有交换文件时的提示
+    fn swap_hint(&self) -> Option<String> {
+        let swap = self.swaps.get(&self.current_buffer()).filter(|s| s.is_foreign())?;
+        Some(match swap.read() {
+            Some(recovered) if recovered.is_running() => {
+                format!("注意: 进程 {} 正在编辑这个文件", recovered.pid)
+            }
+            Some(recovered) => format!(
+                "发现交换文件（{}），:recover 恢复未保存的修改，:recover! 删除交换文件",
+                recovered.age()
+            ),
+            None => format!("无法读取交换文件 {}，:recover! 删除", swap.path().display()),
+        })
+    }
+    
+    /// `:recover` 用交换文件的内容替换当前缓冲区，`:recover!` 删除交换文件
+    fn recover(&mut self, discard: bool) {
+        let swap = match self.swaps.get_mut(&self.windows.active().buffer) {
+            Some(swap) if swap.is_foreign() => swap,
+            _ => {
+                self.status_message = "没有需要恢复的交换文件".to_string();
+                return;
+            }
+        };
+        let recovered = swap.read();
+        if let Some(recovered) = recovered.as_ref().filter(|r| r.is_running()) {
+            self.status_message = format!("进程 {} 正在编辑这个文件", recovered.pid);
+            return;
+        }
+        if discard {
+            self.status_message = match swap.discard() {
+                Ok(()) => "已删除交换文件".to_string(),
+                Err(e) => format!("无法删除交换文件: {}", e),
+            };
+            return;
+        }
+        let recovered = match recovered {
+            Some(recovered) => recovered,
+            None => {
+                self.status_message = format!("无法读取交换文件 {}", swap.path().display());
+                return;
+            }
+        };
+        swap.claim();
+        
+        // 整个替换作为一步撤销，`u` 回到文件原来的内容
+        self.buffer.begin_undo_group();
+        let last = self.buffer.line_count() - 1;
+        self.buffer.delete_range((0, 0), (last, self.buffer.line_len(last)));
+        self.buffer.insert_text(0, 0, &recovered.text);
+        self.buffer.end_undo_group();
+        self.cursor.clamp(&self.buffer);
+        self.modified = self.buffer.is_modified();
+        self.status_message = format!("已从交换文件恢复（{}），确认无误后 :w 保存", recovered.age());
+    }
+    
+    /// `:wa` 保存所有修改过的缓冲区
+    fn save_all(&mut self) {
+        let mut saved = 0;
+        let mut error = None;
+        if self.modified {
+            match self.save() {
+                Ok(()) => saved += 1,
+                Err(e) => error = Some(format!("保存失败: {}", e)),
+            }
+        }
+        let current = self.current_buffer();
+        for entry in self.buffers.iter_mut().filter(|e| e.number != current && e.modified) {
+            let result = match entry.path {
+                Some(ref path) => entry.buffer.save_to_file(path),
+                None => Err(io::Error::new(io::ErrorKind::Other, "没有文件名")),
+            };
+            match result {
+                Ok(()) => {
+                    entry.buffer.mark_saved();
+                    entry.modified = false;
+                    if let Some(swap) = self.swaps.get_mut(&entry.number) {
+                        swap.remove();
+                    }
+                    saved += 1;
+                }
+                Err(e) => error = Some(format!("保存失败: 缓冲区 {}: {}", entry.number, e)),
+            }
+        }
+        self.status_message = error.unwrap_or_else(|| format!("已保存 {} 个文件", saved));
+    }
+    
     /// 运行编辑器主循环
     pub fn run(&mut self) -> io::Result<()> {
         self.renderer.enter_alternate_screen()?;
         self.renderer.enable_raw_mode()?;
         
         // 初始渲染
-        self.renderer.render(&self.buffer, &self.cursor, &self.mode, 
-                              &self.status_message, &self.current_suggestion,
-                              self.show_welcome_screen)?;
+        self.render()?;
         
         loop {
             if self.should_quit {
@@ -136,11 +598,16 @@ impl Editor {
                 // 只有在有实际事件时才处理和重新渲染
                 if !matches!(event, input::EditorEvent::None) {
                     self.handle_event(event);
-                    self.renderer.render(&self.buffer, &self.cursor, &self.mode, 
-                                          &self.status_message, &self.current_suggestion,
-                                          self.show_welcome_screen)?;
+                    self.render()?;
+                    self.last_input = Instant::now();
                 }
             }
+            self.update_swap(false);
+        }
+        
+        // 正常退出时不再需要交换文件
+        for swap in self.swaps.values_mut() {
+            swap.remove();
         }
         
         self.renderer.disable_raw_mode()?;
@@ -149,10 +616,90 @@ impl Editor {
         Ok(())
     }
     
+    /// 渲染当前状态
+    fn render(&mut self) -> io::Result<()> {
+        // 替换确认中高亮当前匹配，输入搜索模式时高亮预览
+        let (highlight, current) = if let Some((ref sub, m)) = self.substitute {
+            (Some(sub.regex().clone()), Some(m))
+        } else if let Some((ref regex, m)) = self.search_preview {
+            (Some(regex.clone()), Some(m))
+        } else if self.hlsearch {
+            (self.last_search.clone(), None)
+        } else {
+            (None, None)
+        };
+        self.renderer.set_highlight(highlight, current);
+        self.renderer.set_selection(self.selection());
+        self.renderer.set_popup(self.popup.clone());
+        
+        // 当前窗口显示 `buffer` 和 `cursor`，其他窗口的缓冲区在列表中
+        let area = self.renderer.area()?;
+        let rects = self.windows.rects(area);
+        let active = self.windows.active().id;
+        let current = self.current_buffer();
+        let current_name = buffers::display_name(self.file_path.as_deref());
+        let (buffer, cursor, modified, list) = (&self.buffer, &self.cursor, self.modified, &self.buffers);
+        let mut views: Vec<View> = self.windows.iter_mut()
+            .filter_map(|window| {
+                let rect = rects.iter().find(|(id, _)| *id == window.id)?.1;
+                let is_active = window.id == active;
+                let cursor = if is_active { cursor } else { &window.cursor };
+                let (buffer, name, modified) = if window.buffer == current {
+                    (buffer, current_name.clone(), modified)
+                } else {
+                    let entry = list.get(window.buffer)?;
+                    (&entry.buffer, entry.name(), entry.modified)
+                };
+                Some(View {
+                    number: window.buffer,
+                    buffer,
+                    cursor,
+                    scroll: &mut window.scroll,
+                    rect,
+                    active: is_active,
+                    name,
+                    modified,
+                })
+            })
+            .collect();
+        self.renderer.render(&mut views, &self.mode,
+                             &self.status_message,
+                             self.show_welcome_screen)
+    }
+    
     /// 处理事件
     fn handle_event(&mut self, event: input::EditorEvent) {
         use input::EditorEvent::*;
         
+        // Ctrl+W 之后的一个按键是窗口命令
+        if self.window_pending {
+            self.window_pending = false;
+            if let Char(c) | Ctrl(c) = event {
+                self.window_command(c);
+            }
+            return;
+        }
+        
+        // 替换确认中只接受确认按键
+        if self.substitute.is_some() {
+            match event {
+                Char(c) => self.confirm_substitute(c),
+                Escape => self.confirm_substitute('q'),
+                _ => {}
+            }
+            return;
+        }
+        
+        // 补全菜单中上下选择，Tab 依次填入各项，→ 接受选中的一项
+        if self.popup.is_some() && matches!(self.mode, Mode::Insert) {
+            match event {
+                Up | Down => return self.select_popup(matches!(event, Down)),
+                Tab => return self.cycle_popup(),
+                Right => return self.accept_popup(),
+                _ => {}
+            }
+        }
+        
         match event {
             Char(c) => self.handle_char(c),
             Enter => self.handle_enter(),
@@ -172,15 +719,9 @@ impl Editor {
             None => {}
         }
         
-        // 更新补全建议（在 Insert 模式下实时更新）
+        // 更新补全菜单（在 Insert 模式下随输入过滤）
         if matches!(self.mode, Mode::Insert) {
-            // 对于字符输入事件，延迟更新建议（避免在输入时清除建议）
-            if !matches!(event, input::EditorEvent::Char(_)) {
-                self.update_suggestion();
-            } else {
-                // 字符输入后，延迟更新建议以允许上下文分析
-                self.update_suggestion();
-            }
+            self.update_suggestion();
         }
     }
     
@@ -190,82 +731,446 @@ impl Editor {
             Mode::Normal => self.handle_normal_char(c),
             Mode::Insert => self.handle_insert_char(c),
             Mode::Command => self.handle_command_char(c),
+            Mode::Search => {
+                self.buffer.command_buffer.push(c);
+                self.update_search_preview();
+            }
+            Mode::Visual | Mode::VisualLine | Mode::VisualBlock => self.handle_visual_char(c),
         }
     }
     
     /// Normal 模式字符处理
+    ///
+    /// 按键先交给命令解析器（次数、操作符、动作、寄存器），不是命令的按键再单独处理。
     fn handle_normal_char(&mut self, c: char) {
+        self.pending_keys.push(c);
+        match normal::parse(&self.pending_keys) {
+            Parse::Pending => self.status_message = self.pending_keys.clone(),
+            Parse::Complete(command) => {
+                if self.pending_keys.chars().count() > 1 {
+                    self.status_message.clear();
+                }
+                self.pending_keys.clear();
+                self.execute_normal(command);
+            }
+            Parse::Invalid => {
+                let keys = std::mem::take(&mut self.pending_keys);
+                if keys.chars().count() == 1 {
+                    self.handle_normal_key(c);
+                } else {
+                    self.status_message.clear();
+                }
+            }
+        }
+    }
+    
+    /// 不属于命令语法的 Normal 模式按键
+    fn handle_normal_key(&mut self, c: char) {
         match c {
-            'i' => {
-                self.close_welcome_screen();
-                self.mode = Mode::Insert;
-                self.status_message = "-- 插入 --".to_string();
+            'u' => self.undo(),
+            ':' => {
+                self.mode = Mode::Command;
+                self.buffer.command_buffer.clear();
+                self.status_message = ":".to_string();
             }
-            'a' => {
-                self.close_welcome_screen();
-                self.cursor.move_right(&self.buffer);
-                self.mode = Mode::Insert;
-                self.status_message = "-- 插入 --".to_string();
+            '/' | '?' => {
+                self.mode = Mode::Search;
+                self.search_forward = c == '/';
+                self.search_origin = (self.cursor.row, self.cursor.col);
+                self.buffer.command_buffer.clear();
+                self.status_message = c.to_string();
             }
-            'A' => {
-                self.close_welcome_screen();
-                self.cursor.move_to_end_of_line(&self.buffer);
-                self.mode = Mode::Insert;
-                self.status_message = "-- 插入 --".to_string();
+            'n' => self.search_next(self.search_forward),
+            'N' => self.search_next(!self.search_forward),
+            'v' => self.enter_visual(Shape::Chars),
+            'V' => self.enter_visual(Shape::Lines),
+            _ => {}
+        }
+    }
+    
+    /// 执行 Normal 模式命令
+    fn execute_normal(&mut self, command: Command) {
+        match command.action {
+            Action::Move(motion) => self.move_by(motion, command.count),
+            Action::Operate(op, target) => self.operate(command, op, target),
+            Action::Put { before } => self.put(command.register, before, command.count()),
+            Action::Insert(key) => self.enter_insert(key),
+            Action::Repeat => {
+                self.repeat_change(command.count);
+                return;
             }
-            'I' => {
-                self.close_welcome_screen();
-                self.cursor.move_to_start_of_line();
-                self.mode = Mode::Insert;
-                self.status_message = "-- 插入 --".to_string();
+        }
+        
+        if command.is_change() {
+            if self.mode.is_insert() {
+                // 插入模式结束时连同输入的文本一起记下
+                self.inserting = Some((command, String::new()));
+            } else {
+                self.last_change = Some((command, String::new()));
+            }
+        }
+    }
+    
+    /// 按动作移动光标
+    fn move_by(&mut self, motion: Motion, count: Option<usize>) {
+        let n = count.unwrap_or(1);
+        match motion {
+            Motion::Up => (0..n).for_each(|_| self.cursor.move_up(&self.buffer)),
+            Motion::Down => (0..n).for_each(|_| self.cursor.move_down(&self.buffer)),
+            motion => {
+                let pos = (self.cursor.row, self.cursor.col);
+                let (row, col) = normal::motion_target(&self.buffer, pos, motion, count);
+                self.move_cursor_to(row, col);
             }
+        }
+    }
+    
+    /// 进入插入模式（i、a、A、I、o、O）
+    fn enter_insert(&mut self, key: char) {
+        self.close_welcome_screen();
+        self.buffer.begin_undo_group();
+        match key {
+            'a' => self.cursor.move_right(&self.buffer),
+            'A' => self.cursor.move_to_end_of_line(&self.buffer),
+            'I' => self.cursor.move_to_start_of_line(),
             'o' => {
-                self.close_welcome_screen();
                 self.cursor.move_to_end_of_line(&self.buffer);
                 self.buffer.insert_newline(self.cursor.row, self.cursor.col);
                 self.cursor.row += 1;
                 self.cursor.col = 0;
-                self.mode = Mode::Insert;
                 self.modified = true;
-                self.status_message = "-- 插入 --".to_string();
             }
             'O' => {
-                self.close_welcome_screen();
                 self.buffer.insert_line_above(self.cursor.row);
                 self.cursor.col = 0;
-                self.mode = Mode::Insert;
                 self.modified = true;
-                self.status_message = "-- 插入 --".to_string();
-            }
-            'h' => self.cursor.move_left(),
-            'j' => self.cursor.move_down(&self.buffer),
-            'k' => self.cursor.move_up(),
-            'l' => self.cursor.move_right(&self.buffer),
-            'x' => {
-                if self.buffer.delete_char(self.cursor.row, self.cursor.col) {
-                    self.modified = true;
+            }
+            _ => {}
+        }
+        self.mode = Mode::Insert;
+        self.status_message = "-- 插入 --".to_string();
+    }
+    
+    /// 执行操作符
+    fn operate(&mut self, command: Command, op: Operator, target: Target) {
+        let pos = (self.cursor.row, self.cursor.col);
+        if let Some(range) = normal::target_range(&self.buffer, pos, op, target, command.count) {
+            self.apply_operator(command.register, op, range, pos);
+        }
+    }
+    
+    /// 对范围执行操作符，`pos` 为执行前的光标位置
+    fn apply_operator(&mut self, register_name: Option<char>, op: Operator, range: Range, pos: (usize, usize)) {
+        let (first, last) = (range.start.0, range.end.0);
+        let line_end = (last, self.buffer.line_len(last));
+        let register = if range.linewise {
+            Register::new(format!("{}\n", self.buffer.text_range((first, 0), line_end)), true)
+        } else {
+            Register::new(self.buffer.text_range(range.start, range.end), false)
+        };
+        
+        match op {
+            Operator::Yank => {
+                let lines = last - first + 1;
+                self.registers.yank(register_name, register);
+                if range.linewise {
+                    self.move_cursor_to(first, if first == pos.0 { pos.1 } else { 0 });
+                    if lines > 1 {
+                        self.status_message = format!("复制了 {} 行", lines);
+                    }
+                } else {
+                    self.move_cursor_to(range.start.0, range.start.1);
+                }
+            }
+            Operator::Delete => {
+                self.buffer.begin_undo_group();
+                if range.linewise {
+                    self.buffer.delete_lines(first, last);
+                } else {
+                    self.buffer.delete_range(range.start, range.end);
+                }
+                self.buffer.end_undo_group();
+                self.registers.delete(register_name, register);
+                if range.linewise {
+                    let row = first.min(self.buffer.line_count() - 1);
+                    self.move_cursor_to(row, normal::first_non_blank(&self.buffer, row));
+                } else {
+                    self.move_cursor_to(range.start.0, range.start.1);
                 }
             }
-            'd' => {
-                // 简单实现：dd 删除行
-                self.status_message = "按 d 删除行".to_string();
+            Operator::Change => {
+                // 删除和之后的输入作为一步撤销，插入模式结束时合并
+                self.buffer.begin_undo_group();
+                let start = if range.linewise { (first, 0) } else { range.start };
+                let end = if range.linewise { line_end } else { range.end };
+                self.buffer.delete_range(start, end);
+                self.registers.delete(register_name, register);
+                self.move_cursor_to(start.0, start.1);
+                self.enter_insert('i');
             }
-            'G' => self.cursor.move_to_end(&self.buffer),
-            'g' => {
-                // gg 移到开头
-                self.status_message = "按 g 到开头".to_string();
+            Operator::Indent | Operator::Outdent => self.shift_lines(first, last, op, 1),
+            Operator::Comment => {
+                let marker = self.comment_marker();
+                self.buffer.begin_undo_group();
+                self.buffer.toggle_comment(first, last, &marker);
+                self.buffer.end_undo_group();
+                self.move_cursor_to(first, normal::first_non_blank(&self.buffer, first));
             }
-            '0' => self.cursor.move_to_start_of_line(),
-            '$' => self.cursor.move_to_end_of_line(&self.buffer),
-            ':' => {
+        }
+        self.modified = self.buffer.is_modified();
+    }
+    
+    /// 增减 `first` 到 `last` 行的缩进 `times` 次
+    fn shift_lines(&mut self, first: usize, last: usize, op: Operator, times: usize) {
+        self.buffer.begin_undo_group();
+        for row in first..=last {
+            for _ in 0..times {
+                let line = self.buffer.get_line(row);
+                if op == Operator::Indent {
+                    if !line.is_empty() {
+                        self.buffer.insert_text(row, 0, INDENT);
+                    }
+                } else {
+                    let spaces = line.len() - line.trim_start_matches(' ').len();
+                    let width = if line.starts_with('\t') { 1 } else { spaces.min(INDENT.len()) };
+                    self.buffer.delete_range((row, 0), (row, width));
+                }
+            }
+        }
+        self.buffer.end_undo_group();
+        self.move_cursor_to(first, normal::first_non_blank(&self.buffer, first));
+        self.modified = self.buffer.is_modified();
+    }
+    
+    /// 当前语言的行注释符，没有识别出语言时为 `#`
+    fn comment_marker(&self) -> String {
+        self.renderer.language(self.current_buffer())
+            .and_then(|l| l.line_comment.first().cloned())
+            .unwrap_or_else(|| "#".to_string())
+    }
+    
+    /// 粘贴寄存器的内容
+    fn put(&mut self, register: Option<char>, before: bool, count: usize) {
+        let register = match self.registers.get(register) {
+            Some(register) => register.clone(),
+            None => {
+                self.status_message = "寄存器为空".to_string();
+                return;
+            }
+        };
+        let text = register.text.repeat(count);
+        let row = self.cursor.row;
+        
+        self.buffer.begin_undo_group();
+        if register.linewise {
+            // 整行粘贴到当前行的上方或下方
+            let lines = text.strip_suffix('\n').unwrap_or(&text);
+            let target = if before {
+                self.buffer.insert_text(row, 0, &format!("{}\n", lines));
+                row
+            } else {
+                self.buffer.insert_text(row, self.buffer.line_len(row), &format!("\n{}", lines));
+                row + 1
+            };
+            self.move_cursor_to(target, normal::first_non_blank(&self.buffer, target));
+        } else {
+            let col = if before || self.buffer.line_len(row) == 0 { self.cursor.col } else { self.cursor.col + 1 };
+            let (end_row, end_col) = self.buffer.insert_text(row, col, &text);
+            // 光标停在粘贴的最后一个字符上
+            self.move_cursor_to(end_row, end_col.saturating_sub(1));
+        }
+        self.buffer.end_undo_group();
+        self.modified = self.buffer.is_modified();
+    }
+    
+    /// `.` 重复上一次修改，有次数时替换原来的次数
+    fn repeat_change(&mut self, count: Option<usize>) {
+        let (mut command, text) = match self.last_change {
+            Some((command, ref text)) => (command, text.clone()),
+            None => return,
+        };
+        if count.is_some() {
+            command.count = count;
+        }
+        self.execute_normal(command);
+        if self.mode.is_insert() {
+            let (row, col) = self.buffer.insert_text(self.cursor.row, self.cursor.col, &text);
+            self.move_cursor_to(row, col);
+            if let Some((_, ref mut inserted)) = self.inserting {
+                inserted.push_str(&text);
+            }
+            self.handle_escape();
+        }
+    }
+    
+    /// 记录插入模式中输入的文本（用于 `.` 重复）
+    fn record_insert(&mut self, text: &str) {
+        if let Some((_, ref mut inserted)) = self.inserting {
+            inserted.push_str(text);
+        }
+    }
+    
+    /// 进入可视模式，选择从光标处开始
+    fn enter_visual(&mut self, shape: Shape) {
+        self.close_welcome_screen();
+        self.visual_anchor = (self.cursor.row, self.cursor.col);
+        self.set_visual_shape(shape);
+    }
+    
+    /// 切换选择的形状
+    fn set_visual_shape(&mut self, shape: Shape) {
+        self.mode = shape.mode();
+        self.status_message = match shape {
+            Shape::Chars => "-- 可视 --",
+            Shape::Lines => "-- 可视 行 --",
+            Shape::Block => "-- 可视 块 --",
+        }.to_string();
+    }
+    
+    /// 当前选择，不在可视模式时为 None
+    fn selection(&self) -> Option<Selection> {
+        Shape::of(self.mode).map(|shape| Selection {
+            anchor: self.visual_anchor,
+            cursor: (self.cursor.row, self.cursor.col),
+            shape,
+        })
+    }
+    
+    /// 退出可视模式，记下选择的行（用于 `'<,'>`）
+    fn exit_visual(&mut self) {
+        if let Some(selection) = self.selection() {
+            self.last_visual = Some(selection.rows());
+        }
+        self.mode = Mode::Normal;
+        self.pending_keys.clear();
+        self.status_message.clear();
+    }
+    
+    /// Visual 模式字符处理
+    fn handle_visual_char(&mut self, c: char) {
+        self.pending_keys.push(c);
+        match visual::parse(&self.pending_keys) {
+            visual::Parse::Pending => self.status_message = self.pending_keys.clone(),
+            visual::Parse::Complete(command) => {
+                self.pending_keys.clear();
+                self.execute_visual(command);
+            }
+            visual::Parse::Invalid => self.pending_keys.clear(),
+        }
+    }
+    
+    /// 执行可视模式命令
+    fn execute_visual(&mut self, command: visual::Command) {
+        let selection = match self.selection() {
+            Some(selection) => selection,
+            None => return,
+        };
+        match command.action {
+            visual::Action::Move(motion) => {
+                self.move_by(motion, command.count);
+                self.set_visual_shape(selection.shape);
+            }
+            visual::Action::SwapEnds => {
+                let (row, col) = std::mem::replace(&mut self.visual_anchor, selection.cursor);
+                self.move_cursor_to(row, col);
+            }
+            visual::Action::Switch(shape) if shape == selection.shape => self.exit_visual(),
+            visual::Action::Switch(shape) => self.set_visual_shape(shape),
+            visual::Action::Command => {
+                self.exit_visual();
                 self.mode = Mode::Command;
-                self.buffer.command_buffer.clear();
-                self.status_message = ":".to_string();
+                self.buffer.command_buffer = "'<,'>".to_string();
+                self.status_message = ":'<,'>".to_string();
+            }
+            visual::Action::Insert(key) => {
+                self.exit_visual();
+                self.visual_insert(selection, key);
             }
-            'w' => self.cursor.move_word_forward(&self.buffer),
-            'b' => self.cursor.move_word_backward(&self.buffer),
+            visual::Action::Operate(op) => {
+                self.exit_visual();
+                let (first, last) = selection.rows();
+                match op {
+                    Operator::Indent | Operator::Outdent => self.shift_lines(first, last, op, command.count.unwrap_or(1)),
+                    Operator::Delete | Operator::Change | Operator::Yank if selection.shape == Shape::Block => {
+                        self.block_operate(selection, command.register, op);
+                    }
+                    _ => self.apply_operator(command.register, op, selection.range(&self.buffer), selection.start()),
+                }
+            }
+        }
+    }
+    
+    /// 对块选择执行删除、修改或复制
+    fn block_operate(&mut self, selection: Selection, register_name: Option<char>, op: Operator) {
+        let (first, last) = selection.rows();
+        let (left, _) = selection.block_columns(&self.buffer);
+        let columns: Vec<(usize, Option<(usize, usize)>)> = (first..=last)
+            .map(|row| (row, selection.columns(&self.buffer, row)))
+            .collect();
+        // 每行选中的部分作为一行
+        let text = columns.iter()
+            .map(|&(row, cols)| cols.map_or(String::new(), |(start, end)| self.buffer.text_range((row, start), (row, end))))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let register = Register::new(text, false);
+        
+        if op == Operator::Yank {
+            self.registers.yank(register_name, register);
+        } else {
+            self.buffer.begin_undo_group();
+            for &(row, cols) in &columns {
+                if let Some((start, end)) = cols {
+                    self.buffer.delete_range((row, start), (row, end));
+                }
+            }
+            self.registers.delete(register_name, register);
+        }
+        let col = self.buffer.col_at_display(first, left);
+        self.move_cursor_to(first, col);
+        
+        match op {
+            Operator::Change => {
+                self.block_insert = Some(BlockInsert { start: (first, col), last, x: left, pad: false });
+                self.enter_insert('i');
+            }
+            Operator::Delete => self.buffer.end_undo_group(),
             _ => {}
         }
+        self.modified = self.buffer.is_modified();
+    }
+    
+    /// 可视模式中的 `I`/`A`：在选择之前/之后插入，块选择时插入到每一行
+    fn visual_insert(&mut self, selection: Selection, key: char) {
+        let (first, last) = selection.rows();
+        match selection.shape {
+            Shape::Block => {
+                let (left, right) = selection.block_columns(&self.buffer);
+                let (x, pad) = if key == 'I' { (left, false) } else { (right, true) };
+                self.buffer.begin_undo_group();
+                let width = text::str_width(self.buffer.get_line(first));
+                if pad && width < x {
+                    self.buffer.append_to_line(first, &" ".repeat(x - width));
+                }
+                let col = self.buffer.col_at_display(first, x);
+                self.move_cursor_to(first, col);
+                self.block_insert = Some(BlockInsert { start: (first, col), last, x, pad });
+                self.enter_insert('i');
+            }
+            Shape::Lines => {
+                if key == 'I' {
+                    self.move_cursor_to(first, normal::first_non_blank(&self.buffer, first));
+                } else {
+                    self.move_cursor_to(last, 0);
+                }
+                self.enter_insert(key);
+            }
+            Shape::Chars => {
+                let (row, col) = if key == 'I' { selection.start() } else { selection.end() };
+                let col = if key == 'I' { col } else { col + 1 };
+                self.move_cursor_to(row, col);
+                self.enter_insert('i');
+            }
+        }
     }
     
     /// Insert 模式字符处理
@@ -276,15 +1181,9 @@ impl Editor {
             self.learn_current_word();
         }
         
-        self.buffer.insert_char(self.cursor.row, self.cursor.col, c);
-        self.cursor.col += 1;
+        self.cursor.col = self.buffer.insert_char(self.cursor.row, self.cursor.col, c);
         self.modified = true;
-        
-        // 输入字符后不清除建议，让 update_suggestion 根据新输入更新建议
-        // 只有在输入空格或标点时，才清除建议（因为已经完成了一个词）
-        if c.is_whitespace() || c == ';' || c == ':' || c == ',' {
-            self.current_suggestion = None;
-        }
+        self.record_insert(c.encode_utf8(&mut [0; 4]));
     }
     
     /// 学习当前光标前的词
@@ -294,18 +1193,11 @@ impl Editor {
             return;
         }
         
-        let prefix = &line[..self.cursor.col.min(line.len())];
-        
-        // 找到当前词的开始位置
-        let word_start = prefix.rfind(|c: char| c.is_whitespace() || c == '(' || c == '{' || c == '[')
-            .map(|i| i + 1)
-            .unwrap_or(0);
+        let prefix = self.buffer.prefix(self.cursor.row, self.cursor.col);
         
-        if word_start < prefix.len() {
-            let word = &prefix[word_start..];
-            if word.len() >= 2 {
-                self.completer.learn_word(word);
-            }
+        let word = text::word_before(prefix, |c| c.is_whitespace() || c == '(' || c == '{' || c == '[');
+        if word.len() >= 2 {
+            self.completer.learn_word(word);
         }
     }
     
@@ -326,13 +1218,14 @@ impl Editor {
                 self.cursor.row += 1;
                 self.cursor.col = 0;
                 self.modified = true;
-                self.current_suggestion = None;
+                self.record_insert("\n");
             }
             Mode::Command => {
-                self.execute_command();
                 self.mode = Mode::Normal;
+                self.execute_command();
             }
-            Mode::Normal => {}
+            Mode::Search => self.finish_search(),
+            Mode::Normal | Mode::Visual | Mode::VisualLine | Mode::VisualBlock => {}
         }
     }
     
@@ -348,26 +1241,235 @@ impl Editor {
                 }
             }
             "q" => {
-                if self.modified {
-                    self.status_message = "文件已修改，使用 :q! 强制退出或 :wq 保存退出".to_string();
-                } else {
-                    self.should_quit = true;
+                if !self.close_window() {
+                    self.quit();
                 }
             }
             "q!" => {
-                self.should_quit = true;
+                if !self.close_window() {
+                    self.should_quit = true;
+                }
+            }
+            "u" | "undo" => self.undo(),
+            "rec" | "recover" => self.recover(false),
+            "rec!" | "recover!" => self.recover(true),
+            "red" | "redo" => self.redo(),
+            "noh" | "nohlsearch" => {
+                self.hlsearch = false;
+                self.status_message.clear();
             }
             "wq" | "x" => {
-                if self.save().is_ok() {
-                    self.should_quit = true;
+                if self.save().is_ok() && !self.close_window() {
+                    self.quit();
                 }
             }
+            _ if self.window_command_line(&cmd) => {}
             _ => {
-                self.status_message = format!("未知命令: {}", cmd);
+                let (row, visual) = (self.cursor.row, self.last_visual);
+                if let Some(filter) = Filter::parse(&cmd, &self.buffer, row, visual) {
+                    match filter {
+                        Ok(filter) => self.run_filter(filter),
+                        Err(e) => self.status_message = e,
+                    }
+                    return;
+                }
+                match Substitute::parse(&cmd, &self.buffer, row, visual, self.last_search.as_ref()) {
+                    Some(Ok(substitute)) => self.start_substitute(substitute),
+                    Some(Err(e)) => self.status_message = e,
+                    None => self.status_message = format!("未知命令: {}", cmd),
+                }
             }
         }
     }
     
+    /// 执行 `:!` 命令，有范围时用命令的输出替换这些行
+    fn run_filter(&mut self, filter: Filter) {
+        let (first, last) = match filter.range {
+            Some(range) => range,
+            None => {
+                self.status_message = match filter::run(&filter.command, "") {
+                    Ok(output) => output.lines().rev()
+                        .find(|l| !l.trim().is_empty())
+                        .unwrap_or("命令已完成")
+                        .to_string(),
+                    Err(e) => e,
+                };
+                return;
+            }
+        };
+        
+        let line_end = (last, self.buffer.line_len(last));
+        let input = format!("{}\n", self.buffer.text_range((first, 0), line_end));
+        match filter::run(&filter.command, &input) {
+            Ok(output) => {
+                self.buffer.begin_undo_group();
+                if output.is_empty() {
+                    self.buffer.delete_lines(first, last);
+                } else {
+                    self.buffer.delete_range((first, 0), line_end);
+                    self.buffer.insert_text(first, 0, output.strip_suffix('\n').unwrap_or(&output));
+                }
+                self.buffer.end_undo_group();
+                let row = first.min(self.buffer.line_count() - 1);
+                self.move_cursor_to(row, normal::first_non_blank(&self.buffer, row));
+                self.modified = self.buffer.is_modified();
+                self.status_message = format!("{} 行已过滤", last - first + 1);
+            }
+            Err(e) => self.status_message = e,
+        }
+    }
+    
+    /// 输入搜索模式时，从开始位置查找并预览第一处匹配
+    fn update_search_preview(&mut self) {
+        let prompt = if self.search_forward { '/' } else { '?' };
+        let pattern = self.buffer.command_buffer.clone();
+        self.status_message = format!("{}{}", prompt, pattern);
+        self.search_preview = None;
+        
+        let (row, col) = self.search_origin;
+        self.move_cursor_to(row, col);
+        if pattern.is_empty() {
+            return;
+        }
+        if let Ok(regex) = search::compile(&pattern, false) {
+            let byte = self.buffer.byte_offset(row, col);
+            if let Some((m, _)) = search::find(&self.buffer, &regex, row, byte, self.search_forward) {
+                self.move_to_match(m.row, m.start);
+                self.search_preview = Some((regex, m));
+            }
+        }
+    }
+    
+    /// 按 Enter 结束搜索；模式为空时重复上一次搜索
+    fn finish_search(&mut self) {
+        let pattern = std::mem::take(&mut self.buffer.command_buffer);
+        self.mode = Mode::Normal;
+        self.search_preview = None;
+        let (row, col) = self.search_origin;
+        self.move_cursor_to(row, col);
+        
+        if !pattern.is_empty() {
+            match search::compile(&pattern, false) {
+                Ok(regex) => self.last_search = Some(regex),
+                Err(e) => {
+                    self.status_message = e;
+                    return;
+                }
+            }
+        }
+        self.search_next(self.search_forward);
+    }
+    
+    /// 取消搜索，光标回到开始位置
+    fn cancel_search(&mut self) {
+        self.mode = Mode::Normal;
+        self.search_preview = None;
+        self.buffer.command_buffer.clear();
+        self.status_message.clear();
+        let (row, col) = self.search_origin;
+        self.move_cursor_to(row, col);
+    }
+    
+    /// 查找上一次搜索模式的下一处（`forward` 为 false 时上一处）匹配
+    fn search_next(&mut self, forward: bool) {
+        let regex = match self.last_search.clone() {
+            Some(regex) => regex,
+            None => {
+                self.status_message = "没有上一次搜索的模式".to_string();
+                return;
+            }
+        };
+        self.hlsearch = true;
+        
+        let byte = self.buffer.byte_offset(self.cursor.row, self.cursor.col);
+        match search::find(&self.buffer, &regex, self.cursor.row, byte, forward) {
+            Some((m, wrapped)) => {
+                self.move_to_match(m.row, m.start);
+                self.status_message = match (wrapped, forward) {
+                    (true, true) => "已到文件末尾，从开头继续搜索".to_string(),
+                    (true, false) => "已到文件开头，从末尾继续搜索".to_string(),
+                    (false, _) => format!("{}{}", if forward { '/' } else { '?' }, regex.as_str()),
+                };
+            }
+            None => self.status_message = format!("未找到: {}", regex.as_str()),
+        }
+    }
+    
+    /// 开始替换；带 `c` 标志时逐个确认
+    fn start_substitute(&mut self, mut substitute: Substitute) {
+        self.last_search = Some(substitute.regex().clone());
+        self.hlsearch = true;
+        self.buffer.begin_undo_group();
+        
+        if !substitute.confirm {
+            if let Some((row, byte)) = substitute.run(&mut self.buffer) {
+                self.move_to_match(row, byte);
+            }
+            self.finish_substitute(substitute);
+            return;
+        }
+        match substitute.next_match(&self.buffer) {
+            Some(m) => {
+                self.move_to_match(m.row, m.start);
+                self.substitute = Some((substitute, m));
+                self.status_message = "替换这一处? (y=是 n=否 a=全部 q=退出 l=替换后退出)".to_string();
+            }
+            None => self.finish_substitute(substitute),
+        }
+    }
+    
+    /// 处理替换确认的按键
+    fn confirm_substitute(&mut self, c: char) {
+        let (mut substitute, m) = match self.substitute.take() {
+            Some(pending) => pending,
+            None => return,
+        };
+        match c {
+            'y' | 'l' => {
+                substitute.replace(&mut self.buffer, m);
+            }
+            'n' => substitute.skip(m),
+            'a' => {
+                substitute.replace(&mut self.buffer, m);
+                if let Some((row, byte)) = substitute.run(&mut self.buffer) {
+                    self.move_to_match(row, byte);
+                }
+            }
+            'q' => {}
+            _ => {
+                // 其他按键忽略，继续等待
+                self.substitute = Some((substitute, m));
+                return;
+            }
+        }
+        
+        let next = if matches!(c, 'y' | 'n') { substitute.next_match(&self.buffer) } else { None };
+        match next {
+            Some(next) => {
+                self.move_to_match(next.row, next.start);
+                self.substitute = Some((substitute, next));
+            }
+            None => self.finish_substitute(substitute),
+        }
+    }
+    
+    /// 结束替换，整个替换作为一步撤销
+    fn finish_substitute(&mut self, substitute: Substitute) {
+        self.buffer.end_undo_group();
+        self.modified = self.buffer.is_modified();
+        self.status_message = if substitute.count == 0 {
+            format!("未找到: {}", substitute.regex().as_str())
+        } else {
+            format!("替换了 {} 处", substitute.count)
+        };
+    }
+    
+    /// 把光标移到匹配位置（列为字节偏移）
+    fn move_to_match(&mut self, row: usize, byte: usize) {
+        let col = text::grapheme_col(self.buffer.get_line(row), byte);
+        self.move_cursor_to(row, col);
+    }
+    
     /// 处理 Backspace
     fn handle_backspace(&mut self) {
         if matches!(self.mode, Mode::Command) {
@@ -376,6 +1478,16 @@ impl Editor {
             return;
         }
         
+        if matches!(self.mode, Mode::Search) {
+            // 删光模式后再按退格取消搜索
+            if self.buffer.command_buffer.pop().is_some() {
+                self.update_search_preview();
+            } else {
+                self.cancel_search();
+            }
+            return;
+        }
+        
         if !matches!(self.mode, Mode::Insert) {
             return;
         }
@@ -384,6 +1496,9 @@ impl Editor {
             self.cursor.col -= 1;
             self.buffer.delete_char(self.cursor.row, self.cursor.col);
             self.modified = true;
+            if let Some((_, ref mut inserted)) = self.inserting {
+                inserted.pop();
+            }
         } else if self.cursor.row > 0 {
             // 合并到上一行
             let current_line = self.buffer.get_line(self.cursor.row).to_string();
@@ -393,7 +1508,6 @@ impl Editor {
             self.buffer.append_to_line(self.cursor.row, &current_line);
             self.modified = true;
         }
-        self.current_suggestion = None;
     }
     
     /// 处理 Delete
@@ -405,44 +1519,105 @@ impl Editor {
         }
     }
     
-    /// 处理 Tab - 接受补全
+    /// 处理 Tab：命令行中补全文件名，插入模式中没有补全菜单时缩进
     fn handle_tab(&mut self) {
+        if matches!(self.mode, Mode::Command) {
+            self.complete_command_path();
+            return;
+        }
         if matches!(self.mode, Mode::Insert) {
-            if let Some(ref suggestion) = self.current_suggestion.clone() {
-                // 插入补全内容
-                for c in suggestion.chars() {
-                    self.buffer.insert_char(self.cursor.row, self.cursor.col, c);
-                    self.cursor.col += 1;
-                }
-                self.modified = true;
-                self.current_suggestion = None;
-            } else {
-                // 没有建议时插入制表符（4个空格）
-                for _ in 0..4 {
-                    self.buffer.insert_char(self.cursor.row, self.cursor.col, ' ');
-                    self.cursor.col += 1;
+            // 插入制表符（4个空格）
+            for _ in 0..4 {
+                self.cursor.col = self.buffer.insert_char(self.cursor.row, self.cursor.col, ' ');
+            }
+            self.modified = true;
+            self.record_insert(INDENT);
+        }
+    }
+    
+    /// 命令行中 `:e`、`:sp`、`:vs` 的文件名补全
+    ///
+    /// 只有一个候选项时直接补全；有多个时先补全到公共前缀，不能再补全时每按一次 Tab
+    /// 换一个候选项。
+    fn complete_command_path(&mut self) {
+        let line = self.buffer.command_buffer.clone();
+        
+        // 命令行还是上一次补全的结果时换下一个候选项
+        if let Some((base, candidates, index)) = self.path_completion.take() {
+            if line == format!("{}{}", base, candidates[index]) {
+                let index = (index + 1) % candidates.len();
+                self.set_command_line(format!("{}{}", base, candidates[index]));
+                self.path_completion = Some((base, candidates, index));
+                return;
+            }
+        }
+        
+        let (name, rest) = match line.split_once(' ') {
+            Some(split) => split,
+            None => return,
+        };
+        if !matches!(name, "e" | "edit" | "sp" | "split" | "vs" | "vsplit") {
+            return;
+        }
+        let arg = rest.trim_start();
+        let base = line[..line.len() - arg.len()].to_string();
+        let mut candidates: Vec<String> = FileCompleter::new().complete(arg)
+            .into_iter()
+            .map(|c| c.text)
+            .collect();
+        candidates.sort();
+        
+        match candidates.len() {
+            0 => {}
+            1 => self.set_command_line(format!("{}{}", base, candidates[0])),
+            _ => {
+                let common = common_prefix(&candidates);
+                if common.len() > arg.len() {
+                    self.set_command_line(format!("{}{}", base, common));
+                } else {
+                    self.set_command_line(format!("{}{}", base, candidates[0]));
+                    self.path_completion = Some((base, candidates, 0));
                 }
-                self.modified = true;
             }
         }
     }
     
+    /// 替换命令行的内容
+    fn set_command_line(&mut self, line: String) {
+        self.status_message = format!(":{}", line);
+        self.buffer.command_buffer = line;
+    }
+    
     /// 处理 Escape
     fn handle_escape(&mut self) {
         match self.mode {
             Mode::Insert | Mode::Command => {
+                // 块选择的插入复制到其余各行，与输入合并为一步撤销
+                if let Some(block) = self.block_insert.take() {
+                    block.apply(&mut self.buffer, (self.cursor.row, self.cursor.col));
+                    self.modified = self.buffer.is_modified();
+                }
+                self.buffer.end_undo_group();
+                if let Some(change) = self.inserting.take() {
+                    self.last_change = Some(change);
+                }
                 self.mode = Mode::Normal;
-                self.current_suggestion = None;
+                self.popup = None;
                 self.buffer.command_buffer.clear();
                 self.status_message.clear();
             }
-            Mode::Normal => {}
+            Mode::Search => self.cancel_search(),
+            Mode::Visual | Mode::VisualLine | Mode::VisualBlock => self.exit_visual(),
+            Mode::Normal => {
+                self.pending_keys.clear();
+                self.status_message.clear();
+            }
         }
     }
     
     /// 方向键处理
     fn handle_up(&mut self) {
-        self.cursor.move_up();
+        self.cursor.move_up(&self.buffer);
     }
     
     fn handle_down(&mut self) {
@@ -450,30 +1625,16 @@ impl Editor {
     }
     
     fn handle_left(&mut self) {
-        self.cursor.move_left();
+        self.cursor.move_left(&self.buffer);
     }
     
     fn handle_right(&mut self) {
-        // 在 Insert 模式下，右方向键（->）接受上下文补全建议
-        if matches!(self.mode, Mode::Insert) {
-            if let Some(ref suggestion) = self.current_suggestion.clone() {
-                // 接受上下文补全建议
-                for c in suggestion.chars() {
-                    self.buffer.insert_char(self.cursor.row, self.cursor.col, c);
-                    self.cursor.col += 1;
-                }
-                self.modified = true;
-                self.current_suggestion = None;
-                return;
-            }
-        }
-        // 没有建议时，正常移动光标
         self.cursor.move_right(&self.buffer);
     }
     
     fn handle_home(&mut self) {
         match self.mode {
-            Mode::Insert | Mode::Normal => {
+            Mode::Insert | Mode::Normal | Mode::Visual | Mode::VisualLine | Mode::VisualBlock => {
                 self.cursor.move_to_start_of_line();
             }
             Mode::Command => {
@@ -481,15 +1642,16 @@ impl Editor {
                 self.buffer.command_buffer.clear();
                 self.status_message = ":".to_string();
             }
+            Mode::Search => {}
         }
     }
     
     fn handle_end(&mut self) {
         match self.mode {
-            Mode::Insert | Mode::Normal => {
+            Mode::Insert | Mode::Normal | Mode::Visual | Mode::VisualLine | Mode::VisualBlock => {
                 self.cursor.move_to_end_of_line(&self.buffer);
             }
-            Mode::Command => {
+            Mode::Command | Mode::Search => {
                 // 在命令模式下，End 移到命令末尾（已经是末尾）
             }
         }
@@ -497,7 +1659,7 @@ impl Editor {
     
     fn handle_page_up(&mut self) {
         for _ in 0..20 {
-            self.cursor.move_up();
+            self.cursor.move_up(&self.buffer);
         }
     }
     
@@ -516,63 +1678,213 @@ impl Editor {
                 }
             }
             'q' => {
-                if self.modified {
+                if self.modified || self.hidden_modified().is_some() {
                     self.status_message = "文件已修改！按 Ctrl+Q 两次强制退出".to_string();
                 } else {
                     self.should_quit = true;
                 }
             }
+            'r' if matches!(self.mode, Mode::Normal) => self.redo(),
+            'w' if matches!(self.mode, Mode::Normal) => {
+                self.window_pending = true;
+                self.status_message = "^W".to_string();
+            }
+            'v' if matches!(self.mode, Mode::Normal) => self.enter_visual(Shape::Block),
+            'v' if self.mode.is_visual() => {
+                self.pending_keys.clear();
+                self.execute_visual(visual::Command { register: None, count: None, action: visual::Action::Switch(Shape::Block) });
+            }
             _ => {}
         }
     }
     
-    /// 更新补全建议
+    /// 撤销上一步修改
+    fn undo(&mut self) {
+        match self.buffer.undo() {
+            Some((row, col)) => {
+                self.move_cursor_to(row, col);
+                self.status_message = "已撤销".to_string();
+            }
+            None => self.status_message = "已经是最早的修改".to_string(),
+        }
+        self.modified = self.buffer.is_modified();
+    }
+    
+    /// 重做撤销的修改
+    fn redo(&mut self) {
+        match self.buffer.redo() {
+            Some((row, col)) => {
+                self.move_cursor_to(row, col);
+                self.status_message = "已重做".to_string();
+            }
+            None => self.status_message = "已经是最新的修改".to_string(),
+        }
+        self.modified = self.buffer.is_modified();
+    }
+    
+    /// 移动光标，超出内容时停在最后
+    fn move_cursor_to(&mut self, row: usize, col: usize) {
+        self.cursor.row = row.min(self.buffer.line_count() - 1);
+        self.cursor.col = col.min(self.buffer.line_len(self.cursor.row));
+        self.cursor.remember_col(&self.buffer);
+    }
+    
+    /// 更新补全菜单
     fn update_suggestion(&mut self) {
         // 分析文件上下文
         let lines: Vec<String> = (0..self.buffer.line_count())
             .map(|i| self.buffer.get_line(i).to_string())
             .collect();
         
-        self.context.analyze_file(&lines, self.cursor.row, self.cursor.col);
+        // 上下文分析按字符计列
+        let prefix = self.buffer.prefix(self.cursor.row, self.cursor.col).to_string();
+        self.context.analyze_file(&lines, self.cursor.row, prefix.chars().count());
         
-        // 获取当前行光标前的文本
-        let line = self.buffer.get_line(self.cursor.row);
-        if self.cursor.col == 0 || line.is_empty() {
-            self.current_suggestion = None;
+        // shell 脚本中补全光标所在的命令，有候选项时显示菜单
+        self.popup = if self.is_shell() { self.shell_popup() } else { None };
+        if self.popup.is_some() {
             return;
         }
         
-        let col = self.cursor.col.min(line.len());
-        
-        // 处理 UTF-8：确保在字符边界上切片
-        let prefix: String = line.chars().take(col).collect();
+        // 找到当前正在输入的词，只需要 1 个字符就开始建议
+        let current_word = text::word_before(&prefix, |c| {
+            c.is_whitespace() || c == '(' || c == '{' || c == '[' || c == '"' || c == '\'' || c == '='
+        });
+        if !current_word.is_empty() {
+            self.popup = self.word_popup(current_word);
+        }
+    }
+    
+    /// 编辑器自己的补全：上下文建议、环境变量、变量、函数、路径和词，按正在输入的词模糊过滤
+    fn word_popup(&self, word: &str) -> Option<Popup> {
+        let mut items = Vec::new();
         
-        // 找到当前正在输入的词
-        let word_start = prefix.rfind(|c: char| c.is_whitespace() || c == '(' || c == '{' || c == '[' || c == '"' || c == '\'' || c == '=')
-            .map(|i| i + 1)
-            .unwrap_or(0);
+        // `$` 开头时补全变量引用，列出文件中 export 的和当前进程的环境变量
+        let sigil = if word.starts_with('$') { "$" } else { "" };
+        let mut env_vars: Vec<_> = self.context.get_env_vars().iter().collect();
+        env_vars.sort();
+        for (name, value) in env_vars {
+            items.push(PopupItem::new(format!("{}{}", sigil, name), Kind::EnvVar, value.as_str()));
+        }
+        if !sigil.is_empty() {
+            let mut process_vars: Vec<_> = std::env::vars().collect();
+            process_vars.sort();
+            for (name, value) in process_vars {
+                items.push(PopupItem::new(format!("${}", name), Kind::EnvVar, value));
+            }
+        }
+        for name in self.context.get_defined_vars() {
+            items.push(PopupItem::new(format!("{}{}", sigil, name), Kind::Variable, ""));
+        }
         
-        if word_start >= prefix.len() {
-            self.current_suggestion = None;
-            return;
+        if sigil.is_empty() {
+            let mut functions: Vec<_> = self.context.get_functions().iter().collect();
+            functions.sort();
+            for name in functions {
+                items.push(PopupItem::new(name.as_str(), Kind::Function, ""));
+            }
+            
+            // 看起来像路径时补全文件名，保留输入的 `./` 和 `~`
+            if word.contains('/') || word.starts_with('~') {
+                let home = dirs::home_dir().map_or(String::new(), |h| h.to_string_lossy().into_owned());
+                let expanded = expand_home(word).to_string_lossy().into_owned();
+                for c in FileCompleter::new().complete(&expanded) {
+                    let text = match c.text.strip_prefix(home.as_str()) {
+                        Some(rest) if word.starts_with('~') => format!("~{}", rest),
+                        _ if word.starts_with("./") && !c.text.starts_with("./") => format!("./{}", c.text),
+                        _ => c.text,
+                    };
+                    items.push(PopupItem::new(text, Kind::Path, c.description));
+                }
+            }
+            
+            for text in self.completer.words() {
+                items.push(PopupItem::new(text, Kind::Word, ""));
+            }
         }
         
-        let current_word = &prefix[word_start..];
+        // 上下文分析的建议接在词的后面，与其他候选项只有大小写不同时不用
+        if let Some(rest) = self.context.get_contextual_suggestion(word).filter(|r| !r.is_empty()) {
+            let text = format!("{}{}", word, rest);
+            if !items.iter().any(|i| i.text.eq_ignore_ascii_case(&text)) {
+                items.insert(0, PopupItem::new(text, Kind::Context, ""));
+            }
+        }
         
-        // 只需要 1 个字符就开始建议
-        if current_word.is_empty() {
-            self.current_suggestion = None;
+        Popup::new(word, popup::fuzzy_filter(word, items))
+    }
+    
+    /// 当前缓冲区是否是 shell 脚本
+    fn is_shell(&self) -> bool {
+        self.renderer.language(self.current_buffer()).is_some_and(|l| l.name == "shell")
+    }
+    
+    /// 用主补全引擎补全光标所在的 shell 命令，和交互式 shell 中的补全相同
+    fn shell_popup(&mut self) -> Option<Popup> {
+        let command = shell::command_at(&self.buffer, self.cursor.row, self.cursor.col)?;
+        let word = shell::current_word(&command);
+        if word.is_empty() {
+            return None;
+        }
+        let engine = self.shell_engine.get_or_insert_with(CompletionEngine::new);
+        let items = engine.complete(&command, command.len())
+            .into_iter()
+            .filter(|c| c.text != word)
+            .map(|c| PopupItem { label: c.kind.label(), text: c.text, description: c.description })
+            .collect();
+        Popup::new(word, items)
+    }
+    
+    /// 在补全菜单中选择上一项或下一项，已经用 Tab 填入时换成新选中的一项
+    fn select_popup(&mut self, forward: bool) {
+        let Some(mut popup) = self.popup.take() else {
             return;
+        };
+        let typed = popup.typed().to_string();
+        popup.select(forward);
+        if popup.inserted {
+            let text = popup.current().text.clone();
+            self.replace_word(&typed, &text);
         }
-        
-        // 优先使用上下文补全
-        if let Some(context_suggestion) = self.context.get_contextual_suggestion(current_word) {
-            self.current_suggestion = Some(context_suggestion);
+        self.popup = Some(popup);
+    }
+    
+    /// Tab：第一次把选中的一项填入缓冲区，之后每按一次换下一项
+    fn cycle_popup(&mut self) {
+        let Some(mut popup) = self.popup.take() else {
             return;
+        };
+        let typed = popup.typed().to_string();
+        if popup.inserted {
+            popup.select(true);
+        }
+        popup.inserted = true;
+        let text = popup.current().text.clone();
+        self.replace_word(&typed, &text);
+        self.popup = Some(popup);
+    }
+    
+    /// 用选中的一项替换正在输入的词并关闭菜单
+    fn accept_popup(&mut self) {
+        if let Some(popup) = self.popup.take() {
+            let text = popup.current().text.clone();
+            self.replace_word(popup.typed(), &text);
         }
-        
-        // 回退到普通补全
-        self.current_suggestion = self.completer.get_suggestion(current_word);
+    }
+    
+    /// 把光标前的 `old` 换成 `new`
+    fn replace_word(&mut self, old: &str, new: &str) {
+        let row = self.cursor.row;
+        let start = self.cursor.col.saturating_sub(text::grapheme_count(old));
+        self.buffer.delete_range((row, start), (row, self.cursor.col));
+        if let Some((_, ref mut inserted)) = self.inserting {
+            for _ in old.chars() {
+                inserted.pop();
+            }
+        }
+        self.cursor.col = self.buffer.insert_text(row, start, new).1;
+        self.modified = true;
+        self.record_insert(new);
     }
     
     /// 根据文件扩展名添加文件头
@@ -614,8 +1926,9 @@ impl Editor {
         });
         
         if let Some(content) = header {
-            // 将内容按行分割并添加到缓冲区
+            // 将内容按行分割并添加到缓冲区，整个文件头作为一步撤销
             self.buffer = Buffer::new();
+            self.buffer.begin_undo_group();
             for (i, line) in content.lines().enumerate() {
                 if i == 0 {
                     // 第一行替换空缓冲区
@@ -633,6 +1946,7 @@ impl Editor {
             // 添加最后一个空行
             let last = self.buffer.line_count() - 1;
             self.buffer.insert_newline(last, self.buffer.line_len(last));
+            self.buffer.end_undo_group();
             
             // 将光标移到合适位置
             self.cursor.row = self.buffer.line_count().saturating_sub(2);
@@ -640,6 +1954,14 @@ impl Editor {
         }
     }
     
+    /// 按文件名和第一行选择语法高亮
+    fn detect_language(&mut self) {
+        let language = self.file_path.as_ref()
+            .and_then(|path| self.languages.detect(path, self.buffer.get_line(0)))
+            .cloned();
+        self.renderer.set_language(self.current_buffer(), language);
+    }
+    
     /// 显示欢迎信息
     fn show_welcome(&mut self) {
         self.show_welcome_screen = true;
@@ -664,31 +1986,56 @@ impl Default for Editor {
     }
 }
 
-/// 运行编辑器
-pub fn run_editor(file: Option<PathBuf>) -> io::Result<()> {
+/// 运行编辑器，每个文件打开为一个缓冲区，显示第一个
+pub fn run_editor(files: Vec<PathBuf>) -> io::Result<()> {
     let mut editor = Editor::new()?;
     
-    match file {
-        Some(ref path) if path.exists() => {
-            // 打开已存在的文件
-            editor.open(path)?;
-        }
-        Some(ref path) => {
-            // 新文件：自动添加文件头
-            editor.file_path = Some(path.clone());
-            editor.add_file_header(path);
-            editor.modified = true;
-            editor.status_message = format!("新文件: {}", path.display());
-        }
-        None => {
-            // 无文件参数：显示欢迎信息
-            editor.show_welcome();
-        }
+    if files.is_empty() {
+        // 无文件参数：显示欢迎信息
+        editor.show_welcome();
+    }
+    for path in &files {
+        // 已存在的文件直接打开，新文件自动添加文件头
+        editor.edit(path)?;
+    }
+    if files.len() > 1 {
+        editor.show_buffer(1);
+        editor.status_message = format!("{} 个文件，:ls 列出，:bn/:bp 切换", editor.buffers.len());
+    }
+    // 用户语法规则有错时在状态栏提示，其他规则照常使用
+    if let Some(error) = editor.languages.errors().first() {
+        editor.status_message = match editor.languages.errors().len() {
+            1 => format!("语法规则无效: {}", error),
+            n => format!("{} 个语法规则无效: {}", n, error),
+        };
     }
     
     editor.run()
 }
 
+/// 展开路径开头的 `~`
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// 字符串的公共前缀
+fn common_prefix(strings: &[String]) -> &str {
+    let mut prefix = strings.first().map_or("", |s| s.as_str());
+    for s in strings.iter().skip(1) {
+        let len = prefix.char_indices()
+            .zip(s.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(prefix.len().min(s.len()), |((i, _), _)| i);
+        prefix = &prefix[..len];
+    }
+    prefix
+}
+
 /// 文件头模板
 const SHEBANG_BASH: &str = "#!/bin/bash\n# \n# 描述: \n# 作者: \n# 日期: \n\nset -euo pipefail\n\n";
 const SHEBANG_ZSH: &str = "#!/bin/zsh\n# \n# 描述: \n# 作者: \n# 日期: \n\nset -euo pipefail\n\n";
@@ -9,6 +9,14 @@ pub enum Mode {
     Insert,
     /// 命令模式（:w, :q 等）
     Command,
+    /// 搜索模式（/ 和 ?）
+    Search,
+    /// 可视模式，按字符选择（v）
+    Visual,
+    /// 可视模式，按行选择（V）
+    VisualLine,
+    /// 可视模式，按列块选择（Ctrl+V）
+    VisualBlock,
 }
 
 impl Mode {
@@ -18,6 +26,10 @@ impl Mode {
             Mode::Normal => "NORMAL",
             Mode::Insert => "INSERT",
             Mode::Command => "COMMAND",
+            Mode::Search => "SEARCH",
+            Mode::Visual => "VISUAL",
+            Mode::VisualLine => "V-LINE",
+            Mode::VisualBlock => "V-BLOCK",
         }
     }
     
@@ -35,6 +47,16 @@ impl Mode {
     pub fn is_command(&self) -> bool {
         matches!(self, Mode::Command)
     }
+    
+    /// 是否是搜索模式
+    pub fn is_search(&self) -> bool {
+        matches!(self, Mode::Search)
+    }
+    
+    /// 是否是可视模式（任一种选择方式）
+    pub fn is_visual(&self) -> bool {
+        matches!(self, Mode::Visual | Mode::VisualLine | Mode::VisualBlock)
+    }
 }
 
 impl Default for Mode {
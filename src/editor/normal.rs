@@ -0,0 +1,370 @@
+//! Normal 模式命令解析
+//!
+//! 命令的形式为 `["寄存器][次数]操作符[次数](动作|文本对象)`，或者单独的动作、
+//! 粘贴、插入和 `.` 重复。操作符重复一次（`dd`、`yy`、`>>`、`gcc`）表示整行。
+//!
+//! 这里只解析按键和计算范围，执行在 `Editor` 中。
+
+use super::buffer::Buffer;
+use super::cursor::Cursor;
+use super::register::Registers;
+
+/// 移动光标的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    /// `w` 下一个单词开头
+    WordForward,
+    /// `b` 上一个单词开头
+    WordBackward,
+    /// `e` 单词结尾
+    WordEnd,
+    /// `0` 行首
+    LineStart,
+    /// `$` 行尾
+    LineEnd,
+    /// `gg` 第一行（有次数时为第 N 行）
+    FirstLine,
+    /// `G` 最后一行（有次数时为第 N 行）
+    LastLine,
+}
+
+impl Motion {
+    /// 单个按键的动作（`gg` 除外）
+    pub fn from_key(c: char) -> Option<Self> {
+        Some(match c {
+            'h' => Motion::Left,
+            'l' => Motion::Right,
+            'k' => Motion::Up,
+            'j' => Motion::Down,
+            'w' => Motion::WordForward,
+            'b' => Motion::WordBackward,
+            'e' => Motion::WordEnd,
+            '0' => Motion::LineStart,
+            '$' => Motion::LineEnd,
+            'G' => Motion::LastLine,
+            _ => return None,
+        })
+    }
+
+    /// 操作整行的动作
+    pub fn is_linewise(self) -> bool {
+        matches!(self, Motion::Up | Motion::Down | Motion::FirstLine | Motion::LastLine)
+    }
+}
+
+/// 文本对象
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextObject {
+    /// `iw` 光标所在的单词
+    InnerWord,
+    /// `i"`、`i'`、`` i` `` 引号之内
+    InnerQuote(char),
+}
+
+/// 操作符
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// `d` 删除
+    Delete,
+    /// `c` 删除并进入插入模式
+    Change,
+    /// `y` 复制
+    Yank,
+    /// `>` 增加缩进
+    Indent,
+    /// `<` 减少缩进
+    Outdent,
+    /// `gc` 切换行注释
+    Comment,
+}
+
+impl Operator {
+    fn from_key(c: char) -> Option<Self> {
+        Some(match c {
+            'd' => Operator::Delete,
+            'c' => Operator::Change,
+            'y' => Operator::Yank,
+            '>' => Operator::Indent,
+            '<' => Operator::Outdent,
+            _ => return None,
+        })
+    }
+}
+
+/// 操作符作用的对象
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Motion(Motion),
+    Object(TextObject),
+    /// 重复操作符，作用于整行
+    Line,
+}
+
+/// 命令的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Move(Motion),
+    Operate(Operator, Target),
+    /// `p`/`P` 粘贴到光标之后/之前
+    Put { before: bool },
+    /// `i`、`a`、`A`、`I`、`o`、`O` 进入插入模式
+    Insert(char),
+    /// `.` 重复上一次修改
+    Repeat,
+}
+
+/// 一条完整的 Normal 模式命令
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Command {
+    pub register: Option<char>,
+    /// 输入的次数，操作符前后的次数相乘
+    pub count: Option<usize>,
+    pub action: Action,
+}
+
+impl Command {
+    /// 次数，没有输入时为 1
+    pub fn count(&self) -> usize {
+        self.count.unwrap_or(1)
+    }
+
+    /// 是否修改文本（可以用 `.` 重复）
+    pub fn is_change(&self) -> bool {
+        match self.action {
+            Action::Operate(op, _) => op != Operator::Yank,
+            Action::Put { .. } | Action::Insert(_) => true,
+            Action::Move(_) | Action::Repeat => false,
+        }
+    }
+}
+
+/// 解析结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parse {
+    Complete(Command),
+    /// 命令还没输入完
+    Pending,
+    /// 不是有效的命令
+    Invalid,
+}
+
+/// 解析已输入的按键
+pub fn parse(keys: &str) -> Parse {
+    let mut chars = keys.chars().peekable();
+
+    let mut register = None;
+    if chars.peek() == Some(&'"') {
+        chars.next();
+        match chars.next() {
+            None => return Parse::Pending,
+            Some(c) if Registers::is_valid(c) => register = Some(c),
+            Some(_) => return Parse::Invalid,
+        }
+    }
+
+    let mut count = read_count(&mut chars);
+    let key = match chars.next() {
+        Some(key) => key,
+        None => return Parse::Pending,
+    };
+
+    // gc 是两个按键的操作符，gcc 作用于整行
+    let operator = match key {
+        'g' if chars.peek() == Some(&'c') => {
+            chars.next();
+            Some((Operator::Comment, 'c'))
+        }
+        _ => Operator::from_key(key).map(|op| (op, key)),
+    };
+    let action = if let Some((op, key)) = operator {
+        if let Some(n) = read_count(&mut chars) {
+            count = Some(count.unwrap_or(1).saturating_mul(n));
+        }
+        let target = match chars.next() {
+            None => return Parse::Pending,
+            Some(c) if c == key => Target::Line,
+            Some('i') => match chars.next() {
+                None => return Parse::Pending,
+                Some('w') => Target::Object(TextObject::InnerWord),
+                Some(q @ ('"' | '\'' | '`')) => Target::Object(TextObject::InnerQuote(q)),
+                Some(_) => return Parse::Invalid,
+            },
+            Some('g') => match chars.next() {
+                None => return Parse::Pending,
+                Some('g') => Target::Motion(Motion::FirstLine),
+                Some(_) => return Parse::Invalid,
+            },
+            Some(c) => match Motion::from_key(c) {
+                Some(motion) => Target::Motion(motion),
+                None => return Parse::Invalid,
+            },
+        };
+        Action::Operate(op, target)
+    } else {
+        match key {
+            'x' => Action::Operate(Operator::Delete, Target::Motion(Motion::Right)),
+            'p' => Action::Put { before: false },
+            'P' => Action::Put { before: true },
+            '.' => Action::Repeat,
+            'i' | 'a' | 'A' | 'I' | 'o' | 'O' => Action::Insert(key),
+            'g' => match chars.next() {
+                None => return Parse::Pending,
+                Some('g') => Action::Move(Motion::FirstLine),
+                Some(_) => return Parse::Invalid,
+            },
+            c => match Motion::from_key(c) {
+                Some(motion) => Action::Move(motion),
+                None => return Parse::Invalid,
+            },
+        }
+    };
+    Parse::Complete(Command { register, count, action })
+}
+
+/// 读取次数（不以 0 开头，`0` 是移到行首）
+pub(super) fn read_count(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<usize> {
+    let mut count: Option<usize> = None;
+    while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+        if d == 0 && count.is_none() {
+            break;
+        }
+        count = Some(count.unwrap_or(0).saturating_mul(10).saturating_add(d as usize));
+        chars.next();
+    }
+    count
+}
+
+/// 操作符作用的范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    /// 开始位置 (行, 列)
+    pub start: (usize, usize),
+    /// 结束位置（不含）；整行时只看行号（包含）
+    pub end: (usize, usize),
+    pub linewise: bool,
+}
+
+/// 行中第一个非空白字符的列
+pub fn first_non_blank(buffer: &Buffer, row: usize) -> usize {
+    (0..buffer.line_len(row))
+        .find(|&col| buffer.grapheme(row, col).map_or(true, |g| !g.trim().is_empty()))
+        .unwrap_or(0)
+}
+
+/// 动作的目标位置
+pub fn motion_target(buffer: &Buffer, (row, col): (usize, usize), motion: Motion, count: Option<usize>) -> (usize, usize) {
+    let n = count.unwrap_or(1);
+    let last = buffer.line_count() - 1;
+    let mut cursor = Cursor::new();
+    cursor.row = row;
+    cursor.col = col;
+    cursor.remember_col(buffer);
+    match motion {
+        Motion::Left => return (row, col.saturating_sub(n)),
+        Motion::Right => return (row, col.saturating_add(n).min(buffer.line_len(row))),
+        Motion::LineStart => return (row, 0),
+        Motion::LineEnd => {
+            let row = row.saturating_add(n - 1).min(last);
+            return (row, buffer.line_len(row));
+        }
+        Motion::FirstLine | Motion::LastLine => {
+            let default = if motion == Motion::FirstLine { 0 } else { last };
+            let row = count.map_or(default, |n| n - 1).min(last);
+            return (row, first_non_blank(buffer, row));
+        }
+        Motion::Up => (0..n.min(last)).for_each(|_| cursor.move_up(buffer)),
+        Motion::Down => (0..n.min(last)).for_each(|_| cursor.move_down(buffer)),
+        Motion::WordForward | Motion::WordBackward | Motion::WordEnd => {
+            // 次数很大时到文件开头或结尾就停下
+            for _ in 0..n {
+                let before = (cursor.row, cursor.col);
+                match motion {
+                    Motion::WordForward => cursor.move_word_forward(buffer),
+                    Motion::WordBackward => cursor.move_word_backward(buffer),
+                    _ => cursor.move_word_end(buffer),
+                }
+                if (cursor.row, cursor.col) == before {
+                    break;
+                }
+            }
+        }
+    }
+    (cursor.row, cursor.col)
+}
+
+/// 操作符作用的范围，没有可操作的文本时返回 None
+pub fn target_range(buffer: &Buffer, pos: (usize, usize), op: Operator, target: Target, count: Option<usize>) -> Option<Range> {
+    let last = buffer.line_count() - 1;
+    match target {
+        Target::Line => {
+            let end = pos.0.saturating_add(count.unwrap_or(1).saturating_sub(1)).min(last);
+            Some(Range { start: (pos.0, 0), end: (end, 0), linewise: true })
+        }
+        Target::Motion(motion) if motion.is_linewise() => {
+            let (row, _) = motion_target(buffer, pos, motion, count);
+            Some(Range { start: (pos.0.min(row), 0), end: (pos.0.max(row), 0), linewise: true })
+        }
+        Target::Motion(motion) => {
+            // cw 在单词上时与 ce 相同
+            let on_word = buffer.grapheme(pos.0, pos.1).is_some_and(|g| !g.trim().is_empty());
+            let motion = if op == Operator::Change && motion == Motion::WordForward && on_word {
+                Motion::WordEnd
+            } else {
+                motion
+            };
+            let mut end = motion_target(buffer, pos, motion, count);
+            if motion == Motion::WordEnd {
+                // e 包含结尾的字符
+                end.1 += 1;
+            } else if end.0 > pos.0 && end.1 == 0 {
+                // 结束在下一行行首时，只到上一行行尾（dw 不会删掉换行）
+                end = (end.0 - 1, buffer.line_len(end.0 - 1));
+            }
+            let (start, end) = if end < pos { (end, pos) } else { (pos, end) };
+            (start != end || op == Operator::Change).then_some(Range { start, end, linewise: false })
+        }
+        Target::Object(TextObject::InnerWord) => {
+            let (row, col) = pos;
+            let class = |col: usize| buffer.grapheme(row, col).map(char_class);
+            let current = class(col)?;
+            let mut start = col;
+            while start > 0 && class(start - 1) == Some(current) {
+                start -= 1;
+            }
+            let mut end = col + 1;
+            while class(end) == Some(current) {
+                end += 1;
+            }
+            Some(Range { start: (row, start), end: (row, end), linewise: false })
+        }
+        Target::Object(TextObject::InnerQuote(quote)) => {
+            let (row, col) = pos;
+            let q = quote.to_string();
+            let quotes: Vec<usize> = (0..buffer.line_len(row))
+                .filter(|&i| buffer.grapheme(row, i) == Some(q.as_str()) && (i == 0 || buffer.grapheme(row, i - 1) != Some("\\")))
+                .collect();
+            // 引号按顺序两两配对，取包含光标的一对，没有时取光标之后的第一对
+            let pair = quotes.chunks_exact(2)
+                .find(|p| p[0] <= col && col <= p[1])
+                .or_else(|| quotes.chunks_exact(2).find(|p| p[0] > col))?;
+            Some(Range { start: (row, pair[0] + 1), end: (row, pair[1]), linewise: false })
+                .filter(|r| r.start != r.end || op == Operator::Change)
+        }
+    }
+}
+
+/// 字符类别：空白、单词字符、其他符号
+fn char_class(g: &str) -> u8 {
+    let c = g.chars().next().unwrap_or(' ');
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
@@ -0,0 +1,143 @@
+//! 补全弹出菜单
+//!
+//! 插入模式中在光标附近列出补全候选项，随着继续输入模糊过滤。上下方向键选择，Tab 把
+//! 选中的一项填入缓冲区、再按一次换下一项，→ 接受选中的一项并关闭菜单。
+
+use std::collections::HashSet;
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+/// 菜单最多同时显示的行数
+pub const MAX_HEIGHT: usize = 8;
+
+/// 菜单最多列出的候选项
+pub const MAX_ITEMS: usize = 50;
+
+/// 编辑器补全候选项的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// 文件和历史中的词
+    Word,
+    /// 文件中定义的变量
+    Variable,
+    /// 文件中定义的函数
+    Function,
+    /// 文件路径
+    Path,
+    /// 环境变量（文件中 export 的和当前进程的）
+    EnvVar,
+    /// 上下文分析的建议（如 `export PATH=` 之后的值）
+    Context,
+}
+
+impl Kind {
+    /// 菜单中显示的标签
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::Word => "单词",
+            Kind::Variable => "变量",
+            Kind::Function => "函数",
+            Kind::Path => "路径",
+            Kind::EnvVar => "环境变量",
+            Kind::Context => "建议",
+        }
+    }
+}
+
+/// 菜单中的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PopupItem {
+    /// 补全后的词
+    pub text: String,
+    /// 类型标签，如 "选项"
+    pub label: &'static str,
+    /// 说明
+    pub description: String,
+}
+
+impl PopupItem {
+    pub fn new(text: impl Into<String>, kind: Kind, description: impl Into<String>) -> Self {
+        Self { text: text.into(), label: kind.label(), description: description.into() }
+    }
+}
+
+/// 补全弹出菜单
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Popup {
+    /// 正在输入、接受补全时被替换的词
+    pub word: String,
+    pub items: Vec<PopupItem>,
+    /// 选中的一项
+    pub selected: usize,
+    /// 选中的一项是否已经用 Tab 填入缓冲区（替换了 `word`）
+    pub inserted: bool,
+}
+
+impl Popup {
+    /// 没有候选项时为 None
+    pub fn new(word: &str, items: Vec<PopupItem>) -> Option<Self> {
+        if items.is_empty() {
+            return None;
+        }
+        Some(Self { word: word.to_string(), items, selected: 0, inserted: false })
+    }
+
+    /// 选中的一项
+    pub fn current(&self) -> &PopupItem {
+        &self.items[self.selected]
+    }
+
+    /// 缓冲区中光标前的词：输入的词，或已经填入的一项
+    pub fn typed(&self) -> &str {
+        if self.inserted {
+            &self.current().text
+        } else {
+            &self.word
+        }
+    }
+
+    /// 选择下一项（`forward` 为 false 时上一项），到头时回到另一端
+    pub fn select(&mut self, forward: bool) {
+        let len = self.items.len();
+        self.selected = if forward { (self.selected + 1) % len } else { (self.selected + len - 1) % len };
+    }
+
+    /// 菜单有 `height` 行时显示的项，保证选中的一项可见
+    pub fn visible(&self, height: usize) -> std::ops::Range<usize> {
+        let height = height.min(self.items.len());
+        let start = (self.selected + 1).saturating_sub(height);
+        start..start + height
+    }
+}
+
+/// 按正在输入的词过滤候选项
+///
+/// 以 `word` 开头（不区分大小写）的在前，保持原来的顺序；其余首字母相同、能模糊匹配的
+/// 按匹配分数排在后面。去掉重复的和与 `word` 相同的项，最多保留 `MAX_ITEMS` 项。
+pub fn fuzzy_filter(word: &str, items: Vec<PopupItem>) -> Vec<PopupItem> {
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let lower = word.to_lowercase();
+    let first = lower.chars().next();
+    let mut seen = HashSet::new();
+    let mut prefixed = Vec::new();
+    let mut fuzzy = Vec::new();
+    for item in items {
+        if item.text == word || !seen.insert(item.text.clone()) {
+            continue;
+        }
+        let text = item.text.to_lowercase();
+        if text.starts_with(&lower) {
+            prefixed.push(item);
+        } else if text.chars().next() == first {
+            if let Some(score) = matcher.fuzzy_match(&item.text, word) {
+                fuzzy.push((score, item));
+            }
+        }
+    }
+    // 稳定排序，分数相同时保持原来的顺序
+    fuzzy.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    prefixed.extend(fuzzy.into_iter().map(|(_, item)| item));
+    prefixed.truncate(MAX_ITEMS);
+    prefixed
+}
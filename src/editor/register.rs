@@ -0,0 +1,94 @@
+//! 寄存器（复制、删除的文本）
+//!
+//! 与 vim 相同：`"a`…`"z` 为命名寄存器，大写字母追加到对应的寄存器，
+//! `"_` 丢弃文本；复制的文本同时存到 `"0`，删除的文本存到 `"1` 并把原来的
+//! `"1`…`"8` 依次后移。没有指定寄存器时使用无名寄存器 `""`。
+
+use std::collections::HashMap;
+
+/// 寄存器中的文本
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Register {
+    pub text: String,
+    /// 整行文本（粘贴时作为新行插入）
+    pub linewise: bool,
+}
+
+impl Register {
+    pub fn new(text: impl Into<String>, linewise: bool) -> Self {
+        Self { text: text.into(), linewise }
+    }
+}
+
+/// 全部寄存器
+#[derive(Debug, Default)]
+pub struct Registers {
+    unnamed: Register,
+    registers: HashMap<char, Register>,
+}
+
+impl Registers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 是否是有效的寄存器名
+    pub fn is_valid(name: char) -> bool {
+        name.is_ascii_alphanumeric() || name == '"' || name == '_'
+    }
+
+    /// 读取寄存器，`None` 或 `"` 为无名寄存器
+    pub fn get(&self, name: Option<char>) -> Option<&Register> {
+        match name {
+            None | Some('"') => Some(&self.unnamed).filter(|r| !r.text.is_empty()),
+            Some(c) => self.registers.get(&c.to_ascii_lowercase()),
+        }
+    }
+
+    /// 存入复制的文本
+    pub fn yank(&mut self, name: Option<char>, register: Register) {
+        if self.store(name, register.clone()) {
+            self.registers.insert('0', register);
+        }
+    }
+
+    /// 存入删除的文本
+    pub fn delete(&mut self, name: Option<char>, register: Register) {
+        if self.store(name, register.clone()) {
+            for i in (1..9).rev() {
+                let from = char::from(b'0' + i);
+                if let Some(r) = self.registers.remove(&from) {
+                    self.registers.insert(char::from(b'0' + i + 1), r);
+                }
+            }
+            self.registers.insert('1', register);
+        }
+    }
+
+    /// 存到指定的寄存器和无名寄存器，返回是否没有指定寄存器
+    fn store(&mut self, name: Option<char>, register: Register) -> bool {
+        match name {
+            Some('_') => false,
+            None | Some('"') => {
+                self.unnamed = register;
+                true
+            }
+            Some(c) if c.is_ascii_uppercase() => {
+                let target = self.registers.entry(c.to_ascii_lowercase()).or_default();
+                // 整行和非整行混合追加时按整行处理
+                if register.linewise && !target.linewise && !target.text.is_empty() {
+                    target.text.push('\n');
+                }
+                target.text.push_str(&register.text);
+                target.linewise |= register.linewise;
+                self.unnamed = target.clone();
+                false
+            }
+            Some(c) => {
+                self.registers.insert(c, register.clone());
+                self.unnamed = register;
+                false
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 //! 终端渲染
 
+use std::collections::HashMap;
 use std::io::{self, Write, stdout, BufWriter};
 use crossterm::{
     cursor::{Hide, Show, MoveTo},
@@ -12,7 +13,49 @@ use crossterm::{
     },
 };
 
-use super::{Buffer, Cursor, Mode};
+use regex::Regex;
+
+use super::popup::{Popup, MAX_HEIGHT};
+use super::search::Match;
+use super::syntax::{Highlighter, Language, TokenKind};
+use super::visual::Selection;
+use super::window::{Rect, Scroll};
+use super::{text, Buffer, Cursor, Mode};
+
+/// 行号区域的宽度
+const GUTTER_WIDTH: u16 = 5;
+
+/// 行内一段文本的样式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Plain,
+    /// 搜索匹配
+    Match,
+    /// 当前匹配（替换确认中）
+    CurrentMatch,
+    /// 语法高亮
+    Syntax(TokenKind),
+    /// 可视模式的选择
+    Selection,
+}
+
+/// 要渲染的一个窗口
+pub struct View<'a> {
+    /// 缓冲区编号，用于选择语法高亮
+    pub number: usize,
+    pub buffer: &'a Buffer,
+    pub cursor: &'a Cursor,
+    /// 窗口的滚动位置，渲染时跟随光标更新
+    pub scroll: &'a mut Scroll,
+    /// 窗口区域，最后一行是状态栏
+    pub rect: Rect,
+    /// 是否是当前窗口
+    pub active: bool,
+    /// 状态栏中显示的文件名
+    pub name: String,
+    /// 是否有未保存的修改
+    pub modified: bool,
+}
 
 /// 渲染器
 pub struct Renderer {
@@ -20,8 +63,16 @@ pub struct Renderer {
     width: u16,
     /// 终端高度
     height: u16,
-    /// 滚动偏移
-    scroll_offset: usize,
+    /// 高亮的搜索模式
+    highlight: Option<Regex>,
+    /// 当前匹配
+    current_match: Option<Match>,
+    /// 各缓冲区的语法高亮，没有识别出语言的缓冲区没有
+    highlighters: HashMap<usize, Highlighter>,
+    /// 可视模式的选择
+    selection: Option<Selection>,
+    /// 补全弹出菜单
+    popup: Option<Popup>,
 }
 
 impl Renderer {
@@ -31,7 +82,11 @@ impl Renderer {
         Ok(Self {
             width,
             height,
-            scroll_offset: 0,
+            highlight: None,
+            current_match: None,
+            highlighters: HashMap::new(),
+            selection: None,
+            popup: None,
         })
     }
     
@@ -57,21 +112,22 @@ impl Renderer {
         disable_raw_mode()
     }
     
-    /// 渲染编辑器
+    /// 更新终端大小，返回窗口可用的区域（除去最后一行消息行）
+    pub fn area(&mut self) -> io::Result<Rect> {
+        let (width, height) = terminal::size()?;
+        self.width = width;
+        self.height = height;
+        Ok(Rect { x: 0, y: 0, width, height: height.saturating_sub(1) })
+    }
+    
+    /// 渲染编辑器，窗口区域按 `area` 返回的大小计算
     pub fn render(
         &mut self,
-        buffer: &Buffer,
-        cursor: &Cursor,
+        views: &mut [View],
         mode: &Mode,
         status_message: &str,
-        suggestion: &Option<String>,
         show_welcome: bool,
     ) -> io::Result<()> {
-        // 更新终端大小
-        let (width, height) = terminal::size()?;
-        self.width = width;
-        self.height = height;
-        
         // 计算可用于文本的行数（减去状态栏）
         let text_height = (self.height as usize).saturating_sub(2);
         
@@ -100,15 +156,68 @@ impl Renderer {
             return Ok(());
         }
         
+        // 渲染各窗口
+        let mut cursor_pos = None;
+        for view in views.iter_mut() {
+            if let Some(pos) = self.render_window(&mut writer, view, mode)? {
+                cursor_pos = Some((pos, view.rect));
+            }
+        }
+        
+        // 补全菜单画在所有窗口之上
+        if let (Some(popup), Some((pos, rect))) = (&self.popup, cursor_pos) {
+            if mode.is_insert() {
+                self.render_popup(&mut writer, popup, pos, rect)?;
+            }
+        }
+        
+        // 渲染消息行
+        let command_buffer = views.iter()
+            .find(|v| v.active)
+            .map_or("", |v| v.buffer.command_buffer.as_str());
+        queue!(writer, MoveTo(0, self.height.saturating_sub(1)), Clear(ClearType::CurrentLine))?;
+        self.render_message_line_buffered(&mut writer, status_message, mode, command_buffer)?;
+        
+        // 设置光标位置并显示光标
+        if let Some(((x, y), _)) = cursor_pos {
+            queue!(writer, MoveTo(x, y), Show)?;
+        }
+        
+        writer.flush()?;
+        Ok(())
+    }
+    
+    /// 渲染一个窗口的文本和状态栏，当前窗口返回光标的屏幕位置
+    fn render_window(
+        &mut self,
+        writer: &mut impl Write,
+        view: &mut View,
+        mode: &Mode,
+    ) -> io::Result<Option<(u16, u16)>> {
+        let rect = view.rect;
+        let buffer = view.buffer;
+        let text_height = rect.height.saturating_sub(1) as usize;
+        let text_width = rect.width.saturating_sub(GUTTER_WIDTH) as usize;
+        let gutter_width = GUTTER_WIDTH.min(rect.width) as usize;
+        
+        // 其他窗口修改了同一个缓冲区时，这个窗口的光标可能已经超出内容
+        let mut cursor = view.cursor.clone();
+        cursor.clamp(buffer);
+        
         // 更新滚动偏移
-        self.update_scroll(cursor, text_height);
+        let cursor_x = buffer.display_col(cursor.row, cursor.col);
+        update_scroll(view.scroll, &cursor, cursor_x, text_height, text_width);
+        let scroll = *view.scroll;
+        
+        // 只分析到窗口最后一行，没有变化的行使用缓存
+        if let Some(highlighter) = self.highlighters.get_mut(&view.number) {
+            highlighter.update(buffer, scroll.row + text_height);
+        }
         
         // 渲染文本行
         for i in 0..text_height {
-            let line_num = self.scroll_offset + i;
-            
-            // 清除当前行
-            queue!(writer, Clear(ClearType::CurrentLine))?;
+            let line_num = scroll.row + i;
+            queue!(writer, MoveTo(rect.x, rect.y + i as u16))?;
             
             // 行号
             let line_num_str = if line_num < buffer.line_count() {
@@ -118,111 +227,273 @@ impl Renderer {
             };
             
             queue!(writer, SetForegroundColor(Color::DarkGrey))?;
-            write!(writer, "{}", line_num_str)?;
+            write!(writer, "{}", &line_num_str[..gutter_width.min(line_num_str.len())])?;
             queue!(writer, ResetColor)?;
             
             // 文本内容
+            let mut used = gutter_width;
             if line_num < buffer.line_count() {
                 let line = buffer.get_line(line_num);
                 
-                // 如果是当前行并且在插入模式，显示补全建议
-                if line_num == cursor.row && mode.is_insert() {
-                    // 光标前的文本
-                    let cursor_col = cursor.col.min(line.len());
-                    write!(writer, "{}", &line[..cursor_col])?;
-                    
-                    // 补全建议（灰色）
-                    if let Some(ref sug) = suggestion {
-                        queue!(writer, SetForegroundColor(Color::DarkGrey))?;
-                        write!(writer, "{}", sug)?;
-                        queue!(writer, ResetColor)?;
-                    }
-                    
-                    // 光标后的文本
-                    if cursor_col < line.len() {
-                        write!(writer, "{}", &line[cursor_col..])?;
-                    }
-                } else {
-                    let display_line = self.truncate_line(line, 5);
-                    write!(writer, "{}", display_line)?;
-                }
+                let selected = self.selection
+                    .filter(|_| view.active)
+                    .and_then(|s| s.columns(buffer, line_num))
+                    .map(|(start, end)| (buffer.byte_offset(line_num, start), buffer.byte_offset(line_num, end)));
+                let spans = self.line_spans(view, line, line_num, selected);
+                used += self.write_spans(writer, &spans, scroll.col, text_width)?;
             }
             
-            // 移动到下一行
-            if i < text_height - 1 {
-                queue!(writer, MoveTo(0, (i + 1) as u16))?;
-            }
+            // 用空格清除这一行剩下的部分（不能清除整行，右边可能是另一个窗口）
+            write!(writer, "{:1$}", "", (rect.width as usize).saturating_sub(used))?;
         }
         
         // 渲染状态栏
-        queue!(writer, MoveTo(0, text_height as u16))?;
-        self.render_status_bar_buffered(&mut writer, buffer, cursor, mode)?;
+        queue!(writer, MoveTo(rect.x, rect.y + text_height as u16))?;
+        self.render_status_bar_buffered(writer, view, &cursor, mode)?;
         
-        // 渲染消息行
-        queue!(writer, MoveTo(0, (text_height + 1) as u16), Clear(ClearType::CurrentLine))?;
-        self.render_message_line_buffered(&mut writer, status_message, mode, &buffer.command_buffer)?;
+        // 右边还有窗口时画分隔线
+        if rect.x + rect.width < self.width {
+            queue!(writer, SetForegroundColor(Color::DarkGrey))?;
+            for y in rect.y..rect.y + rect.height {
+                queue!(writer, MoveTo(rect.x + rect.width, y))?;
+                write!(writer, "│")?;
+            }
+            queue!(writer, ResetColor)?;
+        }
         
-        // 设置光标位置并显示光标
-        let cursor_x = 5 + cursor.col as u16;
-        let cursor_y = cursor.row.saturating_sub(self.scroll_offset) as u16;
-        queue!(writer, MoveTo(cursor_x, cursor_y), Show)?;
+        if !view.active || text_height == 0 {
+            return Ok(None);
+        }
+        let x = rect.x as usize + gutter_width + cursor_x.saturating_sub(scroll.col);
+        let y = rect.y as usize + cursor.row.saturating_sub(scroll.row);
+        Ok(Some((x.min(u16::MAX as usize) as u16, y as u16)))
+    }
+    
+    /// 设置要高亮的搜索模式和当前匹配（替换确认时）
+    pub fn set_highlight(&mut self, regex: Option<Regex>, current: Option<Match>) {
+        self.highlight = regex;
+        self.current_match = current;
+    }
+    
+    /// 设置缓冲区高亮的语言
+    pub fn set_language(&mut self, number: usize, language: Option<Language>) {
+        match language {
+            Some(language) => {
+                self.highlighters.insert(number, Highlighter::new(language));
+            }
+            None => {
+                self.highlighters.remove(&number);
+            }
+        }
+    }
+    
+    /// 缓冲区高亮的语言
+    pub fn language(&self, number: usize) -> Option<&Language> {
+        self.highlighters.get(&number).map(|h| h.language())
+    }
+    
+    /// 设置可视模式的选择（只显示在当前窗口）
+    pub fn set_selection(&mut self, selection: Option<Selection>) {
+        self.selection = selection;
+    }
+    
+    /// 设置补全弹出菜单（插入模式中显示在当前窗口的光标附近）
+    pub fn set_popup(&mut self, popup: Option<Popup>) {
+        self.popup = popup;
+    }
+    
+    /// 渲染补全菜单：光标下方放得下时在下方，否则在上方，与正在输入的词左对齐
+    fn render_popup(&self, writer: &mut impl Write, popup: &Popup, (x, y): (u16, u16), rect: Rect) -> io::Result<()> {
+        let text_bottom = rect.y + rect.height.saturating_sub(1);
+        let below = text_bottom.saturating_sub(y + 1) as usize;
+        let above = y.saturating_sub(rect.y) as usize;
+        let wanted = popup.items.len().min(MAX_HEIGHT);
+        let (height, top) = if below >= wanted || below >= above {
+            (wanted.min(below), y + 1)
+        } else {
+            (wanted.min(above), y - wanted.min(above) as u16)
+        };
+        if height == 0 {
+            return Ok(());
+        }
         
-        writer.flush()?;
+        // 各列宽度，说明放不下时省略
+        let range = popup.visible(height);
+        let items = &popup.items[range.clone()];
+        let text_width = items.iter().map(|i| text::str_width(&i.text)).max().unwrap_or(0).min(30);
+        let label_width = items.iter().map(|i| text::str_width(i.label)).max().unwrap_or(0);
+        let desc_width = items.iter().map(|i| text::str_width(&i.description)).max().unwrap_or(0).min(30);
+        let screen = self.width as usize;
+        let mut width = text_width + label_width + 4;
+        let show_desc = desc_width > 0 && width + desc_width < screen;
+        if show_desc {
+            width += desc_width + 1;
+        }
+        let width = width.min(screen);
+        let left = (x as usize)
+            .saturating_sub(text::str_width(popup.typed()))
+            .min(screen.saturating_sub(width));
+        
+        for (i, item) in items.iter().enumerate() {
+            let background = if range.start + i == popup.selected {
+                Color::Rgb { r: 70, g: 80, b: 120 }
+            } else {
+                Color::Rgb { r: 45, g: 45, b: 55 }
+            };
+            let mut label = pad(item.label, label_width);
+            label.push(' ');
+            if show_desc {
+                label.push_str(&pad(&item.description, desc_width));
+                label.push(' ');
+            }
+            let (line, _) = text::clip(&format!(" {}  ", pad(&item.text, text_width)), 0, 0, width);
+            let (label, _) = text::clip(&label, 0, 0, width.saturating_sub(text::str_width(&line)));
+            queue!(writer, MoveTo(left as u16, top + i as u16),
+                   SetBackgroundColor(background), SetForegroundColor(Color::White))?;
+            write!(writer, "{}", line)?;
+            queue!(writer, SetForegroundColor(Color::Rgb { r: 150, g: 150, b: 160 }))?;
+            write!(writer, "{}", label)?;
+            queue!(writer, ResetColor)?;
+        }
         Ok(())
     }
     
-    /// 更新滚动偏移
-    fn update_scroll(&mut self, cursor: &Cursor, text_height: usize) {
-        if cursor.row < self.scroll_offset {
-            self.scroll_offset = cursor.row;
-        } else if cursor.row >= self.scroll_offset + text_height {
-            self.scroll_offset = cursor.row - text_height + 1;
+    /// 按语法记号、搜索匹配和选择（字节范围）把一行分段，后面的覆盖前面的
+    fn line_spans<'a>(&self, view: &View, line: &'a str, row: usize, selected: Option<(usize, usize)>) -> Vec<(&'a str, Style)> {
+        // 选中的空行显示一个高亮的空格
+        if line.is_empty() && selected.is_some() {
+            return vec![(" ", Style::Selection)];
+        }
+        let mut styles = vec![Style::Plain; line.len()];
+        if let Some(highlighter) = self.highlighters.get(&view.number) {
+            for token in highlighter.tokens(row).iter().filter(|t| t.end <= line.len()) {
+                styles[token.start..token.end].fill(Style::Syntax(token.kind));
+            }
+        }
+        for m in self.highlight.iter().flat_map(|regex| regex.find_iter(line)) {
+            styles[m.start()..m.end()].fill(Style::Match);
         }
+        let current = self.current_match.filter(|m| view.active && m.row == row && m.end <= line.len());
+        if let Some(current) = current {
+            // 空匹配至少高亮一个字符
+            let end = if current.start == current.end {
+                current.end + line[current.end..].chars().next().map_or(0, char::len_utf8)
+            } else {
+                current.end
+            };
+            styles[current.start..end].fill(Style::CurrentMatch);
+        }
+        if let Some((start, end)) = selected {
+            styles[start..end].fill(Style::Selection);
+        }
+        
+        // 合并样式相同的相邻字节
+        let mut spans = Vec::new();
+        let mut start = 0;
+        for i in 1..=line.len() {
+            if i == line.len() || styles[i] != styles[start] {
+                spans.push((&line[start..i], styles[start]));
+                start = i;
+            }
+        }
+        spans
     }
     
-    /// 截断行以适应屏幕宽度
-    fn truncate_line(&self, line: &str, offset: u16) -> String {
-        let max_len = (self.width - offset) as usize;
-        if line.len() > max_len {
-            format!("{}…", &line[..max_len.saturating_sub(1)])
-        } else {
-            line.to_string()
+    /// 从第 `col_offset` 列开始输出分段着色的一行，最多 `width` 列，超出时最后一列显示省略号
+    ///
+    /// 返回输出的列数。
+    fn write_spans(&self, writer: &mut impl Write, spans: &[(&str, Style)], col_offset: usize, width: usize) -> io::Result<usize> {
+        let total = spans.iter().fold(0, |x, (span, _)| text::advance(span, x));
+        let more = total > col_offset + width && width > 0;
+        let max = if more { width - 1 } else { width };
+        let mut x = 0;
+        let mut written = 0;
+        for (span, style) in spans {
+            let (visible, _) = text::clip(span, x, col_offset, max);
+            x = text::advance(span, x);
+            if visible.is_empty() {
+                continue;
+            }
+            match style {
+                Style::Plain => {}
+                Style::Match => queue!(writer, SetBackgroundColor(Color::Yellow), SetForegroundColor(Color::Black))?,
+                Style::CurrentMatch => queue!(
+                    writer,
+                    SetBackgroundColor(Color::Rgb { r: 230, g: 120, b: 40 }),
+                    SetForegroundColor(Color::Black)
+                )?,
+                Style::Syntax(kind) => queue!(writer, SetForegroundColor(syntax_color(*kind)))?,
+                Style::Selection => queue!(
+                    writer,
+                    SetBackgroundColor(Color::Rgb { r: 70, g: 80, b: 120 }),
+                    SetForegroundColor(Color::White)
+                )?,
+            }
+            write!(writer, "{}", visible)?;
+            written += text::str_width(&visible);
+            if *style != Style::Plain {
+                queue!(writer, ResetColor)?;
+            }
         }
+        if more {
+            write!(writer, "…")?;
+            written += 1;
+        }
+        Ok(written)
     }
     
-    /// 渲染状态栏（使用 queue! 宏的缓冲版本）
+    /// 渲染窗口的状态栏（使用 queue! 宏的缓冲版本），只有当前窗口显示模式
     fn render_status_bar_buffered(
         &self,
         writer: &mut impl Write,
-        buffer: &Buffer,
+        view: &View,
         cursor: &Cursor,
         mode: &Mode,
     ) -> io::Result<()> {
-        queue!(writer, Clear(ClearType::CurrentLine))?;
-        queue!(writer, SetBackgroundColor(Color::DarkGrey), SetForegroundColor(Color::White))?;
+        if view.active {
+            queue!(writer, SetBackgroundColor(Color::DarkGrey), SetForegroundColor(Color::White))?;
+        } else {
+            queue!(writer, SetBackgroundColor(Color::Rgb { r: 40, g: 40, b: 50 }),
+                   SetForegroundColor(Color::Rgb { r: 140, g: 140, b: 150 }))?;
+        }
         
         // 模式
-        let mode_str = match mode {
-            Mode::Normal => " NORMAL ",
-            Mode::Insert => " INSERT ",
-            Mode::Command => " COMMAND ",
+        let mode_str = if !view.active {
+            ""
+        } else {
+            match mode {
+                Mode::Normal => " NORMAL ",
+                Mode::Insert => " INSERT ",
+                Mode::Command => " COMMAND ",
+                Mode::Search => " SEARCH ",
+                Mode::Visual => " VISUAL ",
+                Mode::VisualLine => " V-LINE ",
+                Mode::VisualBlock => " V-BLOCK ",
+            }
         };
         
         // 文件信息
-        let file_info = format!(" {} ", "cntmd");
+        let file_info = format!(" {}{} ", view.name, if view.modified { " [+]" } else { "" });
         
         // 位置信息
         let pos_info = format!(" {}:{} ", cursor.row + 1, cursor.col + 1);
         
-        // 行数信息
-        let lines_info = format!(" {} lines ", buffer.line_count());
+        // 行数信息和高亮的语言
+        let line_count = view.buffer.line_count();
+        let lines_info = match self.language(view.number).map(|l| &l.name) {
+            Some(name) => format!(" {} | {} lines ", name, line_count),
+            None => format!(" {} lines ", line_count),
+        };
         
-        // 计算填充
+        // 计算填充，窗口太窄时截断
         let left = format!("{}{}", mode_str, file_info);
         let right = format!("{}{}", lines_info, pos_info);
-        let padding = (self.width as usize).saturating_sub(left.len() + right.len());
+        let width = view.rect.width as usize;
+        let padding = width.saturating_sub(text::str_width(&left) + text::str_width(&right));
+        let line = format!("{}{:padding$}{}", left, "", right, padding = padding);
+        let (visible, _) = text::clip(&line, 0, 0, width);
         
-        write!(writer, "{}{:padding$}{}", left, "", right, padding = padding)?;
+        write!(writer, "{}{:2$}", visible, "", width.saturating_sub(text::str_width(&visible)))?;
         
         queue!(writer, ResetColor)?;
         
@@ -298,10 +569,14 @@ impl Renderer {
             LineType::Empty,
             LineType::Shortcut("Tab/→", "接受补全"),
             LineType::Shortcut("hjkl", "移动光标"),
+            LineType::Shortcut("u/Ctrl+R", "撤销/重做"),
+            LineType::Shortcut("/ ? n N", "搜索"),
+            LineType::Shortcut("v/V/^V", "选择文本"),
+            LineType::Shortcut(":e/:sp", "打开文件/分割窗口"),
             LineType::Empty,
             LineType::Separator,
             LineType::Empty,
-            LineType::Usage("cntmd <文件>"),
+            LineType::Usage("cntmd <文件>..."),
             LineType::Empty,
         ];
         
@@ -385,3 +660,39 @@ impl Renderer {
     }
 }
 
+/// 记号的颜色
+fn syntax_color(kind: TokenKind) -> Color {
+    match kind {
+        TokenKind::Keyword => Color::Rgb { r: 200, g: 120, b: 220 },
+        TokenKind::Builtin => Color::Rgb { r: 100, g: 180, b: 200 },
+        TokenKind::String => Color::Rgb { r: 150, g: 200, b: 110 },
+        TokenKind::Comment => Color::Rgb { r: 110, g: 110, b: 120 },
+        TokenKind::Number => Color::Rgb { r: 220, g: 160, b: 90 },
+        TokenKind::Variable => Color::Rgb { r: 220, g: 110, b: 110 },
+        TokenKind::Key => Color::Rgb { r: 100, g: 150, b: 230 },
+    }
+}
+
+/// 更新滚动偏移，使光标（`cursor_x` 为屏幕列）在窗口内
+fn update_scroll(scroll: &mut Scroll, cursor: &Cursor, cursor_x: usize, text_height: usize, text_width: usize) {
+    if cursor.row < scroll.row {
+        scroll.row = cursor.row;
+    } else if cursor.row >= scroll.row + text_height {
+        scroll.row = cursor.row + 1 - text_height.max(1);
+    }
+    
+    // 光标超出左右边界时水平滚动，宽字符需要完整显示
+    if cursor_x < scroll.col {
+        scroll.col = cursor_x;
+    } else if cursor_x + 2 > scroll.col + text_width {
+        scroll.col = (cursor_x + 2).saturating_sub(text_width);
+    }
+}
+
+/// 截断或用空格补齐到 `width` 列
+fn pad(s: &str, width: usize) -> String {
+    let (mut out, _) = text::clip(s, 0, 0, width);
+    let used = text::str_width(&out);
+    out.extend(std::iter::repeat(' ').take(width - used));
+    out
+}
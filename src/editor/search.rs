@@ -0,0 +1,319 @@
+//! 搜索与替换
+//!
+//! `/` 和 `?` 使用正则表达式（`regex` 语法）搜索，模式中的 `\c` 表示忽略大小写。
+//! `:[范围]s/模式/替换/[标志]` 替换文本：范围支持 `%`、`.`、`$`、行号、上一次可视选择的
+//! `'<`/`'>` 和 `+N`/`-N`，
+//! 标志支持 `g`（一行内全部替换）、`c`（逐个确认）和 `i`（忽略大小写）。
+//! 替换文本中 `&` 和 `\0` 表示整个匹配，`\1`…`\9` 表示分组。
+//!
+//! 匹配位置的列为字节偏移，换成光标列时使用 `text::grapheme_col`。
+
+use regex::{Regex, RegexBuilder};
+
+use super::buffer::Buffer;
+
+/// 一处匹配，列为字节偏移
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    pub row: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// 编译搜索模式
+pub fn compile(pattern: &str, ignore_case: bool) -> Result<Regex, String> {
+    let ignore_case = ignore_case || pattern.contains("\\c");
+    RegexBuilder::new(&pattern.replace("\\c", ""))
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|e| match e {
+            regex::Error::Syntax(_) => format!("无效的模式: {}", pattern),
+            other => other.to_string(),
+        })
+}
+
+/// 从 (row, byte) 之后查找下一处（或上一处）匹配，到达文件一端时从另一端继续
+///
+/// 返回匹配和是否绕回了。
+pub fn find(buffer: &Buffer, regex: &Regex, row: usize, byte: usize, forward: bool) -> Option<(Match, bool)> {
+    let count = buffer.line_count();
+    let row = row.min(count - 1);
+    for i in 0..=count {
+        let (r, wrapped) = if forward {
+            ((row + i) % count, row + i >= count)
+        } else {
+            ((row + count - i % count) % count, i > row)
+        };
+        let line = buffer.get_line(r);
+        let mut matches = regex.find_iter(line);
+        // 起始行先找光标之后（之前）的部分，绕回一圈后再找另一部分
+        let found = match (forward, i) {
+            (true, 0) => matches.find(|m| m.start() > byte),
+            (true, i) if i == count => matches.find(|m| m.start() <= byte),
+            (true, _) => matches.next(),
+            (false, 0) => matches.filter(|m| m.start() < byte).last(),
+            (false, i) if i == count => matches.filter(|m| m.start() >= byte).last(),
+            (false, _) => matches.last(),
+        };
+        if let Some(m) = found {
+            return Some((Match { row: r, start: m.start(), end: m.end() }, wrapped));
+        }
+    }
+    None
+}
+
+/// 一次 `:s` 替换
+pub struct Substitute {
+    regex: Regex,
+    /// `regex` 展开语法的替换文本
+    replacement: String,
+    /// 一行内替换全部匹配
+    global: bool,
+    /// 逐个确认
+    pub confirm: bool,
+    /// 下次查找开始的位置
+    row: usize,
+    byte: usize,
+    /// 范围的最后一行
+    end_row: usize,
+    /// 上一处匹配的结束位置，紧跟其后的空匹配不算
+    last_end: Option<(usize, usize)>,
+    /// 已替换的次数
+    pub count: usize,
+}
+
+impl Substitute {
+    /// 解析 `[范围]s/模式/替换/[标志]`，不是替换命令时返回 None
+    ///
+    /// 模式为空时使用上一次搜索的模式，`visual` 为上一次可视选择的首尾行。
+    pub fn parse(
+        cmd: &str,
+        buffer: &Buffer,
+        cursor_row: usize,
+        visual: Option<(usize, usize)>,
+        last_pattern: Option<&Regex>,
+    ) -> Option<Result<Self, String>> {
+        let (range, rest) = parse_range(cmd, buffer, cursor_row, visual);
+        let rest = rest.strip_prefix("substitute").or_else(|| rest.strip_prefix('s'))?;
+        let delimiter = rest.chars().next()?;
+        if delimiter.is_alphanumeric() || delimiter == '\\' || delimiter == '"' || delimiter.is_whitespace() {
+            return None;
+        }
+        Some(Self::build(range, &rest[delimiter.len_utf8()..], delimiter, last_pattern))
+    }
+
+    fn build(range: Result<(usize, usize), String>, rest: &str, delimiter: char, last_pattern: Option<&Regex>) -> Result<Self, String> {
+        let (start_row, end_row) = range?;
+        let parts = split_unescaped(rest, delimiter);
+        let pattern = parts.first().cloned().unwrap_or_default();
+        let replacement = parts.get(1).cloned().unwrap_or_default();
+        let flags = parts.get(2).cloned().unwrap_or_default();
+        if let Some(c) = flags.chars().find(|c| !"gci".contains(*c)) {
+            return Err(format!("无效的标志: {}", c));
+        }
+
+        let regex = if pattern.is_empty() {
+            match last_pattern {
+                Some(regex) if !flags.contains('i') => regex.clone(),
+                Some(regex) => compile(regex.as_str(), true)?,
+                None => return Err("没有上一次搜索的模式".to_string()),
+            }
+        } else {
+            compile(&pattern, flags.contains('i'))?
+        };
+
+        Ok(Self {
+            regex,
+            replacement: convert_replacement(&replacement),
+            global: flags.contains('g'),
+            confirm: flags.contains('c'),
+            row: start_row,
+            byte: 0,
+            end_row,
+            last_end: None,
+            count: 0,
+        })
+    }
+
+    /// 使用的模式（作为下一次搜索的模式）
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+
+    /// 下一处要替换的匹配
+    pub fn next_match(&mut self, buffer: &Buffer) -> Option<Match> {
+        while self.row <= self.end_row && self.row < buffer.line_count() {
+            let line = buffer.get_line(self.row);
+            let mut at = self.byte;
+            while at <= line.len() {
+                let m = match self.regex.find_at(line, at) {
+                    Some(m) => m,
+                    None => break,
+                };
+                if m.start() == m.end() && self.last_end == Some((self.row, m.start())) {
+                    // 跳过紧跟在上一处匹配后的空匹配
+                    at = m.start() + line[m.start()..].chars().next().map_or(1, char::len_utf8);
+                    continue;
+                }
+                return Some(Match { row: self.row, start: m.start(), end: m.end() });
+            }
+            self.row += 1;
+            self.byte = 0;
+            self.last_end = None;
+        }
+        None
+    }
+
+    /// 替换一处匹配，返回替换后文本的结束位置（行, 字节）
+    pub fn replace(&mut self, buffer: &mut Buffer, m: Match) -> (usize, usize) {
+        let line = buffer.get_line(m.row).to_string();
+        let mut text = String::new();
+        if let Some(caps) = self.regex.captures_at(&line, m.start) {
+            caps.expand(&self.replacement, &mut text);
+        }
+        buffer.replace_range(m.row, m.start, m.end, &text);
+        self.count += 1;
+
+        // 替换文本中的换行会增加行数
+        let newlines = text.matches('\n').count();
+        self.end_row += newlines;
+        let end = match text.rfind('\n') {
+            Some(i) => (m.row + newlines, text.len() - i - 1),
+            None => (m.row, m.start + text.len()),
+        };
+        self.advance(end);
+        end
+    }
+
+    /// 跳过一处匹配（确认时选择不替换）
+    pub fn skip(&mut self, m: Match) {
+        self.advance((m.row, m.end));
+    }
+
+    /// 从匹配（或替换后文本）的结束处继续；没有 `g` 标志时换到下一行
+    fn advance(&mut self, (row, byte): (usize, usize)) {
+        if self.global {
+            self.row = row;
+            self.byte = byte;
+            self.last_end = Some((row, byte));
+        } else {
+            self.row = row + 1;
+            self.byte = 0;
+            self.last_end = None;
+        }
+    }
+
+    /// 不确认，替换范围内的全部匹配
+    pub fn run(&mut self, buffer: &mut Buffer) -> Option<(usize, usize)> {
+        let mut last = None;
+        while let Some(m) = self.next_match(buffer) {
+            self.replace(buffer, m);
+            last = Some(m);
+        }
+        last.map(|m| (m.row, m.start))
+    }
+}
+
+/// 解析行范围，返回 (起始行, 结束行)（从 0 开始）和剩下的命令
+///
+/// 没有范围时为光标所在行。
+pub(super) fn parse_range<'a>(
+    cmd: &'a str,
+    buffer: &Buffer,
+    cursor_row: usize,
+    visual: Option<(usize, usize)>,
+) -> (Result<(usize, usize), String>, &'a str) {
+    let last = buffer.line_count() - 1;
+    if let Some(rest) = cmd.strip_prefix('%') {
+        return (Ok((0, last)), rest);
+    }
+    let (start, rest) = parse_address(cmd, cursor_row, last, visual);
+    let (end, rest) = match rest.strip_prefix(',') {
+        Some(rest) => parse_address(rest, cursor_row, last, visual),
+        None => (start.clone(), rest),
+    };
+    let range = match (start.unwrap_or(Ok(cursor_row)), end.unwrap_or(Ok(cursor_row))) {
+        (Ok(start), Ok(end)) if start > end => Err("范围颠倒了".to_string()),
+        (Ok(_), Ok(end)) if end > last => Err(format!("超出范围: 只有 {} 行", last + 1)),
+        (Ok(start), Ok(end)) => Ok((start, end)),
+        (Err(e), _) | (_, Err(e)) => Err(e),
+    };
+    (range, rest)
+}
+
+/// 解析一个地址：`.`、`$`、行号、`'<`/`'>`，可以跟 `+N`/`-N`
+fn parse_address(s: &str, cursor_row: usize, last: usize, visual: Option<(usize, usize)>) -> (Option<Result<usize, String>>, &str) {
+    let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let mark = s.strip_prefix("'<").map(|rest| (rest, true)).or_else(|| s.strip_prefix("'>").map(|rest| (rest, false)));
+    if let Some((rest, start)) = mark {
+        let row = match visual {
+            Some((first, last)) => Ok(if start { first } else { last }),
+            None => Err("没有可视选择".to_string()),
+        };
+        return (Some(row), rest);
+    }
+    let (mut row, mut rest) = if let Some(rest) = s.strip_prefix('.') {
+        (Some(cursor_row as i64), rest)
+    } else if let Some(rest) = s.strip_prefix('$') {
+        (Some(last as i64), rest)
+    } else {
+        let n = digits(s);
+        match s[..n].parse::<i64>() {
+            Ok(line) => (Some(line - 1), &s[n..]),
+            Err(_) => (None, s),
+        }
+    };
+    while let Some(sign) = rest.chars().next().filter(|c| *c == '+' || *c == '-') {
+        let n = digits(&rest[1..]);
+        let offset = if n == 0 { 1 } else { rest[1..1 + n].parse::<i64>().unwrap_or(0) };
+        let base = row.unwrap_or(cursor_row as i64);
+        row = Some(if sign == '+' { base + offset } else { base - offset });
+        rest = &rest[1 + n..];
+    }
+    let row = row.map(|row| usize::try_from(row).map_err(|_| "无效的范围".to_string()));
+    (row, rest)
+}
+
+/// 按未转义的分隔符切分，`\分隔符` 换成分隔符本身，其他转义保留
+fn split_unescaped(s: &str, delimiter: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        let part = parts.last_mut().unwrap();
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if next == delimiter => part.push(next),
+                Some(next) => {
+                    part.push('\\');
+                    part.push(next);
+                }
+                None => part.push('\\'),
+            },
+            c if c == delimiter => parts.push(String::new()),
+            c => part.push(c),
+        }
+    }
+    parts
+}
+
+/// 把 vim 风格的替换文本换成 `regex` 的展开语法
+fn convert_replacement(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(d) if d.is_ascii_digit() => out.push_str(&format!("${{{}}}", d)),
+                Some('n') | Some('r') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('$') => out.push_str("$$"),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            },
+            '&' => out.push_str("${0}"),
+            '$' => out.push_str("$$"),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,87 @@
+//! 脚本中的 shell 命令
+//!
+//! 编辑 shell 脚本时，把光标所在的那条命令交给主补全引擎，和交互式 shell 中一样补全
+//! 子命令和选项。一行中用 `;`、`&&`、`||`、`|`、`&`、`(` 等分隔的多条命令只取光标所在的
+//! 一条，以 `\` 结尾的续行和下一行合在一起；`if`、`then`、`do` 等关键字和命令前的
+//! `NAME=value` 赋值不算命令的一部分。
+
+use super::Buffer;
+
+/// 命令前面可以出现的关键字
+const KEYWORDS: &[&str] = &["if", "then", "else", "elif", "while", "until", "do", "!", "{"];
+
+/// 光标之前的命令，光标在注释或引号中时为 None
+///
+/// `col` 为字素簇列号。
+pub fn command_at(buffer: &Buffer, row: usize, col: usize) -> Option<String> {
+    // 向上找续行
+    let mut first = row;
+    while first > 0 && buffer.get_line(first - 1).ends_with('\\') {
+        first -= 1;
+    }
+    let mut text = String::new();
+    for r in first..row {
+        let line = buffer.get_line(r);
+        text.push_str(&line[..line.len() - 1]);
+        text.push(' ');
+    }
+    text.push_str(buffer.prefix(row, col));
+    current_command(&text).map(str::to_string)
+}
+
+/// `text` 是光标之前的文本，返回其中最后一条命令（去掉开头的关键字和赋值）
+pub fn current_command(text: &str) -> Option<&str> {
+    let mut start = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut word_start = true;
+    for (i, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+            word_start = false;
+            continue;
+        }
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (Some('"'), '"') => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '#') if word_start => return None,
+            (None, ';' | '&' | '|' | '(' | ')' | '`') => start = i + 1,
+            _ => {}
+        }
+        word_start = quote.is_none() && (c.is_whitespace() || matches!(c, ';' | '&' | '|' | '(' | ')' | '`'));
+    }
+    if quote.is_some() {
+        return None;
+    }
+
+    // 去掉已经输完的关键字和赋值，正在输入的词保留
+    let mut command = text[start..].trim_start();
+    while let Some((word, rest)) = command.split_once(char::is_whitespace) {
+        if !KEYWORDS.contains(&word) && !is_assignment(word) {
+            break;
+        }
+        command = rest.trim_start();
+    }
+    Some(command)
+}
+
+/// 是否是 `NAME=value` 形式的赋值
+fn is_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    }
+}
+
+/// 命令中正在输入的词（最后一个空白之后的部分）
+pub fn current_word(command: &str) -> &str {
+    command.rsplit(char::is_whitespace).next().unwrap_or("")
+}
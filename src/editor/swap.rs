@@ -0,0 +1,184 @@
+//! 交换文件
+//!
+//! 有未保存的修改时，定期把缓冲区内容写到 `~/.cnmsb/swap/` 下的交换文件，编辑器意外
+//! 退出后再打开同一个文件时提示用 `:recover` 恢复。保存或正常退出时删除交换文件。
+//!
+//! 交换文件名是文件的绝对路径把 `/` 换成 `%`，如 `%home%me%a.sh.swp`。内容是几行
+//! `键: 值` 的文件头、一个空行和缓冲区的文本：
+//!
+//! ```text
+//! cnmsb swap
+//! pid: 1234
+//! file: /home/me/a.sh
+//! time: 1700000000
+//!
+//! echo hello
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::buffer::{self, Buffer};
+
+/// 交换文件的第一行
+const MAGIC: &str = "cnmsb swap";
+
+/// 交换文件所在的目录
+pub fn swap_dir() -> PathBuf {
+    dirs::home_dir()
+        .map(|h| h.join(".cnmsb"))
+        .unwrap_or_else(|| PathBuf::from(".cnmsb"))
+        .join("swap")
+}
+
+/// 一个文件的交换文件
+#[derive(Debug, Clone)]
+pub struct SwapFile {
+    /// 编辑的文件
+    file: PathBuf,
+    /// 交换文件
+    path: PathBuf,
+    /// 上一次写入时缓冲区的修改次数，这次运行还没写过时为 None
+    written: Option<u64>,
+    /// 打开时已经有交换文件（另一个编辑器正在编辑，或上次意外退出留下的），
+    /// 恢复或删除之前不覆盖它
+    foreign: bool,
+}
+
+impl SwapFile {
+    /// `file` 在 `dir` 中的交换文件
+    pub fn new(dir: &Path, file: &Path) -> Self {
+        let absolute = fs::canonicalize(file).unwrap_or_else(|_| {
+            std::env::current_dir().map(|cwd| cwd.join(file)).unwrap_or_else(|_| file.to_path_buf())
+        });
+        let name: String = absolute.to_string_lossy()
+            .chars()
+            .map(|c| if matches!(c, '/' | '\\' | ':') { '%' } else { c })
+            .collect();
+        let path = dir.join(format!("{}.swp", name));
+        Self {
+            file: file.to_path_buf(),
+            foreign: path.exists(),
+            path,
+            written: None,
+        }
+    }
+
+    /// 编辑的文件
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// 交换文件的路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 是否是打开时已经存在的交换文件
+    pub fn is_foreign(&self) -> bool {
+        self.foreign
+    }
+
+    /// 恢复之后由这个编辑器接管交换文件
+    pub fn claim(&mut self) {
+        self.foreign = false;
+        self.written = Some(0);
+    }
+
+    /// 删除打开时已经存在的交换文件
+    pub fn discard(&mut self) -> io::Result<()> {
+        fs::remove_file(&self.path)?;
+        self.foreign = false;
+        Ok(())
+    }
+
+    /// 上一次写入之后缓冲区又修改了几次
+    pub fn pending(&self, buffer: &Buffer) -> u64 {
+        buffer.changes().saturating_sub(self.written.unwrap_or(0))
+    }
+
+    /// 把缓冲区的内容写到交换文件
+    ///
+    /// 失败时也记下修改次数，下次有新的修改时再试。
+    pub fn write(&mut self, buffer: &Buffer) -> io::Result<()> {
+        self.written = Some(buffer.changes());
+        fs::create_dir_all(self.path.parent().unwrap_or(Path::new(".")))?;
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let text = buffer.lines().collect::<Vec<_>>().join("\n");
+        let content = format!(
+            "{}\npid: {}\nfile: {}\ntime: {}\n\n{}",
+            MAGIC, std::process::id(), self.file.display(), time, text
+        );
+        buffer::write_atomic(&self.path, content.as_bytes())
+    }
+
+    /// 读取交换文件，没有或格式不对时为 None
+    pub fn read(&self) -> Option<Recovered> {
+        let content = fs::read_to_string(&self.path).ok()?;
+        let (header, text) = content.split_once("\n\n")?;
+        let mut lines = header.lines();
+        if lines.next()? != MAGIC {
+            return None;
+        }
+        let mut recovered = Recovered { pid: 0, time: 0, text: text.to_string() };
+        for line in lines {
+            match line.split_once(": ") {
+                Some(("pid", pid)) => recovered.pid = pid.parse().ok()?,
+                Some(("time", time)) => recovered.time = time.parse().ok()?,
+                _ => {}
+            }
+        }
+        Some(recovered)
+    }
+
+    /// 删除这次运行写过的交换文件（别的编辑器留下的不删）
+    pub fn remove(&mut self) {
+        if self.written.take().is_some() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// 交换文件中的内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovered {
+    /// 写交换文件的进程
+    pub pid: u32,
+    /// 写入的时间（Unix 时间戳，秒）
+    pub time: u64,
+    /// 缓冲区的文本，行之间用 `\n` 分隔
+    pub text: String,
+}
+
+impl Recovered {
+    /// 写交换文件的编辑器是否还在运行（正在编辑同一个文件）
+    pub fn is_running(&self) -> bool {
+        self.pid != std::process::id() && process_exists(self.pid)
+    }
+
+    /// 写入时间距现在多久，如 "5 分钟前"
+    pub fn age(&self) -> String {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        match now.saturating_sub(self.time) {
+            s if s < 60 => "刚才".to_string(),
+            s if s < 3600 => format!("{} 分钟前", s / 60),
+            s if s < 86400 => format!("{} 小时前", s / 3600),
+            s => format!("{} 天前", s / 86400),
+        }
+    }
+}
+
+/// 进程是否存在
+#[cfg(unix)]
+fn process_exists(pid: u32) -> bool {
+    // 信号 0 只检查进程是否存在；没有权限发信号也说明进程存在
+    pid != 0 && (unsafe { libc::kill(pid as libc::pid_t, 0) } == 0
+        || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM))
+}
+
+#[cfg(not(unix))]
+fn process_exists(_pid: u32) -> bool {
+    false
+}
@@ -0,0 +1,551 @@
+//! 语法高亮
+//!
+//! 每种语言的规则是一份 TOML（关键字、注释、字符串、变量等），内置 shell、Python、
+//! Rust、Go、YAML、Dockerfile 和 Makefile，用户可以在 ~/.cnmsb/syntax/ 下添加
+//! `*.toml` 文件增加语言，或用同名的 `name` 覆盖内置规则：
+//!
+//! ```toml
+//! name = "toml"
+//! extensions = ["toml"]
+//! keywords = ["true", "false"]
+//! line_comment = ["#"]
+//! strings = ["\"", "'"]
+//! keys = true
+//! ```
+//!
+//! 多行的字符串和块注释会影响之后的行，所以每行记下开始和结束时的状态。
+//! 修改后只重新分析内容或开始状态变了的行。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::buffer::Buffer;
+
+/// 记号类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Keyword,
+    /// 内置命令、类型和常量
+    Builtin,
+    String,
+    Comment,
+    Number,
+    /// `$VAR`、`${VAR}`、`$(VAR)`
+    Variable,
+    /// YAML 等配置文件的键
+    Key,
+}
+
+/// 一行中的记号，位置为字节偏移
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub start: usize,
+    pub end: usize,
+    pub kind: TokenKind,
+}
+
+/// 一种语言的高亮规则
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Language {
+    pub name: String,
+    /// 扩展名（不含点，不区分大小写）
+    pub extensions: Vec<String>,
+    /// 完整文件名（不区分大小写），如 Dockerfile
+    pub filenames: Vec<String>,
+    /// 第一行 `#!` 中的解释器名，如 bash、python3
+    pub shebangs: Vec<String>,
+    pub keywords: Vec<String>,
+    pub builtins: Vec<String>,
+    /// 关键字不区分大小写（Dockerfile）
+    pub ignore_case: bool,
+    /// 行注释的开头；`#` 只在行首或空白之后才是注释
+    pub line_comment: Vec<String>,
+    /// 块注释的开头和结尾
+    pub block_comment: Option<(String, String)>,
+    /// 字符串的引号，先列出的优先（如 `"""` 要在 `"` 之前）
+    pub strings: Vec<String>,
+    /// 可以跨行的字符串引号
+    pub multiline_strings: Vec<String>,
+    /// 高亮 `$` 开头的变量
+    pub variables: bool,
+    /// 高亮行首 `键:` 形式的键
+    pub keys: bool,
+}
+
+impl Language {
+    /// 解析 TOML 规则
+    pub fn from_toml(content: &str) -> Result<Self, String> {
+        let language: Language = toml::from_str(content).map_err(|e| e.to_string())?;
+        if language.name.is_empty() {
+            return Err("缺少 name".to_string());
+        }
+        // 空的分隔符在每个位置都能匹配，分析时不会前进
+        if language.strings.iter().chain(&language.multiline_strings).any(String::is_empty) {
+            return Err("strings 中有空的引号".to_string());
+        }
+        if language.line_comment.iter().any(String::is_empty) {
+            return Err("line_comment 中有空的注释符".to_string());
+        }
+        if language.block_comment.as_ref().is_some_and(|(open, close)| open.is_empty() || close.is_empty()) {
+            return Err("block_comment 的开头和结尾不能为空".to_string());
+        }
+        Ok(language)
+    }
+
+    fn is_keyword(&self, word: &str, list: &[String]) -> bool {
+        if self.ignore_case {
+            list.iter().any(|k| k.eq_ignore_ascii_case(word))
+        } else {
+            list.iter().any(|k| k == word)
+        }
+    }
+
+    /// 分析一行，返回记号和行尾的状态
+    pub fn tokenize(&self, line: &str, state: State) -> (Vec<Token>, State) {
+        let mut tokens = Vec::new();
+        let mut pos = 0;
+
+        // 续上一行未结束的字符串或注释
+        match state {
+            State::Normal => {}
+            State::BlockComment => match self.block_end(line, 0) {
+                Some(end) => {
+                    tokens.push(Token { start: 0, end, kind: TokenKind::Comment });
+                    pos = end;
+                }
+                None => {
+                    push_nonempty(&mut tokens, 0, line.len(), TokenKind::Comment);
+                    return (tokens, State::BlockComment);
+                }
+            },
+            State::String(i) => match self.string_end(line, 0, &self.strings[i]) {
+                Some(end) => {
+                    tokens.push(Token { start: 0, end, kind: TokenKind::String });
+                    pos = end;
+                }
+                None => {
+                    push_nonempty(&mut tokens, 0, line.len(), TokenKind::String);
+                    return (tokens, state);
+                }
+            },
+        }
+
+        if self.keys {
+            if let Some((start, end)) = yaml_key(line) {
+                if start >= pos {
+                    tokens.push(Token { start, end, kind: TokenKind::Key });
+                    pos = end;
+                }
+            }
+        }
+
+        let bytes = line.as_bytes();
+        while pos < line.len() {
+            let rest = &line[pos..];
+            let after_space = pos == 0 || bytes[pos - 1].is_ascii_whitespace();
+
+            if let Some(marker) = self.line_comment.iter().find(|m| rest.starts_with(m.as_str())) {
+                if marker != "#" || after_space {
+                    tokens.push(Token { start: pos, end: line.len(), kind: TokenKind::Comment });
+                    break;
+                }
+            }
+
+            if let Some((open, _)) = &self.block_comment {
+                if rest.starts_with(open.as_str()) {
+                    match self.block_end(line, pos + open.len()) {
+                        Some(end) => {
+                            tokens.push(Token { start: pos, end, kind: TokenKind::Comment });
+                            pos = end;
+                            continue;
+                        }
+                        None => {
+                            tokens.push(Token { start: pos, end: line.len(), kind: TokenKind::Comment });
+                            return (tokens, State::BlockComment);
+                        }
+                    }
+                }
+            }
+
+            if let Some(i) = self.strings.iter().position(|q| rest.starts_with(q.as_str())) {
+                let quote = &self.strings[i];
+                match self.string_end(line, pos + quote.len(), quote) {
+                    Some(end) => {
+                        tokens.push(Token { start: pos, end, kind: TokenKind::String });
+                        pos = end;
+                        continue;
+                    }
+                    None => {
+                        tokens.push(Token { start: pos, end: line.len(), kind: TokenKind::String });
+                        let state = if self.multiline_strings.contains(quote) { State::String(i) } else { State::Normal };
+                        return (tokens, state);
+                    }
+                }
+            }
+
+            let c = rest.chars().next().unwrap_or(' ');
+            if self.variables && c == '$' {
+                if let Some(len) = variable_len(rest) {
+                    tokens.push(Token { start: pos, end: pos + len, kind: TokenKind::Variable });
+                    pos += len;
+                    continue;
+                }
+            }
+
+            let word_start = pos == 0 || !is_word_byte(bytes[pos - 1]);
+            if c.is_ascii_digit() && word_start {
+                let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.')).unwrap_or(rest.len());
+                tokens.push(Token { start: pos, end: pos + len, kind: TokenKind::Number });
+                pos += len;
+                continue;
+            }
+
+            if (c.is_ascii_alphabetic() || c == '_') && word_start {
+                let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+                let word = &rest[..len];
+                if self.is_keyword(word, &self.keywords) {
+                    tokens.push(Token { start: pos, end: pos + len, kind: TokenKind::Keyword });
+                } else if self.is_keyword(word, &self.builtins) {
+                    tokens.push(Token { start: pos, end: pos + len, kind: TokenKind::Builtin });
+                }
+                pos += len;
+                continue;
+            }
+
+            pos += c.len_utf8();
+        }
+        (tokens, State::Normal)
+    }
+
+    /// 块注释结尾之后的位置
+    fn block_end(&self, line: &str, from: usize) -> Option<usize> {
+        let (_, close) = self.block_comment.as_ref()?;
+        line[from..].find(close.as_str()).map(|i| from + i + close.len())
+    }
+
+    /// 字符串结尾之后的位置，`\` 转义下一个字符
+    fn string_end(&self, line: &str, from: usize, quote: &str) -> Option<usize> {
+        let mut chars = line[from..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            if c == '\\' {
+                chars.next();
+            } else if line[from + i..].starts_with(quote) {
+                return Some(from + i + quote.len());
+            }
+        }
+        None
+    }
+}
+
+/// 行开始时的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum State {
+    #[default]
+    Normal,
+    /// 在块注释中
+    BlockComment,
+    /// 在跨行的字符串中（`Language::strings` 的序号）
+    String(usize),
+}
+
+fn push_nonempty(tokens: &mut Vec<Token>, start: usize, end: usize, kind: TokenKind) {
+    if start < end {
+        tokens.push(Token { start, end, kind });
+    }
+}
+
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+/// `$` 开头的变量长度：`$NAME`、`$1`、`$@`、`${...}`、`$(NAME)`
+fn variable_len(s: &str) -> Option<usize> {
+    let rest = &s[1..];
+    let c = rest.chars().next()?;
+    if let Some(close) = match c {
+        '{' => Some('}'),
+        '(' => Some(')'),
+        _ => None,
+    } {
+        let end = rest.find(close)?;
+        let name = &rest[1..end];
+        // $(命令 参数) 是命令替换，只高亮 $(NAME) 形式的 make 变量
+        if c == '(' && !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return None;
+        }
+        return Some(end + 2);
+    }
+    if c.is_ascii_alphabetic() || c == '_' {
+        return Some(1 + rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len()));
+    }
+    if c.is_ascii_digit() || "@#?$!*-".contains(c) {
+        return Some(2);
+    }
+    None
+}
+
+/// 行首的键（`key:`、`- key:`），返回键的位置
+fn yaml_key(line: &str) -> Option<(usize, usize)> {
+    let start = line.len() - line.trim_start_matches([' ', '\t', '-']).len();
+    let rest = &line[start..];
+    let colon = rest.find(':')?;
+    let key = &rest[..colon];
+    let after = &rest[colon + 1..];
+    let valid = !key.is_empty()
+        && !key.starts_with(['#', '"', '\'', '{', '['])
+        && !key.contains(char::is_whitespace)
+        && (after.is_empty() || after.starts_with(char::is_whitespace));
+    valid.then_some((start, start + colon))
+}
+
+/// 一行的分析结果
+#[derive(Debug, Clone)]
+struct LineCache {
+    text: String,
+    /// 行开始时的状态，None 表示还没有分析
+    start: Option<State>,
+    end: State,
+    tokens: Vec<Token>,
+}
+
+/// 带缓存的高亮器
+#[derive(Debug, Clone)]
+pub struct Highlighter {
+    language: Language,
+    lines: Vec<LineCache>,
+}
+
+impl Highlighter {
+    pub fn new(language: Language) -> Self {
+        Self { language, lines: Vec::new() }
+    }
+
+    pub fn language(&self) -> &Language {
+        &self.language
+    }
+
+    /// 分析到第 `last` 行（包含），返回重新分析的行数
+    ///
+    /// 行数变化时先按变化量对齐缓存，插入或删除行之后的行不需要重新分析。
+    pub fn update(&mut self, buffer: &Buffer, last: usize) -> usize {
+        let count = buffer.line_count();
+        if count != self.lines.len() {
+            let changed = (0..count.min(self.lines.len()))
+                .find(|&row| self.lines[row].text != buffer.get_line(row))
+                .unwrap_or(count.min(self.lines.len()));
+            if count > self.lines.len() {
+                // 新插入的行还没有分析过
+                let added = count - self.lines.len();
+                let placeholder = LineCache { text: String::new(), start: None, end: State::Normal, tokens: Vec::new() };
+                self.lines.splice(changed..changed, std::iter::repeat(placeholder).take(added));
+            } else {
+                self.lines.drain(changed..changed + (self.lines.len() - count));
+            }
+        }
+
+        let mut analyzed = 0;
+        let mut state = State::Normal;
+        for row in 0..=last.min(count.saturating_sub(1)) {
+            let text = buffer.get_line(row);
+            let cached = &self.lines[row];
+            if cached.start != Some(state) || cached.text != text {
+                let (tokens, end) = self.language.tokenize(text, state);
+                self.lines[row] = LineCache { text: text.to_string(), start: Some(state), end, tokens };
+                analyzed += 1;
+            }
+            state = self.lines[row].end;
+        }
+        analyzed
+    }
+
+    /// 已分析的一行的记号
+    pub fn tokens(&self, row: usize) -> &[Token] {
+        self.lines.get(row).map(|l| l.tokens.as_slice()).unwrap_or(&[])
+    }
+}
+
+/// 全部语言规则
+#[derive(Debug, Clone, Default)]
+pub struct Languages {
+    languages: Vec<Language>,
+    /// 读取用户规则时出错的文件和原因
+    errors: Vec<String>,
+}
+
+impl Languages {
+    /// 内置规则
+    pub fn builtin() -> Self {
+        let languages = BUILTIN
+            .iter()
+            .map(|rules| Language::from_toml(rules).expect("内置语法规则无效"))
+            .collect();
+        Self { languages, errors: Vec::new() }
+    }
+
+    /// 内置规则加上 ~/.cnmsb/syntax/ 下的用户规则，出错的规则文件跳过，见 `errors`
+    pub fn load() -> Self {
+        let mut languages = Self::builtin();
+        if let Some(dir) = user_syntax_dir() {
+            languages.errors = languages.load_dir(&dir);
+        }
+        languages
+    }
+
+    /// `load` 时出错的用户规则文件和原因
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    /// 读取目录下的 `*.toml` 规则，同名的替换已有规则，返回出错的文件和原因
+    pub fn load_dir(&mut self, dir: &Path) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries.flatten().map(|e| e.path()).collect(),
+            Err(_) => return errors,
+        };
+        paths.sort();
+        for path in paths.iter().filter(|p| p.extension().is_some_and(|e| e == "toml")) {
+            match fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|c| Language::from_toml(&c)) {
+                Ok(language) => self.add(language),
+                Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        errors
+    }
+
+    /// 添加规则，用户规则排在前面，优先匹配
+    pub fn add(&mut self, language: Language) {
+        self.languages.retain(|l| l.name != language.name);
+        self.languages.insert(0, language);
+    }
+
+    /// 按名称查找
+    pub fn get(&self, name: &str) -> Option<&Language> {
+        self.languages.iter().find(|l| l.name.eq_ignore_ascii_case(name))
+    }
+
+    /// 按文件名、扩展名和第一行的 `#!` 判断语言
+    pub fn detect(&self, path: &Path, first_line: &str) -> Option<&Language> {
+        let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+        let matches = |list: &[String], value: &str| !value.is_empty() && list.iter().any(|v| v.eq_ignore_ascii_case(value));
+
+        self.languages.iter().find(|l| matches(&l.filenames, &filename))
+            .or_else(|| self.languages.iter().find(|l| matches(&l.extensions, &ext)))
+            .or_else(|| {
+                // #!/usr/bin/env python3、#!/bin/bash
+                let shebang = first_line.strip_prefix("#!")?;
+                let mut words = shebang.split_whitespace();
+                let mut program = words.next()?.rsplit('/').next()?;
+                if program == "env" {
+                    program = words.find(|w| !w.starts_with('-'))?;
+                }
+                self.languages.iter().find(|l| matches(&l.shebangs, program))
+            })
+    }
+}
+
+/// 用户规则目录
+pub fn user_syntax_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".cnmsb").join("syntax"))
+}
+
+const BUILTIN: &[&str] = &[SHELL, PYTHON, RUST, GO, YAML, DOCKERFILE, MAKEFILE];
+
+const SHELL: &str = r##"
+name = "shell"
+extensions = ["sh", "bash", "zsh"]
+filenames = [".bashrc", ".bash_profile", ".zshrc", ".profile"]
+shebangs = ["sh", "bash", "zsh", "dash", "ksh"]
+keywords = ["if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done",
+            "case", "esac", "in", "function", "return", "select", "time"]
+builtins = ["echo", "printf", "read", "cd", "export", "local", "readonly", "declare", "set",
+            "unset", "shift", "exit", "source", "test", "eval", "exec", "trap", "alias",
+            "true", "false"]
+line_comment = ["#"]
+strings = ["\"", "'"]
+multiline_strings = ["\"", "'"]
+variables = true
+"##;
+
+const PYTHON: &str = r##"
+name = "python"
+extensions = ["py", "pyw"]
+shebangs = ["python", "python3", "python2"]
+keywords = ["and", "as", "assert", "async", "await", "break", "class", "continue", "def",
+            "del", "elif", "else", "except", "finally", "for", "from", "global", "if",
+            "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise",
+            "return", "try", "while", "with", "yield"]
+builtins = ["True", "False", "None", "self", "print", "len", "range", "open", "int", "str",
+            "float", "list", "dict", "set", "tuple", "bool", "isinstance", "super"]
+line_comment = ["#"]
+strings = ['"""', "'''", "\"", "'"]
+multiline_strings = ['"""', "'''"]
+"##;
+
+const RUST: &str = r##"
+name = "rust"
+extensions = ["rs"]
+keywords = ["as", "async", "await", "break", "const", "continue", "crate", "dyn", "else",
+            "enum", "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "static", "struct", "super", "trait",
+            "type", "unsafe", "use", "where", "while"]
+builtins = ["self", "Self", "true", "false", "Some", "None", "Ok", "Err", "Option", "Result",
+            "String", "Vec", "Box", "bool", "char", "str", "u8", "u16", "u32", "u64", "usize",
+            "i8", "i16", "i32", "i64", "isize", "f32", "f64"]
+line_comment = ["//"]
+block_comment = ["/*", "*/"]
+strings = ["\""]
+multiline_strings = ["\""]
+"##;
+
+const GO: &str = r##"
+name = "go"
+extensions = ["go"]
+keywords = ["break", "case", "chan", "const", "continue", "default", "defer", "else",
+            "fallthrough", "for", "func", "go", "goto", "if", "import", "interface", "map",
+            "package", "range", "return", "select", "struct", "switch", "type", "var"]
+builtins = ["true", "false", "nil", "iota", "string", "int", "int64", "uint", "byte", "rune",
+            "bool", "error", "float64", "make", "new", "len", "cap", "append", "panic"]
+line_comment = ["//"]
+block_comment = ["/*", "*/"]
+strings = ["\"", "`"]
+multiline_strings = ["`"]
+"##;
+
+const YAML: &str = r##"
+name = "yaml"
+extensions = ["yml", "yaml"]
+builtins = ["true", "false", "null", "yes", "no", "on", "off"]
+line_comment = ["#"]
+strings = ["\"", "'"]
+keys = true
+"##;
+
+const DOCKERFILE: &str = r##"
+name = "dockerfile"
+extensions = ["dockerfile"]
+filenames = ["dockerfile", "containerfile"]
+keywords = ["FROM", "RUN", "CMD", "LABEL", "EXPOSE", "ENV", "ADD", "COPY", "ENTRYPOINT",
+            "VOLUME", "USER", "WORKDIR", "ARG", "ONBUILD", "STOPSIGNAL", "HEALTHCHECK",
+            "SHELL", "AS"]
+ignore_case = true
+line_comment = ["#"]
+strings = ["\"", "'"]
+variables = true
+"##;
+
+const MAKEFILE: &str = r##"
+name = "makefile"
+extensions = ["mk"]
+filenames = ["makefile", "gnumakefile"]
+keywords = ["ifeq", "ifneq", "ifdef", "ifndef", "else", "endif", "include", "define",
+            "endef", "export", "override"]
+builtins = [".PHONY"]
+line_comment = ["#"]
+strings = ["\"", "'"]
+variables = true
+"##;
@@ -0,0 +1,100 @@
+//! 字素簇与显示宽度
+//!
+//! 缓冲区中的列号以字素簇（用户看到的一个字符，如 "é"、"👨‍👩‍👧"）计，
+//! 屏幕上的列号以显示宽度计：中文、全角符号和 emoji 占两列，组合字符不占列，
+//! 制表符展开到下一个制表位。
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+/// 制表位间隔
+pub const TAB_WIDTH: usize = 4;
+
+/// 字符串的字素簇
+pub fn graphemes(s: &str) -> impl Iterator<Item = &str> {
+    s.graphemes(true)
+}
+
+/// 字素簇个数
+pub fn grapheme_count(s: &str) -> usize {
+    graphemes(s).count()
+}
+
+/// 第 `col` 个字素簇开始的字节偏移，超出时为字符串末尾
+pub fn byte_offset(s: &str, col: usize) -> usize {
+    s.grapheme_indices(true).nth(col).map(|(i, _)| i).unwrap_or(s.len())
+}
+
+/// 字节偏移所在的字素簇序号
+pub fn grapheme_col(s: &str, byte: usize) -> usize {
+    s.grapheme_indices(true).take_while(|(i, _)| *i < byte).count()
+}
+
+/// `prefix` 中最后一个分隔符之后的部分，即光标前正在输入的词（分隔符可以是全角空格等多字节字符）
+pub fn word_before(prefix: &str, is_delimiter: impl FnMut(char) -> bool) -> &str {
+    prefix.rsplit(is_delimiter).next().unwrap_or("")
+}
+
+/// 一个字素簇在第 `x` 列开始时占的列数
+pub fn grapheme_width(g: &str, x: usize) -> usize {
+    if g == "\t" {
+        return TAB_WIDTH - x % TAB_WIDTH;
+    }
+    let mut chars = g.chars();
+    let width = chars.next().and_then(|c| c.width()).unwrap_or(0);
+    // 带 emoji 变体选择符或零宽连接符的序列显示为一个宽字符
+    if g.contains('\u{FE0F}') || g.contains('\u{200D}') {
+        2
+    } else {
+        width
+    }
+}
+
+/// 从行首开始显示的宽度
+pub fn str_width(s: &str) -> usize {
+    advance(s, 0)
+}
+
+/// 从第 `x` 列开始显示后到达的列
+pub fn advance(s: &str, x: usize) -> usize {
+    graphemes(s).fold(x, |x, g| x + grapheme_width(g, x))
+}
+
+/// 显示宽度不超过 `width` 的最后一个字素簇的序号（宽字符中间算它本身）
+pub fn col_at_width(s: &str, width: usize) -> usize {
+    let mut x = 0;
+    for (col, g) in graphemes(s).enumerate() {
+        let w = grapheme_width(g, x);
+        if x + w > width {
+            return col;
+        }
+        x += w;
+    }
+    grapheme_count(s)
+}
+
+/// 截取从第 `start` 列开始、最多 `max` 列的可见部分，`s` 从第 `x` 列开始显示
+///
+/// 被窗口边界切开的宽字符用空格代替，制表符展开为空格。返回可见文本和右边是否还有内容。
+pub fn clip(s: &str, mut x: usize, start: usize, max: usize) -> (String, bool) {
+    let mut out = String::new();
+    let end = start + max;
+    for g in graphemes(s) {
+        let w = grapheme_width(g, x);
+        if x + w > end {
+            out.extend(std::iter::repeat(' ').take(end.saturating_sub(x.max(start))));
+            return (out, true);
+        }
+        if x >= start {
+            if g == "\t" {
+                out.extend(std::iter::repeat(' ').take(w));
+            } else {
+                out.push_str(g);
+            }
+        } else if x + w > start {
+            out.extend(std::iter::repeat(' ').take(x + w - start));
+        }
+        x += w;
+    }
+    (out, false)
+}
@@ -0,0 +1,160 @@
+//! 撤销/重做历史
+//!
+//! 缓冲区的每次修改都记录为一次插入或删除文本（可以跨行），撤销时执行相反的操作。
+//! 插入模式下的一段输入合并为一步，保存文件不会清空历史，也不会结束正在合并的一步。
+
+/// 最多保留的撤销步数
+const MAX_STEPS: usize = 1000;
+
+/// 一次修改，位置为修改开始处（列为字节偏移）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    /// 在 (row, col) 插入文本，文本中的换行会拆分行
+    Insert { row: usize, col: usize, text: String },
+    /// 删除从 (row, col) 开始的文本
+    Delete { row: usize, col: usize, text: String },
+}
+
+impl Edit {
+    /// 相反的修改
+    pub fn inverse(&self) -> Edit {
+        match self {
+            Edit::Insert { row, col, text } => Edit::Delete { row: *row, col: *col, text: text.clone() },
+            Edit::Delete { row, col, text } => Edit::Insert { row: *row, col: *col, text: text.clone() },
+        }
+    }
+
+    /// 是否没有修改任何文本
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Edit::Insert { text, .. } | Edit::Delete { text, .. } => text.is_empty(),
+        }
+    }
+
+    /// 修改开始的位置
+    pub fn start(&self) -> (usize, usize) {
+        match self {
+            Edit::Insert { row, col, .. } | Edit::Delete { row, col, .. } => (*row, *col),
+        }
+    }
+
+    /// 执行后光标应在的位置：插入文本的末尾，或删除处
+    pub fn end(&self) -> (usize, usize) {
+        match self {
+            Edit::Insert { row, col, text } => match text.rfind('\n') {
+                Some(i) => (row + text.matches('\n').count(), text.len() - i - 1),
+                None => (*row, col + text.len()),
+            },
+            Edit::Delete { row, col, .. } => (*row, *col),
+        }
+    }
+}
+
+/// 一个撤销步骤
+#[derive(Debug, Clone)]
+struct UndoStep {
+    /// 唯一编号，用于判断是否回到了保存时的状态
+    id: u64,
+    edits: Vec<Edit>,
+}
+
+/// 线性的撤销/重做历史
+#[derive(Debug, Default)]
+pub struct UndoHistory {
+    undo: Vec<UndoStep>,
+    redo: Vec<UndoStep>,
+    /// 正在合并的一组修改（插入模式）
+    group: Option<Vec<Edit>>,
+    next_id: u64,
+    /// 保存时的状态：最后一步的编号和正在合并的修改数
+    saved: (u64, usize),
+}
+
+impl UndoHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次修改，新的修改会清空重做历史
+    pub fn record(&mut self, edit: Edit) {
+        self.redo.clear();
+        match self.group {
+            Some(ref mut edits) => edits.push(edit),
+            None => self.push_step(vec![edit]),
+        }
+    }
+
+    /// 开始合并修改，直到 `end_group`
+    pub fn begin_group(&mut self) {
+        if self.group.is_none() {
+            self.group = Some(Vec::new());
+        }
+    }
+
+    /// 结束合并，把这一组修改作为一步
+    pub fn end_group(&mut self) {
+        if let Some(edits) = self.group.take() {
+            if edits.is_empty() {
+                return;
+            }
+            let (base, len) = (self.current_id(), edits.len());
+            self.push_step(edits);
+            // 在合并中途保存过：保存后没有再修改时保存的就是这一步，否则撤销也回不到保存时的状态
+            if self.saved.0 == base && self.saved.1 > 0 {
+                self.saved = if self.saved.1 == len { (self.next_id, 0) } else { (u64::MAX, 0) };
+            }
+        }
+    }
+
+    fn push_step(&mut self, edits: Vec<Edit>) {
+        self.next_id += 1;
+        self.undo.push(UndoStep { id: self.next_id, edits });
+        if self.undo.len() > MAX_STEPS {
+            self.undo.remove(0);
+        }
+    }
+
+    /// 取出要撤销的一步，返回按执行顺序排列的相反修改
+    pub fn take_undo(&mut self) -> Option<Vec<Edit>> {
+        self.end_group();
+        let step = self.undo.pop()?;
+        let inverse = step.edits.iter().rev().map(Edit::inverse).collect();
+        self.redo.push(step);
+        Some(inverse)
+    }
+
+    /// 取出要重做的一步
+    pub fn take_redo(&mut self) -> Option<Vec<Edit>> {
+        self.end_group();
+        let step = self.redo.pop()?;
+        let edits = step.edits.clone();
+        self.undo.push(step);
+        Some(edits)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || self.group.as_ref().is_some_and(|g| !g.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// 记下保存时的状态，正在合并的修改继续合并
+    pub fn mark_saved(&mut self) {
+        self.saved = self.current_state();
+    }
+
+    /// 与保存时的状态是否不同
+    pub fn is_modified(&self) -> bool {
+        self.current_state() != self.saved
+    }
+
+    fn current_id(&self) -> u64 {
+        self.undo.last().map(|s| s.id).unwrap_or(0)
+    }
+
+    fn current_state(&self) -> (u64, usize) {
+        (self.current_id(), self.group.as_ref().map_or(0, Vec::len))
+    }
+}
@@ -0,0 +1,254 @@
+//! 可视模式（选择文本）
+//!
+//! `v` 按字符选择，`V` 按行选择，`Ctrl+V` 按列块选择。选择的一端固定在进入可视模式
+//! 时的位置，另一端随光标移动；`o` 交换两端。块选择按屏幕列计算，宽字符只要有一部分
+//! 在块内就算选中。
+//!
+//! 这里只解析按键和计算选择范围，执行在 `Editor` 中。
+
+use super::buffer::Buffer;
+use super::mode::Mode;
+use super::normal::{self, Motion, Operator, Range};
+use super::register::Registers;
+use super::text;
+
+/// 选择的形状
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    /// `v` 按字符
+    Chars,
+    /// `V` 按行
+    Lines,
+    /// `Ctrl+V` 按列块
+    Block,
+}
+
+impl Shape {
+    /// 模式对应的选择形状，不是可视模式时为 None
+    pub fn of(mode: Mode) -> Option<Self> {
+        match mode {
+            Mode::Visual => Some(Shape::Chars),
+            Mode::VisualLine => Some(Shape::Lines),
+            Mode::VisualBlock => Some(Shape::Block),
+            _ => None,
+        }
+    }
+
+    /// 对应的模式
+    pub fn mode(self) -> Mode {
+        match self {
+            Shape::Chars => Mode::Visual,
+            Shape::Lines => Mode::VisualLine,
+            Shape::Block => Mode::VisualBlock,
+        }
+    }
+}
+
+/// 当前选择
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    /// 固定的一端 (行, 列)
+    pub anchor: (usize, usize),
+    /// 光标所在的一端
+    pub cursor: (usize, usize),
+    pub shape: Shape,
+}
+
+impl Selection {
+    /// 靠前的一端
+    pub fn start(&self) -> (usize, usize) {
+        self.anchor.min(self.cursor)
+    }
+
+    /// 靠后的一端（包含）
+    pub fn end(&self) -> (usize, usize) {
+        self.anchor.max(self.cursor)
+    }
+
+    /// 第一行和最后一行
+    pub fn rows(&self) -> (usize, usize) {
+        (self.start().0, self.end().0)
+    }
+
+    /// 按字符或按行选择时操作的范围；块选择时为所在的整行
+    pub fn range(&self, buffer: &Buffer) -> Range {
+        let (first, last) = self.rows();
+        match self.shape {
+            Shape::Lines | Shape::Block => Range { start: (first, 0), end: (last, 0), linewise: true },
+            Shape::Chars => {
+                let (row, col) = self.end();
+                // 结尾包含光标下的字符，光标在行尾时包含换行
+                let end = if col >= buffer.line_len(row) && row + 1 < buffer.line_count() {
+                    (row + 1, 0)
+                } else {
+                    (row, (col + 1).min(buffer.line_len(row)))
+                };
+                Range { start: self.start(), end, linewise: false }
+            }
+        }
+    }
+
+    /// 块选择的屏幕列范围 [左, 右)
+    pub fn block_columns(&self, buffer: &Buffer) -> (usize, usize) {
+        let edge = |(row, col): (usize, usize)| {
+            let x = buffer.display_col(row, col);
+            let width = buffer.grapheme(row, col).map_or(1, |g| text::grapheme_width(g, x).max(1));
+            (x, x + width)
+        };
+        let (a, b) = (edge(self.anchor), edge(self.cursor));
+        (a.0.min(b.0), a.1.max(b.1))
+    }
+
+    /// 一行中被选中的列范围 [开始, 结束)，这一行没有选中内容时为 None
+    pub fn columns(&self, buffer: &Buffer, row: usize) -> Option<(usize, usize)> {
+        let (first, last) = self.rows();
+        if row < first || row > last || row >= buffer.line_count() {
+            return None;
+        }
+        let len = buffer.line_len(row);
+        match self.shape {
+            Shape::Lines => Some((0, len)),
+            Shape::Chars => {
+                let range = self.range(buffer);
+                let start = if row == first { range.start.1 } else { 0 };
+                let end = if row == range.end.0 { range.end.1 } else { len };
+                Some((start, end.max(start)))
+            }
+            Shape::Block => {
+                let (left, right) = self.block_columns(buffer);
+                let line = buffer.get_line(row);
+                let (mut start, mut end) = (None, len);
+                let mut x = 0;
+                for (col, g) in text::graphemes(line).enumerate() {
+                    let w = text::grapheme_width(g, x);
+                    if start.is_none() && x + w > left {
+                        start = Some(col);
+                    }
+                    if x >= right {
+                        end = col;
+                        break;
+                    }
+                    x += w;
+                }
+                start.map(|start| (start, end))
+            }
+        }
+    }
+}
+
+/// 可视模式中的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// 移动光标，扩大或缩小选择
+    Move(Motion),
+    /// 对选择执行操作符
+    Operate(Operator),
+    /// `I`/`A` 在选择之前/之后插入，块选择时每一行都插入
+    Insert(char),
+    /// `o` 交换两端
+    SwapEnds,
+    /// `v`、`V`、`Ctrl+V` 切换形状，与当前形状相同时退出
+    Switch(Shape),
+    /// `:` 对选中的行执行命令
+    Command,
+}
+
+/// 一条可视模式命令
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Command {
+    pub register: Option<char>,
+    pub count: Option<usize>,
+    pub action: Action,
+}
+
+/// 解析结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parse {
+    Complete(Command),
+    /// 命令还没输入完
+    Pending,
+    /// 不是有效的命令
+    Invalid,
+}
+
+/// 解析可视模式中已输入的按键：`["寄存器][次数]按键`
+pub fn parse(keys: &str) -> Parse {
+    let mut chars = keys.chars().peekable();
+
+    let mut register = None;
+    if chars.peek() == Some(&'"') {
+        chars.next();
+        match chars.next() {
+            None => return Parse::Pending,
+            Some(c) if Registers::is_valid(c) => register = Some(c),
+            Some(_) => return Parse::Invalid,
+        }
+    }
+
+    let count = normal::read_count(&mut chars);
+    let key = match chars.next() {
+        Some(key) => key,
+        None => return Parse::Pending,
+    };
+    let action = match key {
+        'd' | 'x' => Action::Operate(Operator::Delete),
+        'c' | 's' => Action::Operate(Operator::Change),
+        'y' => Action::Operate(Operator::Yank),
+        '>' => Action::Operate(Operator::Indent),
+        '<' => Action::Operate(Operator::Outdent),
+        'I' | 'A' => Action::Insert(key),
+        'o' => Action::SwapEnds,
+        'v' => Action::Switch(Shape::Chars),
+        'V' => Action::Switch(Shape::Lines),
+        ':' => Action::Command,
+        'g' => match chars.next() {
+            None => return Parse::Pending,
+            Some('g') => Action::Move(Motion::FirstLine),
+            Some('c') => Action::Operate(Operator::Comment),
+            Some(_) => return Parse::Invalid,
+        },
+        c => match Motion::from_key(c) {
+            Some(motion) => Action::Move(motion),
+            None => return Parse::Invalid,
+        },
+    };
+    Parse::Complete(Command { register, count, action })
+}
+
+/// 块选择中 `I`、`A`、`c` 开始的插入
+///
+/// 插入模式结束时，把第一行输入的文本插入到其余各行的同一屏幕列。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockInsert {
+    /// 第一行开始插入的位置
+    pub start: (usize, usize),
+    /// 块的最后一行
+    pub last: usize,
+    /// 插入的屏幕列
+    pub x: usize,
+    /// 不够长的行用空格补齐（`A`），否则跳过
+    pub pad: bool,
+}
+
+impl BlockInsert {
+    /// 插入结束时光标在 `end`，把第一行新输入的文本复制到其余各行
+    ///
+    /// 输入了换行或光标离开了开始的位置时不复制。
+    pub fn apply(&self, buffer: &mut Buffer, end: (usize, usize)) {
+        if end.0 != self.start.0 || end.1 <= self.start.1 {
+            return;
+        }
+        let text = buffer.text_range(self.start, end);
+        for row in self.start.0 + 1..=self.last.min(buffer.line_count() - 1) {
+            let width = text::str_width(buffer.get_line(row));
+            if width < self.x {
+                if !self.pad {
+                    continue;
+                }
+                buffer.append_to_line(row, &" ".repeat(self.x - width));
+            }
+            let col = buffer.col_at_display(row, self.x);
+            buffer.insert_text(row, col, &text);
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! 窗口与分割
+//!
+//! 屏幕可以上下（`:sp`）或左右（`:vs`）分割成多个窗口，每个窗口显示一个缓冲区，
+//! 有各自的光标和滚动位置，同一个缓冲区可以同时显示在多个窗口中。
+//!
+//! 布局是一棵二叉树，叶子是窗口，每次分割平分所在的区域；左右分割时中间留一列分隔线。
+//! 窗口的区域包含最后一行的状态栏。
+
+use super::cursor::Cursor;
+
+/// 屏幕上的矩形区域
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+/// 分割方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// 上下排列（`:sp`）
+    Horizontal,
+    /// 左右排列（`:vs`）
+    Vertical,
+}
+
+/// 滚动位置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Scroll {
+    /// 第一行显示的行号
+    pub row: usize,
+    /// 水平滚动的屏幕列
+    pub col: usize,
+}
+
+/// 一个窗口
+#[derive(Debug, Clone)]
+pub struct Window {
+    pub id: usize,
+    /// 显示的缓冲区编号
+    pub buffer: usize,
+    /// 光标（当前窗口的光标在 `Editor` 中，切换窗口时同步）
+    pub cursor: Cursor,
+    pub scroll: Scroll,
+}
+
+/// 布局树
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layout {
+    Window(usize),
+    Split(Direction, Box<Layout>, Box<Layout>),
+}
+
+impl Layout {
+    /// 把窗口 `target` 分成两半，`new` 在上面（左边）
+    fn split(&mut self, target: usize, new: usize, direction: Direction) -> bool {
+        match self {
+            Layout::Window(id) if *id == target => {
+                *self = Layout::Split(direction, Box::new(Layout::Window(new)), Box::new(Layout::Window(target)));
+                true
+            }
+            Layout::Window(_) => false,
+            Layout::Split(_, first, second) => first.split(target, new, direction) || second.split(target, new, direction),
+        }
+    }
+
+    /// 去掉窗口，它的位置由同一次分割的另一半占据
+    fn remove(&mut self, target: usize) -> bool {
+        let Layout::Split(_, first, second) = self else {
+            return false;
+        };
+        if **first == Layout::Window(target) {
+            *self = std::mem::replace(second, Layout::Window(0));
+            true
+        } else if **second == Layout::Window(target) {
+            *self = std::mem::replace(first, Layout::Window(0));
+            true
+        } else {
+            first.remove(target) || second.remove(target)
+        }
+    }
+
+    /// 各窗口的区域，按从上到下、从左到右的顺序
+    fn rects(&self, area: Rect, out: &mut Vec<(usize, Rect)>) {
+        match self {
+            Layout::Window(id) => out.push((*id, area)),
+            Layout::Split(Direction::Horizontal, first, second) => {
+                let top = area.height / 2;
+                first.rects(Rect { height: top, ..area }, out);
+                second.rects(Rect { y: area.y + top, height: area.height - top, ..area }, out);
+            }
+            Layout::Split(Direction::Vertical, first, second) => {
+                let left = area.width.saturating_sub(1) / 2;
+                let right = area.width.saturating_sub(left + 1);
+                first.rects(Rect { width: left, ..area }, out);
+                second.rects(Rect { x: area.x + left + 1, width: right, ..area }, out);
+            }
+        }
+    }
+}
+
+/// 全部窗口和布局
+#[derive(Debug, Clone)]
+pub struct Windows {
+    windows: Vec<Window>,
+    layout: Layout,
+    /// 当前窗口
+    active: usize,
+    next_id: usize,
+}
+
+impl Windows {
+    /// 一个显示 `buffer` 的窗口
+    pub fn new(buffer: usize) -> Self {
+        Self {
+            windows: vec![Window { id: 1, buffer, cursor: Cursor::new(), scroll: Scroll::default() }],
+            layout: Layout::Window(1),
+            active: 1,
+            next_id: 2,
+        }
+    }
+
+    /// 窗口个数
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// 当前窗口
+    pub fn active(&self) -> &Window {
+        self.get(self.active).expect("当前窗口不存在")
+    }
+
+    pub fn active_mut(&mut self) -> &mut Window {
+        let active = self.active;
+        self.windows.iter_mut().find(|w| w.id == active).expect("当前窗口不存在")
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Window> {
+        self.windows.iter().find(|w| w.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Window> {
+        self.windows.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Window> {
+        self.windows.iter_mut()
+    }
+
+    /// 分割当前窗口，新窗口显示同一个缓冲区并成为当前窗口，返回它的编号
+    pub fn split(&mut self, direction: Direction) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let window = Window { id, ..self.active().clone() };
+        self.layout.split(self.active, id, direction);
+        self.windows.push(window);
+        self.active = id;
+        id
+    }
+
+    /// 关闭当前窗口，切换到布局中的下一个窗口；只剩一个窗口时不关闭
+    pub fn close(&mut self) -> bool {
+        if self.windows.len() == 1 {
+            return false;
+        }
+        let order = self.order();
+        let index = order.iter().position(|&id| id == self.active).unwrap_or(0);
+        let next = if index + 1 < order.len() { order[index + 1] } else { order[index - 1] };
+        self.layout.remove(self.active);
+        let active = self.active;
+        self.windows.retain(|w| w.id != active);
+        self.active = next;
+        true
+    }
+
+    /// 只保留当前窗口
+    pub fn only(&mut self) {
+        let active = self.active;
+        self.windows.retain(|w| w.id == active);
+        self.layout = Layout::Window(active);
+    }
+
+    /// 切换到窗口 `id`
+    pub fn focus(&mut self, id: usize) -> bool {
+        let exists = self.get(id).is_some();
+        if exists {
+            self.active = id;
+        }
+        exists
+    }
+
+    /// 布局中的下一个（上一个）窗口
+    pub fn next(&self, forward: bool) -> usize {
+        let order = self.order();
+        let index = order.iter().position(|&id| id == self.active).unwrap_or(0);
+        let len = order.len();
+        order[if forward { (index + 1) % len } else { (index + len - 1) % len }]
+    }
+
+    /// 在 `h`/`j`/`k`/`l` 方向上与当前窗口相邻的窗口
+    pub fn neighbor(&self, area: Rect, key: char) -> Option<usize> {
+        let rects = self.rects(area);
+        let current = rects.iter().find(|(id, _)| *id == self.active)?.1;
+        let overlaps = |a: u16, a_len: u16, b: u16, b_len: u16| a < b + b_len && b < a + a_len;
+        rects.iter()
+            .filter(|(id, _)| *id != self.active)
+            .filter(|(_, r)| match key {
+                'h' => r.x + r.width < current.x && overlaps(r.y, r.height, current.y, current.height),
+                'l' => r.x > current.x + current.width && overlaps(r.y, r.height, current.y, current.height),
+                'k' => r.y + r.height <= current.y && overlaps(r.x, r.width, current.x, current.width),
+                'j' => r.y >= current.y + current.height && overlaps(r.x, r.width, current.x, current.width),
+                _ => false,
+            })
+            // 取最近的，距离相同时取布局中靠前的
+            .min_by_key(|(_, r)| match key {
+                'h' => current.x - (r.x + r.width),
+                'l' => r.x - (current.x + current.width),
+                'k' => current.y - (r.y + r.height),
+                _ => r.y - (current.y + current.height),
+            })
+            .map(|(id, _)| *id)
+    }
+
+    /// 各窗口在 `area` 中的区域
+    pub fn rects(&self, area: Rect) -> Vec<(usize, Rect)> {
+        let mut rects = Vec::new();
+        self.layout.rects(area, &mut rects);
+        rects
+    }
+
+    /// 布局中窗口的顺序
+    fn order(&self) -> Vec<usize> {
+        let area = Rect { x: 0, y: 0, width: u16::MAX, height: u16::MAX };
+        self.rects(area).into_iter().map(|(id, _)| id).collect()
+    }
+}
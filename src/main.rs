@@ -3,6 +3,8 @@
 
 use clap::{Parser, Subcommand};
 use cnmsb::{CompletionEngine, CnmsbShell, SqlShell, DatabaseType, run_editor, AiConfig, AiCompleter};
+use cnmsb::sql::{profile, DatabaseConfig, SafeMode};
+use cnmsb::sql::format::{self, FormatOptions, KeywordCase};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -51,15 +53,49 @@ enum Commands {
 
     /// 启动 SQL 交互式客户端（带智能补全）
     Sql {
-        /// 数据库类型 (mysql/postgresql/sqlite)
+        /// 连接目标：@配置名（见 ~/.config/cnmsb/connections.toml）或连接 URL
+        target: Option<String>,
+
+        /// 数据库类型 (mysql/postgresql/sqlite/mariadb)
         #[arg(short, long)]
         db_type: Option<String>,
+
+        /// 危险语句安全模式 (off/confirm/refuse)
+        #[arg(long, default_value = "confirm")]
+        safe_mode: String,
+    },
+
+    /// 格式化 SQL（读取文件或标准输入，输出到标准输出）
+    #[command(name = "sql-fmt")]
+    SqlFmt {
+        /// SQL 文件，留空读取标准输入
+        file: Option<PathBuf>,
+
+        /// 数据库方言 (mysql/postgresql/sqlite/mariadb)
+        #[arg(short, long, default_value = "postgresql")]
+        db_type: String,
+
+        /// 关键字大小写 (upper/lower/preserve)
+        #[arg(short, long, default_value = "upper")]
+        case: String,
+
+        /// 每层缩进的空格数
+        #[arg(long, default_value_t = 4)]
+        indent: usize,
+
+        /// 超过这个宽度的列表和条件会换行
+        #[arg(long, default_value_t = 80)]
+        width: usize,
+
+        /// 直接改写文件而不是输出
+        #[arg(short, long)]
+        write: bool,
     },
 
     /// 编辑文件（操你他妈的编辑器，带智能补全）
     Edit {
-        /// 要编辑的文件
-        file: Option<PathBuf>,
+        /// 要编辑的文件，可以有多个
+        files: Vec<PathBuf>,
     },
 
     /// 显示版本信息
@@ -110,14 +146,14 @@ fn main() {
     
     // 如果通过 cnmsb-sql 调用，直接进入 SQL 模式
     if prog_name == "cnmsb-sql" || prog_name == "cnmsb-sql.exe" {
-        run_sql_mode(None);
+        run_sql_mode(None, args.get(1).cloned(), SafeMode::Confirm);
         return;
     }
     
     // 如果通过 cntmd 或 操你他妈的 调用，直接进入编辑器模式
     if prog_name == "cntmd" || prog_name == "cntmd.exe" || prog_name == "操你他妈的" {
-        let file = args.get(1).map(PathBuf::from);
-        run_editor_mode(file);
+        let files = args[1..].iter().map(PathBuf::from).collect();
+        run_editor_mode(files);
         return;
     }
     
@@ -266,8 +302,20 @@ fn main() {
             }
         }
 
-        Some(Commands::Sql { db_type }) => {
-            run_sql_mode(db_type);
+        Some(Commands::Sql { target, db_type, safe_mode }) => {
+            let safe_mode = match SafeMode::parse(&safe_mode) {
+                Some(m) => m,
+                None => {
+                    eprintln!("\x1b[31m不认识的安全模式: {}\x1b[0m", safe_mode);
+                    eprintln!("支持的模式: off, confirm, refuse");
+                    std::process::exit(1);
+                }
+            };
+            run_sql_mode(db_type, target, safe_mode);
+        }
+
+        Some(Commands::SqlFmt { file, db_type, case, indent, width, write }) => {
+            run_sql_fmt(file, &db_type, &case, indent, width, write);
         }
 
         Some(Commands::Version) => {
@@ -280,8 +328,8 @@ fn main() {
             engine.record_command(&command);
         }
 
-        Some(Commands::Edit { file }) => {
-            run_editor_mode(file);
+        Some(Commands::Edit { files }) => {
+            run_editor_mode(files);
         }
 
         Some(Commands::AiComplete { line, cursor }) => {
@@ -379,19 +427,77 @@ fn run_ai_config(action: &str, key: Option<&str>, value: Option<&str>) {
 }
 
 /// 运行编辑器模式
-fn run_editor_mode(file: Option<PathBuf>) {
-    if let Err(e) = run_editor(file) {
+fn run_editor_mode(files: Vec<PathBuf>) {
+    if let Err(e) = run_editor(files) {
         eprintln!("编辑器错误: {}", e);
         std::process::exit(1);
     }
 }
 
+/// 格式化 SQL 文件或标准输入
+fn run_sql_fmt(file: Option<PathBuf>, db_type: &str, case: &str, indent: usize, width: usize, write: bool) {
+    use std::io::Read;
+    
+    let db = DatabaseType::from_str(db_type).unwrap_or_else(|| {
+        eprintln!("\x1b[31m不认识的数据库类型: {}\x1b[0m", db_type);
+        eprintln!("支持的类型: mysql, postgresql, sqlite, mariadb");
+        std::process::exit(1);
+    });
+    let keyword_case = KeywordCase::parse(case).unwrap_or_else(|| {
+        eprintln!("\x1b[31m不认识的大小写: {}\x1b[0m", case);
+        eprintln!("支持的选项: upper, lower, preserve");
+        std::process::exit(1);
+    });
+    if write && file.is_none() {
+        eprintln!("\x1b[31m--write 需要指定文件\x1b[0m");
+        std::process::exit(1);
+    }
+    
+    let input = match file {
+        Some(ref path) => std::fs::read_to_string(path),
+        None => {
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input).map(|_| input)
+        }
+    };
+    let input = input.unwrap_or_else(|e| {
+        eprintln!("\x1b[31m读取失败: {}\x1b[0m", e);
+        std::process::exit(1);
+    });
+    
+    let options = FormatOptions { keyword_case, indent, width };
+    let output = format::format(&input, db, &options);
+    match file {
+        Some(path) if write => {
+            if let Err(e) = std::fs::write(&path, format!("{}\n", output)) {
+                eprintln!("\x1b[31m写入 {} 失败: {}\x1b[0m", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+        _ => println!("{}", output),
+    }
+}
+
 /// 运行 SQL 模式
-fn run_sql_mode(db_type: Option<String>) {
+fn run_sql_mode(db_type: Option<String>, target: Option<String>, safe_mode: SafeMode) {
     use std::io::{self, Write};
     
-    let db = match db_type {
-        Some(t) => {
+    // 命令行指定的连接目标决定数据库类型
+    let target_config = target.map(|t| {
+        let config = if t.starts_with('@') {
+            profile::find_profile(&t)
+        } else {
+            DatabaseConfig::parse(&t).ok_or_else(|| format!("无法解析连接: {}", t))
+        };
+        config.unwrap_or_else(|e| {
+            eprintln!("\x1b[31m{}\x1b[0m", e);
+            std::process::exit(1);
+        })
+    });
+    
+    let db = match (db_type, &target_config) {
+        (_, Some(config)) => config.db_type,
+        (Some(t), None) => {
             match DatabaseType::from_str(&t) {
                 Some(db) => db,
                 None => {
@@ -401,7 +507,7 @@ fn run_sql_mode(db_type: Option<String>) {
                 }
             }
         }
-        None => {
+        (None, None) => {
             println!("\x1b[1;33m");
             println!("  ███████╗ ██████╗ ██╗         ███╗   ███╗ ██████╗ ██████╗ ███████╗");
             println!("  ██╔════╝██╔═══██╗██║         ████╗ ████║██╔═══██╗██╔══██╗██╔════╝");
@@ -417,8 +523,9 @@ fn run_sql_mode(db_type: Option<String>) {
             println!("    \x1b[1;36m1.\x1b[0m MySQL");
             println!("    \x1b[1;36m2.\x1b[0m PostgreSQL");
             println!("    \x1b[1;36m3.\x1b[0m SQLite");
+            println!("    \x1b[1;36m4.\x1b[0m MariaDB");
             println!();
-            print!("  输入选择 (1-3): ");
+            print!("  输入选择 (1-4): ");
             io::stdout().flush().unwrap();
             
             let mut input = String::new();
@@ -428,8 +535,9 @@ fn run_sql_mode(db_type: Option<String>) {
                 "1" | "mysql" => DatabaseType::MySQL,
                 "2" | "postgresql" | "postgres" | "psql" => DatabaseType::PostgreSQL,
                 "3" | "sqlite" | "sqlite3" => DatabaseType::SQLite,
+                "4" | "mariadb" | "maria" => DatabaseType::MariaDB,
                 _ => {
-                    eprintln!("\x1b[31m选个鸡巴！输入 1、2 或 3\x1b[0m");
+                    eprintln!("\x1b[31m选个鸡巴！输入 1、2、3 或 4\x1b[0m");
                     std::process::exit(1);
                 }
             }
@@ -441,6 +549,14 @@ fn run_sql_mode(db_type: Option<String>) {
     println!();
     
     let mut sql_shell = SqlShell::new(db);
+    sql_shell.set_safe_mode(safe_mode);
+    if let Some(config) = target_config {
+        let safe_string = config.to_safe_string();
+        match sql_shell.connect_config(config) {
+            Ok(()) => println!("  \x1b[32m✓ 已连接到 {}\x1b[0m", safe_string),
+            Err(e) => eprintln!("  \x1b[31m✗ 连接失败: {}\x1b[0m", e),
+        }
+    }
     if let Err(e) = sql_shell.run() {
         eprintln!("SQL Shell 错误: {}", e);
         std::process::exit(1);
@@ -1,7 +1,11 @@
 //! 数据库连接管理
 
-use super::database::DatabaseType;
+use super::database::{DatabaseConfig, DatabaseType, SslMode};
+use super::lexer;
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
 
 /// 数据库连接错误
 #[derive(Debug)]
@@ -26,12 +30,201 @@ impl fmt::Display for DbError {
 impl std::error::Error for DbError {}
 
 /// 列信息
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ColumnInfo {
     pub name: String,
     pub data_type: String,
     pub nullable: bool,
     pub primary_key: bool,
+    /// 默认值表达式
+    #[serde(default)]
+    pub default: Option<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+impl ColumnInfo {
+    pub fn new(name: &str, data_type: &str, nullable: bool, primary_key: bool) -> Self {
+        ColumnInfo {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            nullable,
+            primary_key,
+            default: None,
+            comment: None,
+        }
+    }
+}
+
+/// 索引信息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexInfo {
+    pub name: String,
+    /// 索引的列，按索引中的顺序
+    pub columns: Vec<String>,
+    pub unique: bool,
+    /// 是否是主键索引
+    pub primary: bool,
+}
+
+/// 外键：`table` 的 `columns` 引用 `ref_table` 的 `ref_columns`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForeignKey {
+    pub name: String,
+    pub table: String,
+    pub columns: Vec<String>,
+    pub ref_table: String,
+    pub ref_columns: Vec<String>,
+}
+
+impl ForeignKey {
+    /// 连接条件，如 `orders.user_id = users.id`；`table_alias`、`ref_alias` 为两边在 SQL 中使用的名字
+    pub fn join_condition(&self, table_alias: &str, ref_alias: &str) -> String {
+        self.columns.iter()
+            .zip(&self.ref_columns)
+            .map(|(column, ref_column)| format!("{}.{} = {}.{}", table_alias, column, ref_alias, ref_column))
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    }
+}
+
+/// 表的详细结构：列、索引和相关的外键
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TableInfo {
+    pub columns: Vec<ColumnInfo>,
+    pub indexes: Vec<IndexInfo>,
+    /// 表自己的外键和其他表引用它的外键
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+/// Schema 对象类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SchemaObjectKind {
+    Table,
+    View,
+    MaterializedView,
+    Sequence,
+    Function,
+}
+
+impl SchemaObjectKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            SchemaObjectKind::Table => "表",
+            SchemaObjectKind::View => "视图",
+            SchemaObjectKind::MaterializedView => "物化视图",
+            SchemaObjectKind::Sequence => "序列",
+            SchemaObjectKind::Function => "函数",
+        }
+    }
+}
+
+/// Schema（PostgreSQL）或数据库（MySQL）中的对象
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaObject {
+    pub name: String,
+    pub kind: SchemaObjectKind,
+}
+
+impl SchemaObject {
+    pub fn new(name: &str, kind: SchemaObjectKind) -> Self {
+        SchemaObject {
+            name: name.to_string(),
+            kind,
+        }
+    }
+}
+
+/// 数据库目录：所有 schema 及其中的对象
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Catalog {
+    pub schemas: Vec<String>,
+    /// 不加前缀即可引用的 schema（PostgreSQL 的 search_path、MySQL 的当前数据库）
+    pub search_path: Vec<String>,
+    /// (schema, 对象列表)
+    pub objects: Vec<(String, Vec<SchemaObject>)>,
+    #[serde(skip)]
+    search_order: Vec<(usize, String)>,
+}
+
+impl Catalog {
+    /// 记录 schema，`search_position` 为其在 search_path 中的位置
+    pub fn add_schema(&mut self, schema: &str, search_position: Option<usize>) {
+        if self.schemas.iter().any(|s| s == schema) {
+            return;
+        }
+        self.schemas.push(schema.to_string());
+        if let Some(position) = search_position {
+            self.search_order.push((position, schema.to_string()));
+        }
+    }
+    
+    /// 记录 schema 中的对象，重载的函数只记录一次
+    pub fn add_object(&mut self, schema: &str, object: SchemaObject) {
+        let index = match self.objects.iter().position(|(s, _)| s == schema) {
+            Some(index) => index,
+            None => {
+                self.objects.push((schema.to_string(), Vec::new()));
+                self.objects.len() - 1
+            }
+        };
+        let objects = &mut self.objects[index].1;
+        if !objects.contains(&object) {
+            objects.push(object);
+        }
+    }
+    
+    /// 按 search_path 的顺序整理
+    pub fn finish(&mut self) {
+        self.search_order.sort();
+        self.search_path = self.search_order.drain(..).map(|(_, s)| s).collect();
+    }
+    
+    /// 获取 schema 中的对象
+    pub fn objects_in(&self, schema: &str) -> &[SchemaObject] {
+        self.objects.iter()
+            .find(|(s, _)| s.eq_ignore_ascii_case(schema))
+            .map(|(_, objects)| objects.as_slice())
+            .unwrap_or(&[])
+    }
+    
+    /// search_path 中可以不加前缀引用的表、视图和物化视图
+    pub fn tables(&self) -> Vec<String> {
+        let mut tables: Vec<String> = Vec::new();
+        for schema in &self.search_path {
+            for object in self.objects_in(schema) {
+                if object.kind != SchemaObjectKind::Function && !tables.contains(&object.name) {
+                    tables.push(object.name.clone());
+                }
+            }
+        }
+        tables
+    }
+    
+    /// 是否包含该表（表名可以带 schema 前缀）
+    pub fn has_table(&self, table: &str) -> bool {
+        let is_table = |o: &SchemaObject, name: &str| o.kind != SchemaObjectKind::Function && o.name.eq_ignore_ascii_case(name);
+        match table.split_once('.') {
+            Some((schema, name)) => self.objects_in(schema).iter().any(|o| is_table(o, name)),
+            None => self.search_path.iter().any(|s| self.objects_in(s).iter().any(|o| is_table(o, table))),
+        }
+    }
+    
+    /// 对象总数
+    pub fn object_count(&self) -> usize {
+        self.objects.iter().map(|(_, objects)| objects.len()).sum()
+    }
+}
+
+/// 读取出来的原始值，用于生成可以重新执行的 INSERT
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    Null,
+    /// 整数或浮点数，原样写入
+    Number(String),
+    Text(String),
+    /// 不是 UTF-8 的二进制数据
+    Bytes(Vec<u8>),
 }
 
 /// 查询结果
@@ -63,9 +256,13 @@ impl QueryResult {
 }
 
 /// 数据库连接
+///
+/// 每种数据库都只保持一个会话连接，用户开启的事务和会话变量在后续语句中仍然有效
 pub enum DbConnection {
     SQLite(rusqlite::Connection),
-    MySQL(mysql::Pool),
+    MySQL(mysql::Conn),
+    /// MariaDB 使用 MySQL 协议的驱动
+    MariaDB(mysql::Conn),
     PostgreSQL(postgres::Client),
     None,
 }
@@ -84,16 +281,37 @@ impl DbConnection {
             .map_err(|e| DbError::Connection(e.to_string()))
     }
     
-    /// 连接 MySQL 数据库
-    pub fn connect_mysql(host: &str, port: u16, user: &str, password: &str, database: &str) -> Result<Self, DbError> {
-        let url = if password.is_empty() {
-            format!("mysql://{}@{}:{}/{}", user, host, port, database)
-        } else {
-            format!("mysql://{}:{}@{}:{}/{}", user, password, host, port, database)
+    /// 连接 MySQL 数据库，支持 Unix socket 和 SSL 选项
+    pub fn connect_mysql(config: &DatabaseConfig) -> Result<Self, DbError> {
+        let mut opts = mysql::OptsBuilder::new()
+            .ip_or_hostname(Some(config.host.clone()))
+            .tcp_port(config.port)
+            .user(Some(config.username.clone()).filter(|u| !u.is_empty()))
+            .pass(Some(config.password.clone()).filter(|p| !p.is_empty()))
+            .db_name(Some(config.database.clone()).filter(|d| !d.is_empty()))
+            .socket(config.socket.clone());
+        
+        // mysql 驱动没有“可选 TLS”，Prefer 按不使用 TLS 处理
+        let ssl_opts = match config.ssl_mode {
+            SslMode::Disable | SslMode::Prefer => None,
+            SslMode::Require => Some(mysql::SslOpts::default()
+                .with_danger_accept_invalid_certs(true)
+                .with_danger_skip_domain_validation(true)),
+            SslMode::VerifyCa => Some(mysql::SslOpts::default().with_danger_skip_domain_validation(true)),
+            SslMode::VerifyFull => Some(mysql::SslOpts::default()),
         };
+        let ssl_opts = ssl_opts.map(|opts| match config.ssl_root_cert {
+            Some(ref cert) => opts.with_root_cert_path(Some(PathBuf::from(cert))),
+            None => opts,
+        });
+        opts = opts.ssl_opts(ssl_opts);
         
-        mysql::Pool::new(url.as_str())
-            .map(DbConnection::MySQL)
+        if let Some(timeout) = config.param("connect_timeout").and_then(|t| t.parse().ok()) {
+            opts = opts.tcp_connect_timeout(Some(Duration::from_secs(timeout)));
+        }
+        
+        let (user, password) = (config.username.as_str(), config.password.as_str());
+        let mut conn = mysql::Conn::new(opts)
             .map_err(|e| {
                 let err_msg = e.to_string();
                 // 改进常见错误的提示信息
@@ -108,13 +326,29 @@ impl DbConnection {
                 } else {
                     DbError::Connection(err_msg)
                 }
-            })
+            })?;
+        
+        // 按服务器实际类型区分，MySQL 菜单连上 MariaDB 时也使用 MariaDB 语法
+        if config.db_type == DatabaseType::MariaDB || Self::is_mariadb_server(&mut conn) {
+            Ok(DbConnection::MariaDB(conn))
+        } else {
+            Ok(DbConnection::MySQL(conn))
+        }
+    }
+    
+    /// 服务器版本号中带有 MariaDB
+    fn is_mariadb_server(conn: &mut mysql::Conn) -> bool {
+        use mysql::prelude::*;
+        conn.query_first::<String, _>("SELECT VERSION()")
+            .ok()
+            .flatten()
+            .is_some_and(|version| version.contains("MariaDB"))
     }
     
     /// 从连接字符串连接 MySQL
     pub fn connect_mysql_url(url: &str) -> Result<Self, DbError> {
-        mysql::Pool::new(url)
-            .map(DbConnection::MySQL)
+        mysql::Conn::new(url)
+            .map(|mut conn| if Self::is_mariadb_server(&mut conn) { DbConnection::MariaDB(conn) } else { DbConnection::MySQL(conn) })
             .map_err(|e| {
                 let err_msg = e.to_string();
                 // 改进常见错误的提示信息
@@ -128,31 +362,107 @@ impl DbConnection {
             })
     }
     
-    /// 连接 PostgreSQL 数据库
-    pub fn connect_postgres(host: &str, port: u16, user: &str, password: &str, database: &str) -> Result<Self, DbError> {
-        let conn_str = if password.is_empty() {
-            format!("host={} port={} user={} dbname={}", host, port, user, database)
+    /// 连接 PostgreSQL 数据库，支持 Unix socket 和 sslmode
+    pub fn connect_postgres(config: &DatabaseConfig) -> Result<Self, DbError> {
+        let mut pg = postgres::Config::new();
+        pg.host(config.socket.as_deref().unwrap_or(&config.host))
+            .port(config.port);
+        // 没有指定用户名时和 libpq 一样用 PGUSER，再没有就是当前系统用户
+        if !config.username.is_empty() {
+            pg.user(&config.username);
+        } else if let Some(user) = std::env::var("PGUSER").ok().filter(|u| !u.is_empty()) {
+            pg.user(&user);
+        }
+        if !config.password.is_empty() {
+            pg.password(&config.password);
+        }
+        if !config.database.is_empty() {
+            pg.dbname(&config.database);
+        }
+        if let Some(name) = config.param("application_name") {
+            pg.application_name(name);
+        }
+        if let Some(timeout) = config.param("connect_timeout").and_then(|t| t.parse().ok()) {
+            pg.connect_timeout(Duration::from_secs(timeout));
+        }
+        if let Some(options) = config.param("options") {
+            pg.options(options);
+        }
+        
+        let result = if config.ssl_mode == SslMode::Disable {
+            pg.ssl_mode(postgres::config::SslMode::Disable);
+            pg.connect(postgres::NoTls)
         } else {
-            format!("host={} port={} user={} password={} dbname={}", host, port, user, password, database)
+            pg.ssl_mode(if config.ssl_mode == SslMode::Prefer {
+                postgres::config::SslMode::Prefer
+            } else {
+                postgres::config::SslMode::Require
+            });
+            pg.connect(Self::postgres_tls(config.ssl_mode, config.ssl_root_cert.as_deref())?)
         };
         
-        postgres::Client::connect(&conn_str, postgres::NoTls)
+        result
             .map(DbConnection::PostgreSQL)
             .map_err(|e| DbError::Connection(e.to_string()))
     }
     
+    /// 按 sslmode 构造 TLS 连接器：prefer/require 不验证证书，verify-ca 不验证主机名
+    fn postgres_tls(ssl_mode: SslMode, root_cert: Option<&str>) -> Result<postgres_native_tls::MakeTlsConnector, DbError> {
+        let mut builder = native_tls::TlsConnector::builder();
+        match ssl_mode {
+            SslMode::Disable | SslMode::Prefer | SslMode::Require => {
+                builder.danger_accept_invalid_certs(true);
+            }
+            SslMode::VerifyCa => {
+                builder.danger_accept_invalid_hostnames(true);
+            }
+            SslMode::VerifyFull => {}
+        }
+        if let Some(path) = root_cert {
+            let pem = std::fs::read(path)
+                .map_err(|e| DbError::Connection(format!("无法读取 CA 证书 {}: {}", path, e)))?;
+            let cert = native_tls::Certificate::from_pem(&pem)
+                .map_err(|e| DbError::Connection(format!("CA 证书格式错误: {}", e)))?;
+            builder.add_root_certificate(cert);
+        }
+        let connector = builder.build().map_err(|e| DbError::Connection(e.to_string()))?;
+        Ok(postgres_native_tls::MakeTlsConnector::new(connector))
+    }
+    
     /// 从连接字符串连接 PostgreSQL
     pub fn connect_postgres_url(url: &str) -> Result<Self, DbError> {
-        postgres::Client::connect(url, postgres::NoTls)
+        let pg: postgres::Config = url.parse().map_err(|e: postgres::Error| DbError::Connection(e.to_string()))?;
+        // 驱动只解析 disable/prefer/require，后两种和 connect_postgres 一样不验证证书
+        let result = match pg.get_ssl_mode() {
+            postgres::config::SslMode::Disable => pg.connect(postgres::NoTls),
+            postgres::config::SslMode::Prefer => pg.connect(Self::postgres_tls(SslMode::Prefer, None)?),
+            _ => pg.connect(Self::postgres_tls(SslMode::Require, None)?),
+        };
+        result
             .map(DbConnection::PostgreSQL)
             .map_err(|e| DbError::Connection(e.to_string()))
     }
+
+    /// 按数据库类型把连接字符串交给驱动解析（不是 URL 格式时使用）
+    pub fn connect_url(db_type: DatabaseType, url: &str) -> Result<Self, DbError> {
+        match db_type {
+            DatabaseType::MySQL => Self::connect_mysql_url(url),
+            // MariaDB 和 MySQL 使用同一个驱动，连接后保持 MariaDB 语法
+            DatabaseType::MariaDB => Self::connect_mysql_url(url).map(|conn| match conn {
+                DbConnection::MySQL(conn) => DbConnection::MariaDB(conn),
+                other => other,
+            }),
+            DatabaseType::PostgreSQL => Self::connect_postgres_url(url),
+            _ => Err(DbError::Unsupported(format!("{} 数据库的连接字符串", db_type.name()))),
+        }
+    }
     
-    /// 执行 SQL 查询
+    /// 执行单条 SQL 语句（多条语句请先用 `lexer::split_statements` 拆分）
     pub fn execute(&mut self, sql: &str) -> Result<QueryResult, DbError> {
         match self {
             DbConnection::SQLite(conn) => Self::execute_sqlite(conn, sql),
-            DbConnection::MySQL(pool) => Self::execute_mysql(pool, sql),
+            DbConnection::MySQL(conn) => Self::execute_mysql(conn, sql, DatabaseType::MySQL),
+            DbConnection::MariaDB(conn) => Self::execute_mysql(conn, sql, DatabaseType::MariaDB),
             DbConnection::PostgreSQL(client) => Self::execute_postgres(client, sql),
             DbConnection::None => Err(DbError::NotConnected),
         }
@@ -160,10 +470,8 @@ impl DbConnection {
     
     /// SQLite 执行
     fn execute_sqlite(conn: &rusqlite::Connection, sql: &str) -> Result<QueryResult, DbError> {
-        let sql_upper = sql.trim().to_uppercase();
-        
         // 判断是否是查询语句
-        if sql_upper.starts_with("SELECT") || sql_upper.starts_with("PRAGMA") || sql_upper.starts_with("EXPLAIN") {
+        if lexer::returns_rows(sql, DatabaseType::SQLite) {
             let mut stmt = conn.prepare(sql).map_err(|e| DbError::Query(e.to_string()))?;
             
             let columns: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
@@ -229,13 +537,10 @@ impl DbConnection {
     }
     
     /// MySQL 执行
-    fn execute_mysql(pool: &mysql::Pool, sql: &str) -> Result<QueryResult, DbError> {
+    fn execute_mysql(conn: &mut mysql::Conn, sql: &str, db_type: DatabaseType) -> Result<QueryResult, DbError> {
         use mysql::prelude::*;
         
-        let mut conn = pool.get_conn().map_err(|e| DbError::Query(e.to_string()))?;
-        let sql_upper = sql.trim().to_uppercase();
-        
-        if sql_upper.starts_with("SELECT") || sql_upper.starts_with("SHOW") || sql_upper.starts_with("DESCRIBE") || sql_upper.starts_with("EXPLAIN") {
+        if lexer::returns_rows(sql, db_type) {
             let result: Vec<mysql::Row> = conn.query(sql).map_err(|e| DbError::Query(e.to_string()))?;
             
             if result.is_empty() {
@@ -281,9 +586,7 @@ impl DbConnection {
     
     /// PostgreSQL 执行
     fn execute_postgres(client: &mut postgres::Client, sql: &str) -> Result<QueryResult, DbError> {
-        let sql_upper = sql.trim().to_uppercase();
-        
-        if sql_upper.starts_with("SELECT") || sql_upper.starts_with("SHOW") || sql_upper.starts_with("EXPLAIN") {
+        if lexer::returns_rows(sql, DatabaseType::PostgreSQL) {
             let rows_result = client.query(sql, &[]).map_err(|e| DbError::Query(e.to_string()))?;
             
             if rows_result.is_empty() {
@@ -324,6 +627,125 @@ impl DbConnection {
         }
     }
     
+    /// 在一个事务中用预编译语句批量插入，任何一行失败都会整体回滚
+    ///
+    /// 值以文本传入，由数据库转换为列的类型，None 为 NULL
+    pub fn insert_rows(&mut self, table: &str, columns: &[String], rows: &[Vec<Option<String>>]) -> Result<usize, DbError> {
+        let db_type = self.db_type().ok_or(DbError::NotConnected)?;
+        let column_list = columns.iter().map(|c| db_type.quote_ident(c)).collect::<Vec<_>>().join(", ");
+        let insert = format!("INSERT INTO {} ({}) VALUES", db_type.quote_ident(table), column_list);
+        let row_error = |i: usize, e: &dyn fmt::Display| DbError::Query(format!("第 {} 行: {}", i + 1, e));
+
+        match self {
+            DbConnection::SQLite(conn) => {
+                let placeholders = vec!["?"; columns.len()].join(", ");
+                let tx = conn.transaction().map_err(|e| DbError::Query(e.to_string()))?;
+                {
+                    let mut stmt = tx.prepare(&format!("{} ({})", insert, placeholders))
+                        .map_err(|e| DbError::Query(e.to_string()))?;
+                    for (i, row) in rows.iter().enumerate() {
+                        stmt.execute(rusqlite::params_from_iter(row.iter())).map_err(|e| row_error(i, &e))?;
+                    }
+                }
+                tx.commit().map_err(|e| DbError::Query(e.to_string()))?;
+            }
+            DbConnection::MySQL(conn) | DbConnection::MariaDB(conn) => {
+                use mysql::prelude::*;
+                let placeholders = vec!["?"; columns.len()].join(", ");
+                let mut tx = conn.start_transaction(mysql::TxOpts::default())
+                    .map_err(|e| DbError::Query(e.to_string()))?;
+                let stmt = tx.prep(format!("{} ({})", insert, placeholders))
+                    .map_err(|e| DbError::Query(e.to_string()))?;
+                for (i, row) in rows.iter().enumerate() {
+                    let params: Vec<mysql::Value> = row.iter()
+                        .map(|v| v.as_ref().map_or(mysql::Value::NULL, |s| mysql::Value::Bytes(s.as_bytes().to_vec())))
+                        .collect();
+                    tx.exec_drop(&stmt, params).map_err(|e| row_error(i, &e))?;
+                }
+                tx.commit().map_err(|e| DbError::Query(e.to_string()))?;
+            }
+            DbConnection::PostgreSQL(client) => {
+                let mut tx = client.transaction().map_err(|e| DbError::Query(e.to_string()))?;
+                // 先让服务器推断每个参数（即目标列）的类型，再把文本显式转换过去
+                let numbered: Vec<String> = (1..=columns.len()).map(|i| format!("${}", i)).collect();
+                let probe = tx.prepare(&format!("{} ({})", insert, numbered.join(", ")))
+                    .map_err(|e| DbError::Query(e.to_string()))?;
+                let casts: Vec<String> = probe.params().iter()
+                    .enumerate()
+                    .map(|(i, ty)| format!("${}::text::\"{}\".\"{}\"", i + 1, ty.schema(), ty.name()))
+                    .collect();
+                let stmt = tx.prepare(&format!("{} ({})", insert, casts.join(", ")))
+                    .map_err(|e| DbError::Query(e.to_string()))?;
+                for (i, row) in rows.iter().enumerate() {
+                    let params: Vec<&(dyn postgres::types::ToSql + Sync)> = row.iter()
+                        .map(|v| v as &(dyn postgres::types::ToSql + Sync))
+                        .collect();
+                    tx.execute(&stmt, &params).map_err(|e| row_error(i, &postgres_error(&e)))?;
+                }
+                tx.commit().map_err(|e| DbError::Query(e.to_string()))?;
+            }
+            DbConnection::None => return Err(DbError::NotConnected),
+        }
+        Ok(rows.len())
+    }
+
+    /// 按列的顺序读取表中的所有行，保留 NULL、数字和二进制数据的区别
+    pub fn fetch_rows(&mut self, table: &str, columns: &[String]) -> Result<Vec<Vec<SqlValue>>, DbError> {
+        let db_type = self.db_type().ok_or(DbError::NotConnected)?;
+        let table = db_type.quote_ident(table);
+        match self {
+            DbConnection::SQLite(conn) => {
+                let column_list = columns.iter().map(|c| db_type.quote_ident(c)).collect::<Vec<_>>().join(", ");
+                let mut stmt = conn.prepare(&format!("SELECT {} FROM {}", column_list, table))
+                    .map_err(|e| DbError::Query(e.to_string()))?;
+                let rows = stmt.query_map([], |row| {
+                    (0..columns.len()).map(|i| {
+                        Ok(match row.get_ref(i)? {
+                            rusqlite::types::ValueRef::Null => SqlValue::Null,
+                            rusqlite::types::ValueRef::Integer(n) => SqlValue::Number(n.to_string()),
+                            rusqlite::types::ValueRef::Real(f) => SqlValue::Number(f.to_string()),
+                            rusqlite::types::ValueRef::Text(t) => SqlValue::Text(String::from_utf8_lossy(t).into_owned()),
+                            rusqlite::types::ValueRef::Blob(b) => SqlValue::Bytes(b.to_vec()),
+                        })
+                    }).collect::<Result<Vec<_>, _>>()
+                }).map_err(|e| DbError::Query(e.to_string()))?;
+                rows.collect::<Result<Vec<_>, _>>().map_err(|e| DbError::Query(e.to_string()))
+            }
+            DbConnection::MySQL(conn) | DbConnection::MariaDB(conn) => {
+                use mysql::prelude::*;
+                let column_list = columns.iter().map(|c| db_type.quote_ident(c)).collect::<Vec<_>>().join(", ");
+                let rows: Vec<mysql::Row> = conn.query(format!("SELECT {} FROM {}", column_list, table))
+                    .map_err(|e| DbError::Query(e.to_string()))?;
+                Ok(rows.iter().map(|row| {
+                    (0..row.len()).map(|i| match row.get::<mysql::Value, _>(i) {
+                        None | Some(mysql::Value::NULL) => SqlValue::Null,
+                        Some(mysql::Value::Bytes(b)) => match String::from_utf8(b) {
+                            Ok(text) => SqlValue::Text(text),
+                            Err(e) => SqlValue::Bytes(e.into_bytes()),
+                        },
+                        Some(v @ (mysql::Value::Int(_) | mysql::Value::UInt(_) | mysql::Value::Float(_) | mysql::Value::Double(_))) => {
+                            SqlValue::Number(mysql_value_to_string(&v))
+                        }
+                        Some(v) => SqlValue::Text(mysql_value_to_string(&v)),
+                    }).collect()
+                }).collect())
+            }
+            DbConnection::PostgreSQL(client) => {
+                // 统一转换为文本，由目标列的类型解析回来（bytea 的文本形式为 \x...）
+                let column_list = columns.iter().map(|c| format!("{}::text", db_type.quote_ident(c))).collect::<Vec<_>>().join(", ");
+                let rows = client.query(&format!("SELECT {} FROM {}", column_list, table), &[])
+                    .map_err(|e| DbError::Query(postgres_error(&e)))?;
+                Ok(rows.iter().map(|row| {
+                    (0..row.len()).map(|i| match row.get::<_, Option<String>>(i) {
+                        Some(text) => SqlValue::Text(text),
+                        None => SqlValue::Null,
+                    }).collect()
+                }).collect())
+            }
+            DbConnection::None => Err(DbError::NotConnected),
+        }
+    }
+
     /// 获取表列表
     pub fn get_tables(&mut self) -> Result<Vec<String>, DbError> {
         match self {
@@ -336,16 +758,19 @@ impl DbConnection {
                     .collect();
                 Ok(tables)
             }
-            DbConnection::MySQL(pool) => {
+            DbConnection::MySQL(conn) | DbConnection::MariaDB(conn) => {
                 use mysql::prelude::*;
-                let mut conn = pool.get_conn().map_err(|e| DbError::Query(e.to_string()))?;
+                // SHOW TABLES 只列出当前数据库，包括视图
                 let tables: Vec<String> = conn.query("SHOW TABLES")
                     .map_err(|e| DbError::Query(e.to_string()))?;
                 Ok(tables)
             }
             DbConnection::PostgreSQL(client) => {
+                // search_path 中的表、视图和物化视图都可以不加前缀直接引用
                 let rows = client.query(
-                    "SELECT tablename FROM pg_tables WHERE schemaname = 'public' ORDER BY tablename",
+                    "SELECT c.relname FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace
+                     WHERE c.relkind IN ('r', 'p', 'v', 'm', 'f') AND n.nspname = ANY(current_schemas(false))
+                     ORDER BY c.relname",
                     &[]
                 ).map_err(|e| DbError::Query(e.to_string()))?;
                 let tables: Vec<String> = rows.iter()
@@ -357,18 +782,25 @@ impl DbConnection {
         }
     }
     
-    /// 获取表的列信息
+    /// 获取表的列信息（包括默认值和注释），表名可以带 schema/数据库前缀（schema.table）
     pub fn get_columns(&mut self, table: &str) -> Result<Vec<ColumnInfo>, DbError> {
+        let (schema, table) = match table.split_once('.') {
+            Some((schema, table)) => (Some(schema), table),
+            None => (None, table),
+        };
         match self {
             DbConnection::SQLite(conn) => {
-                let sql = format!("PRAGMA table_info({})", table);
+                let sql = format!("PRAGMA {}table_info(\"{}\")", sqlite_schema_prefix(schema), table);
                 let mut stmt = conn.prepare(&sql).map_err(|e| DbError::Query(e.to_string()))?;
                 let columns: Vec<ColumnInfo> = stmt.query_map([], |row| {
                     Ok(ColumnInfo {
                         name: row.get(1)?,
                         data_type: row.get(2)?,
                         nullable: row.get::<_, i32>(3)? == 0,
-                        primary_key: row.get::<_, i32>(5)? == 1,
+                        // 联合主键的各列依次为 1、2、...
+                        primary_key: row.get::<_, i32>(5)? > 0,
+                        default: row.get(4)?,
+                        comment: None,
                     })
                 })
                 .map_err(|e| DbError::Query(e.to_string()))?
@@ -376,32 +808,53 @@ impl DbConnection {
                 .collect();
                 Ok(columns)
             }
-            DbConnection::MySQL(pool) => {
+            DbConnection::MySQL(conn) | DbConnection::MariaDB(conn) => {
                 use mysql::prelude::*;
-                let mut conn = pool.get_conn().map_err(|e| DbError::Query(e.to_string()))?;
-                let sql = format!("DESCRIBE {}", table);
-                let rows: Vec<mysql::Row> = conn.query(&sql).map_err(|e| DbError::Query(e.to_string()))?;
+                type ColumnRow = (String, String, String, String, Option<String>, String);
+                let rows: Vec<ColumnRow> = conn.exec(
+                    "SELECT COLUMN_NAME, COLUMN_TYPE, IS_NULLABLE, COLUMN_KEY, COLUMN_DEFAULT, COLUMN_COMMENT
+                     FROM information_schema.COLUMNS
+                     WHERE TABLE_SCHEMA = COALESCE(?, DATABASE()) AND TABLE_NAME = ?
+                     ORDER BY ORDINAL_POSITION",
+                    (schema, table),
+                ).map_err(|e| DbError::Query(e.to_string()))?;
                 
-                let columns: Vec<ColumnInfo> = rows.iter().map(|row| {
+                let columns: Vec<ColumnInfo> = rows.into_iter().map(|(name, data_type, nullable, key, default, comment)| {
                     ColumnInfo {
-                        name: row.get::<String, _>(0).unwrap_or_default(),
-                        data_type: row.get::<String, _>(1).unwrap_or_default(),
-                        nullable: row.get::<String, _>(2).map(|s| s == "YES").unwrap_or(true),
-                        primary_key: row.get::<String, _>(3).map(|s| s == "PRI").unwrap_or(false),
+                        name,
+                        data_type,
+                        nullable: nullable == "YES",
+                        primary_key: key == "PRI",
+                        default,
+                        comment: Some(comment).filter(|c| !c.is_empty()),
                     }
                 }).collect();
                 Ok(columns)
             }
             DbConnection::PostgreSQL(client) => {
-                let sql = format!(
-                    "SELECT column_name, data_type, is_nullable, 
-                     (SELECT COUNT(*) FROM information_schema.key_column_usage k 
-                      WHERE k.table_name = c.table_name AND k.column_name = c.column_name) > 0 as is_pk
+                // 没有指定 schema 时使用 search_path 中第一个包含该表的 schema
+                // 类型用 format_type 取完整写法（如 character varying(50)、integer[]），
+                // 主键只看 PRIMARY KEY 约束，不包括唯一约束和外键
+                let sql = "SELECT column_name, 
+                     (SELECT format_type(a.atttypid, a.atttypmod) FROM pg_attribute a
+                      WHERE a.attrelid = format('%I.%I', c.table_schema, c.table_name)::regclass
+                        AND a.attname = c.column_name),
+                     is_nullable,
+                     EXISTS (SELECT 1 FROM information_schema.table_constraints t
+                             JOIN information_schema.key_column_usage k
+                               ON k.constraint_schema = t.constraint_schema AND k.constraint_name = t.constraint_name
+                             WHERE t.constraint_type = 'PRIMARY KEY' AND t.table_schema = c.table_schema
+                               AND t.table_name = c.table_name AND k.column_name = c.column_name) as is_pk,
+                     column_default::text,
+                     col_description(format('%I.%I', c.table_schema, c.table_name)::regclass, c.ordinal_position::int)
                      FROM information_schema.columns c 
-                     WHERE table_name = $1 AND table_schema = 'public'
-                     ORDER BY ordinal_position"
-                );
-                let rows = client.query(&sql, &[&table]).map_err(|e| DbError::Query(e.to_string()))?;
+                     WHERE table_name = $1 AND table_schema = COALESCE($2, (
+                         SELECT s FROM unnest(current_schemas(false)) WITH ORDINALITY AS p(s, i)
+                         WHERE EXISTS (SELECT 1 FROM information_schema.columns x
+                                       WHERE x.table_schema = p.s AND x.table_name = $1)
+                         ORDER BY i LIMIT 1))
+                     ORDER BY ordinal_position";
+                let rows = client.query(sql, &[&table, &schema]).map_err(|e| DbError::Query(e.to_string()))?;
                 
                 let columns: Vec<ColumnInfo> = rows.iter().map(|row| {
                     ColumnInfo {
@@ -409,6 +862,8 @@ impl DbConnection {
                         data_type: row.get::<_, String>(1),
                         nullable: row.get::<_, String>(2) == "YES",
                         primary_key: row.get::<_, bool>(3),
+                        default: row.get(4),
+                        comment: row.get(5),
                     }
                 }).collect();
                 Ok(columns)
@@ -417,11 +872,322 @@ impl DbConnection {
         }
     }
     
+    /// 获取表的索引（包括主键和唯一约束对应的索引），主键排在最前面
+    pub fn get_indexes(&mut self, table: &str) -> Result<Vec<IndexInfo>, DbError> {
+        let (schema, name) = match table.split_once('.') {
+            Some((schema, name)) => (Some(schema), name),
+            None => (None, table),
+        };
+        let mut indexes = match self {
+            DbConnection::SQLite(conn) => {
+                let prefix = sqlite_schema_prefix(schema);
+                let query = |sql: &str| -> Result<Vec<(String, bool, String)>, DbError> {
+                    let mut stmt = conn.prepare(sql).map_err(|e| DbError::Query(e.to_string()))?;
+                    let rows = stmt.query_map([], |row| Ok((row.get(1)?, row.get::<_, i32>(2)? != 0, row.get(3)?)))
+                        .map_err(|e| DbError::Query(e.to_string()))?;
+                    Ok(rows.filter_map(|r| r.ok()).collect())
+                };
+                let mut indexes = Vec::new();
+                for (index, unique, origin) in query(&format!("PRAGMA {}index_list(\"{}\")", prefix, name))? {
+                    let mut stmt = conn.prepare(&format!("PRAGMA {}index_info(\"{}\")", prefix, index))
+                        .map_err(|e| DbError::Query(e.to_string()))?;
+                    let columns: Vec<String> = stmt.query_map([], |row| row.get::<_, Option<String>>(2))
+                        .map_err(|e| DbError::Query(e.to_string()))?
+                        .filter_map(|r| r.ok().flatten())
+                        .collect();
+                    indexes.push(IndexInfo { name: index, columns, unique, primary: origin == "pk" });
+                }
+                indexes
+            }
+            DbConnection::MySQL(conn) | DbConnection::MariaDB(conn) => {
+                use mysql::prelude::*;
+                let rows: Vec<(String, String, i64)> = conn.exec(
+                    "SELECT INDEX_NAME, COLUMN_NAME, NON_UNIQUE FROM information_schema.STATISTICS
+                     WHERE TABLE_SCHEMA = COALESCE(?, DATABASE()) AND TABLE_NAME = ?
+                     ORDER BY INDEX_NAME, SEQ_IN_INDEX",
+                    (schema, name),
+                ).map_err(|e| DbError::Query(e.to_string()))?;
+                let mut indexes: Vec<IndexInfo> = Vec::new();
+                for (index, column, non_unique) in rows {
+                    match indexes.last_mut().filter(|i| i.name == index) {
+                        Some(last) => last.columns.push(column),
+                        None => indexes.push(IndexInfo {
+                            primary: index == "PRIMARY",
+                            name: index,
+                            columns: vec![column],
+                            unique: non_unique == 0,
+                        }),
+                    }
+                }
+                indexes
+            }
+            DbConnection::PostgreSQL(client) => {
+                // 表达式索引中的表达式没有对应的列，不列出
+                let rows = client.query(
+                    "SELECT i.relname::text, ix.indisunique, ix.indisprimary,
+                            ARRAY(SELECT a.attname::text FROM unnest(ix.indkey) WITH ORDINALITY AS k(attnum, n)
+                                  JOIN pg_attribute a ON a.attrelid = ix.indrelid AND a.attnum = k.attnum
+                                  ORDER BY k.n)
+                     FROM pg_index ix JOIN pg_class i ON i.oid = ix.indexrelid
+                     WHERE ix.indrelid = $1::text::regclass
+                     ORDER BY i.relname",
+                    &[&DatabaseType::PostgreSQL.quote_ident(table)],
+                ).map_err(|e| DbError::Query(postgres_error(&e)))?;
+                rows.iter().map(|row| IndexInfo {
+                    name: row.get(0),
+                    unique: row.get(1),
+                    primary: row.get(2),
+                    columns: row.get(3),
+                }).collect()
+            }
+            DbConnection::None => return Err(DbError::NotConnected),
+        };
+        // SQLite 的 INTEGER PRIMARY KEY 就是 rowid，没有单独的索引
+        if matches!(self, DbConnection::SQLite(_)) && !indexes.iter().any(|i| i.primary) {
+            let columns: Vec<String> = self.get_columns(table)?.into_iter().filter(|c| c.primary_key).map(|c| c.name).collect();
+            if !columns.is_empty() {
+                indexes.push(IndexInfo { name: "PRIMARY KEY".to_string(), columns, unique: true, primary: true });
+            }
+        }
+        indexes.sort_by_key(|i| !i.primary);
+        Ok(indexes)
+    }
+    
+    /// 获取与表相关的外键：表自己的外键和其他表引用它的外键
+    pub fn get_foreign_keys(&mut self, table: &str) -> Result<Vec<ForeignKey>, DbError> {
+        let (schema, name) = match table.split_once('.') {
+            Some((schema, name)) => (Some(schema), name),
+            None => (None, table),
+        };
+        let mut keys = match self {
+            DbConnection::SQLite(conn) => {
+                // 引用其他表的外键只记录在引用方，需要检查同一个数据库中的每张表
+                let prefix = sqlite_schema_prefix(schema);
+                let master = format!("SELECT name FROM {}sqlite_master WHERE type = 'table'", prefix);
+                let mut stmt = conn.prepare(&master).map_err(|e| DbError::Query(e.to_string()))?;
+                let tables: Vec<String> = stmt.query_map([], |row| row.get(0))
+                    .map_err(|e| DbError::Query(e.to_string()))?
+                    .filter_map(|r| r.ok())
+                    .collect();
+                
+                let mut keys: Vec<ForeignKey> = Vec::new();
+                for from in &tables {
+                    let mut stmt = conn.prepare(&format!("PRAGMA {}foreign_key_list(\"{}\")", prefix, from))
+                        .map_err(|e| DbError::Query(e.to_string()))?;
+                    // (id, 被引用表, 本表列, 被引用列)
+                    let rows: Vec<(i64, String, String, Option<String>)> = stmt
+                        .query_map([], |row| Ok((row.get(0)?, row.get(2)?, row.get(3)?, row.get(4)?)))
+                        .map_err(|e| DbError::Query(e.to_string()))?
+                        .filter_map(|r| r.ok())
+                        .collect();
+                    for (id, to, column, ref_column) in rows {
+                        if !from.eq_ignore_ascii_case(name) && !to.eq_ignore_ascii_case(name) {
+                            continue;
+                        }
+                        let key_name = format!("{}_fk{}", from, id);
+                        match keys.iter_mut().find(|k| k.name == key_name) {
+                            Some(key) => {
+                                key.columns.push(column);
+                                key.ref_columns.extend(ref_column);
+                            }
+                            None => keys.push(ForeignKey {
+                                name: key_name,
+                                table: from.clone(),
+                                columns: vec![column],
+                                ref_table: to,
+                                ref_columns: ref_column.into_iter().collect(),
+                            }),
+                        }
+                    }
+                }
+                keys
+            }
+            DbConnection::MySQL(conn) | DbConnection::MariaDB(conn) => {
+                use mysql::prelude::*;
+                type KeyRow = (String, String, String, String, String);
+                let rows: Vec<KeyRow> = conn.exec(
+                    "SELECT CONSTRAINT_NAME, TABLE_NAME, COLUMN_NAME, REFERENCED_TABLE_NAME, REFERENCED_COLUMN_NAME
+                     FROM information_schema.KEY_COLUMN_USAGE
+                     WHERE REFERENCED_TABLE_NAME IS NOT NULL
+                       AND ((TABLE_SCHEMA = COALESCE(?, DATABASE()) AND TABLE_NAME = ?)
+                         OR (REFERENCED_TABLE_SCHEMA = COALESCE(?, DATABASE()) AND REFERENCED_TABLE_NAME = ?))
+                     ORDER BY TABLE_NAME, CONSTRAINT_NAME, ORDINAL_POSITION",
+                    (schema, name, schema, name),
+                ).map_err(|e| DbError::Query(e.to_string()))?;
+                let mut keys: Vec<ForeignKey> = Vec::new();
+                for (key_name, from, column, to, ref_column) in rows {
+                    match keys.last_mut().filter(|k| k.name == key_name && k.table == from) {
+                        Some(key) => {
+                            key.columns.push(column);
+                            key.ref_columns.push(ref_column);
+                        }
+                        None => keys.push(ForeignKey {
+                            name: key_name,
+                            table: from,
+                            columns: vec![column],
+                            ref_table: to,
+                            ref_columns: vec![ref_column],
+                        }),
+                    }
+                }
+                keys
+            }
+            DbConnection::PostgreSQL(client) => {
+                // regclass 转文本时，不在 search_path 中的表带 schema 前缀，需要时加引号
+                let rows = client.query(
+                    "SELECT c.conname::text, c.conrelid::regclass::text, c.confrelid::regclass::text,
+                            ARRAY(SELECT a.attname::text FROM unnest(c.conkey) WITH ORDINALITY AS k(attnum, n)
+                                  JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = k.attnum ORDER BY k.n),
+                            ARRAY(SELECT a.attname::text FROM unnest(c.confkey) WITH ORDINALITY AS k(attnum, n)
+                                  JOIN pg_attribute a ON a.attrelid = c.confrelid AND a.attnum = k.attnum ORDER BY k.n)
+                     FROM pg_constraint c
+                     WHERE c.contype = 'f' AND (c.conrelid = $1::text::regclass OR c.confrelid = $1::text::regclass)
+                     ORDER BY 2, 1",
+                    &[&DatabaseType::PostgreSQL.quote_ident(table)],
+                ).map_err(|e| DbError::Query(postgres_error(&e)))?;
+                rows.iter().map(|row| ForeignKey {
+                    name: row.get(0),
+                    table: row.get::<_, String>(1).replace('"', ""),
+                    ref_table: row.get::<_, String>(2).replace('"', ""),
+                    columns: row.get(3),
+                    ref_columns: row.get(4),
+                }).collect()
+            }
+            DbConnection::None => return Err(DbError::NotConnected),
+        };
+        
+        // SQLite 中 REFERENCES t 没有写列时引用的是 t 的主键
+        for key in keys.iter_mut().filter(|k| k.ref_columns.is_empty()) {
+            let ref_table = match schema {
+                Some(schema) => format!("{}.{}", schema, key.ref_table),
+                None => key.ref_table.clone(),
+            };
+            key.ref_columns = self.get_columns(&ref_table)?
+                .into_iter()
+                .filter(|c| c.primary_key)
+                .map(|c| c.name)
+                .collect();
+        }
+        Ok(keys)
+    }
+    
+    /// 获取表的列、索引和外键
+    pub fn get_table_info(&mut self, table: &str) -> Result<TableInfo, DbError> {
+        Ok(TableInfo {
+            columns: self.get_columns(table)?,
+            indexes: self.get_indexes(table)?,
+            foreign_keys: self.get_foreign_keys(table)?,
+        })
+    }
+    
+    /// 用一次目录查询获取所有 schema、search_path 以及其中的表、视图、物化视图和函数
+    pub fn get_catalog(&mut self) -> Result<Catalog, DbError> {
+        let mut catalog = Catalog::default();
+        match self {
+            DbConnection::SQLite(conn) => {
+                let databases: Vec<String> = {
+                    let mut stmt = conn.prepare("PRAGMA database_list")
+                        .map_err(|e| DbError::Query(e.to_string()))?;
+                    let rows = stmt.query_map([], |row| row.get(1))
+                        .map_err(|e| DbError::Query(e.to_string()))?;
+                    rows.filter_map(|r| r.ok()).collect()
+                };
+                if databases.is_empty() {
+                    return Ok(catalog);
+                }
+                
+                // 附加的数据库各有自己的 sqlite_master，合并成一条查询
+                let sql = databases.iter()
+                    .map(|db| format!(
+                        "SELECT '{0}', name, type FROM \"{0}\".sqlite_master WHERE type IN ('table', 'view')",
+                        db.replace('\'', "''")
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(" UNION ALL ");
+                let mut stmt = conn.prepare(&sql).map_err(|e| DbError::Query(e.to_string()))?;
+                let rows: Vec<(String, String, String)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+                    .map_err(|e| DbError::Query(e.to_string()))?
+                    .filter_map(|r| r.ok())
+                    .collect();
+                
+                for db in &databases {
+                    catalog.add_schema(db, if db == "main" { Some(0) } else { None });
+                }
+                for (db, name, kind) in rows {
+                    let kind = if kind == "view" { SchemaObjectKind::View } else { SchemaObjectKind::Table };
+                    catalog.add_object(&db, SchemaObject { name, kind });
+                }
+            }
+            DbConnection::MySQL(conn) | DbConnection::MariaDB(conn) => {
+                use mysql::prelude::*;
+                // (数据库, 对象名, 对象类型, 是否为当前数据库)
+                type CatalogRow = (String, Option<String>, Option<String>, Option<i64>);
+                let rows: Vec<CatalogRow> = conn.query(
+                    "SELECT s.SCHEMA_NAME, t.TABLE_NAME, t.TABLE_TYPE, s.SCHEMA_NAME = DATABASE()
+                     FROM information_schema.SCHEMATA s
+                     LEFT JOIN information_schema.TABLES t ON t.TABLE_SCHEMA = s.SCHEMA_NAME
+                     UNION ALL
+                     SELECT ROUTINE_SCHEMA, ROUTINE_NAME, 'FUNCTION', ROUTINE_SCHEMA = DATABASE()
+                     FROM information_schema.ROUTINES
+                     ORDER BY 1, 2"
+                ).map_err(|e| DbError::Query(e.to_string()))?;
+                
+                for (schema, name, kind, current) in rows {
+                    catalog.add_schema(&schema, if current == Some(1) { Some(0) } else { None });
+                    if let Some(name) = name {
+                        let kind = match kind.as_deref() {
+                            Some("VIEW") | Some("SYSTEM VIEW") => SchemaObjectKind::View,
+                            Some("SEQUENCE") => SchemaObjectKind::Sequence,
+                            Some("FUNCTION") => SchemaObjectKind::Function,
+                            _ => SchemaObjectKind::Table,
+                        };
+                        catalog.add_object(&schema, SchemaObject { name, kind });
+                    }
+                }
+            }
+            DbConnection::PostgreSQL(client) => {
+                let rows = client.query(
+                    "SELECT n.nspname::text, c.relname::text, c.relkind::text,
+                            array_position(current_schemas(false), n.nspname)
+                     FROM pg_namespace n
+                     LEFT JOIN pg_class c ON c.relnamespace = n.oid AND c.relkind IN ('r', 'p', 'v', 'm', 'f')
+                     WHERE n.nspname NOT LIKE 'pg\\_%' AND n.nspname <> 'information_schema'
+                     UNION ALL
+                     SELECT n.nspname::text, p.proname::text, 'F',
+                            array_position(current_schemas(false), n.nspname)
+                     FROM pg_proc p JOIN pg_namespace n ON n.oid = p.pronamespace
+                     WHERE n.nspname NOT LIKE 'pg\\_%' AND n.nspname <> 'information_schema'
+                     ORDER BY 1, 2",
+                    &[]
+                ).map_err(|e| DbError::Query(e.to_string()))?;
+                
+                for row in &rows {
+                    let schema: String = row.get(0);
+                    let position: Option<i32> = row.get(3);
+                    catalog.add_schema(&schema, position.map(|p| p as usize));
+                    if let Some(name) = row.get::<_, Option<String>>(1) {
+                        let kind = match row.get::<_, Option<String>>(2).as_deref() {
+                            Some("v") => SchemaObjectKind::View,
+                            Some("m") => SchemaObjectKind::MaterializedView,
+                            Some("F") => SchemaObjectKind::Function,
+                            _ => SchemaObjectKind::Table,
+                        };
+                        catalog.add_object(&schema, SchemaObject { name, kind });
+                    }
+                }
+            }
+            DbConnection::None => return Err(DbError::NotConnected),
+        }
+        catalog.finish();
+        Ok(catalog)
+    }
+    
     /// 获取数据库类型
     pub fn db_type(&self) -> Option<DatabaseType> {
         match self {
             DbConnection::SQLite(_) => Some(DatabaseType::SQLite),
             DbConnection::MySQL(_) => Some(DatabaseType::MySQL),
+            DbConnection::MariaDB(_) => Some(DatabaseType::MariaDB),
             DbConnection::PostgreSQL(_) => Some(DatabaseType::PostgreSQL),
             DbConnection::None => None,
         }
@@ -431,6 +1197,21 @@ impl DbConnection {
     pub fn is_connected(&self) -> bool {
         !matches!(self, DbConnection::None)
     }
+    
+    /// 从服务器读取会话是否在事务中
+    ///
+    /// SQLite 读取连接的自动提交状态，MariaDB 查询 `@@in_transaction`；
+    /// MySQL 和 PostgreSQL 的驱动拿不到这个状态，返回 None，由调用方按执行过的语句跟踪。
+    pub fn in_transaction(&mut self) -> Option<bool> {
+        match self {
+            DbConnection::SQLite(conn) => Some(!conn.is_autocommit()),
+            DbConnection::MariaDB(conn) => {
+                use mysql::prelude::*;
+                conn.query_first::<u8, _>("SELECT @@in_transaction").ok().flatten().map(|v| v != 0)
+            }
+            DbConnection::MySQL(_) | DbConnection::PostgreSQL(_) | DbConnection::None => None,
+        }
+    }
 }
 
 impl Default for DbConnection {
@@ -439,6 +1220,11 @@ impl Default for DbConnection {
     }
 }
 
+/// SQLite PRAGMA 的数据库前缀，如 `"aux".`
+fn sqlite_schema_prefix(schema: Option<&str>) -> String {
+    schema.map(|s| format!("\"{}\".", s)).unwrap_or_default()
+}
+
 /// MySQL 值转字符串
 fn mysql_value_to_string(value: &mysql::Value) -> String {
     match value {
@@ -459,6 +1245,14 @@ fn mysql_value_to_string(value: &mysql::Value) -> String {
     }
 }
 
+/// PostgreSQL 错误信息，服务器错误取出其中的具体原因
+fn postgres_error(e: &postgres::Error) -> String {
+    match e.as_db_error() {
+        Some(db) => db.message().to_string(),
+        None => e.to_string(),
+    }
+}
+
 /// PostgreSQL 值转字符串
 fn postgres_value_to_string(row: &postgres::Row, idx: usize) -> String {
     // 尝试各种类型
@@ -477,5 +1271,23 @@ fn postgres_value_to_string(row: &postgres::Row, idx: usize) -> String {
     if let Ok(v) = row.try_get::<_, Option<bool>>(idx) {
         return v.map(|b| b.to_string()).unwrap_or_else(|| "NULL".to_string());
     }
+    if let Ok(v) = row.try_get::<_, Option<PgJson>>(idx) {
+        return v.map(|j| j.0).unwrap_or_else(|| "NULL".to_string());
+    }
     "?".to_string()
 }
+
+/// 以文本读取 json / jsonb 列（如 EXPLAIN (FORMAT JSON) 的结果）
+struct PgJson(String);
+
+impl<'a> postgres::types::FromSql<'a> for PgJson {
+    fn from_sql(ty: &postgres::types::Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        // jsonb 的二进制格式以一个版本号字节开头
+        let text = if *ty == postgres::types::Type::JSONB { raw.get(1..).unwrap_or_default() } else { raw };
+        Ok(PgJson(String::from_utf8_lossy(text).into_owned()))
+    }
+
+    fn accepts(ty: &postgres::types::Type) -> bool {
+        matches!(*ty, postgres::types::Type::JSON | postgres::types::Type::JSONB)
+    }
+}
@@ -0,0 +1,213 @@
+//! 比较两个数据库的结构
+//!
+//! `.diff <另一个连接>` 比较表、列、类型、可空和主键，生成把当前数据库
+//! 改成与另一个数据库一致的迁移脚本。不同数据库对同一类型的写法不同
+//! （如 int4 / integer、character varying / varchar），比较前会先统一。
+
+use super::connection::ColumnInfo;
+use super::database::DatabaseType;
+use super::dump::{self, TableSchema};
+
+/// 一处结构差异
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaChange {
+    /// 只在目标中存在的表
+    CreateTable(TableSchema),
+    /// 只在当前数据库中存在的表
+    DropTable(String),
+    AddColumn { table: String, column: ColumnInfo },
+    DropColumn { table: String, column: String },
+    /// 类型或可空性不同
+    AlterColumn { table: String, from: ColumnInfo, to: ColumnInfo },
+    /// 主键列不同，`constraint` 为当前主键约束的名字
+    ChangePrimaryKey { table: String, constraint: Option<String>, from: Vec<String>, to: Vec<String> },
+}
+
+/// 比较两组表，返回把 `current` 变成 `target` 需要的修改
+///
+/// 依次为：新建的表、各表中的列和主键修改、删除的表
+pub fn diff(current: &[TableSchema], target: &[TableSchema]) -> Vec<SchemaChange> {
+    let find = |tables: &'_ [TableSchema], name: &str| tables.iter().position(|t| t.name.eq_ignore_ascii_case(name));
+    let mut changes = Vec::new();
+
+    for table in target.iter().filter(|t| find(current, &t.name).is_none()) {
+        changes.push(SchemaChange::CreateTable(table.clone()));
+    }
+
+    for to in target {
+        let from = match find(current, &to.name) {
+            Some(i) => &current[i],
+            None => continue,
+        };
+        for column in &to.columns {
+            match from.column(&column.name) {
+                None => changes.push(SchemaChange::AddColumn { table: from.name.clone(), column: column.clone() }),
+                Some(existing) if !same_column(existing, column) => changes.push(SchemaChange::AlterColumn {
+                    table: from.name.clone(),
+                    from: existing.clone(),
+                    to: column.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        for column in from.columns.iter().filter(|c| to.column(&c.name).is_none()) {
+            changes.push(SchemaChange::DropColumn { table: from.name.clone(), column: column.name.clone() });
+        }
+
+        let from_key: Vec<String> = from.primary_key().iter().map(|c| c.to_string()).collect();
+        let to_key: Vec<String> = to.primary_key().iter().map(|c| c.to_string()).collect();
+        let same_key = from_key.len() == to_key.len()
+            && from_key.iter().zip(&to_key).all(|(a, b)| a.eq_ignore_ascii_case(b));
+        if !same_key {
+            changes.push(SchemaChange::ChangePrimaryKey {
+                table: from.name.clone(),
+                constraint: from.primary_key_name().map(str::to_string),
+                from: from_key,
+                to: to_key,
+            });
+        }
+    }
+
+    for table in current.iter().filter(|t| find(target, &t.name).is_none()) {
+        changes.push(SchemaChange::DropTable(table.name.clone()));
+    }
+    changes
+}
+
+/// 类型和可空性是否相同（主键单独比较）
+fn same_column(a: &ColumnInfo, b: &ColumnInfo) -> bool {
+    is_nullable(a) == is_nullable(b) && normalize_type(&a.data_type) == normalize_type(&b.data_type)
+}
+
+/// 主键列总是不可空，SQLite 的 PRAGMA table_info 却会把它报告为可空
+fn is_nullable(column: &ColumnInfo) -> bool {
+    column.nullable && !column.primary_key
+}
+
+/// 统一类型的写法：小写、去掉多余空白，同义的类型名换成同一个
+pub fn normalize_type(data_type: &str) -> String {
+    let lower = data_type.to_lowercase();
+    let lower = lower.split_whitespace().collect::<Vec<_>>().join(" ").replace(" (", "(");
+    let (name, args) = match lower.find('(') {
+        Some(i) => (lower[..i].trim_end(), &lower[i..]),
+        None => (lower.as_str(), ""),
+    };
+    let name = match name {
+        "int" | "int4" | "integer" => "integer",
+        "int8" | "bigint" => "bigint",
+        "int2" | "smallint" => "smallint",
+        "character varying" | "varchar" => "varchar",
+        "character" | "char" | "bpchar" => "char",
+        "bool" | "boolean" => "boolean",
+        "double precision" | "float8" | "double" => "double",
+        "real" | "float4" => "real",
+        "decimal" | "numeric" => "numeric",
+        "timestamp without time zone" => "timestamp",
+        "timestamp with time zone" | "timestamptz" => "timestamptz",
+        "time without time zone" => "time",
+        "time with time zone" | "timetz" => "timetz",
+        other => other,
+    };
+    // MySQL 的整数显示宽度（int(11)）不影响取值范围；tinyint(1) 是 MySQL 的布尔类型
+    match (name, args) {
+        ("tinyint", "(1)") => "boolean".to_string(),
+        ("integer" | "bigint" | "smallint" | "tinyint" | "mediumint", _) if args.starts_with('(') => {
+            let rest = args.split_once(')').map(|(_, rest)| rest).unwrap_or("");
+            format!("{}{}", name, rest)
+        }
+        _ => format!("{}{}", name, args),
+    }
+}
+
+/// 迁移脚本，在 `db_type`（当前数据库）上执行；不支持的修改写成注释
+pub fn migration_script(changes: &[SchemaChange], db_type: DatabaseType) -> Vec<String> {
+    let q = |name: &str| db_type.quote_ident(name);
+    let mut lines = Vec::new();
+    // 新建表的外键可能引用后面才新建的表，放在最后添加
+    let mut foreign_keys = Vec::new();
+
+    for change in changes {
+        match change {
+            SchemaChange::CreateTable(table) => {
+                lines.push(format!("-- 新建表 {}", table.name));
+                lines.push(format!("{};", dump::create_table_sql(table, db_type)));
+                lines.extend(dump::index_sql(table, db_type).into_iter().map(|sql| format!("{};", sql)));
+                foreign_keys.extend(dump::foreign_key_sql(table, db_type).into_iter().map(|sql| format!("{};", sql)));
+            }
+            SchemaChange::DropTable(table) => {
+                lines.push(format!("-- 删除表 {}", table));
+                lines.push(format!("DROP TABLE {};", q(table)));
+            }
+            SchemaChange::AddColumn { table, column } => {
+                lines.push(format!("-- {} 新增列 {}", table, column.name));
+                if !column.nullable && column.default.is_none() {
+                    lines.push("-- 注意: 表中已有数据时 NOT NULL 列需要默认值".to_string());
+                }
+                lines.push(format!("ALTER TABLE {} ADD COLUMN {};", q(table), dump::column_definition(column, db_type)));
+            }
+            SchemaChange::DropColumn { table, column } => {
+                lines.push(format!("-- {} 删除列 {}", table, column));
+                lines.push(format!("ALTER TABLE {} DROP COLUMN {};", q(table), q(column)));
+            }
+            SchemaChange::AlterColumn { table, from, to } => {
+                lines.push(format!(
+                    "-- {}.{}: {} {} -> {} {}",
+                    table, to.name,
+                    from.data_type, if is_nullable(from) { "NULL" } else { "NOT NULL" },
+                    to.data_type, if is_nullable(to) { "NULL" } else { "NOT NULL" },
+                ));
+                match db_type {
+                    DatabaseType::PostgreSQL => {
+                        let column = q(&from.name);
+                        if normalize_type(&from.data_type) != normalize_type(&to.data_type) {
+                            lines.push(format!(
+                                "ALTER TABLE {} ALTER COLUMN {} TYPE {} USING {}::{};",
+                                q(table), column, to.data_type, column, to.data_type
+                            ));
+                        }
+                        if is_nullable(from) != is_nullable(to) {
+                            let action = if is_nullable(to) { "DROP" } else { "SET" };
+                            lines.push(format!("ALTER TABLE {} ALTER COLUMN {} {} NOT NULL;", q(table), column, action));
+                        }
+                    }
+                    DatabaseType::MySQL | DatabaseType::MariaDB => {
+                        lines.push(format!("ALTER TABLE {} MODIFY COLUMN {};", q(table), dump::column_definition(to, db_type)));
+                    }
+                    _ => lines.push(format!("-- {} 不支持修改列，需要重建表 {}", db_type.name(), table)),
+                }
+            }
+            SchemaChange::ChangePrimaryKey { table, constraint, from, to } => {
+                lines.push(format!("-- {} 主键: ({}) -> ({})", table, from.join(", "), to.join(", ")));
+                let columns: Vec<String> = to.iter().map(|c| q(c)).collect();
+                match db_type {
+                    DatabaseType::PostgreSQL => {
+                        if !from.is_empty() {
+                            // 读不到约束名时使用 PostgreSQL 默认的约束名
+                            let name = constraint.clone().unwrap_or_else(|| format!("{}_pkey", table));
+                            lines.push(format!("ALTER TABLE {} DROP CONSTRAINT {};", q(table), q(&name)));
+                        }
+                        if !to.is_empty() {
+                            lines.push(format!("ALTER TABLE {} ADD PRIMARY KEY ({});", q(table), columns.join(", ")));
+                        }
+                    }
+                    DatabaseType::MySQL | DatabaseType::MariaDB => {
+                        let mut actions = Vec::new();
+                        if !from.is_empty() {
+                            actions.push("DROP PRIMARY KEY".to_string());
+                        }
+                        if !to.is_empty() {
+                            actions.push(format!("ADD PRIMARY KEY ({})", columns.join(", ")));
+                        }
+                        lines.push(format!("ALTER TABLE {} {};", q(table), actions.join(", ")));
+                    }
+                    _ => lines.push(format!("-- {} 不支持修改主键，需要重建表 {}", db_type.name(), table)),
+                }
+            }
+        }
+    }
+    if !foreign_keys.is_empty() {
+        lines.push("-- 新建表的外键".to_string());
+        lines.extend(foreign_keys);
+    }
+    lines
+}
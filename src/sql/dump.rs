@@ -0,0 +1,285 @@
+//! 导出表结构和数据
+//!
+//! `.dump [表名]` 根据列、索引和外键生成当前数据库可以直接执行的 CREATE TABLE，
+//! 再用 INSERT 写出所有行。索引在数据之后创建；外键在所有表的数据之后用 ALTER TABLE
+//! 添加（SQLite 不支持，写在建表语句中）。
+//!
+//! SQLite 和 PostgreSQL 的脚本整体包在一个事务中。MySQL/MariaDB 的 DDL 会隐式提交，
+//! 无法保证原子性，改为在脚本前后关闭、恢复 `FOREIGN_KEY_CHECKS`。
+
+use super::connection::{ColumnInfo, DbConnection, DbError, ForeignKey, IndexInfo, SchemaObjectKind, SqlValue};
+use super::database::DatabaseType;
+
+/// SQLite 为 UNIQUE 约束自动创建的索引名前缀，这种索引不能用 CREATE INDEX 创建
+const SQLITE_AUTOINDEX: &str = "sqlite_autoindex_";
+
+/// 一张表的结构
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableSchema {
+    pub name: String,
+    pub columns: Vec<ColumnInfo>,
+    /// 包括主键和唯一约束对应的索引
+    pub indexes: Vec<IndexInfo>,
+    /// 表自己的外键（不包括其他表引用它的外键）
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+impl TableSchema {
+    /// 主键列，按列的顺序
+    pub fn primary_key(&self) -> Vec<&str> {
+        self.columns.iter().filter(|c| c.primary_key).map(|c| c.name.as_str()).collect()
+    }
+
+    /// 主键索引的名字，PostgreSQL 中就是主键约束的名字
+    pub fn primary_key_name(&self) -> Option<&str> {
+        self.indexes.iter().find(|i| i.primary).map(|i| i.name.as_str())
+    }
+
+    /// 按名称查找列（不区分大小写）
+    pub fn column(&self, name: &str) -> Option<&ColumnInfo> {
+        self.columns.iter().find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// 读取 search_path（当前数据库）中所有表的结构，按表名排序；不包括视图和 SQLite 的内部表
+pub fn load_tables(conn: &mut DbConnection) -> Result<Vec<TableSchema>, DbError> {
+    let catalog = conn.get_catalog()?;
+    let mut names: Vec<String> = Vec::new();
+    for schema in &catalog.search_path {
+        for object in catalog.objects_in(schema) {
+            if object.kind == SchemaObjectKind::Table && !object.name.starts_with("sqlite_") && !names.contains(&object.name) {
+                names.push(object.name.clone());
+            }
+        }
+    }
+    names.sort();
+
+    names.into_iter()
+        .map(|name| load_table(conn, &name))
+        .collect()
+}
+
+/// 读取一张表的结构，表不存在时报错
+pub fn load_table(conn: &mut DbConnection, name: &str) -> Result<TableSchema, DbError> {
+    let columns = conn.get_columns(name)?;
+    if columns.is_empty() {
+        return Err(DbError::Query(format!("表 '{}' 不存在", name)));
+    }
+    let indexes = conn.get_indexes(name)?;
+    let foreign_keys = conn.get_foreign_keys(name)?
+        .into_iter()
+        .filter(|k| k.table.eq_ignore_ascii_case(name))
+        .collect();
+    Ok(TableSchema { name: name.to_string(), columns, indexes, foreign_keys })
+}
+
+/// PostgreSQL 中默认值来自序列的列（serial），导出为对应的 serial 类型，导入时重新创建序列
+fn serial_type(column: &ColumnInfo, db_type: DatabaseType) -> Option<&'static str> {
+    if db_type != DatabaseType::PostgreSQL || !column.default.as_deref().is_some_and(|d| d.starts_with("nextval(")) {
+        return None;
+    }
+    match column.data_type.as_str() {
+        "integer" => Some("serial"),
+        "bigint" => Some("bigserial"),
+        "smallint" => Some("smallserial"),
+        _ => None,
+    }
+}
+
+/// 默认值表达式；MySQL 的 information_schema 中字符串默认值不带引号，需要加上
+fn default_sql(default: &str, db_type: DatabaseType) -> String {
+    if db_type != DatabaseType::MySQL {
+        return default.to_string();
+    }
+    let upper = default.to_uppercase();
+    let expression = upper == "NULL"
+        || upper.starts_with("CURRENT_TIMESTAMP")
+        || default.starts_with('(')
+        || default.parse::<f64>().is_ok();
+    if expression {
+        default.to_string()
+    } else {
+        literal(&SqlValue::Text(default.to_string()), db_type)
+    }
+}
+
+/// 列定义，如 `"name" varchar(50) NOT NULL DEFAULT ''`
+pub fn column_definition(column: &ColumnInfo, db_type: DatabaseType) -> String {
+    let mut definition = db_type.quote_ident(&column.name);
+    let serial = serial_type(column, db_type);
+    let data_type = serial.unwrap_or(&column.data_type);
+    if !data_type.is_empty() {
+        definition.push(' ');
+        definition.push_str(data_type);
+    }
+    if !column.nullable {
+        definition.push_str(" NOT NULL");
+    }
+    if let Some(default) = column.default.as_deref().filter(|_| serial.is_none()) {
+        definition.push_str(" DEFAULT ");
+        definition.push_str(&default_sql(default, db_type));
+    }
+    definition
+}
+
+/// 外键约束，如 `CONSTRAINT "fk" FOREIGN KEY ("uid") REFERENCES "users" ("id")`
+///
+/// SQLite 的外键没有名字（读取时的名字是生成的），不写 CONSTRAINT。
+pub fn foreign_key_clause(key: &ForeignKey, db_type: DatabaseType) -> String {
+    let quote_all = |names: &[String]| names.iter().map(|n| db_type.quote_ident(n)).collect::<Vec<_>>().join(", ");
+    let constraint = match db_type {
+        DatabaseType::SQLite => String::new(),
+        _ => format!("CONSTRAINT {} ", db_type.quote_ident(&key.name)),
+    };
+    format!(
+        "{}FOREIGN KEY ({}) REFERENCES {} ({})",
+        constraint,
+        quote_all(&key.columns),
+        db_type.quote_ident(&key.ref_table),
+        quote_all(&key.ref_columns)
+    )
+}
+
+/// 建表语句（不带分号），主键统一写成表级约束
+///
+/// SQLite 自动创建的唯一索引写成 UNIQUE 约束，外键也写在建表语句中。
+pub fn create_table_sql(table: &TableSchema, db_type: DatabaseType) -> String {
+    let quote_all = |names: &[String]| names.iter().map(|n| db_type.quote_ident(n)).collect::<Vec<_>>().join(", ");
+    let mut parts: Vec<String> = table.columns.iter()
+        .map(|c| format!("    {}", column_definition(c, db_type)))
+        .collect();
+    let primary_key = table.primary_key();
+    if !primary_key.is_empty() {
+        let columns: Vec<String> = primary_key.iter().map(|c| db_type.quote_ident(c)).collect();
+        parts.push(format!("    PRIMARY KEY ({})", columns.join(", ")));
+    }
+    for index in table.indexes.iter().filter(|i| !i.primary && i.name.starts_with(SQLITE_AUTOINDEX)) {
+        parts.push(format!("    UNIQUE ({})", quote_all(&index.columns)));
+    }
+    if db_type == DatabaseType::SQLite {
+        for key in &table.foreign_keys {
+            parts.push(format!("    {}", foreign_key_clause(key, db_type)));
+        }
+    }
+    format!("CREATE TABLE {} (\n{}\n)", db_type.quote_ident(&table.name), parts.join(",\n"))
+}
+
+/// 创建索引的语句（不带分号），不包括主键和建表语句中的 UNIQUE 约束
+///
+/// 只由表达式组成的索引读不到列，跳过。
+pub fn index_sql(table: &TableSchema, db_type: DatabaseType) -> Vec<String> {
+    table.indexes.iter()
+        .filter(|i| !i.primary && !i.name.starts_with(SQLITE_AUTOINDEX) && !i.columns.is_empty())
+        .map(|index| {
+            let columns: Vec<String> = index.columns.iter().map(|c| db_type.quote_ident(c)).collect();
+            format!(
+                "CREATE {}INDEX {} ON {} ({})",
+                if index.unique { "UNIQUE " } else { "" },
+                db_type.quote_ident(&index.name),
+                db_type.quote_ident(&table.name),
+                columns.join(", ")
+            )
+        })
+        .collect()
+}
+
+/// 添加外键的语句（不带分号）；SQLite 的外键在建表语句中，这里为空
+pub fn foreign_key_sql(table: &TableSchema, db_type: DatabaseType) -> Vec<String> {
+    if db_type == DatabaseType::SQLite {
+        return Vec::new();
+    }
+    table.foreign_keys.iter()
+        .map(|key| format!("ALTER TABLE {} ADD {}", db_type.quote_ident(&table.name), foreign_key_clause(key, db_type)))
+        .collect()
+}
+
+/// 值的 SQL 字面量
+pub fn literal(value: &SqlValue, db_type: DatabaseType) -> String {
+    let quote = |text: &str| {
+        let text = text.replace('\'', "''");
+        // MySQL 默认把反斜杠当作转义字符
+        match db_type {
+            DatabaseType::MySQL | DatabaseType::MariaDB => format!("'{}'", text.replace('\\', "\\\\")),
+            _ => format!("'{}'", text),
+        }
+    };
+    match value {
+        SqlValue::Null => "NULL".to_string(),
+        // inf、NaN 等不是合法的数字字面量
+        SqlValue::Number(n) if n.parse::<f64>().is_ok_and(f64::is_finite) => n.clone(),
+        SqlValue::Number(n) | SqlValue::Text(n) => quote(n),
+        SqlValue::Bytes(bytes) => {
+            let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            match db_type {
+                DatabaseType::PostgreSQL => format!("'\\x{}'", hex),
+                _ => format!("X'{}'", hex),
+            }
+        }
+    }
+}
+
+/// 插入一行的语句（不带分号）
+pub fn insert_sql(table: &TableSchema, row: &[SqlValue], db_type: DatabaseType) -> String {
+    let columns: Vec<String> = table.columns.iter().map(|c| db_type.quote_ident(&c.name)).collect();
+    let values: Vec<String> = row.iter().map(|v| literal(v, db_type)).collect();
+    format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        db_type.quote_ident(&table.name),
+        columns.join(", "),
+        values.join(", ")
+    )
+}
+
+/// 导出表结构和数据，`table` 为空时导出所有表
+pub fn dump(conn: &mut DbConnection, table: Option<&str>) -> Result<String, DbError> {
+    let db_type = conn.db_type().ok_or(DbError::NotConnected)?;
+    let tables = match table {
+        Some(name) => vec![load_table(conn, name)?],
+        None => load_tables(conn)?,
+    };
+
+    let mut out = format!("-- cnmsb .dump ({})\n", db_type.name());
+    // 表按名称排序，被引用的表可能在后面；PRAGMA 在事务中不生效
+    let mysql = matches!(db_type, DatabaseType::MySQL | DatabaseType::MariaDB);
+    if db_type == DatabaseType::SQLite {
+        out.push_str("PRAGMA foreign_keys = OFF;\n");
+    }
+    // MySQL 的 CREATE TABLE 会提交事务，BEGIN/COMMIT 没有意义
+    out.push_str(if mysql { "SET FOREIGN_KEY_CHECKS = 0;\n" } else { "BEGIN;\n" });
+    for table in &tables {
+        out.push('\n');
+        out.push_str(&create_table_sql(table, db_type));
+        out.push_str(";\n");
+
+        let columns: Vec<String> = table.columns.iter().map(|c| c.name.clone()).collect();
+        for row in conn.fetch_rows(&table.name, &columns)? {
+            out.push_str(&insert_sql(table, &row, db_type));
+            out.push_str(";\n");
+        }
+        // 插入的行带有 serial 列的值，序列要跳到最大值之后
+        for column in table.columns.iter().filter(|c| serial_type(c, db_type).is_some()) {
+            out.push_str(&format!(
+                "SELECT setval(pg_get_serial_sequence('{}', '{}'), COALESCE(MAX({}), 0) + 1, false) FROM {};\n",
+                db_type.quote_ident(&table.name).replace('\'', "''"),
+                column.name.replace('\'', "''"),
+                db_type.quote_ident(&column.name),
+                db_type.quote_ident(&table.name)
+            ));
+        }
+        for sql in index_sql(table, db_type) {
+            out.push_str(&sql);
+            out.push_str(";\n");
+        }
+    }
+
+    let foreign_keys: Vec<String> = tables.iter().flat_map(|t| foreign_key_sql(t, db_type)).collect();
+    if !foreign_keys.is_empty() {
+        out.push('\n');
+        for sql in foreign_keys {
+            out.push_str(&sql);
+            out.push_str(";\n");
+        }
+    }
+    out.push_str(if mysql { "\nSET FOREIGN_KEY_CHECKS = 1;\n" } else { "\nCOMMIT;\n" });
+    Ok(out)
+}
@@ -1,27 +1,53 @@
 //! SQL 补全引擎
 
+use super::connection::{Catalog, SchemaObject, SchemaObjectKind, TableInfo};
 use super::database::DatabaseType;
-use super::syntax::{self, SqlSyntax, SqlCompletion};
-use std::collections::HashMap;
+use super::highlight::{self, DialectWords, HighlightKind, HighlightSpan};
+use super::lexer::{self, TokenKind};
+use super::parse_state::{self, Clause, ParseState, SourceKind};
+use super::syntax::{self, SqlSyntax, SqlCompletion, SqlCompletionKind};
+
+/// 按表名加载列的回调，用于在首次用到时才查询列信息
+pub type ColumnLoader = Box<dyn Fn(&str) -> Vec<String>>;
+
+/// 按表名加载列类型、索引和外键的回调
+pub type TableInfoLoader = Box<dyn Fn(&str) -> Option<TableInfo>>;
 
 /// SQL 补全引擎
 pub struct SqlEngine {
     db_type: DatabaseType,
     syntax: Box<dyn SqlSyntax>,
     tables: Vec<String>,
-    columns: Vec<(String, Vec<String>)>, // (表名, 列名列表)
-    aliases: HashMap<String, String>,     // (别名 -> 表名)
+    columns: Vec<(String, Vec<String>)>, // (表名, 列名列表)，非 search_path 中的表以 schema.table 为键
+    schemas: Vec<String>,
+    search_path: Vec<String>,
+    schema_objects: Vec<(String, Vec<SchemaObject>)>, // (schema, 对象列表)
+    column_loader: Option<ColumnLoader>,
+    cached_columns: Option<ColumnLoader>,
+    table_info: Vec<(String, TableInfo)>, // (表名, 列类型、索引和外键)
+    table_info_loader: Option<TableInfoLoader>,
+    history: Vec<(String, usize)>, // (历史片段, 使用次数)，按次数从多到少
+    words: DialectWords,
 }
 
 impl SqlEngine {
     /// 创建新的 SQL 引擎
     pub fn new(db_type: DatabaseType) -> Self {
+        let syntax = syntax::get_syntax(db_type);
         SqlEngine {
             db_type,
-            syntax: syntax::get_syntax(db_type),
+            words: DialectWords::from_syntax(syntax.as_ref()),
+            syntax,
             tables: Vec::new(),
             columns: Vec::new(),
-            aliases: HashMap::new(),
+            schemas: Vec::new(),
+            search_path: Vec::new(),
+            schema_objects: Vec::new(),
+            column_loader: None,
+            cached_columns: None,
+            table_info: Vec::new(),
+            table_info_loader: None,
+            history: Vec::new(),
         }
     }
     
@@ -49,74 +75,222 @@ impl SqlEngine {
         self.columns.push((table.to_string(), columns));
     }
     
-    /// 获取补全建议
+    /// 设置 schema（MySQL 为数据库）列表
+    pub fn set_schemas(&mut self, schemas: Vec<String>) {
+        self.schemas = schemas;
+    }
+    
+    /// 设置不加前缀即可引用的 schema（search_path / 当前数据库）
+    pub fn set_search_path(&mut self, search_path: Vec<String>) {
+        self.search_path = search_path;
+    }
+    
+    /// 设置 schema 中的对象
+    pub fn set_schema_objects(&mut self, schema: &str, objects: Vec<SchemaObject>) {
+        self.schema_objects.retain(|(s, _)| s != schema);
+        self.schema_objects.push((schema.to_string(), objects));
+    }
+    
+    /// 从数据库目录设置表、schema 和对象，已设置的列信息会被清除
+    pub fn set_catalog(&mut self, catalog: &Catalog) {
+        self.tables = catalog.tables();
+        self.columns.clear();
+        self.table_info.clear();
+        self.schemas = catalog.schemas.clone();
+        self.search_path = catalog.search_path.clone();
+        self.schema_objects = catalog.objects.clone();
+    }
+    
+    /// 设置列加载回调，未通过 set_columns 设置的表在首次用到时通过它获取列
+    pub fn set_column_loader(&mut self, loader: ColumnLoader) {
+        self.column_loader = Some(loader);
+    }
+    
+    /// 设置只读取缓存的列回调，高亮时使用，不查询数据库
+    pub fn set_cached_columns(&mut self, lookup: ColumnLoader) {
+        self.cached_columns = Some(lookup);
+    }
+    
+    /// 设置表的详细结构，同时设置其列
+    pub fn set_table_info(&mut self, table: &str, info: TableInfo) {
+        self.set_columns(table, info.columns.iter().map(|c| c.name.clone()).collect());
+        self.table_info.retain(|(t, _)| t != table);
+        self.table_info.push((table.to_string(), info));
+    }
+    
+    /// 设置表结构加载回调，用于列类型和基于外键的 JOIN 建议
+    pub fn set_table_info_loader(&mut self, loader: TableInfoLoader) {
+        self.table_info_loader = Some(loader);
+    }
+    
+    /// 设置查询历史中的常用片段，按使用次数从多到少排列
+    pub fn set_history(&mut self, fragments: Vec<(String, usize)>) {
+        self.history = fragments;
+    }
+    
+    /// 获取补全建议，历史中匹配的片段排在最前面
     pub fn complete(&self, input: &str, cursor: usize) -> Vec<SqlCompletion> {
+        let mut completions = self.history_completions(input, cursor);
+        completions.extend(self.complete_current(input, cursor));
+        completions
+    }
+    
+    /// 用历史片段补全当前语句的剩余部分
+    ///
+    /// 当前语句的某个后缀（从词的开头算起）是历史片段的前缀时，补全为该片段
+    fn history_completions(&self, input: &str, cursor: usize) -> Vec<SqlCompletion> {
+        const MAX_HISTORY_COMPLETIONS: usize = 3;
+        
+        let cursor = cursor.min(input.len());
+        let before = &input[..cursor];
+        let statement = before.rsplit(';').next().unwrap_or(before).trim_start();
+        if self.history.is_empty() || statement.is_empty() {
+            return Vec::new();
+        }
+        if parse_state::analyze(input, cursor, self.db_type).in_literal {
+            return Vec::new();
+        }
+        
+        // 与历史片段一样合并空白
+        let mut typed = statement.split_whitespace().collect::<Vec<_>>().join(" ");
+        if statement.ends_with(char::is_whitespace) {
+            typed.push(' ');
+        }
+        let current_word = self.get_current_word(before);
+        
+        // 每个词的开头
+        let word_starts: Vec<usize> = std::iter::once(0)
+            .chain(typed.match_indices(' ').map(|(i, _)| i + 1))
+            .filter(|&i| i < typed.len())
+            .collect();
+        
+        let mut completions: Vec<SqlCompletion> = Vec::new();
+        for (fragment, count) in &self.history {
+            // 取最长的匹配后缀
+            let matched = word_starts.iter().map(|&i| &typed[i..]).find(|tail| {
+                tail.len() >= 2 && fragment.len() > tail.len() && fragment.is_char_boundary(tail.len())
+                    && fragment[..tail.len()].eq_ignore_ascii_case(tail)
+            });
+            if let Some(tail) = matched {
+                let text = format!("{}{}", current_word, &fragment[tail.len()..]);
+                if !completions.iter().any(|c| c.text == text) {
+                    completions.push(SqlCompletion::history(&text, *count));
+                }
+                if completions.len() >= MAX_HISTORY_COMPLETIONS {
+                    break;
+                }
+            }
+        }
+        completions
+    }
+    
+    /// 根据语法、schema 和光标处的上下文补全当前词
+    fn complete_current(&self, input: &str, cursor: usize) -> Vec<SqlCompletion> {
         let mut completions = self.syntax.complete(input, cursor);
         
         // 获取当前词
-        let input_slice = if cursor <= input.len() {
-            &input[..cursor]
-        } else {
-            input
-        };
-        
+        let cursor = cursor.min(input.len());
+        let input_slice = &input[..cursor];
         let current_word = self.get_current_word(input_slice);
         let current_word_upper = current_word.to_uppercase();
-        let input_upper = input_slice.to_uppercase();
+        let matches_word = |text: &str| current_word_upper.is_empty() || text.to_uppercase().starts_with(&current_word_upper);
         
-        // 解析别名
-        let aliases = self.parse_aliases(&input_upper);
+        // 分析光标处的子句、作用域和可见的表
+        let state = parse_state::analyze(input, cursor, self.db_type);
+        
+        // 字符串或注释中不补全表名和列名
+        if state.in_literal {
+            return completions;
+        }
         
         // 检查是否在输入 table.column 格式
-        if let Some((table_or_alias, col_prefix)) = self.parse_dot_notation(&current_word) {
-            // 解析表名（可能是别名）
-            let table = aliases.get(&table_or_alias.to_uppercase())
-                .map(|s| s.as_str())
-                .unwrap_or(&table_or_alias);
+        if let Some((qualifier, col_prefix)) = self.parse_dot_notation(current_word) {
+            let col_prefix_upper = col_prefix.to_uppercase();
             
-            // 查找表的列
-            if let Some((_, cols)) = self.columns.iter().find(|(t, _)| t.eq_ignore_ascii_case(table)) {
-                for col in cols {
-                    if col_prefix.is_empty() || col.to_uppercase().starts_with(&col_prefix.to_uppercase()) {
-                        let text = format!("{}.{}", table_or_alias, col);
-                        completions.push(SqlCompletion::column(&text, table));
+            // schema. 后面补全该 schema 中的对象
+            if let Some(objects) = self.objects_of(&state, &qualifier) {
+                for object in objects {
+                    // 表名位置不能放函数
+                    if state.expects_table && object.kind == SchemaObjectKind::Function {
+                        continue;
+                    }
+                    if object.name.to_uppercase().starts_with(&col_prefix_upper) {
+                        let text = format!("{}.{}", qualifier, object.name);
+                        completions.push(Self::object_completion(&text, object.kind));
                     }
                 }
+                return completions;
+            }
+            
+            let (source, cols) = self.columns_of(&state, &qualifier);
+            let info = self.info_of(&source);
+            for col in cols {
+                if col.to_uppercase().starts_with(&col_prefix_upper) {
+                    let text = format!("{}.{}", qualifier, col);
+                    completions.push(Self::column_completion(&text, &col, &source, info.as_ref()));
+                }
             }
             return completions;
         }
         
-        // 在 FROM、JOIN 等后面添加表名补全
-        if self.should_complete_tables(&input_upper) {
+        // 在 FROM、JOIN 等后面补全表名和 CTE 名
+        if state.expects_table {
+            // JOIN 后面先给出与已有表有外键关系的表及连接条件
+            if state.prev_word.as_deref() == Some("JOIN") {
+                let joins: Vec<SqlCompletion> = self.join_completions(&state).into_iter().filter(|c| matches_word(&c.text)).collect();
+                completions.splice(0..0, joins);
+            }
+            for cte in &state.ctes {
+                if matches_word(&cte.name) {
+                    completions.push(SqlCompletion::cte(&cte.name));
+                }
+            }
             for table in &self.tables {
-                if current_word_upper.is_empty() || table.to_uppercase().starts_with(&current_word_upper) {
-                    completions.push(SqlCompletion::table(table));
+                if matches_word(table) {
+                    completions.push(self.table_completion(table));
                 }
             }
+            for schema in &self.schemas {
+                if matches_word(schema) {
+                    completions.push(SqlCompletion::schema(schema));
+                }
+            }
+            return completions;
         }
         
-        // 在特定上下文添加列名补全
-        if self.should_complete_columns(&input_upper) {
-            // 尝试从上下文获取表名
-            let context_tables = self.extract_tables_from_context(&input_upper, &aliases);
-            
-            if context_tables.is_empty() {
+        // ON 后面先给出新连接的表与已有表之间的外键条件
+        if state.clause == Clause::On && state.prev_word.as_deref() == Some("ON") {
+            let conditions: Vec<SqlCompletion> = self.join_conditions(&state, input_slice).into_iter().filter(|c| matches_word(&c.text)).collect();
+            completions.splice(0..0, conditions);
+        }
+        
+        // 在需要列名的子句中补全列名
+        if state.clause.expects_column() {
+            if state.tables.is_empty() {
                 // 没有特定表，显示所有列
                 for (table, cols) in &self.columns {
                     for col in cols {
-                        if current_word_upper.is_empty() || col.to_uppercase().starts_with(&current_word_upper) {
+                        if matches_word(col) {
                             completions.push(SqlCompletion::column(col, table));
                         }
                     }
                 }
             } else {
-                // 显示上下文表的列
-                for table in &context_tables {
-                    if let Some((_, cols)) = self.columns.iter().find(|(t, _)| t.eq_ignore_ascii_case(table)) {
-                        for col in cols {
-                            if current_word_upper.is_empty() || col.to_uppercase().starts_with(&current_word_upper) {
-                                completions.push(SqlCompletion::column(col, table));
-                            }
+                // 显示可见表的列，以及可用作限定符的别名/CTE/子查询名
+                for table_ref in &state.tables {
+                    let visible = table_ref.visible_name();
+                    if !visible.is_empty() && (table_ref.alias.is_some() || table_ref.kind != SourceKind::Table) && matches_word(visible) {
+                        let target = match table_ref.kind {
+                            SourceKind::Subquery => "子查询",
+                            _ => table_ref.name.as_str(),
+                        };
+                        completions.push(SqlCompletion::alias(visible, target));
+                    }
+                    let (source, cols) = self.columns_of(&state, visible);
+                    let info = (table_ref.kind == SourceKind::Table).then(|| self.info_of(&source)).flatten();
+                    for col in cols {
+                        if matches_word(&col) {
+                            completions.push(Self::column_completion(&col, &col, &source, info.as_ref()));
                         }
                     }
                 }
@@ -126,16 +300,185 @@ impl SqlEngine {
         completions
     }
     
+    /// 列补全项，知道类型时在描述中注明
+    fn column_completion(text: &str, column: &str, source: &str, info: Option<&TableInfo>) -> SqlCompletion {
+        let data_type = info
+            .and_then(|info| info.columns.iter().find(|c| c.name == column))
+            .map(|c| c.data_type.as_str())
+            .filter(|t| !t.is_empty());
+        match data_type {
+            Some(data_type) => SqlCompletion::typed_column(text, source, data_type),
+            None => SqlCompletion::column(text, source),
+        }
+    }
+    
+    /// 查找表的详细结构，没有设置时通过加载回调获取
+    fn info_of(&self, table: &str) -> Option<TableInfo> {
+        let find = |name: &str| {
+            self.table_info.iter()
+                .find(|(t, _)| t.eq_ignore_ascii_case(name))
+                .map(|(_, info)| info.clone())
+        };
+        find(table)
+            .or_else(|| {
+                table.split_once('.')
+                    .filter(|(schema, _)| self.search_path.iter().any(|s| s.eq_ignore_ascii_case(schema)))
+                    .and_then(|(_, name)| find(name))
+            })
+            .or_else(|| self.table_info_loader.as_ref().and_then(|load| load(table)))
+    }
+    
+    /// JOIN 后面的表建议：与已有的表有外键关系的表，连同 ON 条件
+    fn join_completions(&self, state: &ParseState) -> Vec<SqlCompletion> {
+        let mut completions: Vec<SqlCompletion> = Vec::new();
+        for table_ref in state.tables.iter().filter(|t| t.kind == SourceKind::Table) {
+            let info = match self.info_of(&table_ref.name) {
+                Some(info) => info,
+                None => continue,
+            };
+            let visible = table_ref.visible_name();
+            for key in &info.foreign_keys {
+                let (joined, condition) = if same_table(&key.table, &table_ref.name) {
+                    (&key.ref_table, key.join_condition(visible, &key.ref_table))
+                } else {
+                    (&key.table, key.join_condition(&key.table, visible))
+                };
+                // 自引用的外键需要别名，不自动建议
+                if same_table(joined, &table_ref.name) {
+                    continue;
+                }
+                let text = format!("{} ON {}", joined, condition);
+                if !completions.iter().any(|c| c.text == text) {
+                    completions.push(SqlCompletion::join(&text, &format!("外键 {}", key.name)));
+                }
+            }
+        }
+        completions
+    }
+    
+    /// ON 后面的条件建议：刚连接的表与前面的表之间的外键
+    fn join_conditions(&self, state: &ParseState, before: &str) -> Vec<SqlCompletion> {
+        // 光标前最后一个 JOIN 后面的表就是刚连接的表
+        let tokens = lexer::significant_tokens(before, self.db_type);
+        let index = match tokens.iter().rposition(|t| t.kind == TokenKind::Word && t.text.eq_ignore_ascii_case("JOIN")) {
+            Some(index) => index,
+            None => return Vec::new(),
+        };
+        // 表名，可能是 schema.table
+        let is_ident = |i: usize| tokens.get(i).is_some_and(|t| matches!(t.kind, TokenKind::Word | TokenKind::QuotedIdent));
+        let mut name = String::new();
+        if is_ident(index + 1) {
+            name = parse_state::unquote(tokens[index + 1].text);
+            if tokens.get(index + 2).is_some_and(|t| t.kind == TokenKind::Dot) && is_ident(index + 3) {
+                name = format!("{}.{}", name, parse_state::unquote(tokens[index + 3].text));
+            }
+        }
+        let tables: Vec<_> = state.tables.iter().filter(|t| t.kind == SourceKind::Table).collect();
+        let joined = match tables.iter().find(|t| !name.is_empty() && same_table(&t.name, &name)) {
+            Some(joined) => *joined,
+            None => return Vec::new(),
+        };
+        let info = match self.info_of(&joined.name) {
+            Some(info) => info,
+            None => return Vec::new(),
+        };
+        
+        let mut completions: Vec<SqlCompletion> = Vec::new();
+        for other in tables.iter().filter(|t| !std::ptr::eq(**t, joined)) {
+            for key in &info.foreign_keys {
+                let condition = if same_table(&key.table, &joined.name) && same_table(&key.ref_table, &other.name) {
+                    key.join_condition(joined.visible_name(), other.visible_name())
+                } else if same_table(&key.table, &other.name) && same_table(&key.ref_table, &joined.name) {
+                    key.join_condition(other.visible_name(), joined.visible_name())
+                } else {
+                    continue;
+                };
+                if !completions.iter().any(|c| c.text == condition) {
+                    completions.push(SqlCompletion::join(&condition, &format!("外键 {}", key.name)));
+                }
+            }
+        }
+        completions
+    }
+    
+    /// 如果限定符是 schema（而不是表、别名或 CTE），返回其中的对象
+    fn objects_of(&self, state: &ParseState, qualifier: &str) -> Option<&[SchemaObject]> {
+        if state.resolve(qualifier).is_some() || state.cte(qualifier).is_some() {
+            return None;
+        }
+        if self.tables.iter().any(|t| t.eq_ignore_ascii_case(qualifier)) {
+            return None;
+        }
+        self.schema_objects.iter()
+            .find(|(s, _)| s.eq_ignore_ascii_case(qualifier))
+            .map(|(_, objects)| objects.as_slice())
+    }
+    
+    /// 对象补全项，视图等在描述中注明类型
+    fn object_completion(text: &str, kind: SchemaObjectKind) -> SqlCompletion {
+        match kind {
+            SchemaObjectKind::Table => SqlCompletion::table(text),
+            SchemaObjectKind::Function => SqlCompletion::function(text, kind.description()),
+            _ => SqlCompletion::relation(text, kind.description()),
+        }
+    }
+    
+    /// search_path 中的表补全项
+    fn table_completion(&self, table: &str) -> SqlCompletion {
+        let kind = self.schema_objects.iter()
+            .filter(|(s, _)| self.search_path.contains(s))
+            .flat_map(|(_, objects)| objects.iter())
+            .find(|o| o.name == table && o.kind != SchemaObjectKind::Function)
+            .map(|o| o.kind)
+            .unwrap_or(SchemaObjectKind::Table);
+        Self::object_completion(table, kind)
+    }
+    
+    /// 获取表名/别名/CTE 名对应的列，返回 (来源描述, 列名列表)
+    fn columns_of(&self, state: &ParseState, qualifier: &str) -> (String, Vec<String>) {
+        if let Some(table_ref) = state.resolve(qualifier) {
+            if table_ref.kind != SourceKind::Table {
+                return (table_ref.visible_name().to_string(), table_ref.columns.clone());
+            }
+            return (table_ref.name.clone(), self.table_columns(&table_ref.name));
+        }
+        if let Some(cte) = state.cte(qualifier) {
+            return (cte.name.clone(), cte.columns.clone());
+        }
+        (qualifier.to_string(), self.table_columns(qualifier))
+    }
+    
+    /// 查找表的列，都没有时通过加载回调获取
+    fn table_columns(&self, table: &str) -> Vec<String> {
+        match self.known_columns(table) {
+            Some(cols) => cols,
+            None => self.column_loader.as_ref().map(|load| load(table)).unwrap_or_default(),
+        }
+    }
+    
+    /// 已设置或已缓存的列，search_path 中的 schema.table 也可以按表名查找；不调用加载回调
+    fn known_columns(&self, table: &str) -> Option<Vec<String>> {
+        let find = |name: &str| {
+            self.columns.iter()
+                .find(|(t, _)| t.eq_ignore_ascii_case(name))
+                .map(|(_, cols)| cols.clone())
+        };
+        find(table)
+            .or_else(|| {
+                table.split_once('.')
+                    .filter(|(schema, _)| self.search_path.iter().any(|s| s.eq_ignore_ascii_case(schema)))
+                    .and_then(|(_, name)| find(name))
+            })
+            .or_else(|| {
+                self.cached_columns.as_ref().map(|lookup| lookup(table)).filter(|cols| !cols.is_empty())
+            })
+    }
+    
     /// 获取当前正在输入的词
     fn get_current_word<'a>(&self, input: &'a str) -> &'a str {
-        // 从后向前找到词的开始
+        // 从后向前找到词的开始（光标前是空格时当前词为空）
         let bytes = input.as_bytes();
-        let mut end = bytes.len();
-        
-        // 跳过尾部空格
-        while end > 0 && bytes[end - 1] == b' ' {
-            end -= 1;
-        }
+        let end = bytes.len();
         
         // 找词的开始
         let mut start = end;
@@ -164,103 +507,83 @@ impl SqlEngine {
         None
     }
     
-    /// 解析 SQL 中的别名
-    fn parse_aliases(&self, input: &str) -> HashMap<String, String> {
-        let mut aliases = HashMap::new();
-        
-        // 简单解析: FROM table alias 或 FROM table AS alias
-        // 以及 JOIN table alias
-        let patterns = ["FROM ", "JOIN "];
+    /// 计算一行输入的高亮区间（按起始位置排序）
+    ///
+    /// 关键字、函数和类型按方言的词表判断，`schema.表`、行中引用到的表及其列
+    /// 按已知的 schema 信息判断，光标处的括号与其配对括号一起高亮
+    pub fn highlight(&self, line: &str, cursor: usize) -> Vec<HighlightSpan> {
+        let tokens = lexer::tokenize(line, self.db_type);
+        let significant: Vec<_> = tokens.iter().filter(|t| t.is_significant()).collect();
+        let name_of = |i: usize| -> Option<String> {
+            significant.get(i)
+                .filter(|t| matches!(t.kind, TokenKind::Word | TokenKind::QuotedIdent))
+                .map(|t| parse_state::unquote(t.text))
+        };
+        let is_dot = |i: Option<usize>| i.and_then(|i| significant.get(i)).is_some_and(|t| t.kind == TokenKind::Dot);
+        let is_table = |name: &str| self.tables.iter().any(|t| t.eq_ignore_ascii_case(name));
+        let in_schema = |schema: &str, name: &str| {
+            self.schema_objects.iter()
+                .filter(|(s, _)| s.eq_ignore_ascii_case(schema))
+                .flat_map(|(_, objects)| objects.iter())
+                .any(|o| o.kind != SchemaObjectKind::Function && o.name.eq_ignore_ascii_case(name))
+        };
         
-        for pattern in &patterns {
-            let mut pos = 0;
-            while let Some(found) = input[pos..].find(pattern) {
-                let start = pos + found + pattern.len();
-                let rest = &input[start..];
-                
-                // 获取接下来的词
-                let words: Vec<&str> = rest.split_whitespace().take(3).collect();
-                
-                if words.len() >= 2 {
-                    let table = words[0].trim_matches(|c: char| !c.is_alphanumeric() && c != '_');
-                    
-                    if words.len() >= 2 && words[1].eq_ignore_ascii_case("AS") && words.len() >= 3 {
-                        // FROM table AS alias
-                        let alias = words[2].trim_matches(|c: char| !c.is_alphanumeric() && c != '_');
-                        if !alias.is_empty() && !table.is_empty() {
-                            aliases.insert(alias.to_uppercase(), table.to_string());
-                        }
-                    } else if words.len() >= 2 {
-                        // FROM table alias (without AS)
-                        let potential_alias = words[1].trim_matches(|c: char| !c.is_alphanumeric() && c != '_').to_uppercase();
-                        // 检查是否是关键字
-                        let keywords = ["WHERE", "JOIN", "LEFT", "RIGHT", "INNER", "OUTER", "ON", "AND", "OR", "GROUP", "ORDER", "HAVING", "LIMIT", "SET"];
-                        if !keywords.contains(&potential_alias.as_str()) && !potential_alias.is_empty() && !table.is_empty() {
-                            aliases.insert(potential_alias, table.to_string());
-                        }
-                    }
-                }
-                
-                pos = start;
-            }
+        // 每个标识符是否是表（可能带 schema 前缀），顺便收集这些表的列；
+        // 每次按键都会重新高亮，只使用已知的列，不查询数据库
+        let mut tables = vec![false; significant.len()];
+        let mut columns: Vec<String> = Vec::new();
+        for (i, is_table_name) in tables.iter_mut().enumerate() {
+            let name = match name_of(i) {
+                Some(name) => name,
+                None => continue,
+            };
+            let schema = i.checked_sub(2).filter(|&q| is_dot(Some(q + 1))).and_then(name_of);
+            let table = match schema {
+                Some(schema) if in_schema(&schema, &name) => format!("{}.{}", schema, name),
+                Some(_) => continue,
+                None if is_table(&name) => name,
+                None => continue,
+            };
+            *is_table_name = true;
+            columns.extend(self.known_columns(&table).unwrap_or_default());
         }
         
-        aliases
-    }
-    
-    /// 检查是否应该补全表名
-    fn should_complete_tables(&self, input: &str) -> bool {
-        let keywords = ["FROM ", "JOIN ", "INTO ", "UPDATE ", "TABLE ", "VIEW "];
-        keywords.iter().any(|k| {
-            if let Some(pos) = input.rfind(k) {
-                // 检查关键字后面是否已经有完整的表名
-                let after = &input[pos + k.len()..];
-                let has_space = after.contains(' ');
-                !has_space
-            } else {
-                false
-            }
-        })
-    }
-    
-    /// 检查是否应该补全列名
-    fn should_complete_columns(&self, input: &str) -> bool {
-        let keywords = ["SELECT ", "WHERE ", "AND ", "OR ", "SET ", "ORDER BY ", "GROUP BY ", "HAVING ", "ON "];
-        keywords.iter().any(|k| input.contains(k))
-    }
-    
-    /// 从上下文提取所有相关表名
-    fn extract_tables_from_context(&self, input: &str, aliases: &HashMap<String, String>) -> Vec<String> {
-        let mut tables = Vec::new();
-        
-        // 查找 FROM 和 JOIN 后面的表名
-        let patterns = ["FROM ", "JOIN "];
-        
-        for pattern in &patterns {
-            let mut pos = 0;
-            while let Some(found) = input[pos..].find(pattern) {
-                let start = pos + found + pattern.len();
-                let rest = &input[start..];
-                
-                if let Some(table) = rest.split_whitespace().next() {
-                    let table = table.trim_matches(|c: char| !c.is_alphanumeric() && c != '_');
-                    if !table.is_empty() && !tables.contains(&table.to_string()) {
-                        tables.push(table.to_string());
-                    }
+        let mut spans = Vec::new();
+        for (i, token) in significant.iter().enumerate() {
+            let kind = match token.kind {
+                TokenKind::Word | TokenKind::QuotedIdent => {
+                    let qualified = is_dot(i.checked_sub(1)) || is_dot(Some(i + 1));
+                    let is_call = significant.get(i + 1).is_some_and(|t| t.kind == TokenKind::LParen);
+                    let name = parse_state::unquote(token.text);
+                    let word_kind = if token.kind == TokenKind::Word && !qualified {
+                        self.words.classify(token.text, is_call)
+                    } else {
+                        None
+                    };
+                    word_kind
+                        .or_else(|| tables[i].then_some(HighlightKind::Table))
+                        .or_else(|| {
+                            (!is_dot(Some(i + 1)) && columns.iter().any(|c| c.eq_ignore_ascii_case(&name)))
+                                .then_some(HighlightKind::Column)
+                        })
                 }
-                
-                pos = start;
-            }
-        }
-        
-        // 添加别名对应的表
-        for table in aliases.values() {
-            if !tables.contains(table) {
-                tables.push(table.clone());
+                _ => None,
+            };
+            if let Some(kind) = kind {
+                spans.push(HighlightSpan { start: token.start, end: token.end(), kind });
             }
         }
+        // 注释不在 significant 中
+        spans.extend(tokens.iter().filter_map(|t| {
+            highlight::literal_kind(t).map(|kind| HighlightSpan { start: t.start, end: t.end(), kind })
+        }));
         
-        tables
+        // 括号高亮覆盖在最上面
+        let brackets = highlight::bracket_spans(&tokens, cursor);
+        spans.retain(|s| !brackets.iter().any(|b| b.start < s.end && s.start < b.end));
+        spans.extend(brackets);
+        spans.sort_by_key(|s| s.start);
+        spans
     }
     
     /// 获取当前词的补全建议（用于内联建议）
@@ -302,6 +625,14 @@ impl SqlEngine {
                 }
             };
             
+            // 历史片段原样提示，不改变大小写
+            if completion.kind == SqlCompletionKind::History {
+                if let Some(rest) = text.get(search_word.len()..).filter(|r| !r.is_empty()) {
+                    return Some(rest.to_string());
+                }
+                continue;
+            }
+            
             let text_upper = text.to_uppercase();
             let search_upper = search_word.to_uppercase();
             
@@ -326,3 +657,8 @@ impl SqlEngine {
     }
 }
 
+/// 两个表名是否指同一张表，其中一个可以带 schema 前缀
+fn same_table(a: &str, b: &str) -> bool {
+    let name = |t: &str| t.rsplit('.').next().unwrap_or(t).to_string();
+    a.eq_ignore_ascii_case(b) || (name(a).eq_ignore_ascii_case(&name(b)) && (!a.contains('.') || !b.contains('.')))
+}
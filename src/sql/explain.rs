@@ -0,0 +1,530 @@
+//! 执行计划可视化
+//!
+//! `.explain [analyze] <查询>` 按数据库执行对应的计划查询，把结果整理成
+//! 统一的计划树，显示代价和行数，并标出全表扫描、没有用上索引的过滤和临时排序。
+
+use super::connection::QueryResult;
+use super::database::DatabaseType;
+use super::lexer::{self, TokenKind};
+use regex::Regex;
+use serde_json::{Map, Value};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const GRAY: &str = "\x1b[38;5;240m";
+const YELLOW: &str = "\x1b[38;5;226m";
+const RED: &str = "\x1b[38;5;196m";
+
+/// 计划中值得注意的问题
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanWarning {
+    /// 读取整张表
+    FullScan,
+    /// 读取整张表再按条件过滤，通常说明缺少索引
+    UnindexedFilter,
+    /// 读取整个索引
+    FullIndexScan,
+    /// 无法利用索引排序，需要额外排序或临时表
+    TempSort,
+}
+
+impl PlanWarning {
+    pub fn description(&self) -> &'static str {
+        match self {
+            PlanWarning::FullScan => "全表扫描",
+            PlanWarning::UnindexedFilter => "全表扫描，过滤条件没有用上索引",
+            PlanWarning::FullIndexScan => "全索引扫描",
+            PlanWarning::TempSort => "临时排序",
+        }
+    }
+
+    /// 是否是严重问题（红色显示）
+    fn is_severe(&self) -> bool {
+        matches!(self, PlanWarning::FullScan | PlanWarning::UnindexedFilter)
+    }
+}
+
+/// 计划树中的一个节点
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlanNode {
+    pub label: String,
+    /// 条件、使用的索引等附加信息
+    pub details: Vec<String>,
+    /// 估算代价
+    pub cost: Option<f64>,
+    /// 估算行数
+    pub rows: Option<f64>,
+    /// 实际行数（ANALYZE）
+    pub actual_rows: Option<f64>,
+    /// 实际耗时，毫秒（ANALYZE）
+    pub actual_time: Option<f64>,
+    pub warning: Option<PlanWarning>,
+    pub children: Vec<PlanNode>,
+}
+
+impl PlanNode {
+    fn new(label: &str) -> Self {
+        PlanNode {
+            label: label.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// 完整的执行计划
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Plan {
+    pub nodes: Vec<PlanNode>,
+    pub total_cost: Option<f64>,
+    /// 规划耗时，毫秒
+    pub planning_time: Option<f64>,
+    /// 执行耗时，毫秒（ANALYZE）
+    pub execution_time: Option<f64>,
+}
+
+impl Plan {
+    /// 按深度优先顺序列出所有问题
+    pub fn warnings(&self) -> Vec<(PlanWarning, &str)> {
+        fn collect<'a>(node: &'a PlanNode, out: &mut Vec<(PlanWarning, &'a str)>) {
+            if let Some(warning) = node.warning {
+                out.push((warning, node.label.as_str()));
+            }
+            for child in &node.children {
+                collect(child, out);
+            }
+        }
+        let mut warnings = Vec::new();
+        for node in &self.nodes {
+            collect(node, &mut warnings);
+        }
+        warnings
+    }
+}
+
+/// 生成获取执行计划的语句
+///
+/// `analyze` 会真正执行查询，SQLite 不支持时忽略
+pub fn plan_sql(query: &str, db_type: DatabaseType, analyze: bool) -> String {
+    let query = query.trim().trim_end_matches(';').trim_end();
+    match (db_type, analyze) {
+        (DatabaseType::SQLite, _) => format!("EXPLAIN QUERY PLAN {}", query),
+        (DatabaseType::MySQL, false) | (DatabaseType::MariaDB, false) => format!("EXPLAIN FORMAT=JSON {}", query),
+        // MySQL 的 EXPLAIN ANALYZE 只有树形文本输出
+        (DatabaseType::MySQL, true) => format!("EXPLAIN ANALYZE {}", query),
+        (DatabaseType::MariaDB, true) => format!("ANALYZE FORMAT=JSON {}", query),
+        (_, false) => format!("EXPLAIN (FORMAT JSON) {}", query),
+        (_, true) => format!("EXPLAIN (ANALYZE, FORMAT JSON) {}", query),
+    }
+}
+
+/// 查询是否只读取数据，ANALYZE 只允许用于这类语句
+pub fn is_read_only(query: &str, db_type: DatabaseType) -> bool {
+    let tokens = lexer::significant_tokens(query, db_type);
+    let starts_with_read = tokens.first()
+        .is_some_and(|t| ["SELECT", "WITH", "VALUES", "TABLE"].iter().any(|k| t.is_keyword(k)));
+    // WITH 中可能包含修改数据的 CTE
+    let writes = tokens.iter()
+        .filter(|t| t.kind == TokenKind::Word)
+        .any(|t| ["INSERT", "UPDATE", "DELETE", "MERGE", "REPLACE", "TRUNCATE"].iter().any(|k| t.is_keyword(k)));
+    starts_with_read && !writes
+}
+
+/// 把计划查询的结果解析为计划树
+pub fn parse_plan(result: &QueryResult, db_type: DatabaseType, analyze: bool) -> Result<Plan, String> {
+    if db_type == DatabaseType::SQLite {
+        return Ok(parse_sqlite(&result.rows));
+    }
+
+    let text = result.rows.iter()
+        .filter_map(|row| row.first())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+    if text.is_empty() {
+        return Err("没有返回执行计划".to_string());
+    }
+
+    match db_type {
+        DatabaseType::MySQL if analyze => Ok(parse_mysql_tree(&text)),
+        DatabaseType::MySQL | DatabaseType::MariaDB => {
+            let json: Value = serde_json::from_str(&text).map_err(|e| format!("无法解析执行计划: {}", e))?;
+            Ok(parse_mysql_json(&json))
+        }
+        _ => {
+            let json: Value = serde_json::from_str(&text).map_err(|e| format!("无法解析执行计划: {}", e))?;
+            parse_postgres(&json)
+        }
+    }
+}
+
+/// 解析 SQLite 的 EXPLAIN QUERY PLAN 结果（id, parent, notused, detail）
+pub fn parse_sqlite(rows: &[Vec<String>]) -> Plan {
+    // (id, parent, 节点)
+    let entries: Vec<(String, String, PlanNode)> = rows.iter()
+        .filter(|row| row.len() >= 4)
+        .map(|row| {
+            let detail = row[3].as_str();
+            let mut node = PlanNode::new(detail);
+            node.warning = sqlite_warning(detail);
+            (row[0].clone(), row[1].clone(), node)
+        })
+        .collect();
+
+    fn build(parent: &str, entries: &[(String, String, PlanNode)]) -> Vec<PlanNode> {
+        entries.iter()
+            .filter(|(_, p, _)| p == parent)
+            .map(|(id, _, node)| {
+                let mut node = node.clone();
+                node.children = build(id, entries);
+                node
+            })
+            .collect()
+    }
+
+    Plan {
+        nodes: build("0", &entries),
+        ..Default::default()
+    }
+}
+
+fn sqlite_warning(detail: &str) -> Option<PlanWarning> {
+    if detail.starts_with("USE TEMP B-TREE") {
+        return Some(PlanWarning::TempSort);
+    }
+    let target = detail.strip_prefix("SCAN ")?;
+    // 常量行和子查询结果不是表
+    if target.starts_with("CONSTANT ROW") || target.starts_with('(') {
+        None
+    } else if target.contains(" USING ") && target.contains("INDEX") {
+        Some(PlanWarning::FullIndexScan)
+    } else {
+        Some(PlanWarning::FullScan)
+    }
+}
+
+/// 解析 PostgreSQL 的 EXPLAIN (FORMAT JSON) 结果
+pub fn parse_postgres(json: &Value) -> Result<Plan, String> {
+    let top = json.get(0).unwrap_or(json);
+    let root = top.get("Plan").ok_or("执行计划中没有 Plan")?;
+    let node = postgres_node(root);
+    Ok(Plan {
+        total_cost: node.cost,
+        nodes: vec![node],
+        planning_time: top.get("Planning Time").and_then(number),
+        execution_time: top.get("Execution Time").and_then(number),
+    })
+}
+
+fn postgres_node(plan: &Value) -> PlanNode {
+    let node_type = plan.get("Node Type").and_then(Value::as_str).unwrap_or("?");
+    let mut label = node_type.to_string();
+    if let Some(index) = plan.get("Index Name").and_then(Value::as_str) {
+        label.push_str(&format!(" using {}", index));
+    }
+    if let Some(relation) = plan.get("Relation Name").and_then(Value::as_str) {
+        label.push_str(&format!(" on {}", relation));
+        if let Some(alias) = plan.get("Alias").and_then(Value::as_str).filter(|a| *a != relation) {
+            label.push_str(&format!(" {}", alias));
+        }
+    }
+
+    let mut node = PlanNode::new(&label);
+    for key in ["Index Cond", "Recheck Cond", "Hash Cond", "Merge Cond", "Join Filter", "Filter"] {
+        if let Some(cond) = plan.get(key).and_then(Value::as_str) {
+            node.details.push(format!("{}: {}", key, cond));
+        }
+    }
+    if let Some(keys) = plan.get("Sort Key").and_then(Value::as_array) {
+        let keys: Vec<&str> = keys.iter().filter_map(Value::as_str).collect();
+        node.details.push(format!("Sort Key: {}", keys.join(", ")));
+    }
+    if let Some(method) = plan.get("Sort Method").and_then(Value::as_str) {
+        node.details.push(format!("Sort Method: {}", method));
+    }
+
+    node.cost = plan.get("Total Cost").and_then(number);
+    node.rows = plan.get("Plan Rows").and_then(number);
+    node.actual_rows = plan.get("Actual Rows").and_then(number);
+    node.actual_time = plan.get("Actual Total Time").and_then(number);
+
+    let filtered = plan.get("Filter").is_some();
+    node.warning = match node_type {
+        "Seq Scan" if filtered => Some(PlanWarning::UnindexedFilter),
+        "Seq Scan" => Some(PlanWarning::FullScan),
+        // 排序溢出到磁盘
+        "Sort" if plan.get("Sort Space Type").and_then(Value::as_str) == Some("Disk") => Some(PlanWarning::TempSort),
+        _ => None,
+    };
+
+    node.children = plan.get("Plans")
+        .and_then(Value::as_array)
+        .map(|plans| plans.iter().map(postgres_node).collect())
+        .unwrap_or_default();
+    node
+}
+
+/// 解析 MySQL / MariaDB 的 EXPLAIN FORMAT=JSON 结果
+pub fn parse_mysql_json(json: &Value) -> Plan {
+    let total_cost = json.pointer("/query_block/cost_info/query_cost")
+        .or_else(|| json.pointer("/query_block/cost"))
+        .and_then(number);
+    let execution_time = json.pointer("/query_block/r_total_time_ms").and_then(number);
+    Plan {
+        nodes: json.as_object().map(mysql_nodes).unwrap_or_default(),
+        total_cost,
+        planning_time: None,
+        execution_time,
+    }
+}
+
+/// MySQL 计划中的操作节点及其显示名称
+const MYSQL_OPERATIONS: &[(&str, &str)] = &[
+    ("ordering_operation", "ORDER BY"),
+    ("grouping_operation", "GROUP BY"),
+    ("duplicates_removal", "DISTINCT"),
+    ("windowing", "WINDOW"),
+    ("union_result", "UNION"),
+    ("buffer_result", "BUFFER RESULT"),
+    ("filesort", "filesort"),
+    ("read_sorted_file", "read sorted file"),
+    ("temporary_table", "temporary table"),
+    ("materialized_from_subquery", "materialized subquery"),
+];
+
+fn mysql_nodes(object: &Map<String, Value>) -> Vec<PlanNode> {
+    let mut nodes = Vec::new();
+    for (key, value) in object {
+        match (key.as_str(), value) {
+            ("query_block", Value::Object(block)) => {
+                let label = match block.get("select_id") {
+                    Some(id) => format!("SELECT #{}", id),
+                    None => "SELECT".to_string(),
+                };
+                let mut node = PlanNode::new(&label);
+                node.cost = block.get("cost_info").and_then(|c| c.get("query_cost")).and_then(number);
+                node.children = mysql_nodes(block);
+                nodes.push(node);
+            }
+            ("table", Value::Object(table)) => nodes.push(mysql_table(table)),
+            (_, Value::Object(inner)) => match MYSQL_OPERATIONS.iter().find(|(k, _)| k == key) {
+                Some((_, label)) => {
+                    let mut node = PlanNode::new(label);
+                    let sorts = inner.get("using_filesort").and_then(Value::as_bool).unwrap_or(false)
+                        || inner.get("using_temporary_table").and_then(Value::as_bool).unwrap_or(false)
+                        || key == "filesort";
+                    if sorts {
+                        node.warning = Some(PlanWarning::TempSort);
+                    }
+                    if let Some(sort_key) = inner.get("sort_key").and_then(Value::as_str) {
+                        node.details.push(format!("sort_key: {}", sort_key));
+                    }
+                    node.children = mysql_nodes(inner);
+                    nodes.push(node);
+                }
+                None => nodes.extend(mysql_nodes(inner)),
+            },
+            // nested_loop、query_specifications、attached_subqueries 等
+            (_, Value::Array(items)) => {
+                for item in items.iter().filter_map(Value::as_object) {
+                    nodes.extend(mysql_nodes(item));
+                }
+            }
+            _ => {}
+        }
+    }
+    nodes
+}
+
+fn mysql_table(table: &Map<String, Value>) -> PlanNode {
+    let name = table.get("table_name").and_then(Value::as_str).unwrap_or("?");
+    let access = table.get("access_type").and_then(Value::as_str).unwrap_or("?");
+    let key = table.get("key").and_then(Value::as_str);
+
+    let mut node = PlanNode::new(&format!("{} on {}", access, name));
+    if let Some(key) = key {
+        node.details.push(format!("key: {}", key));
+    }
+    let possible_keys: Vec<&str> = table.get("possible_keys")
+        .and_then(Value::as_array)
+        .map(|keys| keys.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    if !possible_keys.is_empty() {
+        node.details.push(format!("possible_keys: {}", possible_keys.join(", ")));
+    }
+    let condition = table.get("attached_condition").and_then(Value::as_str);
+    if let Some(condition) = condition {
+        node.details.push(format!("condition: {}", condition));
+    }
+
+    node.cost = table.get("cost_info").and_then(|c| c.get("prefix_cost")).and_then(number);
+    node.rows = table.get("rows_examined_per_scan").or_else(|| table.get("rows")).and_then(number);
+    node.actual_rows = table.get("r_rows").and_then(number);
+    node.actual_time = table.get("r_total_time_ms").and_then(number);
+
+    node.warning = match access {
+        "ALL" if condition.is_some() || !possible_keys.is_empty() => Some(PlanWarning::UnindexedFilter),
+        "ALL" => Some(PlanWarning::FullScan),
+        "index" => Some(PlanWarning::FullIndexScan),
+        _ => None,
+    };
+
+    // 派生表、子查询
+    let nested: Map<String, Value> = table.iter()
+        .filter(|(k, _)| !["cost_info", "possible_keys", "used_key_parts", "used_columns"].contains(&k.as_str()))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    node.children = mysql_nodes(&nested);
+    node
+}
+
+/// 解析 MySQL 的 EXPLAIN ANALYZE 树形文本
+///
+/// 每行形如 `-> Table scan on t  (cost=0.55 rows=3) (actual time=0.02..0.03 rows=3 loops=1)`，
+/// 按 `->` 的缩进确定层级
+pub fn parse_mysql_tree(text: &str) -> Plan {
+    let cost_re = Regex::new(r"\(cost=(?:[\d.]+\.\.)?([\d.]+) rows=([\d.e+]+)\)").unwrap();
+    let actual_re = Regex::new(r"\(actual time=[\d.]+\.\.([\d.]+) rows=([\d.e+]+) loops=(\d+)\)").unwrap();
+
+    // (缩进, 节点)，处理完子节点后出栈挂到父节点上
+    let mut stack: Vec<(usize, PlanNode)> = Vec::new();
+    let mut roots = Vec::new();
+
+    fn attach(node: PlanNode, stack: &mut [(usize, PlanNode)], roots: &mut Vec<PlanNode>) {
+        match stack.last_mut() {
+            Some((_, parent)) => parent.children.push(node),
+            None => roots.push(node),
+        }
+    }
+
+    for line in text.lines() {
+        let indent = match line.find("-> ") {
+            Some(indent) => indent,
+            None => continue,
+        };
+        let body = &line[indent + 3..];
+        let label_end = body.find("  (").or_else(|| body.find(" (cost=")).or_else(|| body.find(" (actual")).unwrap_or(body.len());
+        let label = body[..label_end].trim();
+
+        let mut node = PlanNode::new(label);
+        if let Some(caps) = cost_re.captures(body) {
+            node.cost = caps[1].parse().ok();
+            node.rows = caps[2].parse().ok();
+        }
+        if let Some(caps) = actual_re.captures(body) {
+            node.actual_time = caps[1].parse().ok();
+            node.actual_rows = caps[2].parse().ok();
+        }
+        node.warning = if label.starts_with("Table scan on") {
+            Some(PlanWarning::FullScan)
+        } else if label.starts_with("Index scan on") {
+            Some(PlanWarning::FullIndexScan)
+        } else if label.starts_with("Sort:") {
+            Some(PlanWarning::TempSort)
+        } else {
+            None
+        };
+
+        while stack.last().is_some_and(|(i, _)| *i >= indent) {
+            let (_, done) = stack.pop().unwrap();
+            attach(done, &mut stack, &mut roots);
+        }
+        stack.push((indent, node));
+    }
+    while let Some((_, done)) = stack.pop() {
+        attach(done, &mut stack, &mut roots);
+    }
+
+    // 过滤节点下面直接是全表扫描，说明条件没有用上索引
+    fn mark_filters(node: &mut PlanNode) {
+        let is_filter = node.label.starts_with("Filter:");
+        for child in &mut node.children {
+            if is_filter && child.warning == Some(PlanWarning::FullScan) {
+                child.warning = Some(PlanWarning::UnindexedFilter);
+            }
+            mark_filters(child);
+        }
+    }
+    roots.iter_mut().for_each(mark_filters);
+
+    Plan {
+        total_cost: roots.first().and_then(|n| n.cost),
+        nodes: roots,
+        ..Default::default()
+    }
+}
+
+/// JSON 中的数字（MySQL 用字符串表示代价）
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// 把计划渲染为缩进的树
+pub fn render(plan: &Plan, color: bool) -> Vec<String> {
+    let paint = |code: &str, text: &str| if color { format!("{}{}{}", code, text, RESET) } else { text.to_string() };
+
+    fn walk(node: &PlanNode, prefix: &str, connector: &str, child_prefix: &str, out: &mut Vec<String>, paint: &dyn Fn(&str, &str) -> String) {
+        let label = match node.warning {
+            Some(w) if w.is_severe() => paint(RED, &node.label),
+            Some(_) => paint(YELLOW, &node.label),
+            None => paint(BOLD, &node.label),
+        };
+        let mut line = format!("{}{}{}", prefix, connector, label);
+
+        let mut metrics = Vec::new();
+        if let Some(cost) = node.cost {
+            metrics.push(format!("cost={}", format_number(cost)));
+        }
+        if let Some(rows) = node.rows {
+            metrics.push(format!("rows={}", format_number(rows)));
+        }
+        if !metrics.is_empty() {
+            line.push_str(&paint(GRAY, &format!("  ({})", metrics.join(" "))));
+        }
+        if node.actual_rows.is_some() || node.actual_time.is_some() {
+            let mut actual = Vec::new();
+            if let Some(rows) = node.actual_rows {
+                actual.push(format!("rows={}", format_number(rows)));
+            }
+            if let Some(time) = node.actual_time {
+                actual.push(format!("time={}ms", format_number(time)));
+            }
+            line.push_str(&paint(GRAY, &format!(" (actual {})", actual.join(" "))));
+        }
+        if let Some(warning) = node.warning {
+            let code = if warning.is_severe() { RED } else { YELLOW };
+            line.push_str(&paint(code, &format!("  ⚠ {}", warning.description())));
+        }
+        out.push(line);
+
+        let inner = format!("{}{}", prefix, child_prefix);
+        let detail_prefix = if node.children.is_empty() { "   " } else { "│  " };
+        for detail in &node.details {
+            out.push(format!("{}{}{}", inner, detail_prefix, paint(GRAY, detail)));
+        }
+
+        for (i, child) in node.children.iter().enumerate() {
+            let last = i + 1 == node.children.len();
+            let (connector, next) = if last { ("└─ ", "   ") } else { ("├─ ", "│  ") };
+            walk(child, &inner, connector, next, out, paint);
+        }
+    }
+
+    let mut lines = Vec::new();
+    for node in &plan.nodes {
+        walk(node, "", "", "", &mut lines, &paint);
+    }
+    lines
+}
+
+/// 代价和行数：整数不带小数，其他保留两位
+fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        format!("{:.2}", n)
+    }
+}
@@ -0,0 +1,743 @@
+//! SQL 格式化
+//!
+//! `.format` 和 `cnmsb sql-fmt` 共用的格式化器。词法分析和关键字列表都来自
+//! 方言的 `SqlSyntax`，所以 MySQL 的反引号、# 注释和 PostgreSQL 的 `::` 类型转换、
+//! 美元引用都按原样保留。语句按子句换行，子查询缩进一层，SELECT 等列表
+//! 放不下一行时每项一行，WHERE 等条件放不下时在 AND/OR 前换行。
+
+use super::highlight::{DialectWords, HighlightKind};
+use super::lexer::{self, Token, TokenKind};
+use super::database::DatabaseType;
+use super::syntax::{self, SqlSyntax};
+
+/// 多字符操作符，按长度从长到短匹配
+const OPERATORS: &[&str] = &[
+    "->>", "#>>", "<=>", "!~*", "::", "<>", "<=", ">=", "!=", "||", "->", "#>", "@>", "<@", ":=", "<<", ">>",
+    "&&", "!~", "~*", "?|", "?&", "**",
+];
+
+/// 决定子句结构的关键字，方言的关键字列表中没有时也当作关键字
+const CLAUSE_WORDS: &[&str] = &[
+    "SELECT", "FROM", "WHERE", "HAVING", "JOIN", "LIMIT", "OFFSET", "WINDOW", "UNION", "INTERSECT", "EXCEPT",
+    "WITH", "RECURSIVE", "GROUP", "ORDER", "BY", "INSERT", "INTO", "DELETE", "UPDATE", "SET", "VALUES",
+    "RETURNING", "CONFLICT", "DUPLICATE", "KEY", "DISTINCT", "LEFT", "RIGHT", "FULL", "INNER", "CROSS",
+    "NATURAL", "OUTER", "ON", "AND", "OR", "BETWEEN",
+];
+
+/// 表名位置之后的词不改大小写（MySQL 在 Linux 上表名区分大小写）
+const TABLE_NAME_KEYWORDS: &[&str] = &["FROM", "JOIN", "INTO", "UPDATE", "TABLE"];
+
+/// 关键字大小写
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeywordCase {
+    Upper,
+    Lower,
+    /// 保持原样
+    Preserve,
+}
+
+impl KeywordCase {
+    pub fn parse(s: &str) -> Option<KeywordCase> {
+        match s.to_lowercase().as_str() {
+            "upper" => Some(KeywordCase::Upper),
+            "lower" => Some(KeywordCase::Lower),
+            "preserve" => Some(KeywordCase::Preserve),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, word: &str) -> String {
+        match self {
+            KeywordCase::Upper => word.to_uppercase(),
+            KeywordCase::Lower => word.to_lowercase(),
+            KeywordCase::Preserve => word.to_string(),
+        }
+    }
+}
+
+/// 格式化选项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub keyword_case: KeywordCase,
+    /// 每层缩进的空格数
+    pub indent: usize,
+    /// 超过这个宽度的列表和条件会换行
+    pub width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            keyword_case: KeywordCase::Upper,
+            indent: 4,
+            width: 80,
+        }
+    }
+}
+
+/// 按数据库的方言格式化一段 SQL（可以包含多条语句），语句之间空一行
+pub fn format(sql: &str, db_type: DatabaseType, options: &FormatOptions) -> String {
+    format_with(sql, syntax::get_syntax(db_type).as_ref(), options)
+}
+
+/// 按给定的方言语法格式化
+pub fn format_with(sql: &str, syntax: &dyn SqlSyntax, options: &FormatOptions) -> String {
+    let db_type = syntax.db_type();
+    let formatter = Formatter { words: DialectWords::from_syntax(syntax), options };
+
+    let statements = lexer::split_statements(sql, db_type);
+    let mut out: Vec<String> = statements.iter()
+        .map(|stmt| {
+            let atoms = formatter.atoms(&lexer::tokenize(stmt, db_type));
+            formatter.block(&tree(atoms), 0)
+        })
+        .collect();
+
+    // 最后一条语句之后只剩注释时保留这些注释
+    let tail_start = statements.last()
+        .map(|s| s.as_ptr() as usize - sql.as_ptr() as usize + s.len())
+        .unwrap_or(0);
+    let tail_tokens = lexer::tokenize(&sql[tail_start..], db_type);
+    let ends_with_semicolon = tail_tokens.iter().any(|t| t.kind == TokenKind::Semicolon);
+    let tail_comments: Vec<&str> = tail_tokens.iter()
+        .filter(|t| t.kind == TokenKind::Comment)
+        .map(|t| t.text.trim_end())
+        .collect();
+
+    let last = out.len().saturating_sub(1);
+    for (i, stmt) in out.iter_mut().enumerate() {
+        if i < last || ends_with_semicolon {
+            stmt.push(';');
+        }
+    }
+    let mut result = out.join("\n\n");
+    for comment in tail_comments {
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        result.push_str(comment);
+    }
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AtomKind {
+    /// 关键字或数据类型（会改大小写）
+    Keyword,
+    /// 标识符、函数名、字面量和参数
+    Word,
+    Op,
+    LParen,
+    RParen,
+    Comma,
+    Dot,
+    Semicolon,
+    /// 独占一行的行注释
+    LineComment,
+    BlockComment,
+}
+
+/// 格式化的最小单位
+#[derive(Debug, Clone)]
+struct Atom {
+    kind: AtomKind,
+    text: String,
+    /// 原文中前面有空白
+    space_before: bool,
+    /// 数据类型（后面的括号是长度参数，不空格）
+    data_type: bool,
+    /// 同一行末尾的行注释
+    comment: Option<String>,
+}
+
+impl Atom {
+    fn new(kind: AtomKind, text: &str, space_before: bool) -> Self {
+        Atom { kind, text: text.to_string(), space_before, data_type: false, comment: None }
+    }
+
+    fn is(&self, keyword: &str) -> bool {
+        self.kind == AtomKind::Keyword && self.text.eq_ignore_ascii_case(keyword)
+    }
+
+    fn is_comment(&self) -> bool {
+        matches!(self.kind, AtomKind::LineComment | AtomKind::BlockComment)
+    }
+}
+
+/// 括号分组后的语法树
+#[derive(Debug, Clone)]
+enum Node {
+    Atom(Atom),
+    Group { open: Atom, children: Vec<Node>, close: Option<Atom> },
+}
+
+impl Node {
+    fn atom(&self) -> Option<&Atom> {
+        match self {
+            Node::Atom(atom) => Some(atom),
+            Node::Group { .. } => None,
+        }
+    }
+
+    fn is(&self, keyword: &str) -> bool {
+        self.atom().is_some_and(|a| a.is(keyword))
+    }
+
+    fn is_comment(&self) -> bool {
+        self.atom().is_some_and(Atom::is_comment)
+    }
+
+    /// 括号中是子查询（SELECT、WITH 或 VALUES 开头）
+    fn is_subquery(&self) -> bool {
+        match self {
+            Node::Group { children, .. } => children.iter()
+                .find(|n| !n.is_comment())
+                .is_some_and(|n| n.is("SELECT") || n.is("WITH") || n.is("VALUES")),
+            Node::Atom(_) => false,
+        }
+    }
+}
+
+/// 把括号内的 atom 收进分组，多余的右括号当作普通 atom
+fn tree(atoms: Vec<Atom>) -> Vec<Node> {
+    let mut stack: Vec<(Atom, Vec<Node>)> = Vec::new();
+    let mut current: Vec<Node> = Vec::new();
+    for atom in atoms {
+        match atom.kind {
+            AtomKind::LParen => {
+                stack.push((atom, std::mem::take(&mut current)));
+            }
+            AtomKind::RParen if !stack.is_empty() => {
+                let (open, parent) = stack.pop().unwrap_or_else(|| unreachable!());
+                let children = std::mem::replace(&mut current, parent);
+                current.push(Node::Group { open, children, close: Some(atom) });
+            }
+            _ => current.push(Node::Atom(atom)),
+        }
+    }
+    // 没有闭合的括号
+    while let Some((open, parent)) = stack.pop() {
+        let children = std::mem::replace(&mut current, parent);
+        current.push(Node::Group { open, children, close: None });
+    }
+    current
+}
+
+/// 子句的排版方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    /// 逗号分隔的列表，放不下时每项一行
+    List,
+    /// 条件，放不下时在 AND/OR 前换行
+    Condition,
+    /// WITH 的各个 CTE，逗号后换行
+    With,
+    /// 原样排在一行
+    Plain,
+}
+
+/// 一个子句：开头的关键字和后面的内容
+struct Clause<'n> {
+    head: Vec<&'n Atom>,
+    body: Vec<&'n Node>,
+    layout: Layout,
+}
+
+struct Formatter<'a> {
+    words: DialectWords,
+    options: &'a FormatOptions,
+}
+
+impl Formatter<'_> {
+    /// 把 token 合并成 atom：合并多字符操作符和参数占位符，附上行尾注释，改关键字大小写
+    fn atoms(&self, tokens: &[Token]) -> Vec<Atom> {
+        let mut atoms: Vec<Atom> = Vec::new();
+        let mut space_before = false;
+        let mut newline_before = true;
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            // 紧跟在当前 token 后面（中间没有空白）的 token
+            let adjacent = |offset: usize| tokens.get(i + offset).filter(|t| t.start == tokens[i + offset - 1].end());
+            let mut consumed = 1;
+            let atom = match token.kind {
+                TokenKind::Whitespace => {
+                    space_before = true;
+                    newline_before |= token.text.contains('\n');
+                    i += 1;
+                    continue;
+                }
+                TokenKind::Comment if token.text.starts_with("/*") => {
+                    Atom::new(AtomKind::BlockComment, token.text, space_before)
+                }
+                TokenKind::Comment => {
+                    let text = token.text.trim_end().to_string();
+                    match atoms.last_mut() {
+                        Some(last) if !newline_before && last.comment.is_none() => {
+                            last.comment = Some(text);
+                            i += 1;
+                            continue;
+                        }
+                        _ => Atom::new(AtomKind::LineComment, &text, space_before),
+                    }
+                }
+                TokenKind::Operator => {
+                    // 尽量长地匹配连续的操作符
+                    let mut end = i + 1;
+                    while end < tokens.len() && tokens[end].kind == TokenKind::Operator && tokens[end].start == tokens[end - 1].end() {
+                        end += 1;
+                    }
+                    let run: String = tokens[i..end].iter().map(|t| t.text).collect();
+                    let op = OPERATORS.iter().find(|op| run.starts_with(*op)).copied().unwrap_or(token.text);
+                    consumed = tokens[i..end].iter().scan(0, |len, t| { *len += t.text.len(); Some(*len) })
+                        .position(|len| len >= op.len())
+                        .map_or(1, |p| p + 1);
+                    let next = adjacent(consumed).filter(|t| matches!(t.kind, TokenKind::Number | TokenKind::Word));
+                    match next {
+                        // $1、:name 这样的参数占位符
+                        Some(next) if op == "$" || op == ":" => {
+                            consumed += 1;
+                            Atom::new(AtomKind::Word, &format!("{}{}", op, next.text), space_before)
+                        }
+                        _ => Atom::new(AtomKind::Op, op, space_before),
+                    }
+                }
+                TokenKind::Word => {
+                    let is_call = adjacent(1).is_some_and(|t| t.kind == TokenKind::LParen);
+                    let class = self.words.classify(token.text, is_call);
+                    // 操作符后面的 VALUES(col) 是 MySQL 的函数
+                    let is_function = token.text.eq_ignore_ascii_case("VALUES")
+                        && atoms.last().is_some_and(|a| a.kind == AtomKind::Op);
+                    let kind = match class {
+                        _ if is_function => AtomKind::Word,
+                        Some(HighlightKind::Keyword | HighlightKind::DataType) => AtomKind::Keyword,
+                        None if CLAUSE_WORDS.contains(&token.text.to_uppercase().as_str()) => AtomKind::Keyword,
+                        _ => AtomKind::Word,
+                    };
+                    let mut atom = Atom::new(kind, token.text, space_before);
+                    atom.data_type = class == Some(HighlightKind::DataType);
+                    atom
+                }
+                TokenKind::LParen => Atom::new(AtomKind::LParen, "(", space_before),
+                TokenKind::RParen => Atom::new(AtomKind::RParen, ")", space_before),
+                TokenKind::Comma => Atom::new(AtomKind::Comma, ",", space_before),
+                TokenKind::Dot => Atom::new(AtomKind::Dot, ".", space_before),
+                TokenKind::Semicolon => Atom::new(AtomKind::Semicolon, ";", space_before),
+                TokenKind::QuotedIdent | TokenKind::String | TokenKind::Number => {
+                    Atom::new(AtomKind::Word, token.text, space_before)
+                }
+            };
+            atoms.push(atom);
+            space_before = false;
+            newline_before = false;
+            i += consumed;
+        }
+
+        self.apply_case(&mut atoms);
+        atoms
+    }
+
+    /// 改关键字的大小写；限定名（a.b）中的词和表名位置的词当作标识符
+    fn apply_case(&self, atoms: &mut [Atom]) {
+        let mut previous_keyword: Option<String> = None;
+        for i in 0..atoms.len() {
+            if atoms[i].is_comment() {
+                continue;
+            }
+            if atoms[i].kind == AtomKind::Keyword {
+                let qualified = (i > 0 && atoms[i - 1].kind == AtomKind::Dot)
+                    || atoms.get(i + 1).is_some_and(|a| a.kind == AtomKind::Dot);
+                let upper = atoms[i].text.to_uppercase();
+                let table_name = previous_keyword.as_deref().is_some_and(|k| TABLE_NAME_KEYWORDS.contains(&k))
+                    && !["IF", "ONLY", "LATERAL"].contains(&upper.as_str());
+                if qualified || table_name {
+                    atoms[i].kind = AtomKind::Word;
+                } else {
+                    atoms[i].text = self.options.keyword_case.apply(&atoms[i].text);
+                }
+            }
+            previous_keyword = (atoms[i].kind == AtomKind::Keyword).then(|| atoms[i].text.to_uppercase());
+        }
+    }
+
+    fn indent(&self, level: usize) -> String {
+        " ".repeat(level * self.options.indent)
+    }
+
+    /// 排版一条语句或子查询，每个子句从新的一行开始
+    fn block(&self, nodes: &[Node], level: usize) -> String {
+        let clauses = split_clauses(nodes);
+        let is_create_table = nodes.iter().find(|n| !n.is_comment()).is_some_and(|n| n.is("CREATE"));
+        clauses.iter()
+            .enumerate()
+            .map(|(i, clause)| {
+                let text = if i == 0 && is_create_table && clause.head.is_empty() {
+                    self.create_table(&clause.body, level)
+                } else {
+                    self.clause(clause, level)
+                };
+                format!("{}{}", self.indent(level), text.trim_end())
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn clause(&self, clause: &Clause, level: usize) -> String {
+        let head: Vec<&str> = clause.head.iter().map(|a| a.text.as_str()).collect();
+        let head = head.join(" ");
+        let prefix = if head.is_empty() { String::new() } else { format!("{} ", head) };
+        if clause.body.is_empty() {
+            return head;
+        }
+
+        // 先尝试放在一行
+        let inline = format!("{}{}", prefix, self.inline(&clause.body, level));
+        if clause.layout == Layout::Plain || self.fits(&inline, level) {
+            return inline;
+        }
+
+        match clause.layout {
+            Layout::List => {
+                let items = split_list(&clause.body);
+                if items.len() == 1 {
+                    return inline;
+                }
+                let lines: Vec<String> = items.iter()
+                    .map(|(item, comma)| {
+                        let mut line = format!("{}{}", self.indent(level + 1), self.inline(item, level + 1).trim_end());
+                        if let Some(comma) = comma {
+                            line.push(',');
+                            if let Some(ref comment) = comma.comment {
+                                line.push(' ');
+                                line.push_str(comment);
+                            }
+                        }
+                        line
+                    })
+                    .collect();
+                format!("{}\n{}", head, lines.join("\n"))
+            }
+            Layout::With => {
+                let items = split_list(&clause.body);
+                let lines: Vec<String> = items.iter()
+                    .enumerate()
+                    .map(|(i, (item, comma))| {
+                        let indent = if i == 0 { prefix.clone() } else { self.indent(level) };
+                        let comma = match comma {
+                            Some(comma) => format!(",{}", comma.comment.as_ref().map(|c| format!(" {}", c)).unwrap_or_default()),
+                            None => String::new(),
+                        };
+                        format!("{}{}{}", indent, self.inline(item, level).trim_end(), comma)
+                    })
+                    .collect();
+                lines.join("\n")
+            }
+            Layout::Condition => {
+                let mut out = prefix;
+                for (i, (connector, part)) in split_conditions(&clause.body).iter().enumerate() {
+                    if i > 0 {
+                        out = out.trim_end().to_string();
+                        out.push('\n');
+                        out.push_str(&self.indent(level + 1));
+                    }
+                    if let Some(connector) = connector {
+                        out.push_str(&connector.text);
+                        match connector.comment {
+                            Some(ref comment) => {
+                                out.push(' ');
+                                out.push_str(comment);
+                                out.push('\n');
+                                out.push_str(&self.indent(level + 1));
+                            }
+                            None => out.push(' '),
+                        }
+                    }
+                    out.push_str(&self.inline(part, level + usize::from(i > 0)));
+                }
+                out
+            }
+            Layout::Plain => inline,
+        }
+    }
+
+    /// CREATE TABLE 的列定义每项一行
+    fn create_table(&self, body: &[&Node], level: usize) -> String {
+        let position = body.iter().position(|n| matches!(n, Node::Group { .. }) && !n.is_subquery());
+        let has_table = position.is_some_and(|p| body[..p].iter().any(|n| n.is("TABLE")));
+        let (position, (children, close)) = match (position, has_table) {
+            (Some(p), true) => match body[p] {
+                Node::Group { children, close, .. } => (p, (children, close)),
+                Node::Atom(_) => unreachable!(),
+            },
+            _ => return self.inline(body, level),
+        };
+
+        let mut out = self.inline(&body[..position], level);
+        out.push_str(" (");
+        let children: Vec<&Node> = children.iter().collect();
+        for (item, comma) in split_list(&children) {
+            out.push('\n');
+            out.push_str(&self.indent(level + 1));
+            out.push_str(self.inline(&item, level + 1).trim_end());
+            if let Some(comma) = comma {
+                out.push(',');
+                if let Some(ref comment) = comma.comment {
+                    out.push(' ');
+                    out.push_str(comment);
+                }
+            }
+        }
+        if close.is_some() {
+            out.push('\n');
+            out.push_str(&self.indent(level));
+            out.push(')');
+            if let Some(comment) = close.as_ref().and_then(|c| c.comment.as_ref()) {
+                out.push(' ');
+                out.push_str(comment);
+            }
+        }
+        let rest = self.inline(&body[position + 1..], level);
+        if !rest.is_empty() {
+            out.push(' ');
+            out.push_str(&rest);
+        }
+        out
+    }
+
+    /// 一行内排版，子查询和注释会换行；续行缩进到 level + 1
+    fn inline(&self, nodes: &[&Node], level: usize) -> String {
+        let mut out = String::new();
+        let mut prev: Option<(Atom, bool)> = None; // (上一个 atom, 是否是一元操作符)
+        self.inline_into(&mut out, &mut prev, nodes, level);
+        out
+    }
+
+    fn inline_into(&self, out: &mut String, prev: &mut Option<(Atom, bool)>, nodes: &[&Node], level: usize) {
+        for node in nodes {
+            match node {
+                Node::Atom(atom) => self.push_atom(out, prev, atom, level),
+                Node::Group { open, children, close } => {
+                    self.push_atom(out, prev, open, level);
+                    if node.is_subquery() {
+                        out.push('\n');
+                        out.push_str(&self.block(children, level + 1));
+                        if close.is_some() {
+                            out.push('\n');
+                            out.push_str(&self.indent(level));
+                        }
+                        *prev = None;
+                    } else {
+                        let children: Vec<&Node> = children.iter().collect();
+                        self.inline_into(out, prev, &children, level);
+                    }
+                    if let Some(close) = close {
+                        // 子查询的右括号前已经换行
+                        let at_line_start = out.ends_with(&format!("\n{}", self.indent(level))) || out.ends_with('\n');
+                        if at_line_start {
+                            out.push_str(&close.text);
+                            self.push_comment(out, close, level);
+                            *prev = Some((close.clone(), false));
+                        } else {
+                            self.push_atom(out, prev, close, level);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn push_atom(&self, out: &mut String, prev: &mut Option<(Atom, bool)>, atom: &Atom, level: usize) {
+        let at_line_start = out.is_empty() || out.ends_with(' ') && out.trim_end_matches(' ').ends_with('\n');
+        if atom.kind == AtomKind::LineComment {
+            if !at_line_start {
+                out.push('\n');
+                out.push_str(&self.indent(level + 1));
+            }
+            out.push_str(&atom.text);
+            out.push('\n');
+            out.push_str(&self.indent(level + 1));
+            *prev = None;
+            return;
+        }
+
+        if !at_line_start && needs_space(prev.as_ref(), atom) {
+            out.push(' ');
+        }
+        out.push_str(&atom.text);
+
+        let unary = atom.kind == AtomKind::Op
+            && ["-", "+", "~", "!"].contains(&atom.text.as_str())
+            && prev.as_ref().map_or(true, |(p, _)| {
+                matches!(p.kind, AtomKind::Op | AtomKind::LParen | AtomKind::Comma | AtomKind::Keyword)
+            });
+        *prev = Some((atom.clone(), unary));
+
+        if atom.kind == AtomKind::Semicolon && atom.comment.is_none() {
+            out.push('\n');
+            out.push_str(&self.indent(level));
+            *prev = None;
+        }
+        if self.push_comment(out, atom, level) {
+            *prev = None;
+        }
+    }
+
+    /// 写出行尾注释并换行
+    fn push_comment(&self, out: &mut String, atom: &Atom, level: usize) -> bool {
+        match atom.comment {
+            Some(ref comment) => {
+                out.push(' ');
+                out.push_str(comment);
+                out.push('\n');
+                out.push_str(&self.indent(level + 1));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 单行并且不超过宽度
+    fn fits(&self, line: &str, level: usize) -> bool {
+        !line.contains('\n') && self.indent(level).len() + line.chars().count() <= self.options.width
+    }
+}
+
+/// 两个 atom 之间是否需要空格
+fn needs_space(prev: Option<&(Atom, bool)>, atom: &Atom) -> bool {
+    let (prev, prev_unary) = match prev {
+        Some((prev, unary)) => (prev, *unary),
+        None => return false,
+    };
+    if matches!(atom.kind, AtomKind::Comma | AtomKind::RParen | AtomKind::Semicolon | AtomKind::Dot) {
+        return false;
+    }
+    if matches!(prev.kind, AtomKind::LParen | AtomKind::Dot) || prev_unary {
+        return false;
+    }
+    if prev.text == "::" || atom.text == "::" {
+        return false;
+    }
+    if atom.kind == AtomKind::LParen {
+        // 关键字后面总是空一格；函数名、表名、类型后面保持原样
+        return match prev.kind {
+            AtomKind::Keyword if !prev.data_type => true,
+            AtomKind::Keyword => atom.space_before,
+            AtomKind::Word => atom.space_before,
+            _ => true,
+        };
+    }
+    true
+}
+
+/// 按顶层逗号拆分列表，返回 (项, 后面的逗号)
+fn split_list<'n>(nodes: &[&'n Node]) -> Vec<(Vec<&'n Node>, Option<&'n Atom>)> {
+    let mut items = Vec::new();
+    let mut current = Vec::new();
+    for node in nodes {
+        match node.atom() {
+            Some(atom) if atom.kind == AtomKind::Comma => {
+                items.push((std::mem::take(&mut current), Some(atom)));
+            }
+            _ => current.push(*node),
+        }
+    }
+    if !current.is_empty() || items.is_empty() {
+        items.push((current, None));
+    }
+    items
+}
+
+/// 按顶层的 AND/OR 拆分条件（BETWEEN ... AND ... 中的 AND 除外），返回 (连接词, 条件)
+fn split_conditions<'n>(nodes: &[&'n Node]) -> Vec<(Option<&'n Atom>, Vec<&'n Node>)> {
+    let mut parts = vec![(None, Vec::new())];
+    let mut in_between = false;
+    for node in nodes {
+        if node.is("BETWEEN") {
+            in_between = true;
+        }
+        let connector = node.atom().filter(|a| a.is("OR") || (a.is("AND") && !in_between));
+        if node.is("AND") {
+            in_between = false;
+        }
+        match connector {
+            Some(atom) => parts.push((Some(atom), Vec::new())),
+            None => parts.last_mut().unwrap_or_else(|| unreachable!()).1.push(*node),
+        }
+    }
+    parts
+}
+
+/// 把语句拆成子句
+fn split_clauses(nodes: &[Node]) -> Vec<Clause<'_>> {
+    let mut clauses = vec![Clause { head: Vec::new(), body: Vec::new(), layout: Layout::Plain }];
+    let mut i = 0;
+    // 上一个有意义的节点（不是注释）
+    let mut prev: Option<&Node> = None;
+    while i < nodes.len() {
+        if let Some((len, layout)) = clause_head(nodes, i, prev) {
+            let head: Vec<&Atom> = nodes[i..i + len].iter().filter_map(Node::atom).collect();
+            clauses.push(Clause { head, body: Vec::new(), layout });
+            prev = nodes.get(i + len - 1);
+            i += len;
+
+            // SELECT DISTINCT / DISTINCT ON (...) / WITH RECURSIVE 放在开头的关键字里
+            let clause = clauses.last_mut().unwrap_or_else(|| unreachable!());
+            let first = clause.head[0].text.to_uppercase();
+            let modifiers: &[&str] = match first.as_str() {
+                "SELECT" => &["DISTINCT", "ALL"],
+                "WITH" => &["RECURSIVE"],
+                "UNION" | "INTERSECT" | "EXCEPT" => &["ALL", "DISTINCT"],
+                _ => &[],
+            };
+            if let Some(atom) = nodes.get(i).and_then(Node::atom).filter(|a| modifiers.iter().any(|m| a.is(m))) {
+                clause.head.push(atom);
+                i += 1;
+                if atom.is("DISTINCT") && nodes.get(i).is_some_and(|n| n.is("ON")) {
+                    clause.body.push(&nodes[i]);
+                    i += 1;
+                }
+            }
+            continue;
+        }
+        clauses.last_mut().unwrap_or_else(|| unreachable!()).body.push(&nodes[i]);
+        if !nodes[i].is_comment() {
+            prev = Some(&nodes[i]);
+        }
+        i += 1;
+    }
+    // 没有内容的开头部分
+    if clauses[0].body.is_empty() && clauses.len() > 1 {
+        clauses.remove(0);
+    }
+    clauses
+}
+
+/// 位置 i 处是否是子句开头，返回 (关键字个数, 排版方式)
+fn clause_head(nodes: &[Node], i: usize, prev: Option<&Node>) -> Option<(usize, Layout)> {
+    let word = |offset: usize| nodes.get(i + offset).and_then(Node::atom).filter(|a| a.kind == AtomKind::Keyword);
+    let is = |offset: usize, keyword: &str| word(offset).is_some_and(|a| a.is(keyword));
+    let first = word(0)?.text.to_uppercase();
+
+    // INSERT、UPDATE 等只在语句开头或 CTE 后面开始子句（排除 FOR UPDATE、DO UPDATE 等）
+    let at_start = prev.map_or(true, |p| matches!(p, Node::Group { .. }));
+    match first.as_str() {
+        "SELECT" | "FROM" | "SET" | "VALUES" | "RETURNING" => Some((1, Layout::List)),
+        "WHERE" | "HAVING" | "JOIN" => Some((1, Layout::Condition)),
+        "LIMIT" | "OFFSET" | "WINDOW" | "UNION" | "INTERSECT" | "EXCEPT" => Some((1, Layout::Plain)),
+        "WITH" => Some((1, Layout::With)),
+        "GROUP" | "ORDER" if is(1, "BY") => Some((2, Layout::List)),
+        "INSERT" | "REPLACE" if at_start => Some((if is(1, "INTO") { 2 } else { 1 }, Layout::Plain)),
+        "DELETE" if at_start => Some((if is(1, "FROM") { 2 } else { 1 }, Layout::Plain)),
+        "UPDATE" if at_start => Some((1, Layout::Plain)),
+        "ON" if is(1, "CONFLICT") => Some((2, Layout::Plain)),
+        "ON" if is(1, "DUPLICATE") && is(2, "KEY") && is(3, "UPDATE") => Some((4, Layout::List)),
+        "LEFT" | "RIGHT" | "FULL" | "INNER" | "CROSS" | "NATURAL" | "OUTER" => {
+            // NATURAL LEFT OUTER JOIN 之类
+            let len = (0..4).take_while(|&k| word(k).is_some_and(|a| {
+                ["LEFT", "RIGHT", "FULL", "INNER", "CROSS", "NATURAL", "OUTER", "JOIN"].iter().any(|m| a.is(m))
+            })).count();
+            (len > 0 && is(len - 1, "JOIN")).then_some((len, Layout::Condition))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,162 @@
+//! SQL 输入高亮
+//!
+//! 高亮完全基于词法分析器的 token：关键字、函数和数据类型取自各方言的
+//! `SqlSyntax` 列表，字符串、数字和注释按 token 类型着色，已知的表名和列名
+//! 由补全引擎判断。光标处的括号和与之配对的括号会一起高亮，找不到配对时标红。
+
+use super::lexer::{Token, TokenKind};
+use super::syntax::{SqlCompletion, SqlSyntax};
+use std::collections::HashSet;
+
+const RESET: &str = "\x1b[0m";
+
+/// 高亮类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightKind {
+    Keyword,
+    Function,
+    DataType,
+    Table,
+    Column,
+    String,
+    Number,
+    Comment,
+    /// 光标处的括号及其配对括号
+    MatchedBracket,
+    /// 找不到配对的括号
+    UnmatchedBracket,
+}
+
+impl HighlightKind {
+    pub fn color(&self) -> &'static str {
+        match self {
+            HighlightKind::Keyword => "\x1b[38;5;226m",          // 鲜艳黄色（与补全一致）
+            HighlightKind::Function => "\x1b[38;5;214m",         // 橙黄色
+            HighlightKind::DataType => "\x1b[38;5;171m",         // 紫色
+            HighlightKind::Table => "\x1b[38;5;40m",             // 绿色
+            HighlightKind::Column => "\x1b[38;5;51m",            // 亮青色
+            HighlightKind::String => "\x1b[38;5;180m",           // 浅棕色
+            HighlightKind::Number => "\x1b[38;5;209m",           // 橙红色
+            HighlightKind::Comment => "\x1b[38;5;240m",          // 灰色
+            HighlightKind::MatchedBracket => "\x1b[1;4;38;5;51m", // 粗体下划线
+            HighlightKind::UnmatchedBracket => "\x1b[1;38;5;196m", // 粗体红色
+        }
+    }
+}
+
+/// 一段高亮区间（字节偏移）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HighlightSpan {
+    pub start: usize,
+    pub end: usize,
+    pub kind: HighlightKind,
+}
+
+/// 方言的关键字、函数和数据类型（大写）
+#[derive(Debug, Clone, Default)]
+pub struct DialectWords {
+    keywords: HashSet<String>,
+    functions: HashSet<String>,
+    data_types: HashSet<String>,
+}
+
+impl DialectWords {
+    /// 从方言的补全列表收集单词：多词关键字拆开，函数去掉括号，类型去掉长度参数
+    pub fn from_syntax(syntax: &dyn SqlSyntax) -> Self {
+        let words = |items: Vec<SqlCompletion>, whole: bool| -> HashSet<String> {
+            items.iter()
+                .flat_map(|c| {
+                    let text = c.text.split('(').next().unwrap_or("");
+                    let words: Vec<&str> = if whole { vec![text.trim()] } else { text.split_whitespace().collect() };
+                    words.into_iter().map(str::to_uppercase).collect::<Vec<_>>()
+                })
+                .filter(|w| !w.is_empty() && w.chars().all(|c| c.is_alphanumeric() || c == '_'))
+                .collect()
+        };
+        DialectWords {
+            keywords: words(syntax.keywords(), false),
+            functions: words(syntax.functions(), true),
+            data_types: words(syntax.data_types(), false),
+        }
+    }
+
+    /// 判断单词的类型，`is_call` 表示后面紧跟左括号
+    pub fn classify(&self, word: &str, is_call: bool) -> Option<HighlightKind> {
+        let upper = word.to_uppercase();
+        if is_call && self.functions.contains(&upper) {
+            Some(HighlightKind::Function)
+        } else if self.keywords.contains(&upper) {
+            Some(HighlightKind::Keyword)
+        } else if self.data_types.contains(&upper) {
+            Some(HighlightKind::DataType)
+        } else if self.functions.contains(&upper) {
+            Some(HighlightKind::Function)
+        } else {
+            None
+        }
+    }
+}
+
+/// 字符串、数字和注释的高亮
+pub fn literal_kind(token: &Token) -> Option<HighlightKind> {
+    match token.kind {
+        TokenKind::String => Some(HighlightKind::String),
+        TokenKind::Number => Some(HighlightKind::Number),
+        TokenKind::Comment => Some(HighlightKind::Comment),
+        _ => None,
+    }
+}
+
+/// 光标处（或光标前）的括号及其配对括号
+///
+/// 只考虑词法分析得到的括号 token，字符串和注释中的括号不参与配对
+pub fn bracket_spans(tokens: &[Token], cursor: usize) -> Vec<HighlightSpan> {
+    let is_paren = |t: &&Token| matches!(t.kind, TokenKind::LParen | TokenKind::RParen);
+    let parens: Vec<&Token> = tokens.iter().filter(is_paren).collect();
+
+    // 优先取光标下的括号，其次是光标前的括号
+    let index = parens.iter().position(|t| t.start == cursor)
+        .or_else(|| parens.iter().position(|t| t.end() == cursor));
+    let index = match index {
+        Some(index) => index,
+        None => return Vec::new(),
+    };
+    let bracket = parens[index];
+    let span = |t: &Token, kind| HighlightSpan { start: t.start, end: t.end(), kind };
+
+    let mut depth = 0i32;
+    let found = if bracket.kind == TokenKind::LParen {
+        parens[index..].iter().find(|t| {
+            depth += if t.kind == TokenKind::LParen { 1 } else { -1 };
+            depth == 0
+        })
+    } else {
+        parens[..=index].iter().rev().find(|t| {
+            depth += if t.kind == TokenKind::RParen { 1 } else { -1 };
+            depth == 0
+        })
+    };
+
+    match found {
+        Some(other) => vec![span(bracket, HighlightKind::MatchedBracket), span(other, HighlightKind::MatchedBracket)],
+        None => vec![span(bracket, HighlightKind::UnmatchedBracket)],
+    }
+}
+
+/// 按高亮区间给文本加上颜色，区间需按起始位置排序且互不重叠
+pub fn render(line: &str, spans: &[HighlightSpan]) -> String {
+    let mut out = String::with_capacity(line.len() * 2);
+    let mut pos = 0;
+    for span in spans {
+        if span.start < pos || span.end > line.len() {
+            continue;
+        }
+        out.push_str(&line[pos..span.start]);
+        out.push_str(span.kind.color());
+        out.push_str(&line[span.start..span.end]);
+        out.push_str(RESET);
+        pos = span.end;
+    }
+    out.push_str(&line[pos..]);
+    out
+}
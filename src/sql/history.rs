@@ -0,0 +1,237 @@
+//! 查询历史
+//!
+//! 每个连接的历史保存在 ~/.local/share/cnmsb/sql_history/ 下的 JSON Lines 文件中，
+//! 记录每条语句的执行时间、耗时和行数。常用的语句和子句还会作为补全候选。
+
+use super::database::DatabaseType;
+use super::lexer::{self, TokenKind};
+use super::schema_cache::file_stem;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 每个连接最多保留的条数
+const MAX_ENTRIES: usize = 1000;
+
+/// 最多提供给补全引擎的片段数
+const MAX_FRAGMENTS: usize = 200;
+
+/// 子句片段的起始关键字
+const CLAUSE_STARTS: &[&str] = &[
+    "FROM", "WHERE", "JOIN", "LEFT", "RIGHT", "INNER", "FULL", "CROSS", "NATURAL", "GROUP", "ORDER", "HAVING",
+];
+
+/// JOIN 前面的修饰词
+const JOIN_MODIFIERS: &[&str] = &["LEFT", "RIGHT", "INNER", "FULL", "CROSS", "NATURAL", "OUTER"];
+
+/// 一条历史记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub sql: String,
+    /// 执行时间（Unix 秒）
+    pub time: u64,
+    /// 耗时，毫秒
+    pub duration_ms: f64,
+    /// 返回或影响的行数
+    pub rows: Option<u64>,
+    /// 是否执行成功
+    pub ok: bool,
+}
+
+impl HistoryEntry {
+    /// 以当前时间创建记录
+    pub fn new(sql: &str, duration_ms: f64, rows: Option<u64>, ok: bool) -> Self {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        HistoryEntry {
+            sql: sql.trim().to_string(),
+            time,
+            duration_ms,
+            rows,
+            ok,
+        }
+    }
+}
+
+/// 单个连接的查询历史
+#[derive(Default)]
+pub struct QueryHistory {
+    /// 历史文件路径，为 None 时只保存在内存中
+    path: Option<PathBuf>,
+    entries: Vec<HistoryEntry>,
+}
+
+impl QueryHistory {
+    /// 打开历史文件，损坏的行会被跳过
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let entries: Vec<HistoryEntry> = path.as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .map(|content| content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+            .unwrap_or_default();
+
+        let mut history = QueryHistory { path, entries };
+        if history.entries.len() > MAX_ENTRIES {
+            history.compact();
+        }
+        history
+    }
+
+    /// 打开某个连接的历史，`key` 为不含密码的连接描述
+    pub fn for_connection(key: &str) -> Self {
+        Self::open(Self::history_path(key))
+    }
+
+    /// 连接对应的历史文件路径
+    pub fn history_path(key: &str) -> Option<PathBuf> {
+        dirs::data_dir().map(|p| p.join("cnmsb").join("sql_history").join(format!("{}.jsonl", file_stem(key))))
+    }
+
+    /// 所有记录，从旧到新
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// 按编号（从 1 开始）获取记录
+    pub fn get(&self, number: usize) -> Option<&HistoryEntry> {
+        number.checked_sub(1).and_then(|i| self.entries.get(i))
+    }
+
+    /// 最后一条记录
+    pub fn last(&self) -> Option<&HistoryEntry> {
+        self.entries.last()
+    }
+
+    /// 历史展开 `!!`、`!N`、`!-N` 指向的编号（`!-1` 与 `!!` 相同）
+    ///
+    /// 其他以 `!` 开头的输入不是历史展开，返回 None；编号超出范围时返回的编号取不到记录。
+    pub fn expansion(&self, line: &str) -> Option<usize> {
+        let arg = line.strip_prefix('!')?;
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        if arg == "!" {
+            Some(self.entries.len())
+        } else if digits(arg) {
+            Some(arg.parse().unwrap_or(0))
+        } else if let Some(back) = arg.strip_prefix('-').filter(|s| digits(s)) {
+            let back: usize = back.parse().unwrap_or(usize::MAX);
+            Some((self.entries.len() + 1).saturating_sub(back))
+        } else {
+            None
+        }
+    }
+
+    /// 记录一条语句并追加到历史文件
+    pub fn record(&mut self, entry: HistoryEntry) {
+        if entry.sql.is_empty() {
+            return;
+        }
+        if let Some(ref path) = self.path {
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            if let (Ok(mut file), Ok(line)) = (OpenOptions::new().create(true).append(true).open(path), serde_json::to_string(&entry)) {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+        self.entries.push(entry);
+        if self.entries.len() > MAX_ENTRIES * 2 {
+            self.compact();
+        }
+    }
+
+    /// 查找包含 pattern 的记录（不区分大小写），返回 (编号, 记录)
+    pub fn search(&self, pattern: Option<&str>) -> Vec<(usize, &HistoryEntry)> {
+        let pattern = pattern.map(|p| p.to_lowercase());
+        self.entries.iter()
+            .enumerate()
+            .filter(|(_, e)| pattern.as_ref().map_or(true, |p| e.sql.to_lowercase().contains(p)))
+            .map(|(i, e)| (i + 1, e))
+            .collect()
+    }
+
+    /// 常用的语句和子句片段，按使用次数从多到少排列
+    pub fn fragments(&self, db_type: DatabaseType) -> Vec<(String, usize)> {
+        // 小写文本 -> (片段, 次数, 最后一次出现的位置)
+        let mut counts: HashMap<String, (String, usize, usize)> = HashMap::new();
+        for (i, entry) in self.entries.iter().enumerate().filter(|(_, e)| e.ok) {
+            for fragment in split_fragments(&entry.sql, db_type) {
+                let slot = counts.entry(fragment.to_lowercase()).or_insert_with(|| (fragment.clone(), 0, i));
+                slot.0 = fragment;
+                slot.1 += 1;
+                slot.2 = i;
+            }
+        }
+
+        let mut fragments: Vec<(String, usize, usize)> = counts.into_values().collect();
+        fragments.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2)));
+        fragments.into_iter()
+            .take(MAX_FRAGMENTS)
+            .map(|(text, count, _)| (text, count))
+            .collect()
+    }
+
+    /// 只保留最近的记录并重写历史文件
+    fn compact(&mut self) {
+        let excess = self.entries.len().saturating_sub(MAX_ENTRIES);
+        self.entries.drain(..excess);
+        if let Some(ref path) = self.path {
+            let content: String = self.entries.iter()
+                .filter_map(|e| serde_json::to_string(e).ok())
+                .map(|line| line + "\n")
+                .collect();
+            let _ = fs::write(path, content);
+        }
+    }
+}
+
+/// 把语句拆成整条语句和各个子句（FROM ...、JOIN ... ON ...、WHERE ... 等），空白统一为一个空格
+pub fn split_fragments(sql: &str, db_type: DatabaseType) -> Vec<String> {
+    let tokens = lexer::tokenize(sql, db_type);
+    let mut fragments = vec![normalize(sql)];
+
+    // 顶层子句的起始位置，LEFT JOIN、NATURAL LEFT OUTER JOIN 等从第一个修饰词开始
+    let mut starts = Vec::new();
+    let mut depth = 0i32;
+    let mut previous = String::new();
+    for token in tokens.iter().filter(|t| t.is_significant()) {
+        let word = if token.kind == TokenKind::Word { token.text.to_uppercase() } else { String::new() };
+        match token.kind {
+            TokenKind::LParen => depth += 1,
+            TokenKind::RParen => depth -= 1,
+            TokenKind::Word
+                if depth == 0 && CLAUSE_STARTS.contains(&word.as_str()) && !JOIN_MODIFIERS.contains(&previous.as_str()) =>
+            {
+                starts.push(token.start);
+            }
+            _ => {}
+        }
+        previous = word;
+    }
+
+    for (i, &start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(sql.len());
+        let fragment = normalize(&sql[start..end]);
+        if !fragment.is_empty() && !fragments.contains(&fragment) {
+            fragments.push(fragment);
+        }
+    }
+    fragments
+}
+
+/// 合并连续的空白，去掉结尾的分号
+fn normalize(sql: &str) -> String {
+    sql.split_whitespace().collect::<Vec<_>>().join(" ").trim_end_matches(';').trim_end().to_string()
+}
+
+/// 把时间显示为“3 分钟前”这样的相对时间
+pub fn format_age(time: u64) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let seconds = now.saturating_sub(time);
+    match seconds {
+        0..=59 => "刚才".to_string(),
+        60..=3599 => format!("{} 分钟前", seconds / 60),
+        3600..=86399 => format!("{} 小时前", seconds / 3600),
+        _ => format!("{} 天前", seconds / 86400),
+    }
+}
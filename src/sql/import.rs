@@ -0,0 +1,427 @@
+//! 从 CSV / JSON / JSON Lines 文件导入数据
+//!
+//! `.import [选项] <文件> <表>` 读取文件，根据前若干行推断列类型，表不存在时
+//! 自动建表，然后在一个事务中用预编译语句批量插入。
+
+use super::connection::{DbConnection, DbError};
+use super::database::DatabaseType;
+use serde_json::Value;
+use std::fs;
+
+/// 推断类型时采样的行数
+const SAMPLE_ROWS: usize = 1000;
+
+/// 文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Csv,
+    /// 对象组成的 JSON 数组
+    Json,
+    /// 每行一个 JSON 对象
+    JsonLines,
+}
+
+/// 导入选项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportOptions {
+    /// 为 None 时按扩展名判断
+    pub format: Option<ImportFormat>,
+    /// CSV 分隔符
+    pub delimiter: char,
+    /// CSV 引号
+    pub quote: char,
+    /// CSV 第一行是否是列名
+    pub header: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            format: None,
+            delimiter: ',',
+            quote: '"',
+            header: true,
+        }
+    }
+}
+
+/// 解析后的 `.import` 命令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportCommand {
+    pub file: String,
+    pub table: String,
+    pub options: ImportOptions,
+}
+
+impl ImportCommand {
+    /// 解析 `.import` 后面的参数
+    ///
+    /// 支持 `--csv`、`--json`、`--jsonl`、`--delimiter C`、`--quote C`、`--header`、`--no-header`
+    pub fn parse(args: &str) -> Result<Self, String> {
+        let mut options = ImportOptions::default();
+        let mut positional = Vec::new();
+        let mut delimiter_set = false;
+
+        let mut words = args.split_whitespace();
+        while let Some(word) = words.next() {
+            match word {
+                "--csv" => options.format = Some(ImportFormat::Csv),
+                "--json" => options.format = Some(ImportFormat::Json),
+                "--jsonl" | "--ndjson" => options.format = Some(ImportFormat::JsonLines),
+                "--header" => options.header = true,
+                "--no-header" => options.header = false,
+                "--delimiter" | "-d" => {
+                    let value = words.next().ok_or("--delimiter 需要一个参数")?;
+                    options.delimiter = parse_char(value)?;
+                    delimiter_set = true;
+                }
+                "--quote" | "-q" => {
+                    let value = words.next().ok_or("--quote 需要一个参数")?;
+                    options.quote = parse_char(value)?;
+                }
+                _ if word.starts_with("--") => return Err(format!("未知选项: {}", word)),
+                _ => positional.push(word.to_string()),
+            }
+        }
+
+        if positional.len() != 2 {
+            return Err("用法: .import [--csv|--json|--jsonl] [--delimiter C] [--quote C] [--no-header] <文件> <表>".to_string());
+        }
+        let table = positional.pop().unwrap();
+        let file = positional.pop().unwrap();
+
+        // .tsv 文件默认用制表符分隔
+        if !delimiter_set && file.to_lowercase().ends_with(".tsv") {
+            options.delimiter = '\t';
+        }
+
+        Ok(ImportCommand { file, table, options })
+    }
+
+    /// 实际使用的文件格式
+    pub fn format(&self) -> ImportFormat {
+        self.options.format.unwrap_or_else(|| {
+            let lower = self.file.to_lowercase();
+            if lower.ends_with(".jsonl") || lower.ends_with(".ndjson") {
+                ImportFormat::JsonLines
+            } else if lower.ends_with(".json") {
+                ImportFormat::Json
+            } else {
+                ImportFormat::Csv
+            }
+        })
+    }
+}
+
+/// 解析单个字符参数，支持 \t、tab 等写法
+fn parse_char(value: &str) -> Result<char, String> {
+    match value {
+        "\\t" | "tab" | "TAB" => Ok('\t'),
+        "space" => Ok(' '),
+        _ => {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(format!("需要单个字符: {}", value)),
+            }
+        }
+    }
+}
+
+/// 读取到的数据，None 表示 NULL
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportData {
+    pub columns: Vec<String>,
+    /// 列名是否来自文件（没有表头的 CSV 使用 col1、col2…）
+    pub named: bool,
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+/// 解析 CSV
+///
+/// 引号内可以包含分隔符和换行，两个连续引号表示一个引号。未加引号的空字段为 NULL，
+/// `""` 为空字符串。
+pub fn parse_csv(content: &str, options: &ImportOptions) -> Result<ImportData, String> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let mut records = split_csv_records(content, options.delimiter, options.quote)?;
+
+    let columns: Vec<String> = if options.header && !records.is_empty() {
+        records.remove(0).into_iter()
+            .enumerate()
+            .map(|(i, name)| name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty())
+                .unwrap_or_else(|| format!("col{}", i + 1)))
+            .collect()
+    } else {
+        let width = records.iter().map(|r| r.len()).max().unwrap_or(0);
+        (1..=width).map(|i| format!("col{}", i)).collect()
+    };
+
+    let first_data_line = if options.header { 2 } else { 1 };
+    for (i, record) in records.iter_mut().enumerate() {
+        if record.len() > columns.len() {
+            return Err(format!("第 {} 条记录有 {} 个字段，应为 {} 个", i + first_data_line, record.len(), columns.len()));
+        }
+        record.resize(columns.len(), None);
+    }
+
+    Ok(ImportData { columns, named: options.header, rows: records })
+}
+
+/// 按 CSV 规则切分记录，跳过空行
+fn split_csv_records(content: &str, delimiter: char, quote: char) -> Result<Vec<Vec<Option<String>>>, String> {
+    let mut records = Vec::new();
+    let mut record: Vec<Option<String>> = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut line = 1;
+
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == quote {
+                if chars.peek() == Some(&quote) {
+                    chars.next();
+                    field.push(quote);
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+            continue;
+        }
+
+        if c == quote && field.is_empty() && !quoted {
+            in_quotes = true;
+            quoted = true;
+        } else if c == delimiter {
+            record.push(finish_field(&mut field, &mut quoted));
+        } else if c == '\r' && chars.peek() == Some(&'\n') {
+            // \r\n 换行，交给下一个字符处理
+        } else if c == '\n' {
+            record.push(finish_field(&mut field, &mut quoted));
+            if !(record.len() == 1 && record[0].is_none()) {
+                records.push(std::mem::take(&mut record));
+            }
+            record.clear();
+            line += 1;
+        } else {
+            field.push(c);
+        }
+    }
+
+    if in_quotes {
+        return Err(format!("第 {} 行的引号没有闭合", line));
+    }
+    if !field.is_empty() || quoted || !record.is_empty() {
+        record.push(finish_field(&mut field, &mut quoted));
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn finish_field(field: &mut String, quoted: &mut bool) -> Option<String> {
+    let value = std::mem::take(field);
+    let was_quoted = std::mem::replace(quoted, false);
+    if value.is_empty() && !was_quoted {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// 解析 `.json` 文件
+///
+/// 顶层是数组时每个元素为一行；否则按 JSON Lines 解析（不少 `.json` 文件实际是每行一个对象）。
+pub fn parse_json(content: &str) -> Result<ImportData, String> {
+    if !content.trim_start().starts_with('[') {
+        return parse_json_lines(content);
+    }
+    let values: Vec<Value> = serde_json::from_str(content)
+        .map_err(|e| format!("不是有效的 JSON 数组: {}", e))?;
+    let objects = values.into_iter()
+        .enumerate()
+        .map(|(i, value)| match value {
+            Value::Object(map) => Ok(map),
+            _ => Err(format!("第 {} 个元素不是 JSON 对象", i + 1)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(objects_to_data(objects))
+}
+
+/// 解析 JSON Lines
+pub fn parse_json_lines(content: &str) -> Result<ImportData, String> {
+    let mut objects = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let value: Value = serde_json::from_str(line)
+            .map_err(|e| format!("第 {} 行不是有效的 JSON: {}", i + 1, e))?;
+        match value {
+            Value::Object(map) => objects.push(map),
+            _ => return Err(format!("第 {} 行不是 JSON 对象", i + 1)),
+        }
+    }
+    Ok(objects_to_data(objects))
+}
+
+/// 列为所有对象键的并集，按首次出现的顺序排列。嵌套的对象和数组按 JSON 文本保存。
+fn objects_to_data(objects: Vec<serde_json::Map<String, Value>>) -> ImportData {
+    let mut columns: Vec<String> = Vec::new();
+    for object in &objects {
+        for key in object.keys() {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+
+    let rows = objects.iter()
+        .map(|object| {
+            columns.iter()
+                .map(|col| match object.get(col) {
+                    None | Some(Value::Null) => None,
+                    Some(Value::String(s)) => Some(s.clone()),
+                    Some(other) => Some(other.to_string()),
+                })
+                .collect()
+        })
+        .collect();
+
+    ImportData { columns, named: true, rows }
+}
+
+/// 推断出的列类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Integer,
+    Real,
+    Text,
+}
+
+impl ColumnType {
+    /// 建表时使用的类型名
+    pub fn sql_name(&self, db_type: DatabaseType) -> &'static str {
+        match (self, db_type) {
+            (ColumnType::Integer, DatabaseType::SQLite) => "INTEGER",
+            (ColumnType::Integer, _) => "BIGINT",
+            (ColumnType::Real, DatabaseType::SQLite) => "REAL",
+            (ColumnType::Real, DatabaseType::PostgreSQL) => "DOUBLE PRECISION",
+            (ColumnType::Real, _) => "DOUBLE",
+            (ColumnType::Text, _) => "TEXT",
+        }
+    }
+
+    /// 能容纳该值的最窄类型
+    fn of(value: &str) -> ColumnType {
+        let digits = value.strip_prefix('-').unwrap_or(value);
+        // 前导零（如邮编 007）按文本保存，避免丢失
+        let leading_zero = digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.");
+        if leading_zero || digits.is_empty() {
+            return ColumnType::Text;
+        }
+        if digits.bytes().all(|b| b.is_ascii_digit()) && value.parse::<i64>().is_ok() {
+            return ColumnType::Integer;
+        }
+        let numeric_chars = digits.bytes().all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'+' | b'-'));
+        if numeric_chars && value.parse::<f64>().is_ok_and(|f| f.is_finite()) {
+            return ColumnType::Real;
+        }
+        ColumnType::Text
+    }
+
+    fn widen(self, other: ColumnType) -> ColumnType {
+        match (self, other) {
+            (ColumnType::Text, _) | (_, ColumnType::Text) => ColumnType::Text,
+            (ColumnType::Real, _) | (_, ColumnType::Real) => ColumnType::Real,
+            _ => ColumnType::Integer,
+        }
+    }
+}
+
+/// 根据前若干行推断每列的类型，全为 NULL 的列为 TEXT
+pub fn infer_types(data: &ImportData) -> Vec<ColumnType> {
+    (0..data.columns.len())
+        .map(|i| {
+            data.rows.iter()
+                .take(SAMPLE_ROWS)
+                .filter_map(|row| row.get(i).and_then(|v| v.as_deref()))
+                .map(ColumnType::of)
+                .reduce(ColumnType::widen)
+                .unwrap_or(ColumnType::Text)
+        })
+        .collect()
+}
+
+/// 生成建表语句
+pub fn create_table_sql(table: &str, columns: &[String], types: &[ColumnType], db_type: DatabaseType) -> String {
+    let defs: Vec<String> = columns.iter()
+        .zip(types)
+        .map(|(col, ty)| format!("{} {}", db_type.quote_ident(col), ty.sql_name(db_type)))
+        .collect();
+    format!("CREATE TABLE {} ({})", db_type.quote_ident(table), defs.join(", "))
+}
+
+/// 导入结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub rows: usize,
+    /// 是否新建了表
+    pub created: bool,
+}
+
+/// 读取文件
+pub fn read_file(command: &ImportCommand) -> Result<ImportData, String> {
+    let content = fs::read_to_string(&command.file)
+        .map_err(|e| format!("无法读取 {}: {}", command.file, e))?;
+    match command.format() {
+        ImportFormat::Csv => parse_csv(&content, &command.options),
+        ImportFormat::Json => parse_json(&content),
+        ImportFormat::JsonLines => parse_json_lines(&content),
+    }
+}
+
+/// 把数据导入到表中，表不存在时先建表
+///
+/// 表已存在且 CSV 没有表头时，按表的列顺序插入。
+pub fn import(conn: &mut DbConnection, table: &str, mut data: ImportData) -> Result<ImportSummary, String> {
+    let db_type = conn.db_type().ok_or_else(|| DbError::NotConnected.to_string())?;
+    // 导入自己开启事务，不能嵌套在用户的事务中
+    if conn.in_transaction() == Some(true) {
+        return Err("当前在事务中，请先 COMMIT 或 ROLLBACK".to_string());
+    }
+    if data.columns.is_empty() {
+        return Err("文件中没有数据".to_string());
+    }
+
+    let existing = conn.get_columns(table).map_err(|e| e.to_string())?;
+    let created = existing.is_empty();
+    if created {
+        let types = infer_types(&data);
+        conn.execute(&create_table_sql(table, &data.columns, &types, db_type))
+            .map_err(|e| e.to_string())?;
+    } else if !data.named {
+        // 没有表头，按已有表的列顺序对应
+        if data.columns.len() > existing.len() {
+            return Err(format!("文件有 {} 列，但表 {} 只有 {} 列", data.columns.len(), table, existing.len()));
+        }
+        data.columns = existing.into_iter().take(data.columns.len()).map(|c| c.name).collect();
+    }
+
+    match conn.insert_rows(table, &data.columns, &data.rows) {
+        Ok(rows) => Ok(ImportSummary { rows, created }),
+        Err(e) => {
+            // 插入已回滚，MySQL 的 DDL 不能回滚，这里把新建的表删掉
+            if created {
+                let _ = conn.execute(&format!("DROP TABLE {}", db_type.quote_ident(table)));
+            }
+            Err(e.to_string())
+        }
+    }
+}
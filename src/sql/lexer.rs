@@ -0,0 +1,410 @@
+//! SQL 词法分析器
+//!
+//! 将 SQL 文本切分为带位置的 token，正确处理注释、字符串、带引号的标识符
+//! 和 PostgreSQL 的美元引用（$$ ... $$），供语句分类、拆分、上下文检测和
+//! 安全检查使用，避免简单的 `starts_with` 误判。
+
+use super::database::DatabaseType;
+
+/// Token 类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// 关键字或普通标识符
+    Word,
+    /// 带引号的标识符（"name"、`name`、[name]）
+    QuotedIdent,
+    /// 字符串字面量（包括 PostgreSQL 的 $tag$ ... $tag$）
+    String,
+    /// 数字字面量
+    Number,
+    /// 注释（-- ...、/* ... */、MySQL 的 # ...）
+    Comment,
+    /// 空白字符
+    Whitespace,
+    /// 左括号
+    LParen,
+    /// 右括号
+    RParen,
+    /// 逗号
+    Comma,
+    /// 分号
+    Semicolon,
+    /// 点号
+    Dot,
+    /// 操作符及其他符号
+    Operator,
+}
+
+/// SQL Token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    /// 在原始文本中的字节偏移
+    pub start: usize,
+    /// 字符串、引用标识符或块注释是否已闭合
+    pub terminated: bool,
+}
+
+impl<'a> Token<'a> {
+    /// token 结束位置（字节偏移）
+    pub fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    /// 是否是指定关键字（不区分大小写）
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(keyword)
+    }
+
+    /// 是否是有意义的 token（非空白、非注释）
+    pub fn is_significant(&self) -> bool {
+        !matches!(self.kind, TokenKind::Whitespace | TokenKind::Comment)
+    }
+}
+
+/// 对 SQL 进行词法分析
+pub fn tokenize(sql: &str, db_type: DatabaseType) -> Vec<Token<'_>> {
+    Lexer::new(sql, db_type).collect()
+}
+
+/// 只保留有意义的 token（去掉空白和注释）
+pub fn significant_tokens(sql: &str, db_type: DatabaseType) -> Vec<Token<'_>> {
+    Lexer::new(sql, db_type).filter(|t| t.is_significant()).collect()
+}
+
+/// 按顶层分号拆分多条语句，返回去掉首尾空白和分号的语句文本
+///
+/// 字符串、注释、美元引用中的分号不会拆分；SQLite/MySQL 触发器中
+/// BEGIN ... END、IF ... END IF、CASE ... END 等块内的分号也不会拆分。
+pub fn split_statements(sql: &str, db_type: DatabaseType) -> Vec<&str> {
+    let tokens = significant_tokens(sql, db_type);
+    let mut statements = Vec::new();
+    let mut stmt_start = 0;
+    // 当前语句第一个 token 的下标
+    let mut first = 0;
+    let mut in_trigger = false;
+    let mut block_depth = 0usize;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::Semicolon if block_depth == 0 => {
+                push_statement(&sql[stmt_start..token.start], db_type, &mut statements);
+                stmt_start = token.end();
+                first = i + 1;
+                in_trigger = false;
+            }
+            TokenKind::Word if !in_trigger => {
+                // 触发器名前可能有 DEFINER 等子句，在第一个括号或 BEGIN 之前找 TRIGGER
+                let header = &tokens[first..i];
+                in_trigger = token.is_keyword("TRIGGER")
+                    && header.first().is_some_and(|t| t.is_keyword("CREATE"))
+                    && !header.iter().any(|t| t.kind == TokenKind::LParen || t.is_keyword("BEGIN"));
+            }
+            TokenKind::Word => {
+                let prev = &tokens[i - 1];
+                if token.is_keyword("END") {
+                    // END IF、END LOOP 等和 END 一起关闭一个块
+                    block_depth = block_depth.saturating_sub(1);
+                } else if token.is_keyword("BEGIN") || (token.is_keyword("CASE") && !prev.is_keyword("END")) {
+                    block_depth += 1;
+                } else if ["IF", "LOOP", "WHILE", "REPEAT"].iter().any(|kw| token.is_keyword(kw))
+                    && starts_statement(prev)
+                    && tokens.get(i + 1).map_or(true, |t| t.kind != TokenKind::LParen)
+                {
+                    // 只有在语句开头才是流程控制，IF(...)、REPEAT(...) 是函数
+                    block_depth += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    push_statement(&sql[stmt_start..], db_type, &mut statements);
+
+    statements
+}
+
+/// 触发器体中，这个 token 之后是否开始一条新语句
+fn starts_statement(prev: &Token<'_>) -> bool {
+    match prev.kind {
+        TokenKind::Semicolon => true,
+        // 带标签的循环：label: LOOP
+        TokenKind::Operator => prev.text == ":",
+        TokenKind::Word => ["BEGIN", "THEN", "ELSE", "DO", "LOOP", "REPEAT", "ROW"].iter().any(|kw| prev.is_keyword(kw)),
+        _ => false,
+    }
+}
+
+/// 保存非空语句（只有注释的片段会被丢弃）
+fn push_statement<'a>(text: &'a str, db_type: DatabaseType, statements: &mut Vec<&'a str>) {
+    let text = text.trim();
+    if Lexer::new(text, db_type).any(|t| t.is_significant()) {
+        statements.push(text);
+    }
+}
+
+/// 语句的第一个关键字（大写），跳过前导注释
+pub fn first_keyword(sql: &str, db_type: DatabaseType) -> Option<String> {
+    Lexer::new(sql, db_type)
+        .find(|t| t.is_significant())
+        .filter(|t| t.kind == TokenKind::Word)
+        .map(|t| t.text.to_uppercase())
+}
+
+/// 判断单条语句是否会返回结果集
+pub fn returns_rows(sql: &str, db_type: DatabaseType) -> bool {
+    let tokens = significant_tokens(sql, db_type);
+    let first = match tokens.first() {
+        Some(t) if t.kind == TokenKind::Word => t.text.to_uppercase(),
+        // 以括号开头的通常是 (SELECT ...) UNION ...
+        Some(t) => return t.kind == TokenKind::LParen,
+        None => return false,
+    };
+
+    match first.as_str() {
+        "SELECT" | "VALUES" | "TABLE" | "SHOW" | "DESCRIBE" | "DESC" | "EXPLAIN" | "PRAGMA" => true,
+        // MySQL 的 ANALYZE TABLE 和 MariaDB 的 ANALYZE SELECT 都返回结果集
+        "ANALYZE" => matches!(db_type, DatabaseType::MySQL | DatabaseType::MariaDB),
+        // WITH ... SELECT / INSERT ... RETURNING 等
+        "WITH" | "INSERT" | "UPDATE" | "DELETE" | "REPLACE" => {
+            let mut depth = 0i32;
+            let mut main_seen = first != "WITH";
+            for t in &tokens[1..] {
+                match t.kind {
+                    TokenKind::LParen => depth += 1,
+                    TokenKind::RParen => depth -= 1,
+                    TokenKind::Word if depth == 0 => {
+                        if t.is_keyword("RETURNING") {
+                            return true;
+                        }
+                        if !main_seen {
+                            if t.is_keyword("SELECT") || t.is_keyword("VALUES") {
+                                return true;
+                            }
+                            main_seen = ["INSERT", "UPDATE", "DELETE", "REPLACE"].iter().any(|k| t.is_keyword(k));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            false
+        }
+        _ => false,
+    }
+}
+
+/// SQL 词法分析器
+pub struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+    db_type: DatabaseType,
+    terminated: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(src: &'a str, db_type: DatabaseType) -> Self {
+        Lexer { src, pos: 0, db_type, terminated: true }
+    }
+
+    fn is_mysql(&self) -> bool {
+        matches!(self.db_type, DatabaseType::MySQL | DatabaseType::MariaDB)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.src[self.pos..].chars().nth(offset)
+    }
+
+    /// 前进直到满足条件的字符（不包含该字符）
+    fn advance_while<F: Fn(char) -> bool>(&mut self, f: F) {
+        while let Some(c) = self.peek() {
+            if !f(c) {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    /// 读取到行尾（不包含换行符）
+    fn line_comment(&mut self) {
+        self.advance_while(|c| c != '\n');
+    }
+
+    /// 读取块注释，未闭合时读到结尾
+    fn block_comment(&mut self) {
+        self.pos += 2;
+        match self.src[self.pos..].find("*/") {
+            Some(end) => self.pos += end + 2,
+            None => self.unterminated(),
+        }
+    }
+
+    /// 尝试读取美元引用 $tag$ ... $tag$，不是美元引用时返回 false
+    fn dollar_quoted(&mut self) -> bool {
+        let rest = &self.src[self.pos + 1..];
+        let tag_len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let tag = &rest[..tag_len];
+        // $1 之类的是参数占位符，不是美元引用
+        if !rest[tag_len..].starts_with('$') || tag.starts_with(|c: char| c.is_ascii_digit()) {
+            return false;
+        }
+        let delimiter = &self.src[self.pos..self.pos + tag_len + 2];
+        let body_start = self.pos + delimiter.len();
+        match self.src[body_start..].find(delimiter) {
+            Some(end) => self.pos = body_start + end + delimiter.len(),
+            None => self.unterminated(),
+        }
+        true
+    }
+
+    /// 读取以 quote 结尾的引用内容，连续两个 quote 视为转义
+    fn quoted(&mut self, quote: char, backslash_escape: bool) {
+        self.pos += quote.len_utf8();
+        while let Some(c) = self.peek() {
+            self.pos += c.len_utf8();
+            if backslash_escape && c == '\\' {
+                if let Some(next) = self.peek() {
+                    self.pos += next.len_utf8();
+                }
+            } else if c == quote {
+                if self.peek() == Some(quote) {
+                    self.pos += quote.len_utf8();
+                } else {
+                    return;
+                }
+            }
+        }
+        self.terminated = false;
+    }
+
+    /// 引用内容没有闭合，读到结尾
+    fn unterminated(&mut self) {
+        self.pos = self.src.len();
+        self.terminated = false;
+    }
+
+    fn number(&mut self) {
+        self.advance_while(|c| c.is_ascii_digit());
+        if self.peek() == Some('.') && self.peek_at(1).is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+            self.advance_while(|c| c.is_ascii_digit());
+        }
+        if matches!(self.peek(), Some('e') | Some('E')) {
+            let sign = matches!(self.peek_at(1), Some('+') | Some('-'));
+            let digit_at = if sign { 2 } else { 1 };
+            if self.peek_at(digit_at).is_some_and(|c| c.is_ascii_digit()) {
+                self.pos += digit_at;
+                self.advance_while(|c| c.is_ascii_digit());
+            }
+        }
+    }
+}
+
+fn is_word_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '@'
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$' || c == '@'
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let start = self.pos;
+        let c = self.peek()?;
+        self.terminated = true;
+        let next = self.peek_at(1);
+
+        let kind = match c {
+            c if c.is_whitespace() => {
+                self.advance_while(char::is_whitespace);
+                TokenKind::Whitespace
+            }
+            '-' if next == Some('-') => {
+                self.line_comment();
+                TokenKind::Comment
+            }
+            '#' if self.is_mysql() => {
+                self.line_comment();
+                TokenKind::Comment
+            }
+            '/' if next == Some('*') => {
+                self.block_comment();
+                TokenKind::Comment
+            }
+            '\'' => {
+                self.quoted('\'', self.is_mysql());
+                TokenKind::String
+            }
+            'E' | 'e' if next == Some('\'') && self.db_type == DatabaseType::PostgreSQL => {
+                self.pos += 1;
+                self.quoted('\'', true);
+                TokenKind::String
+            }
+            '$' if self.db_type == DatabaseType::PostgreSQL && self.dollar_quoted() => {
+                TokenKind::String
+            }
+            '"' if self.is_mysql() => {
+                self.quoted('"', true);
+                TokenKind::String
+            }
+            '"' => {
+                self.quoted('"', false);
+                TokenKind::QuotedIdent
+            }
+            '`' => {
+                self.quoted('`', false);
+                TokenKind::QuotedIdent
+            }
+            '[' if matches!(self.db_type, DatabaseType::SQLite | DatabaseType::SQLServer) => {
+                self.pos += 1;
+                match self.src[self.pos..].find(']') {
+                    Some(end) => self.pos += end + 1,
+                    None => self.unterminated(),
+                }
+                TokenKind::QuotedIdent
+            }
+            c if c.is_ascii_digit() => {
+                self.number();
+                TokenKind::Number
+            }
+            '.' if next.is_some_and(|c| c.is_ascii_digit()) => {
+                self.pos += 1;
+                self.number();
+                TokenKind::Number
+            }
+            c if is_word_start(c) => {
+                self.advance_while(is_word_char);
+                TokenKind::Word
+            }
+            '(' | ')' | ',' | ';' | '.' => {
+                self.pos += 1;
+                match c {
+                    '(' => TokenKind::LParen,
+                    ')' => TokenKind::RParen,
+                    ',' => TokenKind::Comma,
+                    ';' => TokenKind::Semicolon,
+                    _ => TokenKind::Dot,
+                }
+            }
+            _ => {
+                self.pos += c.len_utf8();
+                TokenKind::Operator
+            }
+        };
+
+        Some(Token {
+            kind,
+            text: &self.src[start..self.pos],
+            start,
+            terminated: self.terminated,
+        })
+    }
+}
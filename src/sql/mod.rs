@@ -3,12 +3,32 @@
 
 mod connection;
 mod database;
+pub mod diff;
+pub mod dump;
 mod engine;
+pub mod highlight;
+pub mod history;
+pub mod explain;
+pub mod format;
+pub mod import;
+pub mod lexer;
+pub mod parse_state;
+pub mod profile;
+pub mod safety;
+mod schema_cache;
 mod shell;
 mod syntax;
 
-pub use connection::{DbConnection, DbError, QueryResult, ColumnInfo};
-pub use database::{DatabaseType, DatabaseConfig};
-pub use engine::SqlEngine;
+pub use connection::{
+    Catalog, ColumnInfo, DbConnection, DbError, ForeignKey, IndexInfo, QueryResult, SchemaObject, SchemaObjectKind, SqlValue,
+    TableInfo,
+};
+pub use database::{DatabaseType, DatabaseConfig, SslMode};
+pub use engine::{ColumnLoader, SqlEngine, TableInfoLoader};
+pub use history::{HistoryEntry, QueryHistory};
+pub use lexer::{tokenize, Token, TokenKind};
+pub use profile::ConnectionProfile;
+pub use safety::{Danger, SafeMode, TransactionState};
+pub use schema_cache::SchemaCache;
 pub use shell::SqlShell;
 
@@ -0,0 +1,658 @@
+//! SQL 解析状态机
+//!
+//! 基于词法分析结果做轻量级的状态跟踪：括号深度、子查询作用域、CTE 名称、
+//! 表别名以及光标所在的子句。不做完整的语法分析，只为补全提供足够的上下文。
+
+use super::database::DatabaseType;
+use super::lexer::{Lexer, Token, TokenKind};
+
+/// 光标所在的子句
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clause {
+    /// 语句开头
+    None,
+    With,
+    Select,
+    From,
+    Join,
+    On,
+    Where,
+    GroupBy,
+    Having,
+    OrderBy,
+    Limit,
+    Insert,
+    Into,
+    /// INSERT INTO t (...) 的列列表
+    ColumnList,
+    Values,
+    Update,
+    Set,
+    Delete,
+    Returning,
+    Create,
+    Alter,
+    Drop,
+    Other,
+}
+
+impl Clause {
+    /// 该子句中是否应该补全列名
+    pub fn expects_column(&self) -> bool {
+        matches!(
+            self,
+            Clause::Select
+                | Clause::On
+                | Clause::Where
+                | Clause::GroupBy
+                | Clause::Having
+                | Clause::OrderBy
+                | Clause::Set
+                | Clause::Returning
+                | Clause::ColumnList
+        )
+    }
+}
+
+/// 数据来源类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    /// 数据库中的表
+    Table,
+    /// WITH 定义的 CTE
+    Cte,
+    /// FROM (SELECT ...) 子查询
+    Subquery,
+}
+
+/// FROM/JOIN 等子句中引用的数据来源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRef {
+    /// 表名（可能带 schema 前缀）或 CTE 名，子查询为空
+    pub name: String,
+    pub alias: Option<String>,
+    pub kind: SourceKind,
+    /// CTE 和子查询推导出的输出列
+    pub columns: Vec<String>,
+}
+
+impl TableRef {
+    /// 在 SQL 中引用它时使用的名字（有别名用别名）
+    pub fn visible_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// WITH 定义的公用表表达式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cte {
+    pub name: String,
+    pub columns: Vec<String>,
+}
+
+/// 光标处的解析状态
+#[derive(Debug, Clone)]
+pub struct ParseState {
+    /// 光标所在作用域的子句
+    pub clause: Clause,
+    /// 光标处的括号深度
+    pub depth: usize,
+    /// 光标处是否应该输入表名
+    pub expects_table: bool,
+    /// 光标前最后一个关键字/标识符（大写）
+    pub prev_word: Option<String>,
+    /// 光标在字符串或注释中
+    pub in_literal: bool,
+    /// 光标前当前语句还没有内容
+    pub statement_start: bool,
+    /// 光标前有已结束的语句
+    pub after_semicolon: bool,
+    /// 光标处可见的数据来源，内层作用域在前
+    pub tables: Vec<TableRef>,
+    /// 光标处可见的 CTE
+    pub ctes: Vec<Cte>,
+}
+
+impl ParseState {
+    /// 按名称或别名查找数据来源（不区分大小写）
+    pub fn resolve(&self, name: &str) -> Option<&TableRef> {
+        self.tables
+            .iter()
+            .find(|t| t.visible_name().eq_ignore_ascii_case(name))
+            .or_else(|| self.tables.iter().find(|t| t.name.eq_ignore_ascii_case(name)))
+            // FROM schema.table 之后可以只用 table 引用
+            .or_else(|| self.tables.iter().find(|t| {
+                t.alias.is_none() && t.name.rsplit('.').next().is_some_and(|n| n.eq_ignore_ascii_case(name))
+            }))
+    }
+
+    /// 按名称查找 CTE
+    pub fn cte(&self, name: &str) -> Option<&Cte> {
+        self.ctes.iter().find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// 作用域类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ScopeKind {
+    Statement,
+    /// 子查询，from_source 表示位于 FROM/JOIN 中
+    Subquery { from_source: bool },
+    /// CTE 定义体
+    CteBody(String, Vec<String>),
+    /// CTE 的显式列列表 name(a, b)
+    CteColumns,
+    /// 普通括号（函数参数、IN 列表等）
+    Expr,
+}
+
+/// 表名解析进度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TableStep {
+    Idle,
+    /// 等待表名
+    Expect,
+    /// 刚读到表名，可能跟 . 或别名
+    AfterName,
+    /// 读到 schema.，等待后半部分
+    AfterDot,
+    /// 读到 AS，等待别名
+    AfterAs,
+}
+
+/// CTE 定义进度
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CteStep {
+    Name,
+    AfterName(String, Vec<String>),
+    AfterAs(String, Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+struct Scope {
+    parent: Option<usize>,
+    kind: ScopeKind,
+    clause: Clause,
+    table_step: TableStep,
+    cte_step: CteStep,
+    tables: Vec<TableRef>,
+    ctes: Vec<Cte>,
+    /// SELECT 列表推导出的输出列名
+    select_items: Vec<String>,
+    /// 当前 SELECT 项在本层的 token
+    item: Vec<(TokenKind, String)>,
+    /// UNION 之后不再收集输出列
+    select_done: bool,
+}
+
+impl Scope {
+    fn new(parent: Option<usize>, kind: ScopeKind, clause: Clause) -> Self {
+        Scope {
+            parent,
+            kind,
+            clause,
+            table_step: TableStep::Idle,
+            cte_step: CteStep::Name,
+            tables: Vec::new(),
+            ctes: Vec::new(),
+            select_items: Vec::new(),
+            item: Vec::new(),
+            select_done: false,
+        }
+    }
+
+    fn collects_tables(&self) -> bool {
+        !matches!(self.kind, ScopeKind::Expr | ScopeKind::CteColumns)
+    }
+
+    /// 结束当前 SELECT 项，推导其输出列名
+    fn finish_item(&mut self) {
+        let item = std::mem::take(&mut self.item);
+        if self.select_done {
+            return;
+        }
+        let items: Vec<&(TokenKind, String)> = item
+            .iter()
+            .skip_while(|(k, t)| *k == TokenKind::Word && (t.eq_ignore_ascii_case("DISTINCT") || t.eq_ignore_ascii_case("ALL")))
+            .collect();
+        let (last_kind, last) = match items.last() {
+            Some(t) => t,
+            None => return,
+        };
+        if !matches!(last_kind, TokenKind::Word | TokenKind::QuotedIdent)
+            || (*last_kind == TokenKind::Word && is_reserved(last))
+        {
+            return;
+        }
+        let named = match items.len() {
+            1 => true,
+            n => {
+                let (prev_kind, _) = items[n - 2];
+                matches!(
+                    prev_kind,
+                    TokenKind::Dot | TokenKind::Word | TokenKind::QuotedIdent | TokenKind::RParen | TokenKind::Number | TokenKind::String
+                )
+            }
+        };
+        if named {
+            self.select_items.push(unquote(last));
+        }
+    }
+}
+
+/// 不能作为别名的关键字
+const RESERVED: &[&str] = &[
+    "SELECT", "FROM", "WHERE", "JOIN", "INNER", "LEFT", "RIGHT", "FULL", "OUTER", "CROSS", "NATURAL",
+    "ON", "USING", "GROUP", "ORDER", "HAVING", "LIMIT", "OFFSET", "UNION", "INTERSECT", "EXCEPT",
+    "SET", "VALUES", "RETURNING", "WINDOW", "FETCH", "FOR", "AS", "LATERAL", "WITH", "INTO", "AND",
+    "OR", "NOT", "WHEN", "THEN", "ELSE", "END", "DEFAULT",
+];
+
+fn is_reserved(word: &str) -> bool {
+    RESERVED.iter().any(|k| k.eq_ignore_ascii_case(word))
+}
+
+/// 去掉标识符两边的引号
+pub(super) fn unquote(text: &str) -> String {
+    let bytes = text.as_bytes();
+    if bytes.len() >= 2 {
+        let (first, last) = (bytes[0], bytes[bytes.len() - 1]);
+        if matches!((first, last), (b'"', b'"') | (b'`', b'`') | (b'[', b']')) {
+            return text[1..text.len() - 1].to_string();
+        }
+    }
+    text.to_string()
+}
+
+/// 分析 SQL，返回光标处的解析状态
+///
+/// 会继续扫描光标之后的内容直到语句结束，以便 `SELECT | FROM t` 这样的输入
+/// 也能知道可见的表。
+pub fn analyze(sql: &str, cursor: usize, db_type: DatabaseType) -> ParseState {
+    // 光标落在多字节字符中间时退到字符开头
+    let mut cursor = cursor.min(sql.len());
+    while !sql.is_char_boundary(cursor) {
+        cursor -= 1;
+    }
+    Analyzer::new(cursor).run(sql, db_type)
+}
+
+struct Analyzer {
+    cursor: usize,
+    scopes: Vec<Scope>,
+    stack: Vec<usize>,
+    prev: Option<(TokenKind, String)>,
+    has_content: bool,
+    after_semicolon: bool,
+    snapshot: Option<ParseState>,
+    cursor_scope: usize,
+}
+
+impl Analyzer {
+    fn new(cursor: usize) -> Self {
+        Analyzer {
+            cursor,
+            scopes: vec![Scope::new(None, ScopeKind::Statement, Clause::None)],
+            stack: vec![0],
+            prev: None,
+            has_content: false,
+            after_semicolon: false,
+            snapshot: None,
+            cursor_scope: 0,
+        }
+    }
+
+    fn current(&mut self) -> &mut Scope {
+        let idx = *self.stack.last().unwrap_or(&0);
+        &mut self.scopes[idx]
+    }
+
+    fn run(mut self, sql: &str, db_type: DatabaseType) -> ParseState {
+        // 光标所在的词从哪里开始（正在输入的词不影响光标处的状态）
+        let word_start = sql[..self.cursor]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_alphanumeric() || *c == '_' || *c == '$')
+            .last()
+            .map(|(i, _)| i)
+            .unwrap_or(self.cursor);
+
+        let mut in_literal = false;
+
+        for token in Lexer::new(sql, db_type) {
+            if self.snapshot.is_none() {
+                let contains_cursor = token.start < self.cursor
+                    && (self.cursor < token.end() || (self.cursor == token.end() && !token.terminated));
+                let is_line_comment = token.kind == TokenKind::Comment && !token.text.starts_with("/*");
+                if matches!(token.kind, TokenKind::String | TokenKind::QuotedIdent | TokenKind::Comment)
+                    && (contains_cursor || (is_line_comment && token.start < self.cursor && self.cursor == token.end()))
+                {
+                    in_literal = true;
+                }
+                if token.start >= word_start || token.end() > self.cursor {
+                    self.take_snapshot(in_literal);
+                }
+            }
+
+            if !token.is_significant() {
+                continue;
+            }
+
+            if token.kind == TokenKind::Semicolon && self.stack.len() == 1 {
+                if self.snapshot.is_some() {
+                    break;
+                }
+                // 新语句
+                self = Analyzer { after_semicolon: true, ..Analyzer::new(self.cursor) };
+                continue;
+            }
+
+            self.has_content = true;
+            self.feed(&token);
+            self.prev = Some((token.kind, token.text.to_string()));
+        }
+
+        if self.snapshot.is_none() {
+            self.take_snapshot(in_literal);
+        }
+
+        // 语句扫描完后再收集光标作用域可见的表和 CTE
+        let mut state = self.snapshot.take().expect("光标处的状态已记录");
+        let mut idx = Some(self.cursor_scope);
+        while let Some(i) = idx {
+            let scope = &self.scopes[i];
+            state.tables.extend(scope.tables.iter().cloned());
+            state.ctes.extend(scope.ctes.iter().cloned());
+            idx = scope.parent;
+        }
+        state
+    }
+
+    fn take_snapshot(&mut self, in_literal: bool) {
+        let idx = *self.stack.last().unwrap_or(&0);
+        let scope = &self.scopes[idx];
+        let expects_table = scope.collects_tables()
+            && matches!(scope.table_step, TableStep::Expect | TableStep::AfterDot);
+        self.cursor_scope = idx;
+        self.snapshot = Some(ParseState {
+            clause: scope.clause,
+            depth: self.stack.len() - 1,
+            expects_table,
+            prev_word: self
+                .prev
+                .as_ref()
+                .filter(|(k, _)| *k == TokenKind::Word)
+                .map(|(_, t)| t.to_uppercase()),
+            in_literal,
+            statement_start: !self.has_content,
+            after_semicolon: self.after_semicolon,
+            tables: Vec::new(),
+            ctes: Vec::new(),
+        });
+    }
+
+    fn feed(&mut self, token: &Token<'_>) {
+        match token.kind {
+            TokenKind::LParen => self.open_paren(),
+            TokenKind::RParen => self.close_paren(),
+            TokenKind::Comma => {
+                let scope = self.current();
+                if scope.clause == Clause::Select {
+                    scope.finish_item();
+                }
+                if matches!(scope.clause, Clause::From | Clause::Update) {
+                    scope.table_step = TableStep::Expect;
+                }
+                if scope.clause == Clause::With {
+                    scope.cte_step = CteStep::Name;
+                }
+            }
+            TokenKind::Dot => {
+                let scope = self.current();
+                if scope.table_step == TableStep::AfterName {
+                    scope.table_step = TableStep::AfterDot;
+                }
+                self.push_item(token);
+            }
+            TokenKind::Word => self.word(token),
+            TokenKind::QuotedIdent => {
+                self.identifier(token);
+            }
+            _ => self.push_item(token),
+        }
+    }
+
+    fn push_item(&mut self, token: &Token<'_>) {
+        let scope = self.current();
+        if scope.clause == Clause::Select {
+            scope.item.push((token.kind, token.text.to_string()));
+        }
+    }
+
+    fn open_paren(&mut self) {
+        let parent = *self.stack.last().unwrap_or(&0);
+        let scope = &mut self.scopes[parent];
+        let clause = scope.clause;
+
+        let kind = if scope.clause == Clause::With {
+            match std::mem::replace(&mut scope.cte_step, CteStep::Name) {
+                CteStep::AfterAs(name, cols) => ScopeKind::CteBody(name, cols),
+                CteStep::AfterName(name, cols) => {
+                    scope.cte_step = CteStep::AfterName(name, cols);
+                    ScopeKind::CteColumns
+                }
+                CteStep::Name => ScopeKind::Expr,
+            }
+        } else if scope.collects_tables() && scope.table_step == TableStep::Expect {
+            ScopeKind::Subquery { from_source: true }
+        } else {
+            ScopeKind::Expr
+        };
+
+        let child_clause = match kind {
+            ScopeKind::Expr if clause == Clause::Into && scope.table_step == TableStep::AfterName => Clause::ColumnList,
+            ScopeKind::Expr | ScopeKind::CteColumns => clause,
+            _ => Clause::None,
+        };
+        if clause == Clause::Select {
+            scope.item.push((TokenKind::LParen, "(".to_string()));
+        }
+
+        self.scopes.push(Scope::new(Some(parent), kind, child_clause));
+        self.stack.push(self.scopes.len() - 1);
+    }
+
+    fn close_paren(&mut self) {
+        if self.stack.len() <= 1 {
+            return;
+        }
+        let idx = self.stack.pop().unwrap_or(0);
+        let child = &mut self.scopes[idx];
+        if child.clause == Clause::Select {
+            child.finish_item();
+        }
+        let kind = child.kind.clone();
+        let columns = child.select_items.clone();
+        let parent = self.current();
+
+        match kind {
+            ScopeKind::CteBody(name, explicit) => {
+                let columns = if explicit.is_empty() { columns } else { explicit };
+                parent.ctes.push(Cte { name, columns });
+            }
+            ScopeKind::Subquery { from_source: true } if parent.table_step == TableStep::Expect => {
+                parent.tables.push(TableRef {
+                    name: String::new(),
+                    alias: None,
+                    kind: SourceKind::Subquery,
+                    columns,
+                });
+                parent.table_step = TableStep::AfterName;
+            }
+            _ => {}
+        }
+
+        if parent.clause == Clause::Select {
+            parent.item.push((TokenKind::RParen, ")".to_string()));
+        }
+    }
+
+    fn word(&mut self, token: &Token<'_>) {
+        let upper = token.text.to_uppercase();
+        let prev_upper = self.prev.as_ref().map(|(_, t)| t.to_uppercase()).unwrap_or_default();
+        let scope = self.current();
+
+        // 括号里出现 SELECT 说明是子查询（IN (SELECT ...)、EXISTS (SELECT ...) 等）
+        if scope.kind == ScopeKind::Expr && (upper == "SELECT" || upper == "WITH") {
+            scope.kind = ScopeKind::Subquery { from_source: false };
+        }
+
+        // CTE 列列表中的标识符
+        if scope.kind == ScopeKind::CteColumns {
+            let parent = scope.parent.unwrap_or(0);
+            if let CteStep::AfterName(_, cols) = &mut self.scopes[parent].cte_step {
+                cols.push(token.text.to_string());
+            }
+            return;
+        }
+
+        let new_clause = match upper.as_str() {
+            "WITH" => Some(Clause::With),
+            "SELECT" => Some(Clause::Select),
+            "FROM" => Some(Clause::From),
+            "JOIN" => Some(Clause::Join),
+            "INNER" | "LEFT" | "RIGHT" | "FULL" | "CROSS" | "NATURAL" | "OUTER" => Some(Clause::Join),
+            "ON" | "USING" if matches!(scope.clause, Clause::Join | Clause::From) => Some(Clause::On),
+            "WHERE" => Some(Clause::Where),
+            "GROUP" | "PARTITION" => Some(Clause::GroupBy),
+            "HAVING" => Some(Clause::Having),
+            "ORDER" => Some(Clause::OrderBy),
+            "LIMIT" | "OFFSET" | "FETCH" => Some(Clause::Limit),
+            "INSERT" | "REPLACE" if scope.clause == Clause::None => Some(Clause::Insert),
+            "INTO" => Some(Clause::Into),
+            "VALUES" => Some(Clause::Values),
+            // ON DUPLICATE KEY UPDATE / ON CONFLICT DO UPDATE / FOR UPDATE 不是 UPDATE 语句
+            "UPDATE" if prev_upper == "FOR" => Some(Clause::Other),
+            "UPDATE" if scope.clause == Clause::None => Some(Clause::Update),
+            "UPDATE" => Some(Clause::Set),
+            "SET" if matches!(scope.clause, Clause::Update | Clause::Join | Clause::From | Clause::On) => Some(Clause::Set),
+            "DELETE" if scope.clause == Clause::None => Some(Clause::Delete),
+            "RETURNING" => Some(Clause::Returning),
+            "CREATE" if scope.clause == Clause::None => Some(Clause::Create),
+            "ALTER" if scope.clause == Clause::None => Some(Clause::Alter),
+            "DROP" | "TRUNCATE" if scope.clause == Clause::None => Some(Clause::Drop),
+            "UNION" | "INTERSECT" | "EXCEPT" => Some(Clause::Other),
+            _ => None,
+        };
+
+        // AS ( 之后是 CTE 体，AS 本身不是子句
+        if scope.clause == Clause::With && new_clause.is_none() {
+            scope.cte_step = match (std::mem::replace(&mut scope.cte_step, CteStep::Name), upper.as_str()) {
+                (step, "RECURSIVE") => step,
+                (CteStep::AfterName(name, cols), "AS") => CteStep::AfterAs(name, cols),
+                (step @ CteStep::AfterAs(..), "MATERIALIZED" | "NOT") => step,
+                (_, _) => CteStep::AfterName(token.text.to_string(), Vec::new()),
+            };
+            return;
+        }
+
+        if let Some(clause) = new_clause {
+            if scope.clause == Clause::Select {
+                scope.finish_item();
+            }
+            match upper.as_str() {
+                "UNION" | "INTERSECT" | "EXCEPT" => {
+                    // 后续 SELECT 的表不可见，输出列以第一个 SELECT 为准
+                    scope.tables.clear();
+                    scope.select_done = true;
+                }
+                "FROM" | "JOIN" | "INTO" | "TRUNCATE" => scope.table_step = TableStep::Expect,
+                "UPDATE" if clause == Clause::Update => scope.table_step = TableStep::Expect,
+                _ => scope.table_step = TableStep::Idle,
+            }
+            scope.clause = clause;
+            return;
+        }
+
+        // ALTER TABLE / DROP TABLE [IF EXISTS] 后面是表名
+        if matches!(scope.clause, Clause::Alter | Clause::Drop) {
+            match upper.as_str() {
+                "TABLE" | "VIEW" => {
+                    scope.table_step = TableStep::Expect;
+                    return;
+                }
+                "IF" | "NOT" | "EXISTS" if scope.table_step == TableStep::Expect => return,
+                _ => {}
+            }
+        }
+
+        self.identifier(token);
+    }
+
+    /// 在当前作用域及外层查找 CTE
+    fn find_cte(&self, name: &str) -> Option<&Cte> {
+        let mut idx = self.stack.last().copied();
+        while let Some(i) = idx {
+            let scope = &self.scopes[i];
+            if let Some(cte) = scope.ctes.iter().find(|c| c.name.eq_ignore_ascii_case(name)) {
+                return Some(cte);
+            }
+            idx = scope.parent;
+        }
+        None
+    }
+
+    /// 处理标识符（普通或带引号）
+    fn identifier(&mut self, token: &Token<'_>) {
+        let text = unquote(token.text);
+        let cte_columns = self.find_cte(&text).map(|c| c.columns.clone());
+        let scope = self.current();
+
+        if scope.clause == Clause::Select {
+            scope.item.push((token.kind, token.text.to_string()));
+        }
+
+        if !scope.collects_tables() {
+            return;
+        }
+
+        match scope.table_step {
+            TableStep::Expect => {
+                if token.kind == TokenKind::Word && (token.is_keyword("LATERAL") || token.is_keyword("ONLY")) {
+                    return;
+                }
+                let (kind, columns) = match cte_columns {
+                    Some(cols) => (SourceKind::Cte, cols),
+                    None => (SourceKind::Table, Vec::new()),
+                };
+                scope.tables.push(TableRef { name: text, alias: None, kind, columns });
+                scope.table_step = TableStep::AfterName;
+            }
+            TableStep::AfterDot => {
+                if let Some(last) = scope.tables.last_mut() {
+                    last.name = format!("{}.{}", last.name, text);
+                }
+                scope.table_step = TableStep::AfterName;
+            }
+            TableStep::AfterName | TableStep::AfterAs => {
+                let is_as = token.is_keyword("AS");
+                if is_as && scope.table_step == TableStep::AfterName {
+                    scope.table_step = TableStep::AfterAs;
+                    return;
+                }
+                if token.kind == TokenKind::Word && is_reserved(&text) {
+                    scope.table_step = TableStep::Idle;
+                    return;
+                }
+                if let Some(last) = scope.tables.last_mut() {
+                    if last.kind == SourceKind::Subquery {
+                        last.name = text.clone();
+                    }
+                    last.alias = Some(text);
+                }
+                scope.table_step = TableStep::Idle;
+            }
+            TableStep::Idle => {}
+        }
+    }
+}
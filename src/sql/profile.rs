@@ -0,0 +1,281 @@
+//! 命名连接配置与密码来源
+//!
+//! 连接配置保存在 ~/.config/cnmsb/connections.toml，每个表是一个连接：
+//!
+//! ```toml
+//! [prod]
+//! type = "postgresql"
+//! host = "db.example.com"
+//! user = "app"
+//! database = "app"
+//!
+//! [local]
+//! url = "sqlite:///home/me/app.db"
+//! ```
+//!
+//! 配置文件中不保存密码。密码依次从环境变量（PGPASSWORD / MYSQL_PWD）、
+//! ~/.pgpass、~/.my.cnf 中查找，都没有时在终端中不回显地询问。
+
+use super::database::{DatabaseConfig, DatabaseType};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, stdin, stdout, Write};
+use std::path::PathBuf;
+
+/// 单个命名连接
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectionProfile {
+    /// 数据库类型 (mysql/postgresql/sqlite/mariadb)
+    #[serde(rename = "type")]
+    pub db_type: Option<String>,
+    /// 完整连接 URL，设置后忽略其他字段
+    pub url: Option<String>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub user: Option<String>,
+    pub database: Option<String>,
+    /// SQLite 数据库文件
+    pub path: Option<String>,
+}
+
+impl ConnectionProfile {
+    /// 转换为连接配置（不含密码）
+    pub fn to_config(&self) -> Result<DatabaseConfig, String> {
+        if let Some(ref url) = self.url {
+            return DatabaseConfig::parse(url).ok_or_else(|| format!("无法解析连接 URL: {}", url));
+        }
+
+        let type_name = self.db_type.as_deref().ok_or("缺少 type 或 url")?;
+        let db_type = DatabaseType::from_str(type_name)
+            .ok_or_else(|| format!("不认识的数据库类型: {}", type_name))?;
+
+        if db_type == DatabaseType::SQLite {
+            let path = self.path.as_deref().ok_or("SQLite 连接缺少 path")?;
+            return Ok(DatabaseConfig::sqlite(&expand_home(path)));
+        }
+
+        let mut config = DatabaseConfig::new(db_type);
+        if let Some(ref host) = self.host {
+            config.host = host.clone();
+        }
+        if let Some(port) = self.port {
+            config.port = port;
+        }
+        config.username = self.user.clone().unwrap_or_else(|| default_user(db_type).to_string());
+        config.database = self.database.clone().unwrap_or_default();
+        Ok(config)
+    }
+}
+
+/// 各数据库的默认用户名
+fn default_user(db_type: DatabaseType) -> &'static str {
+    match db_type {
+        DatabaseType::PostgreSQL => "postgres",
+        _ => "root",
+    }
+}
+
+/// 展开路径开头的 ~
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().into_owned(),
+        _ => path.to_string(),
+    }
+}
+
+/// 连接配置文件路径
+pub fn profiles_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|p| p.join("cnmsb").join("connections.toml"))
+}
+
+/// 解析连接配置文件内容
+pub fn parse_profiles(content: &str) -> Result<BTreeMap<String, ConnectionProfile>, String> {
+    toml::from_str(content).map_err(|e| format!("connections.toml 格式错误: {}", e))
+}
+
+/// 读取所有命名连接，配置文件不存在时为空
+pub fn load_profiles() -> Result<BTreeMap<String, ConnectionProfile>, String> {
+    match profiles_path().and_then(|p| fs::read_to_string(p).ok()) {
+        Some(content) => parse_profiles(&content),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+/// 按名称查找连接配置，名称可以带 @ 前缀
+pub fn find_profile(name: &str) -> Result<DatabaseConfig, String> {
+    let name = name.trim_start_matches('@');
+    let profiles = load_profiles()?;
+    let profile = profiles.get(name).ok_or_else(|| {
+        let path = profiles_path().map(|p| p.display().to_string()).unwrap_or_default();
+        format!("没有名为 '{}' 的连接（配置文件: {}）", name, path)
+    })?;
+    profile.to_config()
+}
+
+/// 从环境变量、~/.pgpass 或 ~/.my.cnf 中查找密码
+pub fn lookup_password(config: &DatabaseConfig) -> Option<String> {
+    match config.db_type {
+        DatabaseType::PostgreSQL => std::env::var("PGPASSWORD").ok()
+            .or_else(|| {
+                let path = std::env::var_os("PGPASSFILE").map(PathBuf::from)
+                    .or_else(|| dirs::home_dir().map(|h| h.join(".pgpass")))?;
+                read_private_file(&path).and_then(|content| pgpass_password(&content, config))
+            }),
+        DatabaseType::MySQL | DatabaseType::MariaDB => std::env::var("MYSQL_PWD").ok()
+            .or_else(|| {
+                let path = dirs::home_dir()?.join(".my.cnf");
+                read_private_file(&path).and_then(|content| mycnf_password(&content))
+            }),
+        _ => None,
+    }
+}
+
+/// 读取密码文件，和 psql 一样忽略其他用户可读的文件
+fn read_private_file(path: &PathBuf) -> Option<String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(path).ok()?.permissions().mode();
+        if mode & 0o077 != 0 {
+            eprintln!("警告: {} 的权限过于宽松，已忽略（应为 0600）", path.display());
+            return None;
+        }
+    }
+    fs::read_to_string(path).ok()
+}
+
+/// 在 .pgpass 内容中查找密码
+///
+/// 每行格式为 hostname:port:database:username:password，字段可以是 *，
+/// 字段中的 : 和 \ 用 \ 转义，第一个匹配的行生效
+pub fn pgpass_password(content: &str, config: &DatabaseConfig) -> Option<String> {
+    let port = config.port.to_string();
+    let wanted = [config.host.as_str(), port.as_str(), config.database.as_str(), config.username.as_str()];
+
+    content.lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .map(split_pgpass_line)
+        .filter(|fields| fields.len() == 5)
+        .find(|fields| {
+            fields.iter().zip(wanted.iter()).all(|(field, value)| field == "*" || field == value)
+        })
+        .map(|mut fields| fields.remove(4))
+}
+
+/// 按未转义的 : 分割 .pgpass 的一行
+fn split_pgpass_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    fields.last_mut().unwrap().push(next);
+                }
+            }
+            ':' => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+/// 在 .my.cnf 内容中查找 [client] 或 [mysql] 段的密码
+pub fn mycnf_password(content: &str) -> Option<String> {
+    let mut in_section = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            let section = line[1..line.len() - 1].trim();
+            in_section = section == "client" || section == "mysql";
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            if key.trim() == "password" {
+                let value = value.trim();
+                let unquoted = value.strip_prefix('"').and_then(|v| v.strip_suffix('"'))
+                    .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                    .unwrap_or(value);
+                return Some(unquoted.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// 在终端中不回显地读取密码
+pub fn prompt_password(prompt: &str) -> io::Result<String> {
+    print!("{}", prompt);
+    stdout().flush()?;
+
+    let mut input = String::new();
+    {
+        let _no_echo = NoEcho::enable();
+        stdin().read_line(&mut input)?;
+    }
+    // 回车没有回显，手动换行
+    println!();
+
+    Ok(input.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// 关闭终端回显，离开作用域时恢复
+#[cfg(unix)]
+struct NoEcho {
+    original: Option<libc::termios>,
+}
+
+#[cfg(unix)]
+impl NoEcho {
+    fn enable() -> Self {
+        use std::mem::MaybeUninit;
+        use std::os::unix::io::AsRawFd;
+
+        let fd = stdin().as_raw_fd();
+        let mut termios = MaybeUninit::uninit();
+
+        // 不是终端（如管道输入）时什么都不做
+        if unsafe { libc::tcgetattr(fd, termios.as_mut_ptr()) } != 0 {
+            return NoEcho { original: None };
+        }
+
+        let original = unsafe { termios.assume_init() };
+        let mut silent = original;
+        silent.c_lflag &= !libc::ECHO;
+
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &silent) } != 0 {
+            return NoEcho { original: None };
+        }
+
+        NoEcho { original: Some(original) }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for NoEcho {
+    fn drop(&mut self) {
+        use std::os::unix::io::AsRawFd;
+        if let Some(ref original) = self.original {
+            let fd = stdin().as_raw_fd();
+            unsafe { libc::tcsetattr(fd, libc::TCSANOW, original) };
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct NoEcho;
+
+#[cfg(not(unix))]
+impl NoEcho {
+    fn enable() -> Self {
+        NoEcho
+    }
+}
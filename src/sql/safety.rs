@@ -0,0 +1,171 @@
+//! 事务状态跟踪与危险语句检查
+
+use super::database::DatabaseType;
+use super::lexer::{self, Token, TokenKind};
+
+/// 事务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionState {
+    /// 不在事务中（自动提交）
+    Idle,
+    /// 事务进行中
+    Active,
+    /// 事务中有语句失败（PostgreSQL 需要 ROLLBACK 才能继续）
+    Failed,
+}
+
+impl TransactionState {
+    /// 提示符中的状态标记
+    pub fn indicator(&self) -> &'static str {
+        match self {
+            TransactionState::Idle => "",
+            TransactionState::Active => "*",
+            TransactionState::Failed => "!",
+        }
+    }
+
+    /// 根据成功执行的 SQL（可能包含多条语句）更新状态
+    pub fn apply(&mut self, sql: &str, db_type: DatabaseType) {
+        for stmt in lexer::split_statements(sql, db_type) {
+            self.apply_statement(&lexer::significant_tokens(stmt, db_type), db_type);
+        }
+    }
+
+    fn apply_statement(&mut self, tokens: &[Token<'_>], db_type: DatabaseType) {
+        let first = match tokens.first() {
+            Some(t) => t,
+            None => return,
+        };
+        let second = tokens.get(1);
+
+        if first.is_keyword("BEGIN") || (first.is_keyword("START") && second.is_some_and(|t| t.is_keyword("TRANSACTION"))) {
+            *self = TransactionState::Active;
+        } else if first.is_keyword("COMMIT") || first.is_keyword("END") {
+            *self = TransactionState::Idle;
+        } else if first.is_keyword("ROLLBACK") {
+            // ROLLBACK TO [SAVEPOINT] x 只回滚到保存点，事务仍在进行
+            let to_savepoint = tokens.iter().skip(1).any(|t| t.is_keyword("TO"));
+            *self = if to_savepoint { TransactionState::Active } else { TransactionState::Idle };
+        } else if matches!(db_type, DatabaseType::MySQL | DatabaseType::MariaDB) && implicit_commit(tokens) {
+            *self = TransactionState::Idle;
+        }
+    }
+
+    /// 语句执行失败时更新状态
+    pub fn fail(&mut self, db_type: DatabaseType) {
+        if *self == TransactionState::Active && db_type == DatabaseType::PostgreSQL {
+            *self = TransactionState::Failed;
+        }
+    }
+}
+
+/// MySQL 中会隐式提交当前事务的语句（DDL 和 LOCK TABLES，临时表除外）
+fn implicit_commit(tokens: &[Token<'_>]) -> bool {
+    let first = &tokens[0];
+    if first.is_keyword("CREATE") || first.is_keyword("DROP") {
+        return !tokens.get(1).is_some_and(|t| t.is_keyword("TEMPORARY"));
+    }
+    ["ALTER", "TRUNCATE", "RENAME", "LOCK"].iter().any(|kw| first.is_keyword(kw))
+}
+
+/// 安全模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SafeMode {
+    /// 不做检查
+    Off,
+    /// 危险语句执行前要求确认
+    Confirm,
+    /// 直接拒绝危险语句
+    Refuse,
+}
+
+impl SafeMode {
+    /// 从字符串解析
+    pub fn parse(s: &str) -> Option<SafeMode> {
+        match s.to_lowercase().as_str() {
+            "off" | "false" | "0" => Some(SafeMode::Off),
+            "confirm" | "on" | "true" | "1" => Some(SafeMode::Confirm),
+            "refuse" | "strict" => Some(SafeMode::Refuse),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SafeMode::Off => "off",
+            SafeMode::Confirm => "confirm",
+            SafeMode::Refuse => "refuse",
+        }
+    }
+}
+
+/// 危险语句类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Danger {
+    Drop,
+    Truncate,
+    UpdateWithoutWhere,
+    DeleteWithoutWhere,
+}
+
+impl Danger {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Danger::Drop => "DROP 语句会永久删除数据库对象",
+            Danger::Truncate => "TRUNCATE 会清空整张表",
+            Danger::UpdateWithoutWhere => "UPDATE 没有 WHERE 条件，会修改所有行",
+            Danger::DeleteWithoutWhere => "DELETE 没有 WHERE 条件，会删除所有行",
+        }
+    }
+}
+
+/// 检查 SQL（可能包含多条语句）中是否有危险语句
+pub fn check(sql: &str, db_type: DatabaseType) -> Option<Danger> {
+    lexer::split_statements(sql, db_type)
+        .into_iter()
+        .find_map(|stmt| check_statement(&lexer::significant_tokens(stmt, db_type)))
+}
+
+/// 检查单条语句
+fn check_statement(tokens: &[Token<'_>]) -> Option<Danger> {
+    let first = tokens.first()?;
+
+    if first.is_keyword("DROP") {
+        return Some(Danger::Drop);
+    }
+    if first.is_keyword("TRUNCATE") {
+        return Some(Danger::Truncate);
+    }
+
+    // WITH ... 后面真正执行的语句
+    let main = if first.is_keyword("WITH") {
+        top_level_words(tokens)
+            .find(|t| ["SELECT", "INSERT", "UPDATE", "DELETE"].iter().any(|k| t.is_keyword(k)))?
+    } else {
+        first
+    };
+
+    let has_where = || top_level_words(tokens).any(|t| t.is_keyword("WHERE"));
+
+    if main.is_keyword("UPDATE") && !has_where() {
+        return Some(Danger::UpdateWithoutWhere);
+    }
+    if main.is_keyword("DELETE") && !has_where() {
+        return Some(Danger::DeleteWithoutWhere);
+    }
+
+    None
+}
+
+/// 遍历括号深度为 0 的单词 token
+fn top_level_words<'t, 'a>(tokens: &'t [Token<'a>]) -> impl Iterator<Item = &'t Token<'a>> {
+    let mut depth = 0i32;
+    tokens.iter().filter(move |t| {
+        match t.kind {
+            TokenKind::LParen => depth += 1,
+            TokenKind::RParen => depth -= 1,
+            _ => {}
+        }
+        depth == 0 && t.kind == TokenKind::Word
+    })
+}
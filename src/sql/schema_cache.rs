@@ -0,0 +1,117 @@
+//! Schema 缓存
+//!
+//! 连接后只执行一次目录查询获取 schema 和其中的对象，列信息在补全首次用到时再查询。
+//! 结果按连接保存在 ~/.cache/cnmsb/schema/ 下，下次连接直接使用，`.refresh` 重新加载。
+
+use super::connection::{Catalog, TableInfo};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// 持久化的缓存内容
+#[derive(Default, Serialize, Deserialize)]
+struct CacheData {
+    catalog: Option<Catalog>,
+    /// 表名（小写，可能带 schema 前缀）-> 列名
+    columns: HashMap<String, Vec<String>>,
+    /// 表名（小写）-> 列类型、索引和外键
+    #[serde(default)]
+    tables: HashMap<String, TableInfo>,
+}
+
+/// 单个连接的 Schema 缓存
+#[derive(Default)]
+pub struct SchemaCache {
+    /// 缓存文件路径，为 None 时不保存（如内存数据库）
+    path: Option<PathBuf>,
+    data: CacheData,
+    dirty: bool,
+}
+
+impl SchemaCache {
+    /// 打开缓存文件，文件不存在或损坏时为空缓存
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let data = path.as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        SchemaCache { path, data, dirty: false }
+    }
+
+    /// 打开某个连接的缓存，`key` 为不含密码的连接描述
+    pub fn for_connection(key: &str) -> Self {
+        Self::open(Self::cache_path(key))
+    }
+
+    /// 连接对应的缓存文件路径
+    pub fn cache_path(key: &str) -> Option<PathBuf> {
+        dirs::cache_dir().map(|p| p.join("cnmsb").join("schema").join(format!("{}.json", file_stem(key))))
+    }
+
+    /// 已缓存的目录
+    pub fn catalog(&self) -> Option<&Catalog> {
+        self.data.catalog.as_ref()
+    }
+
+    /// 设置新的目录，已缓存的列信息随之失效
+    pub fn set_catalog(&mut self, catalog: Catalog) {
+        self.data.catalog = Some(catalog);
+        self.data.columns.clear();
+        self.data.tables.clear();
+        self.dirty = true;
+    }
+
+    /// 已缓存的列
+    pub fn columns(&self, table: &str) -> Option<&Vec<String>> {
+        self.data.columns.get(&table.to_lowercase())
+    }
+
+    /// 缓存表的列
+    pub fn set_columns(&mut self, table: &str, columns: Vec<String>) {
+        self.data.columns.insert(table.to_lowercase(), columns);
+        self.dirty = true;
+    }
+
+    /// 已缓存的表结构
+    pub fn table_info(&self, table: &str) -> Option<&TableInfo> {
+        self.data.tables.get(&table.to_lowercase())
+    }
+
+    /// 缓存表结构，同时更新列名
+    pub fn set_table_info(&mut self, table: &str, info: TableInfo) {
+        let columns = info.columns.iter().map(|c| c.name.clone()).collect();
+        self.data.columns.insert(table.to_lowercase(), columns);
+        self.data.tables.insert(table.to_lowercase(), info);
+        self.dirty = true;
+    }
+
+    /// 目录中是否有该表，避免为别名、CTE 等去查询数据库
+    pub fn has_table(&self, table: &str) -> bool {
+        self.data.catalog.as_ref().is_some_and(|c| c.has_table(table))
+    }
+
+    /// 有修改时写回磁盘
+    pub fn save(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        if let Some(ref path) = self.path {
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            if let Ok(json) = serde_json::to_string(&self.data) {
+                if fs::write(path, json).is_ok() {
+                    self.dirty = false;
+                }
+            }
+        }
+    }
+}
+
+/// 把连接描述转换为可用作文件名的字符串
+pub(super) fn file_stem(key: &str) -> String {
+    key.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect()
+}
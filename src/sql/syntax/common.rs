@@ -1,5 +1,8 @@
 //! 通用 SQL 语法定义
 
+use crate::sql::parse_state::{self, Clause};
+use crate::sql::DatabaseType;
+
 /// SQL 补全项
 #[derive(Debug, Clone)]
 pub struct SqlCompletion {
@@ -41,6 +44,39 @@ impl SqlCompletion {
         }
     }
     
+    pub fn cte(text: &str) -> Self {
+        SqlCompletion {
+            text: text.to_string(),
+            description: "CTE".to_string(),
+            kind: SqlCompletionKind::Table,
+        }
+    }
+    
+    pub fn alias(text: &str, target: &str) -> Self {
+        SqlCompletion {
+            text: text.to_string(),
+            description: format!("别名 ({})", target),
+            kind: SqlCompletionKind::Table,
+        }
+    }
+    
+    /// 视图、物化视图等表类对象
+    pub fn relation(text: &str, desc: &str) -> Self {
+        SqlCompletion {
+            text: text.to_string(),
+            description: desc.to_string(),
+            kind: SqlCompletionKind::Table,
+        }
+    }
+    
+    pub fn schema(text: &str) -> Self {
+        SqlCompletion {
+            text: text.to_string(),
+            description: "Schema".to_string(),
+            kind: SqlCompletionKind::Database,
+        }
+    }
+    
     pub fn column(text: &str, table: &str) -> Self {
         SqlCompletion {
             text: text.to_string(),
@@ -48,6 +84,33 @@ impl SqlCompletion {
             kind: SqlCompletionKind::Column,
         }
     }
+    
+    /// 已知类型的列
+    pub fn typed_column(text: &str, table: &str, data_type: &str) -> Self {
+        SqlCompletion {
+            text: text.to_string(),
+            description: format!("列 ({}, {})", table, data_type),
+            kind: SqlCompletionKind::Column,
+        }
+    }
+    
+    /// 根据外键生成的 JOIN 表和条件
+    pub fn join(text: &str, desc: &str) -> Self {
+        SqlCompletion {
+            text: text.to_string(),
+            description: desc.to_string(),
+            kind: SqlCompletionKind::Snippet,
+        }
+    }
+    
+    /// 查询历史中的片段，`count` 为使用次数
+    pub fn history(text: &str, count: usize) -> Self {
+        SqlCompletion {
+            text: text.to_string(),
+            description: format!("历史 ({} 次)", count),
+            kind: SqlCompletionKind::History,
+        }
+    }
 }
 
 /// SQL 补全类型
@@ -61,6 +124,7 @@ pub enum SqlCompletionKind {
     Database,
     Operator,
     Snippet,
+    History,
 }
 
 impl SqlCompletionKind {
@@ -74,6 +138,7 @@ impl SqlCompletionKind {
             SqlCompletionKind::Database => "\x1b[38;5;208m",  // 橙色
             SqlCompletionKind::Operator => "\x1b[38;5;226m",  // 鲜艳黄色
             SqlCompletionKind::Snippet => "\x1b[36m",         // 青色（子命令风格）
+            SqlCompletionKind::History => "\x1b[38;5;245m",   // 灰色
         }
     }
     
@@ -87,12 +152,16 @@ impl SqlCompletionKind {
             SqlCompletionKind::Database => "库",
             SqlCompletionKind::Operator => "O",
             SqlCompletionKind::Snippet => "S",
+            SqlCompletionKind::History => "H",
         }
     }
 }
 
 /// SQL 语法特征
 pub trait SqlSyntax: Send + Sync {
+    /// 对应的数据库类型（决定词法规则，如注释和引号）
+    fn db_type(&self) -> DatabaseType;
+    
     /// 获取关键字列表
     fn keywords(&self) -> Vec<SqlCompletion>;
     
@@ -130,8 +199,12 @@ pub trait SqlSyntax: Send + Sync {
         let input_upper = input.to_uppercase();
         let _input_lower = input.to_lowercase();
         
-        // 获取当前正在输入的词
-        let current_word = input.split_whitespace().last().unwrap_or("");
+        // 获取当前正在输入的词（光标前是空白时为空）
+        let current_word = if input.ends_with(char::is_whitespace) {
+            ""
+        } else {
+            input.split_whitespace().last().unwrap_or("")
+        };
         let current_word_upper = current_word.to_uppercase();
         
         // 判断上下文
@@ -188,7 +261,7 @@ pub trait SqlSyntax: Send + Sync {
                 comps
             }
             SqlContext::AfterCreate => {
-                vec![
+                let mut comps = vec![
                     SqlCompletion::keyword("TABLE", "创建表"),
                     SqlCompletion::keyword("DATABASE", "创建数据库"),
                     SqlCompletion::keyword("INDEX", "创建索引"),
@@ -197,18 +270,22 @@ pub trait SqlSyntax: Send + Sync {
                     SqlCompletion::keyword("FUNCTION", "创建函数"),
                     SqlCompletion::keyword("TRIGGER", "创建触发器"),
                     SqlCompletion::keyword("USER", "创建用户"),
-                ]
+                ];
+                comps.extend(dialect_objects(&self.keywords(), "CREATE", &comps));
+                comps
             }
             SqlContext::AfterAlter => {
-                vec![
+                let mut comps = vec![
                     SqlCompletion::keyword("TABLE", "修改表"),
                     SqlCompletion::keyword("DATABASE", "修改数据库"),
                     SqlCompletion::keyword("INDEX", "修改索引"),
                     SqlCompletion::keyword("USER", "修改用户"),
-                ]
+                ];
+                comps.extend(dialect_objects(&self.keywords(), "ALTER", &comps));
+                comps
             }
             SqlContext::AfterDrop => {
-                vec![
+                let mut comps = vec![
                     SqlCompletion::keyword("TABLE", "删除表"),
                     SqlCompletion::keyword("DATABASE", "删除数据库"),
                     SqlCompletion::keyword("INDEX", "删除索引"),
@@ -217,7 +294,9 @@ pub trait SqlSyntax: Send + Sync {
                     SqlCompletion::keyword("FUNCTION", "删除函数"),
                     SqlCompletion::keyword("TRIGGER", "删除触发器"),
                     SqlCompletion::keyword("USER", "删除用户"),
-                ]
+                ];
+                comps.extend(dialect_objects(&self.keywords(), "DROP", &comps));
+                comps
             }
             SqlContext::AfterInsert => {
                 vec![
@@ -242,6 +321,9 @@ pub trait SqlSyntax: Send + Sync {
             SqlContext::DataType => {
                 self.data_types()
             }
+            SqlContext::InLiteral => {
+                return Vec::new();
+            }
             SqlContext::Unknown => {
                 self.all_completions()
             }
@@ -286,62 +368,51 @@ pub trait SqlSyntax: Send + Sync {
     
     /// 检测 SQL 上下文
     fn detect_context(&self, input: &str) -> SqlContext {
-        let input = input.trim();
+        let state = parse_state::analyze(input, input.len(), self.db_type());
         
-        if input.is_empty() {
-            return SqlContext::Start;
+        if state.in_literal {
+            return SqlContext::InLiteral;
         }
-        
-        // 检查最后一个分号后的内容
-        if let Some(last_stmt) = input.rsplit(';').next() {
-            let last_stmt = last_stmt.trim();
-            if last_stmt.is_empty() {
-                return SqlContext::AfterSemicolon;
-            }
+        if state.statement_start {
+            return if state.after_semicolon { SqlContext::AfterSemicolon } else { SqlContext::Start };
         }
         
-        // 从后向前查找关键字
-        let words: Vec<&str> = input.split_whitespace().collect();
-        
-        for i in (0..words.len()).rev() {
-            let word = words[i].to_uppercase();
-            match word.as_str() {
-                "SELECT" => return SqlContext::AfterSelect,
-                "FROM" => return SqlContext::AfterFrom,
-                "WHERE" => return SqlContext::AfterWhere,
-                "AND" => return SqlContext::AfterAnd,
-                "OR" => return SqlContext::AfterOr,
-                "CREATE" => return SqlContext::AfterCreate,
-                "ALTER" => return SqlContext::AfterAlter,
-                "DROP" => return SqlContext::AfterDrop,
-                "INSERT" => return SqlContext::AfterInsert,
-                "UPDATE" => return SqlContext::AfterUpdate,
-                "SET" => return SqlContext::AfterSet,
-                "JOIN" | "INNER" | "LEFT" | "RIGHT" | "OUTER" | "CROSS" => return SqlContext::AfterJoin,
-                "ORDER" if i + 1 < words.len() && words[i + 1].to_uppercase() == "BY" => {
-                    return SqlContext::AfterOrderBy;
-                }
-                "GROUP" if i + 1 < words.len() && words[i + 1].to_uppercase() == "BY" => {
-                    return SqlContext::AfterGroupBy;
-                }
-                "BY" => {
-                    if i > 0 {
-                        let prev = words[i - 1].to_uppercase();
-                        if prev == "ORDER" {
-                            return SqlContext::AfterOrderBy;
-                        } else if prev == "GROUP" {
-                            return SqlContext::AfterGroupBy;
-                        }
-                    }
-                }
-                _ => {}
-            }
+        match state.clause {
+            Clause::Where | Clause::On | Clause::Having => match state.prev_word.as_deref() {
+                Some("AND") => SqlContext::AfterAnd,
+                Some("OR") => SqlContext::AfterOr,
+                _ => SqlContext::AfterWhere,
+            },
+            Clause::Select => SqlContext::AfterSelect,
+            Clause::From => SqlContext::AfterFrom,
+            Clause::Join => SqlContext::AfterJoin,
+            Clause::Create => SqlContext::AfterCreate,
+            Clause::Alter => SqlContext::AfterAlter,
+            Clause::Drop => SqlContext::AfterDrop,
+            Clause::Insert | Clause::Into => SqlContext::AfterInsert,
+            Clause::Update => SqlContext::AfterUpdate,
+            Clause::Set => SqlContext::AfterSet,
+            Clause::OrderBy => SqlContext::AfterOrderBy,
+            Clause::GroupBy => SqlContext::AfterGroupBy,
+            _ => SqlContext::Unknown,
         }
-        
-        SqlContext::Unknown
     }
 }
 
+/// 方言特有的对象类型，如 `CREATE SEQUENCE` 中的 SEQUENCE
+fn dialect_objects(keywords: &[SqlCompletion], verb: &str, existing: &[SqlCompletion]) -> Vec<SqlCompletion> {
+    let prefix = format!("{} ", verb);
+    keywords.iter()
+        .filter_map(|k| {
+            let rest = k.text.strip_prefix(&prefix)?;
+            if rest.contains(' ') || existing.iter().any(|e| e.text == rest) {
+                return None;
+            }
+            Some(SqlCompletion::keyword(rest, &k.description))
+        })
+        .collect()
+}
+
 /// SQL 上下文
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SqlContext {
@@ -362,6 +433,8 @@ pub enum SqlContext {
     AfterOrderBy,
     AfterGroupBy,
     DataType,
+    /// 光标在字符串或注释中，不提供补全
+    InLiteral,
     Unknown,
 }
 
@@ -0,0 +1,146 @@
+//! MariaDB 语法定义
+//!
+//! MariaDB 与 MySQL 共用大部分语法，这里在 MySQL 的基础上加入 MariaDB 特有的
+//! 序列、RETURNING、系统版本表、JSON 函数和数据类型
+
+use super::common::{SqlCompletion, SqlCompletionKind, SqlSyntax};
+use super::mysql::MySqlSyntax;
+use crate::sql::DatabaseType;
+
+/// MariaDB 语法
+pub struct MariaDbSyntax;
+
+impl SqlSyntax for MariaDbSyntax {
+    fn db_type(&self) -> DatabaseType {
+        DatabaseType::MariaDB
+    }
+
+    fn keywords(&self) -> Vec<SqlCompletion> {
+        let mut keywords = MySqlSyntax.keywords();
+        keywords.extend(vec![
+            // 序列 (10.3+)
+            SqlCompletion::keyword("CREATE SEQUENCE", "创建序列"),
+            SqlCompletion::keyword("ALTER SEQUENCE", "修改序列"),
+            SqlCompletion::keyword("DROP SEQUENCE", "删除序列"),
+            SqlCompletion::keyword("NEXT VALUE FOR", "序列下一个值"),
+            SqlCompletion::keyword("PREVIOUS VALUE FOR", "序列当前值"),
+            SqlCompletion::keyword("INCREMENT BY", "序列步长"),
+            SqlCompletion::keyword("START WITH", "序列起始值"),
+            SqlCompletion::keyword("MINVALUE", "序列最小值"),
+            SqlCompletion::keyword("MAXVALUE", "序列最大值"),
+            SqlCompletion::keyword("CYCLE", "序列循环"),
+            SqlCompletion::keyword("NOCACHE", "序列不缓存"),
+            SqlCompletion::keyword("SHOW CREATE SEQUENCE", "显示建序列语句"),
+
+            // RETURNING (INSERT/REPLACE 10.5+，DELETE 10.0+)
+            SqlCompletion::keyword("RETURNING", "返回受影响的行"),
+
+            // 系统版本表 (10.3+)
+            SqlCompletion::keyword("WITH SYSTEM VERSIONING", "启用系统版本"),
+            SqlCompletion::keyword("WITHOUT SYSTEM VERSIONING", "列不参与版本"),
+            SqlCompletion::keyword("FOR SYSTEM_TIME AS OF", "查询某一时刻的数据"),
+            SqlCompletion::keyword("FOR SYSTEM_TIME BETWEEN", "查询时间段内的版本"),
+            SqlCompletion::keyword("FOR SYSTEM_TIME FROM", "查询时间段内的版本"),
+            SqlCompletion::keyword("FOR SYSTEM_TIME ALL", "查询所有历史版本"),
+            SqlCompletion::keyword("PERIOD FOR SYSTEM_TIME", "定义系统时间周期"),
+            SqlCompletion::keyword("GENERATED ALWAYS AS ROW START", "版本起始列"),
+            SqlCompletion::keyword("GENERATED ALWAYS AS ROW END", "版本结束列"),
+            SqlCompletion::keyword("DELETE HISTORY", "删除历史版本"),
+            SqlCompletion::keyword("FOR PORTION OF", "按应用时间周期修改"),
+
+            // 其他 MariaDB 扩展
+            SqlCompletion::keyword("CREATE OR REPLACE", "创建或替换"),
+            SqlCompletion::keyword("CREATE OR REPLACE TABLE", "创建或替换表"),
+            SqlCompletion::keyword("INTERSECT", "交集"),
+            SqlCompletion::keyword("EXCEPT", "差集"),
+            SqlCompletion::keyword("FETCH FIRST", "取前 N 行"),
+            SqlCompletion::keyword("WITH TIES", "包含并列行"),
+            SqlCompletion::keyword("WITH RECURSIVE", "递归 CTE"),
+            SqlCompletion::keyword("ANALYZE", "执行并分析"),
+            SqlCompletion::keyword("SHOW EXPLAIN", "显示其他连接的执行计划"),
+            SqlCompletion::keyword("INVISIBLE", "隐藏列"),
+            SqlCompletion::keyword("COMPRESSED", "压缩列"),
+        ]);
+        keywords
+    }
+
+    fn functions(&self) -> Vec<SqlCompletion> {
+        let mut functions = MySqlSyntax.functions();
+        functions.extend(vec![
+            // 序列函数
+            SqlCompletion::function("NEXTVAL()", "序列下一个值"),
+            SqlCompletion::function("LASTVAL()", "序列当前值"),
+            SqlCompletion::function("SETVAL()", "设置序列值"),
+
+            // JSON 函数（MariaDB 的 JSON 是 LONGTEXT 的别名）
+            SqlCompletion::function("JSON_TABLE()", "JSON 转表 (10.6+)"),
+            SqlCompletion::function("JSON_VALUE()", "提取标量值"),
+            SqlCompletion::function("JSON_QUERY()", "提取对象或数组"),
+            SqlCompletion::function("JSON_EXISTS()", "路径是否存在"),
+            SqlCompletion::function("JSON_DETAILED()", "格式化 JSON"),
+            SqlCompletion::function("JSON_COMPACT()", "压缩 JSON"),
+            SqlCompletion::function("JSON_LOOSE()", "宽松格式 JSON"),
+            SqlCompletion::function("JSON_NORMALIZE()", "规范化 JSON (10.7+)"),
+            SqlCompletion::function("JSON_EQUALS()", "JSON 相等 (10.7+)"),
+            SqlCompletion::function("JSON_ARRAYAGG()", "聚合为 JSON 数组"),
+            SqlCompletion::function("JSON_OBJECTAGG()", "聚合为 JSON 对象"),
+
+            // 动态列
+            SqlCompletion::function("COLUMN_CREATE()", "创建动态列"),
+            SqlCompletion::function("COLUMN_GET()", "读取动态列"),
+            SqlCompletion::function("COLUMN_ADD()", "添加动态列"),
+            SqlCompletion::function("COLUMN_DELETE()", "删除动态列"),
+            SqlCompletion::function("COLUMN_JSON()", "动态列转 JSON"),
+
+            // 其他
+            SqlCompletion::function("SYS_GUID()", "生成 GUID"),
+            SqlCompletion::function("REGEXP_REPLACE()", "正则替换"),
+            SqlCompletion::function("REGEXP_SUBSTR()", "正则截取"),
+            SqlCompletion::function("REGEXP_INSTR()", "正则查找位置"),
+            SqlCompletion::function("NATURAL_SORT_KEY()", "自然排序键 (10.7+)"),
+        ]);
+        functions
+    }
+
+    fn data_types(&self) -> Vec<SqlCompletion> {
+        let mut data_types = MySqlSyntax.data_types();
+        data_types.extend(vec![
+            SqlCompletion::data_type("INET6", "IPv6 地址 (10.5+)"),
+            SqlCompletion::data_type("INET4", "IPv4 地址 (10.10+)"),
+            SqlCompletion::data_type("UUID", "UUID (10.7+)"),
+            SqlCompletion::data_type("ROW", "行类型（存储过程）"),
+        ]);
+        data_types
+    }
+
+    fn operators(&self) -> Vec<SqlCompletion> {
+        MySqlSyntax.operators()
+    }
+
+    fn snippets(&self) -> Vec<SqlCompletion> {
+        let mut snippets = MySqlSyntax.snippets();
+        snippets.extend(vec![
+            SqlCompletion {
+                text: "CREATE SEQUENCE  START WITH 1 INCREMENT BY 1".to_string(),
+                description: "创建序列".to_string(),
+                kind: SqlCompletionKind::Snippet,
+            },
+            SqlCompletion {
+                text: "CREATE TABLE  (\n  id INT PRIMARY KEY,\n  \n) WITH SYSTEM VERSIONING".to_string(),
+                description: "创建系统版本表".to_string(),
+                kind: SqlCompletionKind::Snippet,
+            },
+            SqlCompletion {
+                text: "SELECT * FROM  FOR SYSTEM_TIME AS OF TIMESTAMP ''".to_string(),
+                description: "查询历史数据".to_string(),
+                kind: SqlCompletionKind::Snippet,
+            },
+            SqlCompletion {
+                text: "SELECT * FROM JSON_TABLE(, '$[*]' COLUMNS ( PATH '$.')) AS jt".to_string(),
+                description: "JSON 转表".to_string(),
+                kind: SqlCompletionKind::Snippet,
+            },
+        ]);
+        snippets
+    }
+}
@@ -1,11 +1,16 @@
 //! MySQL 语法定义
 
 use super::common::{SqlCompletion, SqlSyntax};
+use crate::sql::DatabaseType;
 
 /// MySQL 语法
 pub struct MySqlSyntax;
 
 impl SqlSyntax for MySqlSyntax {
+    fn db_type(&self) -> DatabaseType {
+        DatabaseType::MySQL
+    }
+    
     fn keywords(&self) -> Vec<SqlCompletion> {
         vec![
             // DML
@@ -1,11 +1,16 @@
 //! PostgreSQL 语法定义
 
 use super::common::{SqlCompletion, SqlSyntax};
+use crate::sql::DatabaseType;
 
 /// PostgreSQL 语法
 pub struct PostgreSqlSyntax;
 
 impl SqlSyntax for PostgreSqlSyntax {
+    fn db_type(&self) -> DatabaseType {
+        DatabaseType::PostgreSQL
+    }
+    
     fn keywords(&self) -> Vec<SqlCompletion> {
         vec![
             // DML
@@ -1,11 +1,16 @@
 //! SQLite 语法定义
 
 use super::common::{SqlCompletion, SqlSyntax};
+use crate::sql::DatabaseType;
 
 /// SQLite 语法
 pub struct SqliteSyntax;
 
 impl SqlSyntax for SqliteSyntax {
+    fn db_type(&self) -> DatabaseType {
+        DatabaseType::SQLite
+    }
+    
     fn keywords(&self) -> Vec<SqlCompletion> {
         vec![
             // DML
@@ -0,0 +1,109 @@
+//! 编辑器文件保存和交换文件测试
+
+use std::fs;
+use std::path::PathBuf;
+
+use cnmsb::editor::buffer::{Buffer, LineEnding};
+use cnmsb::editor::swap::SwapFile;
+
+/// 测试用的临时目录
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cnmsb_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_line_endings_round_trip() {
+    for content in ["a\nb\n", "a\nb", "a\r\nb\r\n", "a\r\n\r\nb", "", "\n"] {
+        assert_eq!(Buffer::from_text(content).to_text(), content, "{:?}", content);
+    }
+
+    let buffer = Buffer::from_text("one\r\ntwo\r\n");
+    assert_eq!(buffer.line_ending, LineEnding::CrLf);
+    assert!(buffer.trailing_newline);
+    assert_eq!(buffer.lines().collect::<Vec<_>>(), vec!["one", "two"]);
+
+    // 新行使用文件原来的换行符
+    let mut buffer = Buffer::from_text("a\r\nb");
+    buffer.insert_newline(1, 1);
+    buffer.append_to_line(2, "c");
+    assert_eq!(buffer.to_text(), "a\r\nb\r\nc");
+}
+
+#[test]
+fn test_atomic_save() {
+    let dir = temp_dir("save");
+    let path = dir.join("run.sh");
+    fs::write(&path, "echo 1\n").unwrap();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o751)).unwrap();
+    }
+
+    let mut buffer = Buffer::from_file(&path).unwrap();
+    buffer.append_to_line(0, "2");
+    buffer.save_to_file(&path).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "echo 12\n");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o751);
+
+        // 通过符号链接保存时替换它指向的文件
+        let link = dir.join("link.sh");
+        std::os::unix::fs::symlink(&path, &link).unwrap();
+        Buffer::from_text("echo 3\n").save_to_file(&link).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "echo 3\n");
+    }
+
+    // 不留下临时文件
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), if cfg!(unix) { 2 } else { 1 });
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_swap_file() {
+    let dir = temp_dir("swap");
+    let file = dir.join("a.sh");
+    let swap_dir = dir.join("swap");
+
+    let mut buffer = Buffer::from_text("echo a\n");
+    let mut swap = SwapFile::new(&swap_dir, &file);
+    assert!(!swap.is_foreign());
+    assert!(swap.read().is_none());
+    assert_eq!(swap.pending(&buffer), 0);
+
+    buffer.append_to_line(0, "b");
+    buffer.insert_newline(0, 7);
+    assert_eq!(swap.pending(&buffer), 2);
+    swap.write(&buffer).unwrap();
+    assert_eq!(swap.pending(&buffer), 0);
+    assert!(swap.path().starts_with(&swap_dir));
+
+    let recovered = swap.read().unwrap();
+    assert_eq!(recovered.pid, std::process::id());
+    assert_eq!(recovered.text, "echo ab\n");
+    // 自己写的交换文件不算另一个编辑器
+    assert!(!recovered.is_running());
+
+    // 再次打开时发现已有的交换文件，恢复或删除之前不会删掉它
+    let mut other = SwapFile::new(&swap_dir, &file);
+    assert!(other.is_foreign());
+    other.remove();
+    assert!(other.path().exists());
+    other.discard().unwrap();
+    assert!(!other.is_foreign());
+    assert!(!swap.path().exists());
+
+    // 删除自己写的交换文件
+    swap.write(&buffer).unwrap();
+    swap.remove();
+    assert!(!swap.path().exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,136 @@
+//! Normal 模式命令解析、范围和寄存器测试
+
+use cnmsb::editor::buffer::Buffer;
+use cnmsb::editor::normal::{self, Action, Command, Motion, Operator, Parse, Range, Target, TextObject};
+use cnmsb::editor::register::{Register, Registers};
+
+fn buffer(content: &str) -> Buffer {
+    let mut buffer = Buffer::new();
+    buffer.insert_text(0, 0, content);
+    buffer
+}
+
+fn complete(keys: &str) -> Command {
+    match normal::parse(keys) {
+        Parse::Complete(command) => command,
+        other => panic!("{:?} 解析为 {:?}", keys, other),
+    }
+}
+
+fn range(buffer: &Buffer, pos: (usize, usize), keys: &str) -> Option<Range> {
+    let command = complete(keys);
+    match command.action {
+        Action::Operate(op, target) => normal::target_range(buffer, pos, op, target, command.count),
+        other => panic!("不是操作符: {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_commands() {
+    for keys in ["d", "2d", "\"", "\"a", "\"a3", "g", "dg", "di", "c2"] {
+        assert_eq!(normal::parse(keys), Parse::Pending, "{}", keys);
+    }
+    for keys in ["z", "dz", "\"!", "diz", "gx"] {
+        assert_eq!(normal::parse(keys), Parse::Invalid, "{}", keys);
+    }
+
+    // 操作符前后的次数相乘，0 不是次数
+    let command = complete("\"a2d3w");
+    assert_eq!(command.register, Some('a'));
+    assert_eq!(command.count, Some(6));
+    assert_eq!(command.action, Action::Operate(Operator::Delete, Target::Motion(Motion::WordForward)));
+    assert_eq!(complete("0").action, Action::Move(Motion::LineStart));
+    assert_eq!(complete("10j").count, Some(10));
+    assert_eq!(complete("yy").action, Action::Operate(Operator::Yank, Target::Line));
+    assert_eq!(complete("ci\"").action, Action::Operate(Operator::Change, Target::Object(TextObject::InnerQuote('"'))));
+    assert_eq!(complete(">gg").action, Action::Operate(Operator::Indent, Target::Motion(Motion::FirstLine)));
+    assert_eq!(complete("x").action, Action::Operate(Operator::Delete, Target::Motion(Motion::Right)));
+    assert_eq!(complete("gcc").action, Action::Operate(Operator::Comment, Target::Line));
+    assert_eq!(complete("gcj").action, Action::Operate(Operator::Comment, Target::Motion(Motion::Down)));
+
+    assert!(complete("P").is_change());
+    assert!(!complete("yw").is_change());
+    assert!(!complete("gg").is_change());
+}
+
+#[test]
+fn test_motions_and_ranges() {
+    let buf = buffer("echo hello-world\n  foo bar\nlast");
+    assert_eq!(normal::motion_target(&buf, (0, 0), Motion::WordEnd, None), (0, 3));
+    assert_eq!(normal::motion_target(&buf, (0, 0), Motion::WordEnd, Some(2)), (0, 15));
+    assert_eq!(normal::motion_target(&buf, (0, 5), Motion::LastLine, None), (2, 0));
+    assert_eq!(normal::motion_target(&buf, (0, 5), Motion::LastLine, Some(2)), (1, 2));
+    assert_eq!(normal::motion_target(&buf, (2, 2), Motion::FirstLine, None), (0, 0));
+
+    // dw 到行尾为止，不删换行；cw 与 ce 相同；de 包含最后一个字符
+    assert_eq!(range(&buf, (0, 5), "dw").map(|r| r.end), Some((0, 16)));
+    assert_eq!(range(&buf, (0, 0), "cw").map(|r| r.end), Some((0, 4)));
+    assert_eq!(range(&buf, (0, 0), "dw").map(|r| r.end), Some((0, 5)));
+    assert_eq!(range(&buf, (0, 0), "de").map(|r| r.end), Some((0, 4)));
+    assert_eq!(range(&buf, (1, 6), "db").map(|r| (r.start, r.end)), Some(((1, 2), (1, 6))));
+
+    // 整行
+    let lines = range(&buf, (1, 3), "3dd").unwrap();
+    assert!(lines.linewise);
+    assert_eq!((lines.start.0, lines.end.0), (1, 2));
+    assert_eq!(range(&buf, (2, 0), "dk").map(|r| (r.start.0, r.end.0)), Some((1, 2)));
+
+    // 很大的次数不溢出，到文件结尾为止
+    let huge = "99999999999999999999";
+    let lines = range(&buf, (1, 0), &format!("{}dd", huge)).unwrap();
+    assert_eq!((lines.start.0, lines.end.0), (1, 2));
+    assert_eq!(range(&buf, (0, 0), &format!("2d{}j", huge)).map(|r| (r.start.0, r.end.0)), Some((0, 2)));
+    assert_eq!(normal::motion_target(&buf, (0, 0), Motion::LineEnd, Some(usize::MAX)), (2, 4));
+    assert_eq!(normal::motion_target(&buf, (0, 0), Motion::Right, Some(usize::MAX)), (0, 16));
+    assert_eq!(normal::motion_target(&buf, (0, 0), Motion::WordForward, Some(usize::MAX)).0, 2);
+
+    // 文本对象
+    assert_eq!(range(&buf, (0, 8), "diw").map(|r| (r.start, r.end)), Some(((0, 5), (0, 10))));
+    assert_eq!(range(&buf, (0, 10), "yiw").map(|r| (r.start, r.end)), Some(((0, 10), (0, 11))));
+    let quoted = buffer("say \"你好 \\\" x\" and 'y'");
+    assert_eq!(range(&quoted, (0, 0), "di\"").map(|r| (r.start, r.end)), Some(((0, 5), (0, 12))));
+    assert_eq!(range(&quoted, (0, 20), "ci'").map(|r| (r.start, r.end)), Some(((0, 19), (0, 20))));
+    assert_eq!(range(&quoted, (0, 0), "di`"), None);
+}
+
+#[test]
+fn test_buffer_ranges() {
+    let mut buf = buffer("one two\nthree\nfour");
+    assert_eq!(buf.text_range((0, 4), (2, 2)), "two\nthree\nfo");
+    assert_eq!(buf.delete_range((0, 3), (1, 0)), " two\n");
+    assert_eq!(buf.lines().collect::<Vec<_>>(), ["onethree", "four"]);
+
+    assert_eq!(buf.delete_lines(1, 1), "four\n");
+    assert_eq!(buf.lines().collect::<Vec<_>>(), ["onethree"]);
+    assert_eq!(buf.delete_lines(0, 0), "onethree\n");
+    assert_eq!(buf.line_count(), 1);
+    assert_eq!(buf.get_line(0), "");
+
+    assert_eq!(buf.insert_text(0, 0, "a\nbc"), (1, 2));
+    buf.undo();
+    buf.undo();
+    assert_eq!(buf.get_line(0), "onethree");
+}
+
+#[test]
+fn test_registers() {
+    let mut registers = Registers::new();
+    assert!(registers.get(None).is_none());
+
+    registers.yank(None, Register::new("abc", false));
+    registers.delete(None, Register::new("line\n", true));
+    assert_eq!(registers.get(None).unwrap().text, "line\n");
+    assert_eq!(registers.get(Some('0')).unwrap().text, "abc");
+    assert_eq!(registers.get(Some('1')).unwrap().text, "line\n");
+    registers.delete(None, Register::new("x", false));
+    assert_eq!(registers.get(Some('2')).unwrap().text, "line\n");
+
+    // 命名寄存器，大写追加，_ 丢弃
+    registers.yank(Some('a'), Register::new("foo", false));
+    registers.yank(Some('A'), Register::new("bar", false));
+    assert_eq!(registers.get(Some('a')).unwrap().text, "foobar");
+    assert_eq!(registers.get(None).unwrap().text, "foobar");
+    registers.delete(Some('_'), Register::new("gone", false));
+    assert_eq!(registers.get(None).unwrap().text, "foobar");
+    assert_eq!(registers.get(Some('0')).unwrap().text, "abc");
+}
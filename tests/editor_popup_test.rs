@@ -0,0 +1,59 @@
+//! 编辑器补全菜单测试
+
+use cnmsb::editor::buffer::Buffer;
+use cnmsb::editor::popup::{fuzzy_filter, Kind, Popup, PopupItem};
+use cnmsb::editor::{Completer, HistoryManager};
+
+fn texts(items: &[PopupItem]) -> Vec<&str> {
+    items.iter().map(|i| i.text.as_str()).collect()
+}
+
+#[test]
+fn test_fuzzy_filter() {
+    let items = vec![
+        PopupItem::new("content_length", Kind::Variable, ""),
+        PopupItem::new("count", Kind::Word, ""),
+        PopupItem::new("Config", Kind::Word, ""),
+        PopupItem::new("config", Kind::Word, ""),
+        PopupItem::new("count", Kind::Function, ""),
+        PopupItem::new("socket", Kind::Word, ""),
+        PopupItem::new("co", Kind::Word, ""),
+    ];
+
+    // 前缀匹配不区分大小写，保持原来的顺序；去掉重复的和与输入相同的
+    let filtered = fuzzy_filter("co", items.clone());
+    assert_eq!(texts(&filtered), vec!["content_length", "count", "Config", "config"]);
+    assert_eq!(filtered[1].label, Kind::Word.label());
+
+    // 继续输入时模糊匹配，首字母必须相同
+    assert_eq!(texts(&fuzzy_filter("cnt", items.clone())), vec!["content_length", "count"]);
+    let mut matched = texts(&fuzzy_filter("cfg", items.clone())).into_iter().map(str::to_lowercase).collect::<Vec<_>>();
+    matched.dedup();
+    assert_eq!(matched, vec!["config"]);
+    assert!(fuzzy_filter("xyz", items).is_empty());
+}
+
+#[test]
+fn test_popup_cycle() {
+    let items = vec![PopupItem::new("alpha", Kind::Word, ""), PopupItem::new("alpine", Kind::Word, "")];
+    let mut popup = Popup::new("al", items).unwrap();
+    assert_eq!(popup.typed(), "al");
+
+    // Tab 填入选中的一项之后，光标前是这一项
+    popup.inserted = true;
+    assert_eq!(popup.typed(), "alpha");
+    popup.select(true);
+    assert_eq!(popup.typed(), "alpine");
+}
+
+#[test]
+fn test_completer_words() {
+    let mut completer = Completer::new();
+    completer.build_from_buffer(&Buffer::from_text("zebra_stripes zebra_stripes zorro\n"), &HistoryManager::default());
+    let words = completer.words();
+    // 当前文件的词在预装的常用词之前
+    let zebra = words.iter().position(|w| w == "zebra_stripes").unwrap();
+    let config = words.iter().position(|w| w == "config").unwrap();
+    assert!(zebra < config);
+    assert!(words.iter().any(|w| w == "zorro"));
+}
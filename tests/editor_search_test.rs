@@ -0,0 +1,89 @@
+//! 编辑器搜索与替换测试
+
+use cnmsb::editor::buffer::Buffer;
+use cnmsb::editor::search::{self, Match, Substitute};
+
+fn text(buffer: &Buffer) -> String {
+    buffer.lines().collect::<Vec<_>>().join("\n")
+}
+
+fn substitute(buffer: &mut Buffer, cmd: &str, cursor_row: usize) -> Result<usize, String> {
+    let mut sub = Substitute::parse(cmd, buffer, cursor_row, None, None).expect("不是替换命令")?;
+    sub.run(buffer);
+    Ok(sub.count)
+}
+
+#[test]
+fn test_find_wraps_around() {
+    let buffer = Buffer::from_text("foo bar\nbaz foo\nqux");
+    let regex = search::compile("foo", false).unwrap();
+
+    let (m, wrapped) = search::find(&buffer, &regex, 0, 0, true).unwrap();
+    assert_eq!((m, wrapped), (Match { row: 1, start: 4, end: 7 }, false));
+    let (m, wrapped) = search::find(&buffer, &regex, 1, 4, true).unwrap();
+    assert_eq!((m.row, m.start, wrapped), (0, 0, true));
+
+    // 向后搜索
+    let (m, wrapped) = search::find(&buffer, &regex, 1, 4, false).unwrap();
+    assert_eq!((m.row, m.start, wrapped), (0, 0, false));
+    let (m, wrapped) = search::find(&buffer, &regex, 0, 0, false).unwrap();
+    assert_eq!((m.row, m.start, wrapped), (1, 4, true));
+
+    // \c 忽略大小写，无效模式报错
+    let regex = search::compile("\\cQUX", false).unwrap();
+    assert_eq!(search::find(&buffer, &regex, 0, 0, true).unwrap().0.row, 2);
+    assert!(search::compile("(", false).is_err());
+}
+
+#[test]
+fn test_substitute_ranges_and_flags() {
+    let mut buf = Buffer::from_text("a a\na a\na a\na a");
+    assert_eq!(substitute(&mut buf, "s/a/b/", 1), Ok(1));
+    assert_eq!(text(&buf), "a a\nb a\na a\na a");
+    assert_eq!(substitute(&mut buf, "3,$s/a/c/g", 0), Ok(4));
+    assert_eq!(text(&buf), "a a\nb a\nc c\nc c");
+    assert_eq!(substitute(&mut buf, ".,+1s#a#[&]#", 0), Ok(2));
+    assert_eq!(text(&buf), "[a] a\nb [a]\nc c\nc c");
+    assert_eq!(substitute(&mut buf, "%s/C/d/gi", 0), Ok(4));
+    assert_eq!(text(&buf), "[a] a\nb [a]\nd d\nd d");
+
+    // 分组、换行和空匹配
+    let mut buf = Buffer::from_text("key=value\nabc");
+    assert_eq!(substitute(&mut buf, "1s/(\\w+)=(\\w+)/\\2=\\1\\n/", 0), Ok(1));
+    assert_eq!(text(&buf), "value=key\n\nabc");
+    assert_eq!(substitute(&mut buf, "$s/x*/-/g", 0), Ok(4));
+    assert_eq!(text(&buf), "value=key\n\n-a-b-c-");
+
+    // 不是替换命令，或参数有误
+    assert!(Substitute::parse("set", &buf, 0, None, None).is_none());
+    assert!(Substitute::parse("wq", &buf, 0, None, None).is_none());
+    assert!(Substitute::parse("s/a/b/z", &buf, 0, None, None).unwrap().is_err());
+    assert!(Substitute::parse("5,9s/a/b/", &buf, 0, None, None).unwrap().is_err());
+    assert!(Substitute::parse("s//b/", &buf, 0, None, None).unwrap().is_err());
+}
+
+#[test]
+fn test_confirm_and_undo() {
+    let mut buf = Buffer::from_text("x x x");
+    let last = search::compile("x", false).unwrap();
+    // 空模式使用上一次搜索的模式
+    let mut sub = Substitute::parse("s//y/gc", &buf, 0, None, Some(&last)).unwrap().unwrap();
+    assert!(sub.confirm);
+
+    buf.begin_undo_group();
+    let m = sub.next_match(&buf).unwrap();
+    sub.replace(&mut buf, m);
+    let m = sub.next_match(&buf).unwrap();
+    assert_eq!(m.start, 2);
+    sub.skip(m);
+    let m = sub.next_match(&buf).unwrap();
+    sub.replace(&mut buf, m);
+    assert!(sub.next_match(&buf).is_none());
+    buf.end_undo_group();
+    assert_eq!(text(&buf), "y x y");
+    assert_eq!(sub.count, 2);
+
+    // 整个替换是一步撤销
+    buf.undo();
+    assert_eq!(text(&buf), "x x x");
+}
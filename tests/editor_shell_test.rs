@@ -0,0 +1,62 @@
+//! 编辑器中 shell 脚本命令补全测试
+
+use cnmsb::editor::buffer::Buffer;
+use cnmsb::editor::popup::{Popup, PopupItem};
+use cnmsb::editor::shell::{command_at, current_command, current_word};
+
+fn item(text: &str) -> PopupItem {
+    PopupItem { text: text.to_string(), label: "选项", description: String::new() }
+}
+
+#[test]
+fn test_current_command() {
+    assert_eq!(current_command("tar -"), Some("tar -"));
+    assert_eq!(current_command("    git che"), Some("git che"));
+    assert_eq!(current_command("cd /tmp && git che"), Some("git che"));
+    assert_eq!(current_command("cat a.txt | grep -"), Some("grep -"));
+    assert_eq!(current_command("x=$(git rev"), Some("git rev"));
+    assert_eq!(current_command("if true; then git che"), Some("git che"));
+    assert_eq!(current_command("while read line; do ech"), Some("ech"));
+    assert_eq!(current_command("LANG=C FOO=1 sort -"), Some("sort -"));
+
+    // 正在输入的关键字和赋值保留
+    assert_eq!(current_command("the"), Some("the"));
+    assert_eq!(current_command("FOO=ba"), Some("FOO=ba"));
+
+    // 注释和引号中不补全，引号中的分隔符不算
+    assert_eq!(current_command("# tar -"), None);
+    assert_eq!(current_command("ls # tar -"), None);
+    assert_eq!(current_command("echo \"a; tar -"), None);
+    assert_eq!(current_command("echo 'a;b' && tar -"), Some("tar -"));
+    assert_eq!(current_command("echo a#b; git che"), Some("git che"));
+
+    assert_eq!(current_word("git che"), "che");
+    assert_eq!(current_word("git "), "");
+}
+
+#[test]
+fn test_command_at_continuation() {
+    let buffer = Buffer::from_text("tar \\\n    -c \\\n    --ex\nls -");
+    assert_eq!(command_at(&buffer, 2, 8).as_deref(), Some("tar      -c      --ex"));
+    assert_eq!(command_at(&buffer, 3, 4).as_deref(), Some("ls -"));
+}
+
+#[test]
+fn test_popup_selection() {
+    assert!(Popup::new("-", Vec::new()).is_none());
+
+    let mut popup = Popup::new("-", vec![item("-a"), item("-b"), item("-c")]).unwrap();
+    assert_eq!(popup.current().text, "-a");
+    assert_eq!(popup.visible(2), 0..2);
+
+    // 选中的一项总在显示范围内，到头时回到另一端
+    popup.select(true);
+    popup.select(true);
+    assert_eq!(popup.current().text, "-c");
+    assert_eq!(popup.visible(2), 1..3);
+    popup.select(true);
+    assert_eq!(popup.current().text, "-a");
+    popup.select(false);
+    assert_eq!(popup.current().text, "-c");
+    assert_eq!(popup.visible(8), 0..3);
+}
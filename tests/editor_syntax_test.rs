@@ -0,0 +1,141 @@
+//! 编辑器语法高亮测试
+
+use std::fs;
+use std::path::Path;
+
+use cnmsb::editor::buffer::Buffer;
+use cnmsb::editor::syntax::{Highlighter, Language, Languages, State, TokenKind};
+
+/// 一行中的记号文本和类别
+fn tokens<'a>(language: &Language, line: &'a str) -> Vec<(&'a str, TokenKind)> {
+    let (tokens, _) = language.tokenize(line, State::Normal);
+    tokens.iter().map(|t| (&line[t.start..t.end], t.kind)).collect()
+}
+
+#[test]
+fn test_tokenize_languages() {
+    let languages = Languages::builtin();
+
+    let shell = languages.get("shell").unwrap();
+    assert_eq!(tokens(shell, r#"if [ -n "$HOME" ]; then echo ${PATH} $1 # 注释"#), vec![
+        ("if", TokenKind::Keyword),
+        ("\"$HOME\"", TokenKind::String),
+        ("then", TokenKind::Keyword),
+        ("echo", TokenKind::Builtin),
+        ("${PATH}", TokenKind::Variable),
+        ("$1", TokenKind::Variable),
+        ("# 注释", TokenKind::Comment),
+    ]);
+    // 单词中间的 # 不是注释
+    assert_eq!(tokens(shell, "echo a#b"), vec![("echo", TokenKind::Builtin)]);
+
+    let python = languages.get("python").unwrap();
+    assert_eq!(tokens(python, "def f(x=10): return 'a' # c"), vec![
+        ("def", TokenKind::Keyword),
+        ("10", TokenKind::Number),
+        ("return", TokenKind::Keyword),
+        ("'a'", TokenKind::String),
+        ("# c", TokenKind::Comment),
+    ]);
+
+    let rust = languages.get("rust").unwrap();
+    assert_eq!(tokens(rust, r#"let s: String = "a\"b"; /* x */ // y"#), vec![
+        ("let", TokenKind::Keyword),
+        ("String", TokenKind::Builtin),
+        ("\"a\\\"b\"", TokenKind::String),
+        ("/* x */", TokenKind::Comment),
+        ("// y", TokenKind::Comment),
+    ]);
+
+    let yaml = languages.get("yaml").unwrap();
+    assert_eq!(tokens(yaml, "  - name: true # c"), vec![
+        ("name", TokenKind::Key),
+        ("true", TokenKind::Builtin),
+        ("# c", TokenKind::Comment),
+    ]);
+
+    // Dockerfile 指令不区分大小写
+    let dockerfile = languages.get("dockerfile").unwrap();
+    assert_eq!(tokens(dockerfile, "from alpine AS build"), vec![
+        ("from", TokenKind::Keyword),
+        ("AS", TokenKind::Keyword),
+    ]);
+}
+
+#[test]
+fn test_multiline_state_and_incremental_update() {
+    let languages = Languages::builtin();
+    let mut buffer = Buffer::from_text("x = 1\ns = \"\"\"\ndef\n\"\"\"\ny = 2");
+    let mut highlighter = Highlighter::new(languages.get("python").unwrap().clone());
+
+    assert_eq!(highlighter.update(&buffer, 100), 5);
+    // 多行字符串中的关键字不高亮
+    assert_eq!(highlighter.tokens(2)[0].kind, TokenKind::String);
+    assert_eq!(highlighter.tokens(4)[0].kind, TokenKind::Number);
+
+    // 没有修改时不重新分析
+    assert_eq!(highlighter.update(&buffer, 100), 0);
+
+    // 修改一行只分析这一行
+    buffer.append_to_line(0, "0");
+    assert_eq!(highlighter.update(&buffer, 100), 1);
+
+    // 插入一行只分析新行，之后的行不受影响
+    buffer.insert_newline(0, buffer.line_len(0));
+    assert_eq!(highlighter.update(&buffer, 100), 1);
+    assert_eq!(highlighter.tokens(3)[0].kind, TokenKind::String);
+
+    // 删掉字符串的开头，之后的行都要重新分析
+    buffer.delete_line(2);
+    assert_eq!(highlighter.update(&buffer, 100), 3);
+    assert_eq!(highlighter.tokens(2)[0].kind, TokenKind::Keyword);
+
+    // 只分析到指定的行
+    let mut highlighter = Highlighter::new(languages.get("python").unwrap().clone());
+    assert_eq!(highlighter.update(&buffer, 1), 2);
+}
+
+#[test]
+fn test_detect_and_user_rules() {
+    let mut languages = Languages::builtin();
+    let detect = |languages: &Languages, path: &str, first_line: &str| {
+        languages.detect(Path::new(path), first_line).map(|l| l.name.clone())
+    };
+    assert_eq!(detect(&languages, "deploy.SH", "").as_deref(), Some("shell"));
+    assert_eq!(detect(&languages, "/tmp/Dockerfile", "").as_deref(), Some("dockerfile"));
+    assert_eq!(detect(&languages, "ci.yml", "").as_deref(), Some("yaml"));
+    assert_eq!(detect(&languages, "run", "#!/usr/bin/env python3").as_deref(), Some("python"));
+    assert_eq!(detect(&languages, "run", "#!/bin/bash -e").as_deref(), Some("shell"));
+    assert_eq!(detect(&languages, "notes.txt", "hello"), None);
+
+    let dir = std::env::temp_dir().join(format!("cnmsb_syntax_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("toml.toml"), "name = \"toml\"\nextensions = [\"toml\"]\nkeywords = [\"true\"]\nline_comment = [\"#\"]\nkeys = true\n").unwrap();
+    // 同名规则覆盖内置的 shell
+    fs::write(dir.join("shell.toml"), "name = \"shell\"\nextensions = [\"sh\"]\nkeywords = [\"echo\"]\n").unwrap();
+    fs::write(dir.join("bad.toml"), "name = \"bad\"\nunknown = 1\n").unwrap();
+    let errors = languages.load_dir(&dir);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("bad.toml"));
+    assert_eq!(detect(&languages, "Cargo.toml", "").as_deref(), Some("toml"));
+    let shell = languages.get("shell").unwrap();
+    assert_eq!(tokens(shell, "echo $HOME"), vec![("echo", TokenKind::Keyword)]);
+    // 覆盖后只剩用户规则中的扩展名
+    assert_eq!(detect(&languages, "a.bash", ""), None);
+}
+
+#[test]
+fn test_reject_empty_delimiters() {
+    for rules in [
+        "name = \"x\"\nstrings = [\"\\\"\", \"\"]",
+        "name = \"x\"\nmultiline_strings = [\"\"]",
+        "name = \"x\"\nline_comment = [\"\"]",
+        "name = \"x\"\nblock_comment = [\"/*\", \"\"]",
+        "name = \"x\"\nblock_comment = [\"\", \"*/\"]",
+    ] {
+        assert!(Language::from_toml(rules).is_err(), "{}", rules);
+    }
+    assert!(Language::from_toml("name = \"x\"\nblock_comment = [\"/*\", \"*/\"]").is_ok());
+}
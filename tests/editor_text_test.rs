@@ -0,0 +1,95 @@
+//! 编辑器 Unicode 处理测试（中文、emoji、组合字符）
+
+use cnmsb::editor::buffer::Buffer;
+use cnmsb::editor::cursor::Cursor;
+use cnmsb::editor::text;
+
+#[test]
+fn test_buffer_columns_are_graphemes() {
+    let mut buffer = Buffer::new();
+    let mut col = 0;
+    for c in "你好👨‍👩‍👧e\u{301}!".chars() {
+        col = buffer.insert_char(0, col, c);
+    }
+    // 家庭 emoji 由 5 个码点组成，é 由 2 个码点组成，组合字符不新增列
+    assert_eq!(col, 5);
+    assert_eq!(buffer.line_len(0), 5);
+    assert_eq!(buffer.grapheme(0, 2), Some("👨‍👩‍👧"));
+    assert_eq!(buffer.prefix(0, 2), "你好");
+    assert_eq!(buffer.display_col(0, 3), 6);
+
+    // 删除和换行都按字素簇
+    assert!(buffer.delete_char(0, 2));
+    assert_eq!(buffer.get_line(0), "你好e\u{301}!");
+    buffer.insert_newline(0, 1);
+    assert_eq!(buffer.get_line(0), "你");
+    assert_eq!(buffer.get_line(1), "好e\u{301}!");
+    assert!(!buffer.delete_char(1, 3));
+
+    // 撤销后光标列也以字素簇计
+    assert_eq!(buffer.undo(), Some((0, 1)));
+    assert_eq!(buffer.undo(), Some((0, 2)));
+    assert_eq!(buffer.get_line(0), "你好👨‍👩‍👧e\u{301}!");
+}
+
+#[test]
+fn test_cursor_keeps_display_column() {
+    let mut buffer = Buffer::new();
+    buffer.append_to_line(0, "ab中文cd");
+    buffer.insert_newline(0, 6);
+    buffer.append_to_line(1, "一二三四");
+    buffer.insert_newline(1, 4);
+    buffer.append_to_line(2, "abcdefgh");
+
+    let mut cursor = Cursor::new();
+    for _ in 0..3 {
+        cursor.move_right(&buffer);
+    }
+    // "ab中" 占 4 列，下一行第 4 列是 "三"，再下一行是 "e"
+    cursor.move_down(&buffer);
+    assert_eq!(cursor.col, 2);
+    cursor.move_down(&buffer);
+    assert_eq!(cursor.col, 4);
+    cursor.move_up(&buffer);
+    cursor.move_up(&buffer);
+    assert_eq!(cursor.col, 3);
+
+    // 单词移动跳过中文全角空格
+    let mut cursor = Cursor::new();
+    let mut buffer = Buffer::new();
+    buffer.append_to_line(0, "中文\u{3000}单词 x");
+    cursor.move_word_forward(&buffer);
+    assert_eq!(cursor.col, 3);
+    cursor.move_word_forward(&buffer);
+    assert_eq!(cursor.col, 6);
+    cursor.move_word_backward(&buffer);
+    assert_eq!(cursor.col, 3);
+}
+
+#[test]
+fn test_width_and_clip() {
+    assert_eq!(text::str_width("中a"), 3);
+    assert_eq!(text::str_width("\tx"), text::TAB_WIDTH + 1);
+    assert_eq!(text::col_at_width("中文", 1), 0);
+    assert_eq!(text::col_at_width("中文", 2), 1);
+
+    // 被左右边界切开的宽字符换成空格，制表符展开
+    assert_eq!(text::clip("中文ab", 0, 1, 4), (" 文a".to_string(), true));
+    assert_eq!(text::clip("a中文", 0, 0, 4), ("a中 ".to_string(), true));
+    assert_eq!(text::clip("a\tb", 0, 0, 10), ("a   b".to_string(), false));
+    assert_eq!(text::clip("b", 3, 0, 10), ("b".to_string(), false));
+}
+
+#[test]
+fn test_word_before_multibyte_delimiter() {
+    // 插入模式中逐个输入，每次按光标前的文本取正在输入的词
+    let mut buffer = Buffer::new();
+    let mut col = 0;
+    let mut words = Vec::new();
+    for c in "中文\u{3000}ab".chars() {
+        col = buffer.insert_char(0, col, c);
+        words.push(text::word_before(buffer.prefix(0, col), char::is_whitespace).to_string());
+    }
+    assert_eq!(words, vec!["中", "中文", "", "a", "ab"]);
+    assert_eq!(text::word_before("f(x", |c| c == '('), "x");
+}
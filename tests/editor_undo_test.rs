@@ -0,0 +1,111 @@
+//! 编辑器撤销/重做测试
+
+use cnmsb::editor::buffer::Buffer;
+
+fn text(buffer: &Buffer) -> String {
+    buffer.lines().collect::<Vec<_>>().join("\n")
+}
+
+#[test]
+fn test_insert_run_is_one_step() {
+    let mut buffer = Buffer::new();
+    buffer.begin_undo_group();
+    for (i, c) in "echo".chars().enumerate() {
+        buffer.insert_char(0, i, c);
+    }
+    buffer.insert_newline(0, 4);
+    buffer.insert_char(1, 0, 'x');
+    buffer.end_undo_group();
+    buffer.delete_char(0, 0);
+    assert_eq!(text(&buffer), "cho\nx");
+
+    // 单独的删除是一步，整段插入是一步
+    assert_eq!(buffer.undo(), Some((0, 0)));
+    assert_eq!(text(&buffer), "echo\nx");
+    assert_eq!(buffer.undo(), Some((0, 0)));
+    assert_eq!(text(&buffer), "");
+    assert_eq!(buffer.undo(), None);
+
+    assert_eq!(buffer.redo(), Some((0, 0)));
+    assert_eq!(text(&buffer), "echo\nx");
+    assert_eq!(buffer.redo(), Some((0, 0)));
+    assert_eq!(text(&buffer), "cho\nx");
+    assert_eq!(buffer.redo(), None);
+}
+
+#[test]
+fn test_line_edits_and_redo_cleared() {
+    let mut buffer = Buffer::new();
+    buffer.append_to_line(0, "a");
+    buffer.insert_newline(0, 1);
+    buffer.append_to_line(1, "b");
+    buffer.insert_line_above(0);
+    assert_eq!(text(&buffer), "\na\nb");
+
+    // 删除最后一行也能恢复
+    buffer.delete_line(2);
+    assert_eq!(text(&buffer), "\na");
+    buffer.undo();
+    assert_eq!(text(&buffer), "\na\nb");
+    buffer.delete_line(0);
+    assert_eq!(text(&buffer), "a\nb");
+    buffer.undo();
+    buffer.undo();
+    assert_eq!(text(&buffer), "a\nb");
+
+    // 新的修改清空重做历史
+    buffer.insert_char(0, 0, '#');
+    assert_eq!(buffer.redo(), None);
+    assert_eq!(text(&buffer), "#a\nb");
+}
+
+#[test]
+fn test_modified_survives_save() {
+    let mut buffer = Buffer::new();
+    buffer.insert_char(0, 0, 'a');
+    buffer.mark_saved();
+    assert!(!buffer.is_modified());
+
+    buffer.insert_char(0, 1, 'b');
+    assert!(buffer.is_modified());
+    // 撤销回到保存时的内容不算修改，再撤销到保存之前又算修改
+    buffer.undo();
+    assert!(!buffer.is_modified());
+    buffer.undo();
+    assert!(buffer.is_modified());
+    assert_eq!(text(&buffer), "");
+    buffer.redo();
+    assert!(!buffer.is_modified());
+}
+
+#[test]
+fn test_save_keeps_insert_group() {
+    let mut buffer = Buffer::new();
+    buffer.begin_undo_group();
+    buffer.insert_char(0, 0, 'a');
+    buffer.insert_char(0, 1, 'b');
+    // 插入模式中保存，之后的输入仍和之前的合并为一步
+    buffer.mark_saved();
+    assert!(!buffer.is_modified());
+    buffer.insert_char(0, 2, 'c');
+    assert!(buffer.is_modified());
+    buffer.end_undo_group();
+
+    assert_eq!(buffer.undo(), Some((0, 0)));
+    assert_eq!(text(&buffer), "");
+    assert!(buffer.is_modified());
+    buffer.redo();
+    assert_eq!(text(&buffer), "abc");
+
+    // 保存后没有再输入，结束这一步时保存的就是这一步
+    let mut buffer = Buffer::new();
+    buffer.begin_undo_group();
+    buffer.insert_char(0, 0, 'a');
+    buffer.mark_saved();
+    buffer.end_undo_group();
+    assert!(!buffer.is_modified());
+    buffer.undo();
+    assert!(buffer.is_modified());
+    buffer.redo();
+    assert!(!buffer.is_modified());
+}
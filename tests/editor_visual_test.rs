@@ -0,0 +1,119 @@
+//! 可视模式选择、块插入、注释切换和外部命令过滤测试
+
+use cnmsb::editor::buffer::Buffer;
+use cnmsb::editor::filter::{self, Filter};
+use cnmsb::editor::normal::{Motion, Operator, Range};
+use cnmsb::editor::search::Substitute;
+use cnmsb::editor::visual::{self, Action, BlockInsert, Parse, Selection, Shape};
+
+fn buffer(content: &str) -> Buffer {
+    let mut buffer = Buffer::new();
+    buffer.insert_text(0, 0, content);
+    buffer
+}
+
+fn text(buffer: &Buffer) -> String {
+    buffer.lines().collect::<Vec<_>>().join("\n")
+}
+
+fn selection(anchor: (usize, usize), cursor: (usize, usize), shape: Shape) -> Selection {
+    Selection { anchor, cursor, shape }
+}
+
+#[test]
+fn test_parse_visual_commands() {
+    assert_eq!(visual::parse("g"), Parse::Pending);
+    assert_eq!(visual::parse("\"a"), Parse::Pending);
+    assert_eq!(visual::parse("z"), Parse::Invalid);
+
+    let action = |keys: &str| match visual::parse(keys) {
+        Parse::Complete(command) => command.action,
+        other => panic!("{:?} 解析为 {:?}", keys, other),
+    };
+    assert_eq!(action("x"), Action::Operate(Operator::Delete));
+    assert_eq!(action("gc"), Action::Operate(Operator::Comment));
+    assert_eq!(action("gg"), Action::Move(Motion::FirstLine));
+    assert_eq!(action("V"), Action::Switch(Shape::Lines));
+    assert_eq!(action("o"), Action::SwapEnds);
+    match visual::parse("\"b3>") {
+        Parse::Complete(command) => assert_eq!((command.register, command.count), (Some('b'), Some(3))),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn test_selection_ranges() {
+    let buf = buffer("hello world\nfoo\nbar baz");
+
+    // 按字符选择包含两端，反向选择相同
+    let sel = selection((1, 2), (0, 6), Shape::Chars);
+    assert_eq!(sel.range(&buf), Range { start: (0, 6), end: (1, 3), linewise: false });
+    assert_eq!(sel.columns(&buf, 0), Some((6, 11)));
+    assert_eq!(sel.columns(&buf, 1), Some((0, 3)));
+    assert_eq!(sel.columns(&buf, 2), None);
+    // 光标在行尾时包含换行
+    let sel = selection((0, 6), (0, 11), Shape::Chars);
+    assert_eq!(sel.range(&buf).end, (1, 0));
+
+    let sel = selection((2, 4), (1, 1), Shape::Lines);
+    assert_eq!(sel.range(&buf), Range { start: (1, 0), end: (2, 0), linewise: true });
+    assert_eq!(sel.columns(&buf, 2), Some((0, 7)));
+
+    // 块选择按屏幕列，跳过不够长的行；宽字符部分在块内也算选中
+    let buf = buffer("abcdef\nab\n中文字符");
+    let sel = selection((0, 2), (2, 1), Shape::Block);
+    assert_eq!(sel.block_columns(&buf), (2, 4));
+    assert_eq!(sel.columns(&buf, 0), Some((2, 4)));
+    assert_eq!(sel.columns(&buf, 1), None);
+    assert_eq!(sel.columns(&buf, 2), Some((1, 2)));
+    let sel = selection((0, 3), (2, 0), Shape::Block);
+    assert_eq!(sel.block_columns(&buf), (0, 4));
+    assert_eq!(sel.columns(&buf, 2), Some((0, 2)));
+}
+
+#[test]
+fn test_block_insert_and_comment_toggle() {
+    // 第一行输入的文本复制到其余各行的同一屏幕列
+    let mut buf = buffer("abcd\nab\nabcd");
+    buf.insert_text(0, 2, "XY");
+    BlockInsert { start: (0, 2), last: 2, x: 2, pad: false }.apply(&mut buf, (0, 4));
+    assert_eq!(text(&buf), "abXYcd\nabXY\nabXYcd");
+
+    // A 把短行补齐到块的右边
+    let mut buf = buffer("abcd\na\nabcd");
+    buf.insert_text(0, 4, ";");
+    BlockInsert { start: (0, 4), last: 2, x: 4, pad: true }.apply(&mut buf, (0, 5));
+    assert_eq!(text(&buf), "abcd;\na   ;\nabcd;");
+
+    let mut buf = buffer("fn a() {\n    b();\n\n}");
+    assert!(buf.toggle_comment(0, 3, "//"));
+    assert_eq!(text(&buf), "// fn a() {\n//     b();\n\n// }");
+    assert!(!buf.toggle_comment(0, 3, "//"));
+    assert_eq!(text(&buf), "fn a() {\n    b();\n\n}");
+    // 在最小缩进处注释，去掉注释时保留缩进
+    let mut buf = buffer("    a\n      b");
+    buf.toggle_comment(0, 1, "#");
+    assert_eq!(text(&buf), "    # a\n    #   b");
+    buf.toggle_comment(0, 1, "#");
+    assert_eq!(text(&buf), "    a\n      b");
+}
+
+#[test]
+fn test_visual_range_commands() {
+    let buf = buffer("c\nb\na\nd");
+    let visual = Some((0, 2));
+
+    let filter = Filter::parse("'<,'>!sort", &buf, 3, visual).unwrap().unwrap();
+    assert_eq!(filter, Filter { range: Some((0, 2)), command: "sort".to_string() });
+    assert_eq!(filter::run(&filter.command, "c\nb\na\n").unwrap(), "a\nb\nc\n");
+    assert!(filter::run("exit 3", "").is_err());
+
+    assert_eq!(Filter::parse("!ls", &buf, 0, None).unwrap().unwrap().range, None);
+    assert!(Filter::parse("'<,'>!sort", &buf, 0, None).unwrap().is_err());
+    assert!(Filter::parse("s/a/b/", &buf, 0, None).is_none());
+
+    let mut buf = buffer("a\na\na\na");
+    let mut sub = Substitute::parse("'<,'>s/a/x/", &buf, 0, Some((1, 2)), None).unwrap().unwrap();
+    sub.run(&mut buf);
+    assert_eq!(text(&buf), "a\nx\nx\na");
+}
@@ -0,0 +1,86 @@
+//! 编辑器窗口分割和缓冲区列表测试
+
+use std::path::{Path, PathBuf};
+
+use cnmsb::editor::buffer::Buffer;
+use cnmsb::editor::buffers::BufferList;
+use cnmsb::editor::window::{Direction, Rect, Windows};
+
+const AREA: Rect = Rect { x: 0, y: 0, width: 81, height: 24 };
+
+fn rect(x: u16, y: u16, width: u16, height: u16) -> Rect {
+    Rect { x, y, width, height }
+}
+
+#[test]
+fn test_split_layout() {
+    let mut windows = Windows::new(1);
+    assert_eq!(windows.rects(AREA), vec![(1, AREA)]);
+
+    // 左右分割，中间留一列分隔线，新窗口在左边并成为当前窗口
+    assert_eq!(windows.split(Direction::Vertical), 2);
+    assert_eq!(windows.active().id, 2);
+    assert_eq!(windows.active().buffer, 1);
+    assert_eq!(windows.rects(AREA), vec![(2, rect(0, 0, 40, 24)), (1, rect(41, 0, 40, 24))]);
+
+    // 再把左边上下分割
+    windows.split(Direction::Horizontal);
+    assert_eq!(windows.rects(AREA), vec![
+        (3, rect(0, 0, 40, 12)),
+        (2, rect(0, 12, 40, 12)),
+        (1, rect(41, 0, 40, 24)),
+    ]);
+
+    // 关闭后由同一次分割的另一半占据，切换到布局中的下一个窗口
+    assert!(windows.close());
+    assert_eq!(windows.active().id, 2);
+    assert_eq!(windows.rects(AREA), vec![(2, rect(0, 0, 40, 24)), (1, rect(41, 0, 40, 24))]);
+    assert!(windows.close());
+    assert_eq!(windows.rects(AREA), vec![(1, AREA)]);
+    // 最后一个窗口不能关闭
+    assert!(!windows.close());
+    assert_eq!(windows.len(), 1);
+}
+
+#[test]
+fn test_window_navigation() {
+    let mut windows = Windows::new(1);
+    windows.split(Direction::Vertical);
+    windows.split(Direction::Horizontal);
+    // 布局：左上 3、左下 2、右边 1
+
+    assert_eq!(windows.next(true), 2);
+    assert_eq!(windows.next(false), 1);
+    assert_eq!(windows.neighbor(AREA, 'j'), Some(2));
+    assert_eq!(windows.neighbor(AREA, 'l'), Some(1));
+    assert_eq!(windows.neighbor(AREA, 'k'), None);
+    assert_eq!(windows.neighbor(AREA, 'h'), None);
+
+    assert!(windows.focus(1));
+    assert_eq!(windows.neighbor(AREA, 'h'), Some(3));
+    assert_eq!(windows.next(true), 3);
+    assert!(!windows.focus(9));
+
+    windows.only();
+    assert_eq!(windows.len(), 1);
+    assert_eq!(windows.rects(AREA), vec![(1, AREA)]);
+}
+
+#[test]
+fn test_buffer_list() {
+    let mut list = BufferList::new();
+    let a = list.add(Buffer::new(), Some(PathBuf::from("a.sh")));
+    let b = list.add(Buffer::new(), None);
+    let c = list.add(Buffer::new(), Some(PathBuf::from("dir/c.sh")));
+    assert_eq!((a, b, c), (1, 2, 3));
+
+    assert_eq!(list.find(Path::new("a.sh")), Some(1));
+    assert_eq!(list.find(Path::new("./dir/c.sh")), Some(3));
+    assert_eq!(list.find(Path::new("b.sh")), None);
+    assert_eq!(list.get(b).unwrap().name(), "[未命名]");
+
+    // 到头时回到另一端
+    assert_eq!(list.next(1, true), 2);
+    assert_eq!(list.next(3, true), 1);
+    assert_eq!(list.next(1, false), 3);
+}
@@ -0,0 +1,191 @@
+//! 结构导出与比较测试
+
+use cnmsb::sql::diff::{self, SchemaChange};
+use cnmsb::sql::dump::{self, TableSchema};
+use cnmsb::sql::{ColumnInfo, DatabaseType, DbConnection, IndexInfo, SqlValue};
+
+fn column(name: &str, data_type: &str, nullable: bool, primary_key: bool) -> ColumnInfo {
+    ColumnInfo::new(name, data_type, nullable, primary_key)
+}
+
+#[test]
+fn test_create_table_and_literals() {
+    let table = TableSchema {
+        name: "order_items".to_string(),
+        columns: vec![
+            column("order_id", "integer", false, true),
+            column("item_id", "integer", false, true),
+            column("note", "varchar(20)", true, false),
+        ],
+        ..Default::default()
+    };
+    assert_eq!(
+        dump::create_table_sql(&table, DatabaseType::MySQL),
+        "CREATE TABLE `order_items` (\n    `order_id` integer NOT NULL,\n    `item_id` integer NOT NULL,\n    \
+         `note` varchar(20),\n    PRIMARY KEY (`order_id`, `item_id`)\n)"
+    );
+
+    let row = [SqlValue::Number("1".to_string()), SqlValue::Null, SqlValue::Text("it's a\\b".to_string())];
+    assert_eq!(
+        dump::insert_sql(&table, &row, DatabaseType::PostgreSQL),
+        "INSERT INTO \"order_items\" (\"order_id\", \"item_id\", \"note\") VALUES (1, NULL, 'it''s a\\b')"
+    );
+    assert_eq!(dump::literal(&SqlValue::Text("a\\b".to_string()), DatabaseType::MySQL), "'a\\\\b'");
+    assert_eq!(dump::literal(&SqlValue::Bytes(vec![0, 255]), DatabaseType::SQLite), "X'00ff'");
+    assert_eq!(dump::literal(&SqlValue::Number("inf".to_string()), DatabaseType::SQLite), "'inf'");
+}
+
+#[test]
+fn test_dump_roundtrip() {
+    let mut conn = DbConnection::connect_sqlite(":memory:").unwrap();
+    conn.execute("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT NOT NULL, data BLOB, score REAL)").unwrap();
+    conn.execute("INSERT INTO t VALUES (1, 'O''Brien', x'0102', 1.5), (2, 'NULL', NULL, NULL)").unwrap();
+    conn.execute("CREATE VIEW v AS SELECT * FROM t").unwrap();
+
+    let script = dump::dump(&mut conn, None).unwrap();
+    assert!(!script.contains("\"v\""));
+
+    // 在新数据库中执行导出的脚本，数据应完全一致
+    let mut copy = DbConnection::connect_sqlite(":memory:").unwrap();
+    for stmt in cnmsb::sql::lexer::split_statements(&script, DatabaseType::SQLite) {
+        copy.execute(stmt).unwrap();
+    }
+    let query = "SELECT id, name, hex(data), score, data IS NULL FROM t ORDER BY id";
+    assert_eq!(copy.execute(query).unwrap().rows, conn.execute(query).unwrap().rows);
+    assert_eq!(dump::load_tables(&mut copy).unwrap(), dump::load_tables(&mut conn).unwrap());
+
+    assert!(dump::dump(&mut conn, Some("missing")).is_err());
+}
+
+#[test]
+fn test_column_defaults() {
+    let mut status = column("status", "varchar(10)", false, false);
+    status.default = Some("new".to_string());
+    assert_eq!(dump::column_definition(&status, DatabaseType::MySQL), "`status` varchar(10) NOT NULL DEFAULT 'new'");
+    status.default = Some("CURRENT_TIMESTAMP".to_string());
+    assert_eq!(dump::column_definition(&status, DatabaseType::MySQL), "`status` varchar(10) NOT NULL DEFAULT CURRENT_TIMESTAMP");
+    status.default = Some("'new'::character varying".to_string());
+    assert_eq!(
+        dump::column_definition(&status, DatabaseType::PostgreSQL),
+        "\"status\" varchar(10) NOT NULL DEFAULT 'new'::character varying"
+    );
+
+    // 默认值来自序列的列导出为 serial
+    let mut id = column("id", "integer", false, true);
+    id.default = Some("nextval('t_id_seq'::regclass)".to_string());
+    assert_eq!(dump::column_definition(&id, DatabaseType::PostgreSQL), "\"id\" serial NOT NULL");
+}
+
+#[test]
+fn test_dump_roundtrip_constraints() {
+    let mut conn = DbConnection::connect_sqlite(":memory:").unwrap();
+    conn.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL UNIQUE, status TEXT DEFAULT 'new', \
+                  created TEXT DEFAULT CURRENT_TIMESTAMP)").unwrap();
+    conn.execute("CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER REFERENCES users (id), \
+                  amount REAL NOT NULL DEFAULT 0)").unwrap();
+    conn.execute("CREATE INDEX idx_orders_user ON orders (user_id)").unwrap();
+    conn.execute("INSERT INTO users (id, email) VALUES (1, 'a@example.com')").unwrap();
+    conn.execute("INSERT INTO orders (user_id, amount) VALUES (1, 9.5)").unwrap();
+
+    let script = dump::dump(&mut conn, None).unwrap();
+    assert!(script.contains("DEFAULT 'new'"));
+    assert!(script.contains("UNIQUE (\"email\")"));
+    assert!(script.contains("FOREIGN KEY (\"user_id\") REFERENCES \"users\" (\"id\")"));
+    assert!(script.contains("CREATE INDEX \"idx_orders_user\" ON \"orders\" (\"user_id\");"));
+
+    // 在新数据库中执行导出的脚本，默认值、索引和外键都应保留
+    let mut copy = DbConnection::connect_sqlite(":memory:").unwrap();
+    for stmt in cnmsb::sql::lexer::split_statements(&script, DatabaseType::SQLite) {
+        copy.execute(stmt).unwrap();
+    }
+    let tables = dump::load_tables(&mut copy).unwrap();
+    assert_eq!(tables, dump::load_tables(&mut conn).unwrap());
+    assert_eq!(tables[0].foreign_keys.len(), 1);
+    assert!(tables[1].indexes.iter().any(|i| i.unique && i.columns == ["email"]));
+
+    copy.execute("INSERT INTO users (id, email) VALUES (2, 'b@example.com')").unwrap();
+    let status = copy.execute("SELECT status FROM users WHERE id = 2").unwrap();
+    assert_eq!(status.rows[0][0], "new");
+    assert!(copy.execute("INSERT INTO users (id, email) VALUES (3, 'a@example.com')").is_err());
+}
+
+#[test]
+fn test_normalize_type() {
+    assert_eq!(diff::normalize_type("character varying(50)"), diff::normalize_type("VARCHAR(50)"));
+    assert_eq!(diff::normalize_type("int(11)"), diff::normalize_type("integer"));
+    assert_eq!(diff::normalize_type("int(10) unsigned"), "integer unsigned");
+    assert_eq!(diff::normalize_type("tinyint(1)"), "boolean");
+    assert_eq!(diff::normalize_type("timestamp without time zone"), "timestamp");
+    assert_ne!(diff::normalize_type("varchar(20)"), diff::normalize_type("varchar(50)"));
+}
+
+#[test]
+fn test_schema_diff() {
+    let current = vec![
+        TableSchema {
+            name: "users".to_string(),
+            columns: vec![
+                // SQLite 把 INTEGER PRIMARY KEY 报告为可空
+                column("id", "INTEGER", true, true),
+                column("name", "TEXT", true, false),
+                column("legacy", "TEXT", true, false),
+            ],
+            indexes: vec![IndexInfo {
+                name: "users_pk".to_string(),
+                columns: vec!["id".to_string()],
+                unique: true,
+                primary: true,
+            }],
+            ..Default::default()
+        },
+        TableSchema { name: "old".to_string(), columns: vec![column("x", "TEXT", true, false)], ..Default::default() },
+    ];
+    let target = vec![
+        TableSchema {
+            name: "users".to_string(),
+            columns: vec![
+                column("id", "integer", false, true),
+                column("name", "character varying(50)", false, false),
+                column("email", "text", true, false),
+            ],
+            ..Default::default()
+        },
+        TableSchema { name: "orders".to_string(), columns: vec![column("id", "bigint", false, true)], ..Default::default() },
+    ];
+
+    let changes = diff::diff(&current, &target);
+    assert_eq!(changes.len(), 5);
+    assert!(matches!(&changes[0], SchemaChange::CreateTable(t) if t.name == "orders"));
+    assert!(matches!(&changes[1], SchemaChange::AlterColumn { to, .. } if to.name == "name"));
+    assert!(matches!(&changes[2], SchemaChange::AddColumn { column, .. } if column.name == "email"));
+    assert!(matches!(&changes[3], SchemaChange::DropColumn { column, .. } if column == "legacy"));
+    assert_eq!(changes[4], SchemaChange::DropTable("old".to_string()));
+
+    let script = diff::migration_script(&changes, DatabaseType::PostgreSQL);
+    assert!(script.contains(&"ALTER TABLE \"users\" ALTER COLUMN \"name\" TYPE character varying(50) USING \"name\"::character varying(50);".to_string()));
+    assert!(script.contains(&"ALTER TABLE \"users\" ALTER COLUMN \"name\" SET NOT NULL;".to_string()));
+    assert!(script.contains(&"ALTER TABLE \"users\" ADD COLUMN \"email\" text;".to_string()));
+    assert!(script.contains(&"DROP TABLE \"old\";".to_string()));
+
+    let key_change = [SchemaChange::ChangePrimaryKey {
+        table: "t".to_string(),
+        constraint: Some("t_custom_pk".to_string()),
+        from: vec!["a".to_string()],
+        to: vec!["a".to_string(), "b".to_string()],
+    }];
+    assert_eq!(
+        diff::migration_script(&key_change, DatabaseType::MySQL)[1],
+        "ALTER TABLE `t` DROP PRIMARY KEY, ADD PRIMARY KEY (`a`, `b`);"
+    );
+    assert!(diff::migration_script(&key_change, DatabaseType::SQLite)[1].starts_with("-- SQLite 不支持"));
+
+    // PostgreSQL 按读到的约束名删除主键
+    assert_eq!(
+        diff::migration_script(&key_change, DatabaseType::PostgreSQL)[1],
+        "ALTER TABLE \"t\" DROP CONSTRAINT \"t_custom_pk\";"
+    );
+    let mut target = current.clone();
+    target[0].columns[0].primary_key = false;
+    let changes = diff::diff(&current, &target);
+    assert!(changes.iter().any(|c| matches!(c, SchemaChange::ChangePrimaryKey { constraint: Some(name), .. } if name == "users_pk")));
+}
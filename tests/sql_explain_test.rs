@@ -0,0 +1,124 @@
+//! 执行计划解析与渲染测试
+
+use cnmsb::sql::explain::{self, PlanWarning};
+use cnmsb::sql::DatabaseType;
+use serde_json::json;
+
+fn row(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+#[test]
+fn test_plan_sql() {
+    assert_eq!(explain::plan_sql("SELECT 1;", DatabaseType::SQLite, true), "EXPLAIN QUERY PLAN SELECT 1");
+    assert_eq!(explain::plan_sql("SELECT 1", DatabaseType::MySQL, false), "EXPLAIN FORMAT=JSON SELECT 1");
+    assert_eq!(explain::plan_sql("SELECT 1", DatabaseType::MariaDB, true), "ANALYZE FORMAT=JSON SELECT 1");
+    assert_eq!(explain::plan_sql("SELECT 1", DatabaseType::PostgreSQL, true), "EXPLAIN (ANALYZE, FORMAT JSON) SELECT 1");
+
+    let db = DatabaseType::PostgreSQL;
+    assert!(explain::is_read_only("SELECT * FROM t", db));
+    assert!(!explain::is_read_only("DELETE FROM t", db));
+    assert!(!explain::is_read_only("WITH d AS (DELETE FROM t RETURNING *) SELECT * FROM d", db));
+}
+
+#[test]
+fn test_sqlite_plan() {
+    let rows = vec![
+        row(&["2", "0", "0", "SCAN o"]),
+        row(&["5", "0", "0", "SEARCH u USING INTEGER PRIMARY KEY (rowid=?)"]),
+        row(&["9", "0", "0", "SCAN u USING COVERING INDEX i_name"]),
+        row(&["12", "0", "0", "USE TEMP B-TREE FOR ORDER BY"]),
+    ];
+    let plan = explain::parse_sqlite(&rows);
+    assert_eq!(plan.nodes.len(), 4);
+    let warnings: Vec<PlanWarning> = plan.warnings().into_iter().map(|(w, _)| w).collect();
+    assert_eq!(warnings, vec![PlanWarning::FullScan, PlanWarning::FullIndexScan, PlanWarning::TempSort]);
+
+    let nested = vec![row(&["1", "0", "0", "CO-ROUTINE sub"]), row(&["3", "1", "0", "SCAN CONSTANT ROW"])];
+    let plan = explain::parse_sqlite(&nested);
+    assert_eq!(plan.nodes[0].children.len(), 1);
+    assert!(plan.warnings().is_empty());
+}
+
+#[test]
+fn test_postgres_plan() {
+    let json = json!([{
+        "Plan": {
+            "Node Type": "Hash Join", "Total Cost": 42.5, "Plan Rows": 10, "Hash Cond": "(o.uid = u.id)",
+            "Plans": [
+                {"Node Type": "Seq Scan", "Relation Name": "orders", "Alias": "o", "Total Cost": 20.0,
+                 "Plan Rows": 1000, "Filter": "(total > 10)"},
+                {"Node Type": "Index Scan", "Relation Name": "users", "Alias": "users", "Index Name": "users_pkey",
+                 "Total Cost": 8.3, "Plan Rows": 1, "Actual Rows": 1, "Actual Total Time": 0.02}
+            ]
+        },
+        "Planning Time": 0.1,
+        "Execution Time": 0.5
+    }]);
+    let plan = explain::parse_postgres(&json).unwrap();
+    assert_eq!(plan.total_cost, Some(42.5));
+    assert_eq!(plan.execution_time, Some(0.5));
+
+    let root = &plan.nodes[0];
+    assert_eq!(root.details, vec!["Hash Cond: (o.uid = u.id)"]);
+    assert_eq!(root.children[0].label, "Seq Scan on orders o");
+    assert_eq!(root.children[0].warning, Some(PlanWarning::UnindexedFilter));
+    assert_eq!(root.children[1].label, "Index Scan using users_pkey on users");
+    assert_eq!(root.children[1].actual_rows, Some(1.0));
+
+    let lines = explain::render(&plan, false);
+    assert_eq!(lines[0], "Hash Join  (cost=42.50 rows=10)");
+    assert_eq!(lines[1], "│  Hash Cond: (o.uid = u.id)");
+    assert_eq!(lines[2], "├─ Seq Scan on orders o  (cost=20 rows=1000)  ⚠ 全表扫描，过滤条件没有用上索引");
+    assert_eq!(lines[3], "│     Filter: (total > 10)");
+    assert_eq!(lines[4], "└─ Index Scan using users_pkey on users  (cost=8.30 rows=1) (actual rows=1 time=0.02ms)");
+}
+
+#[test]
+fn test_mysql_json_plan() {
+    let json = json!({
+        "query_block": {
+            "select_id": 1,
+            "cost_info": {"query_cost": "12.75"},
+            "ordering_operation": {
+                "using_filesort": true,
+                "nested_loop": [
+                    {"table": {"table_name": "o", "access_type": "ALL", "possible_keys": ["idx_uid"],
+                               "rows_examined_per_scan": 100, "cost_info": {"prefix_cost": "10.25"}}},
+                    {"table": {"table_name": "u", "access_type": "eq_ref", "key": "PRIMARY",
+                               "rows_examined_per_scan": 1, "attached_condition": "(u.age > 3)"}}
+                ]
+            }
+        }
+    });
+    let plan = explain::parse_mysql_json(&json);
+    assert_eq!(plan.total_cost, Some(12.75));
+
+    let select = &plan.nodes[0];
+    assert_eq!(select.label, "SELECT #1");
+    let order = &select.children[0];
+    assert_eq!(order.label, "ORDER BY");
+    assert_eq!(order.warning, Some(PlanWarning::TempSort));
+    assert_eq!(order.children[0].label, "ALL on o");
+    assert_eq!(order.children[0].warning, Some(PlanWarning::UnindexedFilter));
+    assert_eq!(order.children[0].cost, Some(10.25));
+    assert_eq!(order.children[1].warning, None);
+    assert!(order.children[1].details.contains(&"key: PRIMARY".to_string()));
+}
+
+#[test]
+fn test_mysql_analyze_tree() {
+    let text = "-> Sort: t.a  (cost=1.2 rows=3) (actual time=0.1..0.2 rows=3 loops=1)\n    \
+                -> Filter: (t.b > 1)  (cost=0.55 rows=1) (actual time=0.04..0.05 rows=1 loops=1)\n        \
+                -> Table scan on t  (cost=0.55 rows=3) (actual time=0.03..0.04 rows=3 loops=1)\n";
+    let plan = explain::parse_mysql_tree(text);
+    assert_eq!(plan.nodes.len(), 1);
+    let sort = &plan.nodes[0];
+    assert_eq!(sort.label, "Sort: t.a");
+    assert_eq!(sort.warning, Some(PlanWarning::TempSort));
+    let scan = &sort.children[0].children[0];
+    assert_eq!(scan.label, "Table scan on t");
+    assert_eq!(scan.warning, Some(PlanWarning::UnindexedFilter));
+    assert_eq!(scan.actual_rows, Some(3.0));
+    assert_eq!(scan.actual_time, Some(0.04));
+}
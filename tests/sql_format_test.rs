@@ -0,0 +1,81 @@
+//! SQL 格式化测试
+
+use cnmsb::sql::format::{format, FormatOptions, KeywordCase};
+use cnmsb::sql::DatabaseType;
+
+fn fmt(sql: &str, db_type: DatabaseType) -> String {
+    format(sql, db_type, &FormatOptions::default())
+}
+
+#[test]
+fn test_clauses_and_subqueries() {
+    let sql = "select a, count(*) from users u left join orders o on o.user_id = u.id \
+               where u.id in (select user_id from vip where level > -1) and name like 'a%' \
+               group by a order by 2 desc limit 10";
+    let expected = "\
+SELECT a, count(*)
+FROM users u
+LEFT JOIN orders o ON o.user_id = u.id
+WHERE u.id IN (
+    SELECT user_id
+    FROM vip
+    WHERE level > -1
+)
+    AND name LIKE 'a%'
+GROUP BY a
+ORDER BY 2 DESC
+LIMIT 10";
+    assert_eq!(fmt(sql, DatabaseType::PostgreSQL), expected);
+
+    // 结果再格式化一次不变
+    assert_eq!(fmt(expected, DatabaseType::PostgreSQL), expected);
+}
+
+#[test]
+fn test_wrapping_and_comments() {
+    let sql = "select u.id, u.name, u.email, u.created_at, o.total_amount, o.status, o.shipping_address \
+               from users u where a = 1 and b between 1 and 2 and c = 'a long string value'";
+    let expected = "\
+SELECT
+    u.id,
+    u.name,
+    u.email,
+    u.created_at,
+    o.total_amount,
+    o.status,
+    o.shipping_address
+FROM users u
+WHERE a = 1 AND b BETWEEN 1 AND 2 AND c = 'a long string value'";
+    assert_eq!(fmt(sql, DatabaseType::PostgreSQL), expected);
+
+    // 行尾注释留在原来的行，建表语句每列一行
+    let sql = "create table t (id serial primary key, -- 主键\nname varchar(20) not null default 'x');\n-- end";
+    let expected = "\
+CREATE TABLE t (
+    id SERIAL PRIMARY KEY, -- 主键
+    name VARCHAR(20) NOT NULL DEFAULT 'x'
+);
+-- end";
+    assert_eq!(fmt(sql, DatabaseType::PostgreSQL), expected);
+}
+
+#[test]
+fn test_dialect_tokens_survive() {
+    // PostgreSQL 的 :: 类型转换、美元引用和 JSON 操作符
+    let sql = "select b::text, $$ a;b $$ from t where j->>'k' = $1";
+    assert_eq!(fmt(sql, DatabaseType::PostgreSQL), "SELECT b::TEXT, $$ a;b $$\nFROM t\nWHERE j ->> 'k' = $1");
+
+    // MySQL 的反引号、# 注释和 VALUES() 函数；表名不改大小写
+    let sql = "insert into `order`(a, b) values (1, 2) on duplicate key update a = values(a); select `key` from user # c";
+    let expected = "\
+INSERT INTO `order`(a, b)
+VALUES (1, 2)
+ON DUPLICATE KEY UPDATE a = values(a);
+
+SELECT `key`
+FROM user # c";
+    assert_eq!(fmt(sql, DatabaseType::MySQL), expected);
+
+    let options = FormatOptions { keyword_case: KeywordCase::Lower, ..FormatOptions::default() };
+    assert_eq!(format("SELECT 1 FROM t WHERE X IS NULL", DatabaseType::SQLite, &options), "select 1\nfrom t\nwhere X is null");
+}
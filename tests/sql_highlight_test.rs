@@ -0,0 +1,98 @@
+//! SQL 输入高亮测试
+
+use cnmsb::sql::highlight::{self, HighlightKind, HighlightSpan};
+use cnmsb::sql::{DatabaseType, SqlEngine};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// 把高亮区间转换为 (文本, 类型) 便于断言
+fn classify(engine: &SqlEngine, line: &str, cursor: usize) -> Vec<(String, HighlightKind)> {
+    engine.highlight(line, cursor)
+        .into_iter()
+        .map(|s| (line[s.start..s.end].to_string(), s.kind))
+        .collect()
+}
+
+#[test]
+fn test_highlight_tokens() {
+    let mut engine = SqlEngine::new(DatabaseType::PostgreSQL);
+    engine.set_tables(vec!["users".to_string()]);
+    engine.set_columns("users", vec!["id".to_string(), "name".to_string()]);
+
+    let line = "select count(id), u.name, 'x(' from users u where id > 10 -- note";
+    let spans = classify(&engine, line, 0);
+    assert_eq!(spans, vec![
+        ("select".to_string(), HighlightKind::Keyword),
+        ("count".to_string(), HighlightKind::Function),
+        ("id".to_string(), HighlightKind::Column),
+        ("name".to_string(), HighlightKind::Column),
+        ("'x('".to_string(), HighlightKind::String),
+        ("from".to_string(), HighlightKind::Keyword),
+        ("users".to_string(), HighlightKind::Table),
+        ("where".to_string(), HighlightKind::Keyword),
+        ("id".to_string(), HighlightKind::Column),
+        ("10".to_string(), HighlightKind::Number),
+        ("-- note".to_string(), HighlightKind::Comment),
+    ]);
+
+    let spans = classify(&engine, "CREATE TABLE t (n INTEGER, v VARCHAR(10))", 0);
+    assert!(spans.contains(&("INTEGER".to_string(), HighlightKind::DataType)));
+    assert!(spans.contains(&("VARCHAR".to_string(), HighlightKind::DataType)));
+    // 未知的表名不高亮
+    assert!(!spans.iter().any(|(text, _)| text == "t"));
+}
+
+#[test]
+fn test_highlight_uses_cached_columns() {
+    let mut engine = SqlEngine::new(DatabaseType::SQLite);
+    engine.set_tables(vec!["users".to_string(), "orders".to_string()]);
+    let calls = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&calls);
+    engine.set_column_loader(Box::new(move |_| {
+        counter.set(counter.get() + 1);
+        vec!["id".to_string()]
+    }));
+    engine.set_cached_columns(Box::new(|table| match table {
+        "orders" => vec!["amount".to_string()],
+        _ => Vec::new(),
+    }));
+
+    // 高亮不调用加载回调，没有缓存的列按普通标识符处理
+    let spans = classify(&engine, "SELECT id, amount FROM users, orders", 0);
+    assert_eq!(calls.get(), 0);
+    assert!(!spans.iter().any(|(text, _)| text == "id"));
+    assert!(spans.contains(&("amount".to_string(), HighlightKind::Column)));
+}
+
+#[test]
+fn test_highlight_brackets() {
+    let engine = SqlEngine::new(DatabaseType::SQLite);
+    let line = "SELECT (1 + (2)) ')'";
+
+    // 光标在第一个左括号上
+    let spans = engine.highlight(line, 7);
+    let brackets: Vec<&HighlightSpan> = spans.iter().filter(|s| s.kind == HighlightKind::MatchedBracket).collect();
+    assert_eq!(brackets.iter().map(|s| s.start).collect::<Vec<_>>(), vec![7, 15]);
+
+    // 光标在内层右括号上
+    let spans = engine.highlight(line, 14);
+    let brackets: Vec<usize> = spans.iter().filter(|s| s.kind == HighlightKind::MatchedBracket).map(|s| s.start).collect();
+    assert_eq!(brackets, vec![12, 14]);
+
+    // 光标在行尾，紧跟在右括号之后
+    let spans = engine.highlight("SELECT (1)", 10);
+    let brackets: Vec<usize> = spans.iter().filter(|s| s.kind == HighlightKind::MatchedBracket).map(|s| s.start).collect();
+    assert_eq!(brackets, vec![7, 9]);
+
+    // 字符串中的括号不参与配对
+    let line = "SELECT (')'";
+    let spans = engine.highlight(line, 7);
+    assert!(spans.iter().any(|s| s.start == 7 && s.kind == HighlightKind::UnmatchedBracket));
+}
+
+#[test]
+fn test_render() {
+    let spans = [HighlightSpan { start: 0, end: 6, kind: HighlightKind::Keyword }];
+    let rendered = highlight::render("SELECT 1", &spans);
+    assert_eq!(rendered, format!("{}SELECT\x1b[0m 1", HighlightKind::Keyword.color()));
+}
@@ -0,0 +1,102 @@
+//! 查询历史测试
+
+use cnmsb::sql::history::{self, HistoryEntry, QueryHistory};
+use cnmsb::sql::{DatabaseType, SqlEngine};
+use std::fs;
+
+#[test]
+fn test_history_persistence() {
+    let path = std::env::temp_dir().join(format!("cnmsb_history_test_{}.jsonl", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    let mut history = QueryHistory::open(Some(path.clone()));
+    history.record(HistoryEntry::new("SELECT * FROM users;", 1.5, Some(3), true));
+    history.record(HistoryEntry::new("DELETE FROM nope", 0.2, None, false));
+    history.record(HistoryEntry::new("   ", 0.0, None, true));
+
+    // 重新打开后记录仍然存在，损坏的行被跳过
+    fs::write(&path, fs::read_to_string(&path).unwrap() + "not json\n").unwrap();
+    let history = QueryHistory::open(Some(path.clone()));
+    assert_eq!(history.entries().len(), 2);
+    assert_eq!(history.get(1).unwrap().sql, "SELECT * FROM users;");
+    assert_eq!(history.get(1).unwrap().rows, Some(3));
+    assert!(!history.last().unwrap().ok);
+    assert!(history.get(0).is_none());
+
+    let found = history.search(Some("users"));
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].0, 1);
+    assert_eq!(history.search(None).len(), 2);
+
+    // 失败的语句不作为补全片段
+    let fragments = history.fragments(DatabaseType::SQLite);
+    assert!(fragments.iter().all(|(f, _)| !f.contains("nope")));
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_history_expansion() {
+    let mut history = QueryHistory::default();
+    for sql in ["SELECT 1", "SELECT 2", "SELECT 3"] {
+        history.record(HistoryEntry::new(sql, 1.0, Some(1), true));
+    }
+    assert_eq!(history.expansion("!!"), Some(3));
+    assert_eq!(history.expansion("!2"), Some(2));
+    assert_eq!(history.expansion("!-1"), Some(3));
+    assert_eq!(history.expansion("!-3"), Some(1));
+
+    // 超出范围的编号取不到记录
+    for line in ["!9", "!0", "!-4", "!-0", "!99999999999999999999999"] {
+        assert!(history.expansion(line).and_then(|n| history.get(n)).is_none(), "{}", line);
+    }
+
+    // 其他以 ! 开头的输入交给 SQL 执行
+    for line in ["!= 1", "!abc", "! 2", "!2a", "!-", "!", "SELECT 1"] {
+        assert_eq!(history.expansion(line), None, "{}", line);
+    }
+}
+
+#[test]
+fn test_split_fragments() {
+    let sql = "SELECT u.name FROM users u\n  LEFT JOIN orders o ON o.uid = u.id WHERE o.total > (SELECT 1 FROM t WHERE x) ORDER BY u.name;";
+    let fragments = history::split_fragments(sql, DatabaseType::SQLite);
+    assert_eq!(fragments, vec![
+        "SELECT u.name FROM users u LEFT JOIN orders o ON o.uid = u.id WHERE o.total > (SELECT 1 FROM t WHERE x) ORDER BY u.name",
+        "FROM users u",
+        "LEFT JOIN orders o ON o.uid = u.id",
+        "WHERE o.total > (SELECT 1 FROM t WHERE x)",
+        "ORDER BY u.name",
+    ]);
+
+    let mut history = QueryHistory::default();
+    for _ in 0..2 {
+        history.record(HistoryEntry::new("SELECT * FROM orders WHERE status = 'paid'", 1.0, Some(1), true));
+    }
+    history.record(HistoryEntry::new("SELECT id FROM orders", 1.0, Some(1), true));
+    let fragments = history.fragments(DatabaseType::SQLite);
+    assert_eq!(fragments[0], ("FROM orders".to_string(), 3));
+    assert!(fragments.contains(&("SELECT * FROM orders WHERE status = 'paid'".to_string(), 2)));
+}
+
+#[test]
+fn test_history_completion() {
+    let mut engine = SqlEngine::new(DatabaseType::SQLite);
+    engine.set_history(vec![
+        ("WHERE status = 'paid'".to_string(), 3),
+        ("SELECT * FROM orders WHERE status = 'paid'".to_string(), 2),
+    ]);
+
+    // 整条语句的前缀
+    let completions = engine.complete("select * fr", 11);
+    assert_eq!(completions[0].text, "frOM orders WHERE status = 'paid'");
+
+    // 子句的前缀
+    let input = "SELECT id FROM t WHERE st";
+    let completions = engine.complete(input, input.len());
+    assert_eq!(completions[0].text, "status = 'paid'");
+
+    // 字符串中不使用历史补全
+    let input = "SELECT 'WHERE st";
+    assert!(engine.complete(input, input.len()).iter().all(|c| !c.text.contains("paid")));
+}
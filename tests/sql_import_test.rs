@@ -0,0 +1,135 @@
+//! CSV / JSON Lines 导入测试
+
+use cnmsb::sql::import::{self, ColumnType, ImportCommand, ImportFormat, ImportOptions};
+use cnmsb::sql::{DatabaseType, DbConnection};
+
+#[test]
+fn test_parse_csv() {
+    let content = "id,name,note\n1,\"Smith, J\",\"say \"\"hi\"\"\"\n2,,\"\"\n3,\"two\nlines\",x\n";
+    let data = import::parse_csv(content, &ImportOptions::default()).unwrap();
+    assert_eq!(data.columns, vec!["id", "name", "note"]);
+    assert_eq!(data.rows.len(), 3);
+    assert_eq!(data.rows[0][1].as_deref(), Some("Smith, J"));
+    assert_eq!(data.rows[0][2].as_deref(), Some("say \"hi\""));
+    // 未加引号的空字段为 NULL，"" 为空字符串
+    assert_eq!(data.rows[1][1], None);
+    assert_eq!(data.rows[1][2].as_deref(), Some(""));
+    assert_eq!(data.rows[2][1].as_deref(), Some("two\nlines"));
+
+    let options = ImportOptions { delimiter: ';', quote: '\'', header: false, ..Default::default() };
+    let data = import::parse_csv("a;'b;c'\r\nd;e\r\n", &options).unwrap();
+    assert_eq!(data.columns, vec!["col1", "col2"]);
+    assert!(!data.named);
+    assert_eq!(data.rows[0][1].as_deref(), Some("b;c"));
+    assert_eq!(data.rows[1][1].as_deref(), Some("e"));
+
+    assert!(import::parse_csv("a\n\"open\n", &ImportOptions::default()).is_err());
+    assert!(import::parse_csv("a\n1,2\n", &ImportOptions::default()).is_err());
+}
+
+#[test]
+fn test_parse_json_lines() {
+    let content = "{\"b\": 1, \"a\": \"x\"}\n\n{\"a\": null, \"c\": {\"k\": [1]}, \"d\": true}\n";
+    let data = import::parse_json_lines(content).unwrap();
+    assert_eq!(data.columns, vec!["b", "a", "c", "d"]);
+    assert_eq!(data.rows[0], vec![Some("1".to_string()), Some("x".to_string()), None, None]);
+    assert_eq!(data.rows[1][2].as_deref(), Some("{\"k\":[1]}"));
+    assert_eq!(data.rows[1][3].as_deref(), Some("true"));
+
+    assert!(import::parse_json_lines("[1, 2]\n").is_err());
+}
+
+#[test]
+fn test_parse_json_array() {
+    let content = "[\n  {\"id\": 1, \"name\": \"a\"},\n  {\"id\": 2, \"tags\": [\"x\"]}\n]\n";
+    let data = import::parse_json(content).unwrap();
+    assert_eq!(data.columns, vec!["id", "name", "tags"]);
+    assert_eq!(data.rows.len(), 2);
+    assert_eq!(data.rows[1], vec![Some("2".to_string()), None, Some("[\"x\"]".to_string())]);
+
+    // 不是数组时按 JSON Lines 解析
+    assert_eq!(import::parse_json("{\"a\": 1}\n{\"a\": 2}\n").unwrap().rows.len(), 2);
+    assert!(import::parse_json("[{\"a\": 1}, 2]").is_err());
+    assert!(import::parse_json("[{\"a\": 1}").is_err());
+}
+
+#[test]
+fn test_infer_types() {
+    let data = import::parse_csv("i,r,zip,t,empty\n1,1.5,007,a,\n-2,3,123,1,\n", &ImportOptions::default()).unwrap();
+    assert_eq!(
+        import::infer_types(&data),
+        vec![ColumnType::Integer, ColumnType::Real, ColumnType::Text, ColumnType::Text, ColumnType::Text]
+    );
+
+    let types = [ColumnType::Integer, ColumnType::Real];
+    let columns = vec!["id".to_string(), "score".to_string()];
+    assert_eq!(
+        import::create_table_sql("t", &columns, &types, DatabaseType::PostgreSQL),
+        "CREATE TABLE \"t\" (\"id\" BIGINT, \"score\" DOUBLE PRECISION)"
+    );
+    assert_eq!(
+        import::create_table_sql("db.t", &columns, &types, DatabaseType::MySQL),
+        "CREATE TABLE `db`.`t` (`id` BIGINT, `score` DOUBLE)"
+    );
+}
+
+#[test]
+fn test_import_command() {
+    let command = ImportCommand::parse("--delimiter tab --no-header data.txt t").unwrap();
+    assert_eq!(command.file, "data.txt");
+    assert_eq!(command.table, "t");
+    assert_eq!(command.options.delimiter, '\t');
+    assert!(!command.options.header);
+    assert_eq!(command.format(), ImportFormat::Csv);
+
+    assert_eq!(ImportCommand::parse("x.tsv t").unwrap().options.delimiter, '\t');
+    assert_eq!(ImportCommand::parse("x.ndjson t").unwrap().format(), ImportFormat::JsonLines);
+    assert_eq!(ImportCommand::parse("x.json t").unwrap().format(), ImportFormat::Json);
+    assert_eq!(ImportCommand::parse("--jsonl x.json t").unwrap().format(), ImportFormat::JsonLines);
+    assert!(ImportCommand::parse("only_file").is_err());
+    assert!(ImportCommand::parse("--bogus a b").is_err());
+}
+
+#[test]
+fn test_import_into_sqlite() {
+    let mut conn = DbConnection::connect_sqlite(":memory:").unwrap();
+
+    let data = import::parse_csv("id,name\n1,a\n2,b\n", &ImportOptions::default()).unwrap();
+    let summary = import::import(&mut conn, "people", data).unwrap();
+    assert!(summary.created);
+    assert_eq!(summary.rows, 2);
+
+    // 表已存在，没有表头时按表的列顺序插入
+    let options = ImportOptions { header: false, ..Default::default() };
+    let data = import::parse_csv("3,c\n", &options).unwrap();
+    let summary = import::import(&mut conn, "people", data).unwrap();
+    assert!(!summary.created);
+
+    let result = conn.execute("SELECT typeof(id), name FROM people ORDER BY id").unwrap();
+    assert_eq!(result.rows.len(), 3);
+    assert_eq!(result.rows[2], vec!["integer".to_string(), "c".to_string()]);
+
+    // 任何一行失败都整体回滚
+    conn.execute("CREATE TABLE positive (n INTEGER CHECK (n > 0))").unwrap();
+    let data = import::parse_json_lines("{\"n\": 1}\n{\"n\": -1}\n").unwrap();
+    assert!(import::import(&mut conn, "positive", data).unwrap_err().contains("第 2 行"));
+    let result = conn.execute("SELECT COUNT(*) FROM positive").unwrap();
+    assert_eq!(result.rows[0][0], "0");
+}
+
+#[test]
+fn test_import_refused_in_transaction() {
+    let mut conn = DbConnection::connect_sqlite(":memory:").unwrap();
+    let csv = "id\n1\n";
+
+    conn.execute("BEGIN").unwrap();
+    assert_eq!(conn.in_transaction(), Some(true));
+    let data = import::parse_csv(csv, &ImportOptions::default()).unwrap();
+    assert!(import::import(&mut conn, "t", data).unwrap_err().contains("事务"));
+
+    // 事务结束后按服务器的状态放行
+    conn.execute("ROLLBACK").unwrap();
+    assert_eq!(conn.in_transaction(), Some(false));
+    let data = import::parse_csv(csv, &ImportOptions::default()).unwrap();
+    assert_eq!(import::import(&mut conn, "t", data).unwrap().rows, 1);
+}
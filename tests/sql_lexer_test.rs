@@ -0,0 +1,93 @@
+//! SQL 词法分析器测试
+
+use cnmsb::sql::lexer::{self, TokenKind};
+use cnmsb::sql::{DatabaseType, SqlEngine};
+
+#[test]
+fn test_tokenize_literals_and_comments() {
+    let sql = "SELECT 'a;b', \"col\" -- note\nFROM t /* x */";
+    let kinds: Vec<TokenKind> = lexer::significant_tokens(sql, DatabaseType::SQLite)
+        .iter()
+        .map(|t| t.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![TokenKind::Word, TokenKind::String, TokenKind::Comma, TokenKind::QuotedIdent, TokenKind::Word, TokenKind::Word]
+    );
+
+    // MySQL 的 # 注释和反引号
+    let tokens = lexer::tokenize("SELECT `id` # comment", DatabaseType::MySQL);
+    assert!(tokens.iter().any(|t| t.kind == TokenKind::QuotedIdent && t.text == "`id`"));
+    assert_eq!(tokens.last().unwrap().kind, TokenKind::Comment);
+}
+
+#[test]
+fn test_dollar_quotes() {
+    let sql = "CREATE FUNCTION f() RETURNS int AS $body$ SELECT 1; $body$ LANGUAGE sql; SELECT $1";
+    let stmts = lexer::split_statements(sql, DatabaseType::PostgreSQL);
+    assert_eq!(stmts.len(), 2);
+    assert!(stmts[0].ends_with("LANGUAGE sql"));
+    assert_eq!(stmts[1], "SELECT $1");
+}
+
+#[test]
+fn test_split_statements() {
+    let db = DatabaseType::SQLite;
+    assert_eq!(lexer::split_statements("SELECT 1; SELECT ';'; -- end", db), vec!["SELECT 1", "SELECT ';'"]);
+
+    let trigger = "CREATE TRIGGER tr AFTER INSERT ON t BEGIN UPDATE c SET n = n + 1; END; SELECT 1";
+    let stmts = lexer::split_statements(trigger, db);
+    assert_eq!(stmts.len(), 2);
+    assert!(stmts[0].ends_with("END"));
+}
+
+#[test]
+fn test_split_trigger_blocks() {
+    let db = DatabaseType::MySQL;
+    let trigger = "CREATE TRIGGER tr BEFORE INSERT ON t FOR EACH ROW BEGIN \
+                   IF NEW.a > 0 THEN SET NEW.b = 1; ELSE SET NEW.b = IF(NEW.c, 2, 3); END IF; \
+                   lbl: LOOP SET NEW.n = NEW.n + 1; IF NEW.n > 3 THEN LEAVE lbl; END IF; END LOOP lbl; \
+                   WHILE NEW.n > 0 DO SET NEW.n = NEW.n - 1; END WHILE; \
+                   CASE NEW.a WHEN 1 THEN SET NEW.d = REPEAT('x', 2); ELSE SET NEW.d = 'y'; END CASE; \
+                   END; SELECT 1";
+    let stmts = lexer::split_statements(trigger, db);
+    assert_eq!(stmts.len(), 2);
+    assert!(stmts[0].ends_with("END"));
+    assert_eq!(stmts[1], "SELECT 1");
+
+    // 触发器名前的 DEFINER 子句
+    let trigger = "CREATE DEFINER=`u`@`h` TRIGGER tr BEFORE INSERT ON t FOR EACH ROW BEGIN SET NEW.b = 1; END; SELECT 1";
+    let stmts = lexer::split_statements(trigger, db);
+    assert_eq!(stmts.len(), 2);
+    assert_eq!(stmts[1], "SELECT 1");
+
+    // 没有 BEGIN 的触发器体
+    let trigger = "CREATE TRIGGER tr BEFORE INSERT ON t FOR EACH ROW IF NEW.a > 0 THEN SET NEW.b = 1; END IF; SELECT 1";
+    assert_eq!(lexer::split_statements(trigger, db).len(), 2);
+
+    // 列名叫 trigger 的表不是触发器
+    let stmts = lexer::split_statements("CREATE TABLE log (`trigger` TEXT, note TEXT); BEGIN; SELECT 1", db);
+    assert_eq!(stmts, vec!["CREATE TABLE log (`trigger` TEXT, note TEXT)", "BEGIN", "SELECT 1"]);
+}
+
+#[test]
+fn test_returns_rows() {
+    let db = DatabaseType::PostgreSQL;
+    assert!(lexer::returns_rows("SELECT 1", db));
+    assert!(lexer::returns_rows("-- leading comment\nselect 1", db));
+    assert!(lexer::returns_rows("WITH x AS (SELECT 1) SELECT * FROM x", db));
+    assert!(lexer::returns_rows("VALUES (1), (2)", db));
+    assert!(lexer::returns_rows("INSERT INTO t (a) VALUES (1) RETURNING id", db));
+    assert!(!lexer::returns_rows("INSERT INTO t (a) VALUES (1)", db));
+    assert!(!lexer::returns_rows("WITH x AS (SELECT 1) DELETE FROM t", db));
+    assert!(!lexer::returns_rows("CREATE TABLE t (a int)", db));
+}
+
+#[test]
+fn test_no_completion_inside_string() {
+    let mut engine = SqlEngine::new(DatabaseType::SQLite);
+    engine.set_tables(vec!["users".to_string()]);
+
+    let input = "SELECT * FROM users WHERE name = 'us";
+    assert!(engine.complete(input, input.len()).is_empty());
+}
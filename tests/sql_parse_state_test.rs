@@ -0,0 +1,88 @@
+//! SQL 解析状态机测试
+
+use cnmsb::sql::parse_state::{self, Clause, SourceKind};
+use cnmsb::sql::{DatabaseType, SqlEngine};
+
+fn analyze_end(sql: &str) -> parse_state::ParseState {
+    parse_state::analyze(sql, sql.len(), DatabaseType::PostgreSQL)
+}
+
+fn texts(engine: &SqlEngine, sql: &str) -> Vec<String> {
+    engine.complete(sql, sql.len()).into_iter().map(|c| c.text).collect()
+}
+
+#[test]
+fn test_subquery_does_not_leak_clause() {
+    let state = analyze_end("SELECT (SELECT max(id) FROM t) , ");
+    assert_eq!(state.clause, Clause::Select);
+    assert_eq!(state.depth, 0);
+    assert!(!state.expects_table);
+
+    let state = analyze_end("SELECT * FROM users WHERE id IN (SELECT user_id FROM ");
+    assert_eq!(state.depth, 1);
+    assert!(state.expects_table);
+}
+
+#[test]
+fn test_cte_and_aliases() {
+    let sql = "WITH recent (uid, total) AS (SELECT user_id, sum(amount) FROM orders GROUP BY user_id) \
+               SELECT  FROM users u JOIN recent r ON r.uid = u.id";
+    let cursor = sql.find("SELECT  ").unwrap() + 7;
+    let state = parse_state::analyze(sql, cursor, DatabaseType::PostgreSQL);
+
+    assert_eq!(state.clause, Clause::Select);
+    assert_eq!(state.cte("recent").map(|c| c.columns.clone()), Some(vec!["uid".to_string(), "total".to_string()]));
+
+    let u = state.resolve("u").unwrap();
+    assert_eq!(u.name, "users");
+    assert_eq!(u.kind, SourceKind::Table);
+
+    let r = state.resolve("r").unwrap();
+    assert_eq!(r.kind, SourceKind::Cte);
+}
+
+#[test]
+fn test_subquery_alias_columns() {
+    let state = analyze_end("SELECT s. FROM (SELECT id, name AS n, count(*) FROM users) s");
+    let s = state.resolve("s").unwrap();
+    assert_eq!(s.kind, SourceKind::Subquery);
+    assert_eq!(s.columns, vec!["id".to_string(), "n".to_string()]);
+}
+
+#[test]
+fn test_engine_offers_ctes_and_aliases() {
+    let mut engine = SqlEngine::new(DatabaseType::PostgreSQL);
+    engine.set_tables(vec!["users".to_string(), "orders".to_string()]);
+    engine.set_columns("users", vec!["id".to_string(), "name".to_string()]);
+    engine.set_columns("orders", vec!["id".to_string(), "user_id".to_string()]);
+
+    // CTE 名出现在 FROM 后的补全中
+    let comps = texts(&engine, "WITH big AS (SELECT * FROM orders) SELECT * FROM ");
+    assert!(comps.contains(&"big".to_string()));
+    assert!(comps.contains(&"users".to_string()));
+
+    // 别名解析到正确的表
+    let comps = texts(&engine, "SELECT * FROM orders o WHERE o.");
+    assert!(comps.contains(&"o.user_id".to_string()));
+    assert!(!comps.iter().any(|c| c == "o.name"));
+
+    // 子查询之后不再认为在 FROM 后面
+    let comps = texts(&engine, "SELECT (SELECT max(id) FROM orders) , ");
+    assert!(!comps.contains(&"users".to_string()));
+
+    // CTE 的列（光标在 FROM 之前）
+    let sql = "WITH big AS (SELECT user_id AS uid FROM orders) SELECT big. FROM big";
+    let cursor = sql.find("big. ").unwrap() + 4;
+    assert!(engine.complete(sql, cursor).iter().any(|c| c.text == "big.uid"));
+}
+
+#[test]
+fn test_cursor_inside_multibyte_char() {
+    // 光标在中文标识符和字符串的字节中间时不 panic
+    let sql = "SELECT 名字 FROM 用户 WHERE 备注 = '你好";
+    for cursor in 0..=sql.len() {
+        parse_state::analyze(sql, cursor, DatabaseType::PostgreSQL);
+    }
+    let state = parse_state::analyze(sql, sql.find("用户").unwrap() + 1, DatabaseType::PostgreSQL);
+    assert!(state.expects_table);
+}
@@ -0,0 +1,65 @@
+//! 连接配置与密码文件测试
+
+use cnmsb::sql::profile;
+use cnmsb::sql::{DatabaseConfig, DatabaseType};
+
+#[test]
+fn test_parse_profiles() {
+    let content = r#"
+[prod]
+type = "postgresql"
+host = "db.example.com"
+user = "app"
+database = "app"
+
+[local]
+type = "sqlite"
+path = "/tmp/app.db"
+
+[legacy]
+url = "mysql://root@10.0.0.2:3307/shop"
+"#;
+    let profiles = profile::parse_profiles(content).unwrap();
+    assert_eq!(profiles.len(), 3);
+    
+    let prod = profiles["prod"].to_config().unwrap();
+    assert_eq!(prod.db_type, DatabaseType::PostgreSQL);
+    assert_eq!(prod.host, "db.example.com");
+    assert_eq!(prod.port, 5432);
+    assert_eq!(prod.username, "app");
+    assert!(prod.password.is_empty());
+    
+    let local = profiles["local"].to_config().unwrap();
+    assert_eq!(local.file_path.as_deref(), Some("/tmp/app.db"));
+    
+    let legacy = profiles["legacy"].to_config().unwrap();
+    assert_eq!(legacy.db_type, DatabaseType::MySQL);
+    assert_eq!(legacy.port, 3307);
+    
+    // 不允许在配置中写密码
+    assert!(profile::parse_profiles("[x]\ntype = \"mysql\"\npassword = \"secret\"").is_err());
+}
+
+#[test]
+fn test_pgpass_password() {
+    let mut config = DatabaseConfig::new(DatabaseType::PostgreSQL);
+    config.host = "db.example.com".to_string();
+    config.username = "app".to_string();
+    config.database = "app".to_string();
+    
+    let content = "# comment\n\
+                   other:5432:*:app:wrong\n\
+                   db.example.com:5432:app:app:p\\:ss\\\\word\n\
+                   *:*:*:*:fallback\n";
+    assert_eq!(profile::pgpass_password(content, &config).as_deref(), Some("p:ss\\word"));
+    
+    config.username = "admin".to_string();
+    assert_eq!(profile::pgpass_password(content, &config).as_deref(), Some("fallback"));
+}
+
+#[test]
+fn test_mycnf_password() {
+    let content = "[mysqld]\npassword = server\n\n[client]\nuser = root\npassword = \"s3cret\"\n";
+    assert_eq!(profile::mycnf_password(content).as_deref(), Some("s3cret"));
+    assert_eq!(profile::mycnf_password("[mysqld]\npassword=x\n"), None);
+}
@@ -0,0 +1,85 @@
+//! SQL 事务状态与安全模式测试
+
+use cnmsb::sql::{safety, DatabaseType, Danger, TransactionState};
+
+#[test]
+fn test_dangerous_statements() {
+    let db = DatabaseType::SQLite;
+
+    assert_eq!(safety::check("DROP TABLE users", db), Some(Danger::Drop));
+    assert_eq!(safety::check("truncate table logs", db), Some(Danger::Truncate));
+    assert_eq!(safety::check("UPDATE users SET name = 'x'", db), Some(Danger::UpdateWithoutWhere));
+    assert_eq!(safety::check("DELETE FROM users", db), Some(Danger::DeleteWithoutWhere));
+
+    // 有 WHERE 的语句是安全的
+    assert_eq!(safety::check("DELETE FROM users WHERE id = 1", db), None);
+    assert_eq!(safety::check("UPDATE users SET a = 1 WHERE id = 2", db), None);
+}
+
+#[test]
+fn test_danger_detection_uses_tokens() {
+    let db = DatabaseType::PostgreSQL;
+
+    // 注释和字符串里的 WHERE 不算
+    assert_eq!(safety::check("DELETE FROM users -- WHERE id = 1", db), Some(Danger::DeleteWithoutWhere));
+    assert_eq!(safety::check("UPDATE t SET note = 'where'", db), Some(Danger::UpdateWithoutWhere));
+    // 子查询里的 WHERE 不算
+    assert_eq!(
+        safety::check("UPDATE t SET a = (SELECT max(x) FROM y WHERE y.id = 1)", db),
+        Some(Danger::UpdateWithoutWhere)
+    );
+    // 前置注释不影响判断
+    assert_eq!(safety::check("/* cleanup */ DROP TABLE t", db), Some(Danger::Drop));
+    // 多语句中任意一条危险即报告
+    assert_eq!(safety::check("SELECT 1; DELETE FROM t", db), Some(Danger::DeleteWithoutWhere));
+    // CTE 后的 DELETE
+    assert_eq!(
+        safety::check("WITH old AS (SELECT id FROM t WHERE x < 1) DELETE FROM t", db),
+        Some(Danger::DeleteWithoutWhere)
+    );
+    assert_eq!(safety::check("SELECT 'DROP TABLE t'", db), None);
+}
+
+#[test]
+fn test_transaction_state() {
+    let db = DatabaseType::PostgreSQL;
+    let mut state = TransactionState::Idle;
+
+    state.apply("BEGIN", db);
+    assert_eq!(state, TransactionState::Active);
+
+    state.fail(db);
+    assert_eq!(state, TransactionState::Failed);
+
+    state.apply("ROLLBACK TO SAVEPOINT sp1", db);
+    assert_eq!(state, TransactionState::Active);
+
+    state.apply("COMMIT", db);
+    assert_eq!(state, TransactionState::Idle);
+
+    state.apply("start transaction; insert into t values (1)", db);
+    assert_eq!(state, TransactionState::Active);
+
+    state.apply("ROLLBACK", db);
+    assert_eq!(state, TransactionState::Idle);
+}
+
+#[test]
+fn test_mysql_implicit_commit() {
+    let db = DatabaseType::MySQL;
+    let mut state = TransactionState::Idle;
+
+    state.apply("BEGIN; CREATE TEMPORARY TABLE tmp (id INT)", db);
+    assert_eq!(state, TransactionState::Active);
+
+    state.apply("ALTER TABLE t ADD COLUMN c INT", db);
+    assert_eq!(state, TransactionState::Idle);
+
+    state.apply("START TRANSACTION; CREATE TABLE t2 (id INT)", db);
+    assert_eq!(state, TransactionState::Idle);
+
+    // PostgreSQL 的 DDL 可以在事务中回滚
+    let mut state = TransactionState::Idle;
+    state.apply("BEGIN; CREATE TABLE t2 (id INT)", DatabaseType::PostgreSQL);
+    assert_eq!(state, TransactionState::Active);
+}
@@ -0,0 +1,89 @@
+//! 表结构（默认值、索引、外键）测试
+
+use cnmsb::sql::{DatabaseType, DbConnection, SchemaCache, SqlEngine};
+
+fn shop() -> DbConnection {
+    let mut conn = DbConnection::connect_sqlite(":memory:").unwrap();
+    conn.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL DEFAULT 'anon', email TEXT UNIQUE)").unwrap();
+    conn.execute("CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER REFERENCES users, total REAL DEFAULT 0)").unwrap();
+    conn.execute("CREATE INDEX idx_orders_user ON orders (user_id)").unwrap();
+    conn.execute("CREATE TABLE order_items (order_id INTEGER, line INTEGER, PRIMARY KEY (order_id, line), FOREIGN KEY (order_id) REFERENCES orders (id))").unwrap();
+    conn
+}
+
+fn shop_engine() -> SqlEngine {
+    let mut conn = shop();
+    let mut engine = SqlEngine::new(DatabaseType::SQLite);
+    engine.set_catalog(&conn.get_catalog().unwrap());
+    for table in ["users", "orders", "order_items"] {
+        engine.set_table_info(table, conn.get_table_info(table).unwrap());
+    }
+    engine
+}
+
+#[test]
+fn test_columns_indexes_and_foreign_keys() {
+    let mut conn = shop();
+
+    let columns = conn.get_columns("users").unwrap();
+    assert_eq!(columns[1].default.as_deref(), Some("'anon'"));
+    assert!(!columns[1].nullable);
+    let keys: Vec<String> = conn.get_columns("order_items").unwrap().into_iter()
+        .filter(|c| c.primary_key)
+        .map(|c| c.name)
+        .collect();
+    assert_eq!(keys, ["order_id", "line"]);
+
+    // 主键排在最前，rowid 主键也会列出
+    let indexes = conn.get_indexes("users").unwrap();
+    assert!(indexes[0].primary);
+    assert_eq!(indexes[0].columns, ["id"]);
+    assert!(indexes.iter().any(|i| i.unique && !i.primary && i.columns == ["email"]));
+    let indexes = conn.get_indexes("orders").unwrap();
+    assert!(indexes.iter().any(|i| i.name == "idx_orders_user" && !i.unique));
+
+    // 同时包括引用别的表和被别的表引用的外键，省略的被引用列解析为主键
+    let keys = conn.get_foreign_keys("orders").unwrap();
+    assert_eq!(keys.len(), 2);
+    let to_users = keys.iter().find(|k| k.ref_table == "users").unwrap();
+    assert_eq!((to_users.columns.clone(), to_users.ref_columns.clone()), (vec!["user_id".to_string()], vec!["id".to_string()]));
+    assert!(keys.iter().any(|k| k.table == "order_items" && k.ref_table == "orders"));
+    assert_eq!(to_users.join_condition("o", "u"), "o.user_id = u.id");
+}
+
+#[test]
+fn test_join_suggestions_from_foreign_keys() {
+    let engine = shop_engine();
+
+    let sql = "SELECT * FROM users u JOIN ";
+    let completions = engine.complete(sql, sql.len());
+    assert_eq!(completions[0].text, "orders ON orders.user_id = u.id");
+
+    let sql = "SELECT * FROM orders o JOIN order_items i ON ";
+    let completions = engine.complete(sql, sql.len());
+    assert_eq!(completions[0].text, "i.order_id = o.id");
+
+    // 列补全注明类型
+    let sql = "SELECT to FROM orders";
+    let completions = engine.complete(sql, 9);
+    let total = completions.iter().find(|c| c.text == "total").unwrap();
+    assert!(total.description.contains("REAL"));
+}
+
+#[test]
+fn test_schema_cache_keeps_table_info() {
+    let path = std::env::temp_dir().join(format!("cnmsb_table_info_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut conn = shop();
+
+    let mut cache = SchemaCache::open(Some(path.clone()));
+    cache.set_catalog(conn.get_catalog().unwrap());
+    cache.set_table_info("orders", conn.get_table_info("orders").unwrap());
+    cache.save();
+
+    let cache = SchemaCache::open(Some(path.clone()));
+    let info = cache.table_info("ORDERS").unwrap();
+    assert_eq!(info.foreign_keys.len(), 2);
+    assert_eq!(cache.columns("orders").unwrap(), &["id", "user_id", "total"]);
+    let _ = std::fs::remove_file(&path);
+}
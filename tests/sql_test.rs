@@ -1,6 +1,8 @@
 //! SQL 补全引擎测试
 
-use cnmsb::sql::{SqlEngine, DatabaseType};
+use cnmsb::sql::{Catalog, SchemaCache, SqlEngine, DatabaseType, SchemaObject, SchemaObjectKind};
+use std::cell::Cell;
+use std::rc::Rc;
 
 #[test]
 fn test_sql_keyword_completion() {
@@ -100,3 +102,110 @@ fn main() {
     println!("\n=== 测试完成 ===");
 }
 
+
+#[test]
+fn test_schema_qualified_completion() {
+    let mut engine = SqlEngine::new(DatabaseType::PostgreSQL);
+    engine.set_tables(vec!["users".to_string()]);
+    engine.set_columns("users", vec!["id".to_string(), "name".to_string()]);
+    engine.set_columns("sales.orders", vec!["order_id".to_string(), "amount".to_string()]);
+    engine.set_schemas(vec!["public".to_string(), "sales".to_string()]);
+    engine.set_search_path(vec!["public".to_string()]);
+    engine.set_schema_objects("sales", vec![
+        SchemaObject::new("orders", SchemaObjectKind::Table),
+        SchemaObject::new("order_totals", SchemaObjectKind::MaterializedView),
+        SchemaObject::new("order_count", SchemaObjectKind::Function),
+    ]);
+    
+    // schema. 补全表和物化视图，不包括函数
+    let input = "SELECT * FROM sales.ord";
+    let texts: Vec<String> = engine.complete(input, input.len()).into_iter().map(|c| c.text).collect();
+    assert!(texts.contains(&"sales.orders".to_string()));
+    assert!(texts.contains(&"sales.order_totals".to_string()));
+    assert!(!texts.contains(&"sales.order_count".to_string()));
+    
+    // 表名位置也补全 schema 名
+    let input = "SELECT * FROM sa";
+    assert!(engine.complete(input, input.len()).iter().any(|c| c.text == "sales"));
+    
+    // schema.table. 补全列
+    let input = "SELECT sales.orders.am";
+    assert!(engine.complete(input, input.len()).iter().any(|c| c.text == "sales.orders.amount"));
+    
+    // search_path 中的表可以带前缀也可以不带
+    let input = "SELECT public.users.na";
+    assert!(engine.complete(input, input.len()).iter().any(|c| c.text == "public.users.name"));
+    
+    // FROM schema.table 后用表名引用列
+    let input = "SELECT orders.o FROM sales.orders";
+    assert!(engine.complete(input, 15).iter().any(|c| c.text == "orders.order_id"));
+}
+
+#[test]
+fn test_lazy_column_loading() {
+    let mut catalog = Catalog::default();
+    catalog.add_schema("public", Some(0));
+    catalog.add_schema("sales", None);
+    catalog.add_object("public", SchemaObject::new("users", SchemaObjectKind::Table));
+    catalog.add_object("sales", SchemaObject::new("orders", SchemaObjectKind::Table));
+    catalog.finish();
+    assert_eq!(catalog.tables(), vec!["users".to_string()]);
+    assert!(catalog.has_table("sales.orders"));
+    assert!(!catalog.has_table("orders"));
+    
+    let mut engine = SqlEngine::new(DatabaseType::PostgreSQL);
+    engine.set_catalog(&catalog);
+    
+    // 列只在第一次用到时加载
+    let calls = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&calls);
+    engine.set_column_loader(Box::new(move |table| {
+        counter.set(counter.get() + 1);
+        if table == "users" { vec!["id".to_string(), "email".to_string()] } else { Vec::new() }
+    }));
+    assert_eq!(calls.get(), 0);
+    
+    let input = "SELECT em";
+    let completions = engine.complete("SELECT em FROM users", input.len());
+    assert!(completions.iter().any(|c| c.text == "email"));
+    assert!(calls.get() > 0);
+}
+
+#[test]
+fn test_schema_cache_persistence() {
+    let path = std::env::temp_dir().join(format!("cnmsb_schema_cache_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    
+    let mut catalog = Catalog::default();
+    catalog.add_schema("main", Some(0));
+    catalog.add_object("main", SchemaObject::new("users", SchemaObjectKind::Table));
+    catalog.finish();
+    
+    let mut cache = SchemaCache::open(Some(path.clone()));
+    assert!(cache.catalog().is_none());
+    cache.set_catalog(catalog);
+    cache.set_columns("Users", vec!["id".to_string()]);
+    cache.save();
+    
+    let cache = SchemaCache::open(Some(path.clone()));
+    assert!(cache.has_table("users"));
+    assert_eq!(cache.columns("users"), Some(&vec!["id".to_string()]));
+    assert_eq!(cache.catalog().unwrap().search_path, vec!["main".to_string()]);
+    
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_mariadb_dialect() {
+    let engine = SqlEngine::new(DatabaseType::MariaDB);
+    
+    let completions = engine.complete("CREATE SEQ", 10);
+    assert!(completions.iter().any(|c| c.text == "SEQUENCE"));
+    
+    let completions = engine.complete("SELECT NEXTV", 12);
+    assert!(completions.iter().any(|c| c.text == "NEXTVAL()"));
+    
+    // MySQL 没有序列
+    let mysql = SqlEngine::new(DatabaseType::MySQL);
+    assert!(!mysql.complete("SELECT NEXTV", 12).iter().any(|c| c.text == "NEXTVAL()"));
+}
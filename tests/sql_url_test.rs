@@ -0,0 +1,132 @@
+//! 数据库连接 URL 解析测试
+
+use cnmsb::sql::{DatabaseConfig, DatabaseType, DbConnection, DbError, SslMode};
+
+#[test]
+fn test_parse_query_params() {
+    let config = DatabaseConfig::parse("postgresql://app@db.example.com/app?sslmode=require&application_name=cnmsb").unwrap();
+    assert_eq!(config.db_type, DatabaseType::PostgreSQL);
+    assert_eq!(config.host, "db.example.com");
+    assert_eq!(config.port, 5432);
+    assert_eq!(config.ssl_mode, SslMode::Require);
+    assert_eq!(config.param("application_name"), Some("cnmsb"));
+    
+    let config = DatabaseConfig::parse("mysql://root@localhost/shop?ssl-mode=VERIFY_IDENTITY&ssl-ca=/etc/ca.pem").unwrap();
+    assert_eq!(config.ssl_mode, SslMode::VerifyFull);
+    assert_eq!(config.ssl_root_cert.as_deref(), Some("/etc/ca.pem"));
+    
+    assert!(DatabaseConfig::parse("postgresql://localhost/db?sslmode=sometimes").is_none());
+    assert!(DatabaseConfig::parse("postgresql://localhost:abc/db").is_none());
+}
+
+#[test]
+fn test_parse_unix_socket() {
+    let config = DatabaseConfig::parse("postgres:///mydb?host=/var/run/postgresql").unwrap();
+    assert_eq!(config.socket.as_deref(), Some("/var/run/postgresql"));
+    assert_eq!(config.database, "mydb");
+    
+    // 百分号编码的 socket 目录写在主机位置
+    let config = DatabaseConfig::parse("postgresql://postgres@%2Ftmp/mydb").unwrap();
+    assert_eq!(config.socket.as_deref(), Some("/tmp"));
+    assert_eq!(config.username, "postgres");
+    
+    let config = DatabaseConfig::parse("mysql://root@localhost/test?socket=/run/mysqld/mysqld.sock").unwrap();
+    assert_eq!(config.socket.as_deref(), Some("/run/mysqld/mysqld.sock"));
+}
+
+#[test]
+fn test_parse_ipv6_and_encoding() {
+    let config = DatabaseConfig::parse("postgresql://user:p%40ss%3Aword@[::1]:5433/db").unwrap();
+    assert_eq!(config.host, "::1");
+    assert_eq!(config.port, 5433);
+    assert_eq!(config.password, "p@ss:word");
+    
+    let config = DatabaseConfig::parse("mysql://root@[fe80::1]/db").unwrap();
+    assert_eq!(config.host, "fe80::1");
+    assert_eq!(config.port, 3306);
+    
+    // 兼容未编码的 @
+    let config = DatabaseConfig::parse("mysql://root:a@b@localhost:3306/db").unwrap();
+    assert_eq!(config.password, "a@b");
+    
+    let config = DatabaseConfig::parse("mariadb://root@localhost/db").unwrap();
+    assert_eq!(config.db_type, DatabaseType::MariaDB);
+}
+
+#[test]
+fn test_round_trip() {
+    let mut pg = DatabaseConfig::new(DatabaseType::PostgreSQL);
+    pg.username = "app user".to_string();
+    pg.password = "p@ss:w/rd?&%".to_string();
+    pg.database = "my db".to_string();
+    pg.host = "2001:db8::10".to_string();
+    pg.ssl_mode = SslMode::VerifyCa;
+    pg.ssl_root_cert = Some("/etc/ssl/root.crt".to_string());
+    pg.params.push(("application_name".to_string(), "a&b=c".to_string()));
+    
+    let mut socket = DatabaseConfig::new(DatabaseType::PostgreSQL);
+    socket.username = "postgres".to_string();
+    socket.database = "postgres".to_string();
+    socket.socket = Some("/var/run/postgresql".to_string());
+    
+    let mut mysql = DatabaseConfig::new(DatabaseType::MySQL);
+    mysql.username = "root".to_string();
+    mysql.password = "secret#1".to_string();
+    mysql.database = "shop".to_string();
+    mysql.ssl_mode = SslMode::Disable;
+    mysql.socket = Some("/tmp/mysql.sock".to_string());
+    
+    let mut maria = DatabaseConfig::new(DatabaseType::MariaDB);
+    maria.host = "db.local".to_string();
+    maria.port = 3307;
+    
+    let sqlite = DatabaseConfig::sqlite("/tmp/app data.db");
+    
+    for config in [pg, socket, mysql, maria, sqlite] {
+        let url = config.to_connection_string();
+        assert_eq!(DatabaseConfig::parse(&url).as_ref(), Some(&config), "{}", url);
+    }
+}
+
+#[test]
+fn test_connect_url_by_type() {
+    // 不是 URL 格式的连接字符串交给驱动解析，MariaDB 和 MySQL 一样走 mysql 驱动
+    for db_type in [DatabaseType::MySQL, DatabaseType::MariaDB] {
+        match DbConnection::connect_url(db_type, "host=localhost user=root") {
+            Err(DbError::Connection(_)) => {}
+            other => panic!("{:?}: {:?}", db_type, other.err()),
+        }
+    }
+    assert!(matches!(
+        DbConnection::connect_url(DatabaseType::SQLite, "host=localhost"),
+        Err(DbError::Unsupported(_))
+    ));
+}
+
+#[test]
+fn test_postgres_key_value_sslmode() {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    // 假服务器同意 TLS：sslmode=require 要先发 SSLRequest，再开始 TLS 握手
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 8];
+        stream.read_exact(&mut request).unwrap();
+        stream.write_all(b"S").unwrap();
+        let mut record = [0u8; 1];
+        let handshake = stream.read_exact(&mut record).is_ok() && record[0] == 0x16;
+        (request, handshake)
+    });
+
+    let url = format!("host=127.0.0.1 port={} user=app sslmode=require connect_timeout=5", port);
+    assert!(matches!(
+        DbConnection::connect_url(DatabaseType::PostgreSQL, &url),
+        Err(DbError::Connection(_))
+    ));
+    let (request, handshake) = server.join().unwrap();
+    assert_eq!(request, [0, 0, 0, 8, 0x04, 0xd2, 0x16, 0x2f]);
+    assert!(handshake);
+}